@@ -5,6 +5,7 @@ import "./utils/SolRsaVerify.sol";
 import "./utils/Base64.sol";
 import "./utils/BytesUtils.sol";
 import "./utils/Secp256k1.sol";
+import "./utils/DcapQuote.sol";
 
 contract ReportHandle {
     using SafeMath for uint256;
@@ -23,6 +24,10 @@ contract ReportHandle {
     // `openssl x509 -noout -modulus -in AttestationReportSigningCert.pem` and `openssl x509 -in AttestationReportSigningCert.pem -text`.
     bytes constant internal RSA_EXP = hex"0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010001";
     bytes constant internal RSA_MOD = hex"A97A2DE0E66EA6147C9EE745AC0162686C7192099AFC4B3F040FAD6DE093511D74E802F510D716038157DCAF84F4104BD3FED7E6B8F99C8817FD1FF5B9B864296C3D81FA8F1B729E02D21D72FFEE4CED725EFE74BEA68FBC4D4244286FCDD4BF64406A439A15BCB4CF67754489C423972B4A80DF5C2E7C5BC2DBAF2D42BB7B244F7C95BF92C75D3B33FC5410678A89589D1083DA3ACC459F2704CD99598C275E7C1878E00757E5BDB4E840226C11C0A17FF79C80B15C1DDB5AF21CC2417061FBD2A2DA819ED3B72B7EFAA3BFEBE2805C9B8AC19AA346512D484CFC81941E15F55881CC127E8F7AA12300CD5AFB5742FA1D20CB467A5BEB1C666CF76A368978B5";
+    // This is the public key of Intel SGX Root CA, which roots the PCK certificate chains of DCAP quotes.
+    // You can extract it using: `openssl x509 -noout -pubkey -in Intel_SGX_Provisioning_Certification_RootCA.pem | openssl ec -pubin -text`.
+    uint256 constant internal INTEL_ROOT_CA_X = 0x0ba9c4c0c0c86193a3fe23d6b02cda10a8bbd4e88e48b4458561a36e705525f5;
+    uint256 constant internal INTEL_ROOT_CA_Y = 0x67918e2edc88e40d860bd0cc4ee26aacc988e505a953558c453f6b0904ae7394;
     uint constant internal WORD_SIZE = 32;
 
     // Set new mrenclave value and enclave address
@@ -71,10 +76,19 @@ contract ReportHandle {
         return encryptionKeyMapping[inpEncryptionKey];
     }
 
+    // For EPID, `_report` is the attestation report of IAS and `_reportSig` is its signature.
+    // For DCAP, `_report` is the quote and `_reportSig` is its PCK certificate chain in DER.
     function extractFromReport(bytes memory _report, bytes memory _reportSig) internal view returns (bytes32, address, bytes32) {
-        require(verifyReportSig(_report, _reportSig) == 0, "Invalid report's signature");
-        bytes memory quote = extractQuote(_report);
+        bytes memory quote;
+        if (DcapQuote.isDcapQuote(_report)) {
+            DcapQuote.verify(_report, _reportSig, INTEL_ROOT_CA_X, INTEL_ROOT_CA_Y);
+            quote = _report;
+        } else {
+            require(verifyReportSig(_report, _reportSig) == 0, "Invalid report's signature");
+            quote = extractQuote(_report);
+        }
         // See https://api.trustedservices.intel.com/documents/sgx-attestation-api-spec.pdf, P.23.
+        // The header and the report body of DCAP quotes have the same layout as EPID's.
         bytes32 inpMrEnclave = BytesUtils.toBytes32(extractElement(quote, 112, 32), 0);
         address inpVerifyingKey = BytesUtils.toAddress(extractElement(quote, 368, 20), 0);
         bytes32 inpEncryptionKey = BytesUtils.toBytes32(extractElement(quote, 388, 32), 0);
@@ -0,0 +1,235 @@
+pragma solidity ^0.5.0;
+
+import "./BytesUtils.sol";
+import "./P256.sol";
+
+// Verification of DCAP quotes (version 3, ECDSA-256-with-P-256 attestation key) and their PCK certificate chains.
+// For the layout of quotes, see "Intel® SGX ECDSA Quote Library API".
+// The PCK certificate chain is passed in DER, concatenated from the PCK certificate to the CA certificate.
+// Validity periods, revocation and the TCB status of the platform are not checked here.
+library DcapQuote {
+    // The header and the ISV enclave report body, which is signed by the attestation key
+    uint256 constant QUOTE_BODY_LEN = 432;
+    uint256 constant REPORT_BODY_LEN = 384;
+    uint256 constant ISV_REPORT_SIG_OFFSET = 436;
+    uint256 constant ATTESTATION_KEY_OFFSET = 500;
+    uint256 constant QE_REPORT_OFFSET = 564;
+    uint256 constant QE_REPORT_SIG_OFFSET = 948;
+    uint256 constant QE_AUTH_DATA_OFFSET = 1014;
+
+    bytes16 constant INTEL_QE_VENDOR_ID = hex"939a7233f79c4ca9940a0db3957f0607";
+    // The MRSIGNER and the ISVPRODID of Intel's quoting enclave
+    bytes32 constant INTEL_QE_MRSIGNER = hex"8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff";
+    uint256 constant INTEL_QE_ISVPRODID = 1;
+    // The DER of the OID of ecdsa-with-SHA256 (1.2.840.10045.4.3.2)
+    bytes8 constant ECDSA_WITH_SHA256 = hex"2a8648ce3d040302";
+
+    struct Certificate {
+        bytes32 tbsHash;
+        uint256 sigR;
+        uint256 sigS;
+        uint256 keyX;
+        uint256 keyY;
+        uint256 end;
+    }
+
+    // EPID reports are JSON, so they never start with the version and the attestation key type of DCAP quotes.
+    function isDcapQuote(bytes memory _quote) internal pure returns (bool) {
+        return _quote.length >= QUOTE_BODY_LEN &&
+            readUintLE(_quote, 0, 2) == 3 &&
+            readUintLE(_quote, 2, 2) == 2;
+    }
+
+    /// @dev Verifies that the quote is generated by Intel's quoting enclave on a platform certified by the PCK certificate chain,
+    /// which is rooted in the public key (x, y) of the root CA.
+    function verify(bytes memory _quote, bytes memory _pckCertChain, uint256 _rootX, uint256 _rootY) internal view {
+        require(isDcapQuote(_quote), "Unsupported quote version or attestation key type");
+        require(bytes16(BytesUtils.toBytes32(_quote, 12)) == INTEL_QE_VENDOR_ID, "Unknown QE vendor");
+        require(_quote.length >= QE_AUTH_DATA_OFFSET, "Invalid quote length");
+        uint256 qeAuthDataLen = readUintLE(_quote, QE_AUTH_DATA_OFFSET - 2, 2);
+        require(_quote.length >= QE_AUTH_DATA_OFFSET + qeAuthDataLen, "Invalid quote length");
+
+        // The quote is signed by the attestation key.
+        require(
+            P256.verify(
+                sha256Slice(_quote, 0, QUOTE_BODY_LEN),
+                readUint(_quote, ISV_REPORT_SIG_OFFSET, 32),
+                readUint(_quote, ISV_REPORT_SIG_OFFSET + 32, 32),
+                readUint(_quote, ATTESTATION_KEY_OFFSET, 32),
+                readUint(_quote, ATTESTATION_KEY_OFFSET + 32, 32)
+            ),
+            "Invalid ISV enclave report signature"
+        );
+
+        // The attestation key is bound to the report of Intel's quoting enclave.
+        bytes32 qeReportData = sha256(abi.encodePacked(
+            slice(_quote, ATTESTATION_KEY_OFFSET, 64),
+            slice(_quote, QE_AUTH_DATA_OFFSET, qeAuthDataLen)
+        ));
+        require(
+            BytesUtils.toBytes32(_quote, QE_REPORT_OFFSET + 320) == qeReportData &&
+            BytesUtils.toBytes32(_quote, QE_REPORT_OFFSET + 352) == bytes32(0),
+            "The attestation key is not bound to the QE report"
+        );
+        require(
+            BytesUtils.toBytes32(_quote, QE_REPORT_OFFSET + 128) == INTEL_QE_MRSIGNER &&
+            readUintLE(_quote, QE_REPORT_OFFSET + 256, 2) == INTEL_QE_ISVPRODID,
+            "The quoting enclave is not Intel's one"
+        );
+
+        // The QE report is signed by the PCK.
+        (uint256 pckX, uint256 pckY) = verifyPckCertChain(_pckCertChain, _rootX, _rootY);
+        require(
+            P256.verify(
+                sha256Slice(_quote, QE_REPORT_OFFSET, REPORT_BODY_LEN),
+                readUint(_quote, QE_REPORT_SIG_OFFSET, 32),
+                readUint(_quote, QE_REPORT_SIG_OFFSET + 32, 32),
+                pckX,
+                pckY
+            ),
+            "Invalid QE report signature"
+        );
+    }
+
+    /// @dev Verifies the PCK certificate and the CA certificate following it, and returns the public key of the PCK.
+    /// The root certificate may follow them, but it is ignored in favor of the given root key.
+    function verifyPckCertChain(bytes memory _chain, uint256 _rootX, uint256 _rootY) internal view returns (uint256, uint256) {
+        Certificate memory pck = parseCertificate(_chain, 0);
+        Certificate memory ca = parseCertificate(_chain, pck.end);
+
+        require(P256.verify(ca.tbsHash, ca.sigR, ca.sigS, _rootX, _rootY), "Invalid PCK CA certificate signature");
+        require(P256.verify(pck.tbsHash, pck.sigR, pck.sigS, ca.keyX, ca.keyY), "Invalid PCK certificate signature");
+
+        return (pck.keyX, pck.keyY);
+    }
+
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+    function parseCertificate(bytes memory _der, uint256 _start) internal pure returns (Certificate memory cert) {
+        (uint256 tag, uint256 offset, uint256 len) = readTlv(_der, _start);
+        require(tag == 0x30, "Invalid certificate");
+        cert.end = offset + len;
+
+        uint256 tbsStart = offset;
+        (tag, offset, len) = readTlv(_der, tbsStart);
+        require(tag == 0x30, "Invalid certificate");
+        cert.tbsHash = sha256Slice(_der, tbsStart, offset + len - tbsStart);
+        (cert.keyX, cert.keyY) = parsePublicKey(_der, offset);
+
+        // signatureAlgorithm ::= SEQUENCE { algorithm OBJECT IDENTIFIER }
+        (tag, offset, len) = readTlv(_der, offset + len);
+        require(tag == 0x30, "Invalid certificate");
+        uint256 sigStart = offset + len;
+        (tag, offset, len) = readTlv(_der, offset);
+        require(
+            tag == 0x06 && len == 8 && bytes8(BytesUtils.toBytes32(_der, offset)) == ECDSA_WITH_SHA256,
+            "Unsupported signature algorithm"
+        );
+
+        // signatureValue ::= BIT STRING of SEQUENCE { r INTEGER, s INTEGER }
+        (tag, offset, len) = readTlv(_der, sigStart);
+        require(tag == 0x03 && uint8(_der[offset]) == 0, "Invalid certificate signature");
+        (tag, offset, len) = readTlv(_der, offset + 1);
+        require(tag == 0x30, "Invalid certificate signature");
+        (cert.sigR, offset) = readInteger(_der, offset);
+        (cert.sigS, offset) = readInteger(_der, offset);
+        require(offset <= cert.end, "Invalid certificate");
+    }
+
+    // TBSCertificate ::= SEQUENCE { [0] version, serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo, ... }
+    function parsePublicKey(bytes memory _der, uint256 _tbsOffset) internal pure returns (uint256, uint256) {
+        (uint256 tag, uint256 offset, uint256 len) = readTlv(_der, _tbsOffset);
+        if (tag == 0xa0) {
+            (tag, offset, len) = readTlv(_der, offset + len);
+        }
+        // from serialNumber to subject
+        for (uint256 i = 0; i < 4; i++) {
+            (tag, offset, len) = readTlv(_der, offset + len);
+        }
+
+        // subjectPublicKeyInfo ::= SEQUENCE { algorithm, subjectPublicKey BIT STRING }
+        (tag, offset, len) = readTlv(_der, offset + len);
+        require(tag == 0x30, "Invalid subject public key info");
+        (tag, offset, len) = readTlv(_der, offset);
+        (tag, offset, len) = readTlv(_der, offset + len);
+        require(
+            tag == 0x03 && len == 66 && uint8(_der[offset]) == 0 && uint8(_der[offset + 1]) == 0x04,
+            "The subject public key must be an uncompressed point"
+        );
+
+        return (readUint(_der, offset + 2, 32), readUint(_der, offset + 34, 32));
+    }
+
+    // Returns the value and the offset following it.
+    function readInteger(bytes memory _der, uint256 _start) internal pure returns (uint256, uint256) {
+        (uint256 tag, uint256 offset, uint256 len) = readTlv(_der, _start);
+        require(tag == 0x02 && len > 0, "Invalid integer");
+        // A leading zero keeps a positive integer from being negative.
+        if (len == 33) {
+            require(uint8(_der[offset]) == 0, "Invalid integer");
+            offset++;
+            len--;
+        }
+        require(len <= 32, "Invalid integer");
+
+        return (readUint(_der, offset, len), offset + len);
+    }
+
+    // Returns the tag, the offset of the contents and the length of them.
+    function readTlv(bytes memory _der, uint256 _start) internal pure returns (uint256, uint256, uint256) {
+        require(_der.length >= _start + 2, "Invalid DER");
+        uint256 tag = uint8(_der[_start]);
+        uint256 len = uint8(_der[_start + 1]);
+        uint256 offset = _start + 2;
+        if (len == 0x81) {
+            len = readUint(_der, offset, 1);
+            offset += 1;
+        } else if (len == 0x82) {
+            len = readUint(_der, offset, 2);
+            offset += 2;
+        } else {
+            require(len < 0x80, "Unsupported DER length");
+        }
+        require(_der.length >= offset + len, "Invalid DER");
+
+        return (tag, offset, len);
+    }
+
+    // Reads the big endian unsigned integer of `_len` bytes.
+    function readUint(bytes memory _bytes, uint256 _start, uint256 _len) internal pure returns (uint256 value) {
+        require(_len <= 32 && _bytes.length >= _start + _len, "Out of bounds");
+        assembly {
+            value := mload(add(add(_bytes, 0x20), _start))
+        }
+        if (_len < 32) {
+            value = value >> (8 * (32 - _len));
+        }
+    }
+
+    // Reads the little endian unsigned integer of `_len` bytes.
+    function readUintLE(bytes memory _bytes, uint256 _start, uint256 _len) internal pure returns (uint256 value) {
+        require(_bytes.length >= _start + _len, "Out of bounds");
+        for (uint256 i = _len; i > 0; i--) {
+            value = (value << 8) | uint8(_bytes[_start + i - 1]);
+        }
+    }
+
+    function slice(bytes memory _bytes, uint256 _start, uint256 _len) internal pure returns (bytes memory) {
+        require(_bytes.length >= _start + _len, "Out of bounds");
+        bytes memory o = new bytes(_len);
+        for (uint256 i = 0; i < _len; i++) {
+            o[i] = _bytes[_start + i];
+        }
+        return o;
+    }
+
+    function sha256Slice(bytes memory _bytes, uint256 _start, uint256 _len) internal view returns (bytes32 hash) {
+        require(_bytes.length >= _start + _len, "Out of bounds");
+        assembly {
+            let out := mload(0x40)
+            if iszero(staticcall(gas, 2, add(add(_bytes, 0x20), _start), _len, out, 0x20)) {
+                revert(0, 0)
+            }
+            hash := mload(out)
+        }
+    }
+}
@@ -0,0 +1,156 @@
+pragma solidity ^0.5.0;
+
+// ECDSA verification on the NIST P-256 curve (secp256r1), which signs DCAP quotes and Intel's certificates.
+// Points are added in Jacobian coordinates, and the two scalar multiplications are interleaved (Shamir's trick).
+// ref: https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-3.html
+library P256 {
+    // the modulus
+    uint256 constant PP = 0xFFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFF;
+    // the order of the base point
+    uint256 constant NN = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551;
+    uint256 constant AA = 0xFFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFC;
+    uint256 constant BB = 0x5AC635D8AA3A93E7B3EBBD55769886BC651D06B0CC53B0F63BCE3C3E27D2604B;
+    uint256 constant GX = 0x6B17D1F2E12C4247F8BCE6E563A440F277037D812DEB33A0F4A13945D898C296;
+    uint256 constant GY = 0x4FE342E2FE1A7F9B8EE7EB4A7C0F9E162BCE33576B315ECECBB6406837BF51F5;
+
+    /// @dev Verifies the signature (r, s) of the hash with the public key (x, y).
+    function verify(bytes32 _hash, uint256 _r, uint256 _s, uint256 _x, uint256 _y) internal view returns (bool) {
+        if (_r == 0 || _r >= NN || _s == 0 || _s >= NN || !isOnCurve(_x, _y)) {
+            return false;
+        }
+
+        uint256 w = modInverse(_s, NN);
+        uint256 u1 = mulmod(uint256(_hash), w, NN);
+        uint256 u2 = mulmod(_r, w, NN);
+
+        // G + Q in affine coordinates, which is added where both of the scalars have the bit
+        uint256[3] memory sum = [GX, GY, 1];
+        addAffine(sum, _x, _y);
+        if (sum[2] == 0) {
+            return false;
+        }
+        (uint256 sumX, uint256 sumY) = toAffine(sum);
+
+        // The point at infinity is represented by z = 0.
+        uint256[3] memory acc;
+        for (uint256 i = 256; i > 0; i--) {
+            doublePoint(acc);
+            uint256 bit1 = (u1 >> (i - 1)) & 1;
+            uint256 bit2 = (u2 >> (i - 1)) & 1;
+            if (bit1 == 1 && bit2 == 1) {
+                addAffine(acc, sumX, sumY);
+            } else if (bit1 == 1) {
+                addAffine(acc, GX, GY);
+            } else if (bit2 == 1) {
+                addAffine(acc, _x, _y);
+            }
+        }
+        if (acc[2] == 0) {
+            return false;
+        }
+
+        (uint256 x, ) = toAffine(acc);
+        return x % NN == _r;
+    }
+
+    function isOnCurve(uint256 _x, uint256 _y) internal pure returns (bool) {
+        if (_x >= PP || _y >= PP) {
+            return false;
+        }
+        // y^2 = x^3 + ax + b
+        uint256 lhs = mulmod(_y, _y, PP);
+        uint256 rhs = addmod(mulmod(mulmod(_x, _x, PP), _x, PP), addmod(mulmod(AA, _x, PP), BB, PP), PP);
+        return lhs == rhs;
+    }
+
+    /// @dev Doubles the point in Jacobian coordinates in place (dbl-2001-b, a = -3).
+    function doublePoint(uint256[3] memory _p) private pure {
+        uint256 x = _p[0];
+        uint256 y = _p[1];
+        uint256 z = _p[2];
+        if (z == 0 || y == 0) {
+            _p[2] = 0;
+            return;
+        }
+
+        uint256 delta = mulmod(z, z, PP);
+        uint256 gamma = mulmod(y, y, PP);
+        uint256 beta = mulmod(x, gamma, PP);
+        uint256 alpha = mulmod(3, mulmod(addmod(x, PP - delta, PP), addmod(x, delta, PP), PP), PP);
+        uint256 x3 = addmod(mulmod(alpha, alpha, PP), PP - mulmod(8, beta, PP), PP);
+        uint256 yz = addmod(y, z, PP);
+        _p[2] = addmod(addmod(mulmod(yz, yz, PP), PP - gamma, PP), PP - delta, PP);
+        _p[1] = addmod(
+            mulmod(alpha, addmod(mulmod(4, beta, PP), PP - x3, PP), PP),
+            PP - mulmod(8, mulmod(gamma, gamma, PP), PP),
+            PP
+        );
+        _p[0] = x3;
+    }
+
+    /// @dev Adds the point (x2, y2) in affine coordinates to the point in Jacobian coordinates in place (madd-2007-bl).
+    function addAffine(uint256[3] memory _p, uint256 _x2, uint256 _y2) private pure {
+        uint256 z1 = _p[2];
+        if (z1 == 0) {
+            _p[0] = _x2;
+            _p[1] = _y2;
+            _p[2] = 1;
+            return;
+        }
+
+        uint256 z1z1 = mulmod(z1, z1, PP);
+        uint256 h = addmod(mulmod(_x2, z1z1, PP), PP - _p[0], PP);
+        uint256 r = addmod(mulmod(_y2, mulmod(z1, z1z1, PP), PP), PP - _p[1], PP);
+        if (h == 0) {
+            if (r == 0) {
+                // The points are the same.
+                _p[0] = _x2;
+                _p[1] = _y2;
+                _p[2] = 1;
+                doublePoint(_p);
+            } else {
+                // The points are the inverse of each other.
+                _p[2] = 0;
+            }
+            return;
+        }
+
+        r = addmod(r, r, PP);
+        uint256 hh = mulmod(h, h, PP);
+        uint256 z1h = addmod(z1, h, PP);
+        _p[2] = addmod(addmod(mulmod(z1h, z1h, PP), PP - z1z1, PP), PP - hh, PP);
+        uint256 i = mulmod(4, hh, PP);
+        uint256 j = mulmod(h, i, PP);
+        uint256 v = mulmod(_p[0], i, PP);
+        uint256 x3 = addmod(addmod(mulmod(r, r, PP), PP - j, PP), PP - addmod(v, v, PP), PP);
+        _p[1] = addmod(
+            mulmod(r, addmod(v, PP - x3, PP), PP),
+            PP - mulmod(2, mulmod(_p[1], j, PP), PP),
+            PP
+        );
+        _p[0] = x3;
+    }
+
+    function toAffine(uint256[3] memory _p) private view returns (uint256, uint256) {
+        uint256 zInv = modInverse(_p[2], PP);
+        uint256 zInv2 = mulmod(zInv, zInv, PP);
+        return (mulmod(_p[0], zInv2, PP), mulmod(_p[1], mulmod(zInv2, zInv, PP), PP));
+    }
+
+    /// @dev The inverse modulo the prime by Fermat's little theorem, using the precompiled modexp.
+    function modInverse(uint256 _a, uint256 _pp) private view returns (uint256 result) {
+        assembly {
+            let input := mload(0x40)
+            mstore(input, 0x20)
+            mstore(add(input, 0x20), 0x20)
+            mstore(add(input, 0x40), 0x20)
+            mstore(add(input, 0x60), _a)
+            mstore(add(input, 0x80), sub(_pp, 2))
+            mstore(add(input, 0xa0), _pp)
+            if iszero(staticcall(gas, 5, input, 0xc0, input, 0x20)) {
+                revert(0, 0)
+            }
+            result := mload(input)
+        }
+    }
+}
@@ -4,15 +4,15 @@ use crate::{
     term::Term,
 };
 use anonify_wallet::{DirOperations, KeyFile, KeystoreDirectory, WalletDirectory};
-use anyhow::anyhow;
 use bip39::{Language, Mnemonic, MnemonicType, Seed};
 use ed25519_dalek::Keypair;
 use frame_common::crypto::{AccountId, Ed25519ChallengeResponse};
-use frame_sodium::{SodiumCiphertext, SodiumPubKey};
+use frame_sodium::SodiumPubKey;
 use rand::Rng;
 use rand_core::{CryptoRng, RngCore};
 use reqwest::Client;
 use serde_json::json;
+use state_runtime_node_client::StateRuntimeClient;
 use std::path::PathBuf;
 
 pub(crate) fn deploy(anonify_url: String) -> Result<()> {
@@ -84,32 +84,22 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
-            "total_supply": total_supply,
-        },
-        "cmd_name": "construct",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
+    let ciphertext = client.encrypt_command(
         csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        enclave_encryption_key,
+        &access_policy,
+        "construct",
+        json!({
+            "total_supply": total_supply,
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -128,33 +118,23 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
+    let ciphertext = client.encrypt_command(
+        csprng,
+        enclave_encryption_key,
+        &access_policy,
+        "transfer",
+        json!({
             "amount": amount,
             "recipient": recipient,
-        },
-        "cmd_name": "transfer",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
-        csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -173,33 +153,23 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
+    let ciphertext = client.encrypt_command(
+        csprng,
+        enclave_encryption_key,
+        &access_policy,
+        "approve",
+        json!({
             "amount": amount,
             "spender": spender,
-        },
-        "cmd_name": "approve",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
-        csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -219,34 +189,24 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
+    let ciphertext = client.encrypt_command(
+        csprng,
+        enclave_encryption_key,
+        &access_policy,
+        "transfer_from",
+        json!({
             "amount": amount,
             "owner": owner,
             "recipient": recipient,
-        },
-        "cmd_name": "transfer_from",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
-        csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -265,33 +225,23 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
+    let ciphertext = client.encrypt_command(
+        csprng,
+        enclave_encryption_key,
+        &access_policy,
+        "mint",
+        json!({
             "amount": amount,
             "recipient": recipient,
-        },
-        "cmd_name": "mint",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
-        csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -309,32 +259,22 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
-            "amount": amount,
-        },
-        "cmd_name": "burn",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
+    let ciphertext = client.encrypt_command(
         csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-
-    let res = Client::new()
-        .post(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::post::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        enclave_encryption_key,
+        &access_policy,
+        "burn",
+        json!({
+            "amount": amount,
+        }),
+    )?;
+    let res = client.send_command(ciphertext)?;
 
-    println!("Transaction hash: {:?}", res);
+    println!("Transaction hash: {}", res.tx_hash);
     Ok(())
 }
 
@@ -363,32 +303,22 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {
-            "spender": spender,
-        },
-        "state_name": "allowance",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
+    let ciphertext = client.encrypt_get_state(
         csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-    let res = Client::new()
-        .get(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::get::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        enclave_encryption_key,
+        &access_policy,
+        "approved",
+        json!({
+            "spender": spender,
+        }),
+    )?;
+    let res = client.get_state(ciphertext)?;
 
-    println!("Current State: {:?}", res);
+    println!("Current State: {:?}", res.state);
     Ok(())
 }
 
@@ -405,30 +335,20 @@ where
     R: Rng,
     CR: RngCore + CryptoRng,
 {
+    let client = StateRuntimeClient::connect(&anonify_url)?;
     let password = prompt_password(term)?;
     let keypair = get_keypair_from_keystore(root_dir, &password, index)?;
     let access_policy = Ed25519ChallengeResponse::new_from_keypair(keypair, rng);
-
-    let req = json!({
-        "access_policy": access_policy,
-        "runtime_params": {},
-        "state_name": "balance_of",
-    });
-    let ciphertext = SodiumCiphertext::encrypt(
+    let ciphertext = client.encrypt_get_state(
         csprng,
-        &enclave_encryption_key,
-        serde_json::to_vec(&req).unwrap(),
-    )
-    .map_err(|e| anyhow!("{:?}", e))?;
-    let res = Client::new()
-        .get(&format!("{}/api/v1/state", &anonify_url))
-        .json(&state_runtime_node_api::state::get::Request::new(
-            ciphertext,
-        ))
-        .send()?
-        .text()?;
+        enclave_encryption_key,
+        &access_policy,
+        "balance_of",
+        json!({}),
+    )?;
+    let res = client.get_state(ciphertext)?;
 
-    println!("Current State: {:?}", res);
+    println!("Current State: {:?}", res.state);
     Ok(())
 }
 
@@ -12,5 +12,7 @@ pub enum ClientError {
     #[error("{0}")]
     ReqwestError(#[from] reqwest::Error),
     #[error("{0}")]
+    StateRuntimeClientError(#[from] state_runtime_node_client::ClientError),
+    #[error("{0}")]
     AnyhowError(#[from] anyhow::Error),
 }
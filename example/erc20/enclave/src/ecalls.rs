@@ -1,11 +1,13 @@
-use crate::state_transition::{Runtime, MAX_MEM_SIZE};
+use crate::state_transition::{erc20::Runtime, MAX_MEM_SIZE};
+#[cfg(feature = "sgx")]
 use crate::ENCLAVE_CONTEXT;
 use anonify_ecall_types::cmd::*;
 use anonify_enclave::{context::AnonifyEnclaveContext, workflow::*};
-use anyhow::anyhow;
 use frame_common::crypto::Ed25519ChallengeResponse;
 use frame_enclave::{register_ecall, EnclaveEngine};
-use std::{ptr, vec::Vec};
+#[cfg(feature = "sgx")]
+use std::ptr;
+use std::vec::Vec;
 
 #[cfg(not(feature = "backup-enable"))]
 register_ecall!(
@@ -26,8 +28,20 @@ register_ecall!(
         REGISTER_NOTIFICATION_CMD,
         RegisterNotification<Ed25519ChallengeResponse>
     ),
+    (
+        UNREGISTER_NOTIFICATION_CMD,
+        UnregisterNotification<Ed25519ChallengeResponse>
+    ),
     (GET_ENCLAVE_ENCRYPTION_KEY_CMD, EncryptionKeyGetter),
     (SEND_REGISTER_REPORT_CMD, ReportRegistration),
+    // Get the state counter and the block number which the persisted states cover.
+    (GET_STATE_COUNTER_CMD, GetStateCounter),
+    (EXPORT_STATE_SNAPSHOT_CMD, StateSnapshotExporter),
+    (IMPORT_STATE_SNAPSHOT_CMD, StateSnapshotImporter),
+    // Get the schema of the runtime's commands, getters and memories.
+    (GET_RUNTIME_SCHEMA_CMD, GetRuntimeSchema),
+    // Reload the measurements of the key vault enclaves trusted by this enclave.
+    (RELOAD_TRUSTED_MEASUREMENTS_CMD, TrustedMeasurementsReloader),
 );
 
 #[cfg(feature = "backup-enable")]
@@ -49,8 +63,20 @@ register_ecall!(
         REGISTER_NOTIFICATION_CMD,
         RegisterNotification<Ed25519ChallengeResponse>
     ),
+    (
+        UNREGISTER_NOTIFICATION_CMD,
+        UnregisterNotification<Ed25519ChallengeResponse>
+    ),
     (GET_ENCLAVE_ENCRYPTION_KEY_CMD, EncryptionKeyGetter),
     (SEND_REGISTER_REPORT_CMD, ReportRegistration),
+    // Get the state counter and the block number which the persisted states cover.
+    (GET_STATE_COUNTER_CMD, GetStateCounter),
+    (EXPORT_STATE_SNAPSHOT_CMD, StateSnapshotExporter),
+    (IMPORT_STATE_SNAPSHOT_CMD, StateSnapshotImporter),
+    // Get the schema of the runtime's commands, getters and memories.
+    (GET_RUNTIME_SCHEMA_CMD, GetRuntimeSchema),
+    // Reload the measurements of the key vault enclaves trusted by this enclave.
+    (RELOAD_TRUSTED_MEASUREMENTS_CMD, TrustedMeasurementsReloader),
     (BACKUP_PATH_SECRET_ALL_CMD, PathSecretBackupper),
     (RECOVER_PATH_SECRET_ALL_CMD, PathSecretRecoverer),
 );
@@ -1,18 +1,27 @@
 #![crate_name = "anonifyenclave"]
-#![crate_type = "staticlib"]
-#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "sgx")]
 #[macro_use]
 extern crate sgx_tstd as std;
+#[cfg(feature = "software-enclave")]
+extern crate anyhow_std as anyhow;
+#[cfg(feature = "software-enclave")]
+extern crate bincode_std as bincode;
 
 mod ecalls;
 mod state_transition;
 
 use anonify_enclave::context::AnonifyEnclaveContext;
+#[cfg(feature = "software-enclave")]
+use frame_types::EnclaveStatus;
+#[cfg(feature = "sgx")]
 use once_cell::sync::Lazy;
+#[cfg(feature = "sgx")]
 use std::backtrace;
 
 const ANONIFY_MRENCLAVE_VERSION: usize = 0;
 
+#[cfg(feature = "sgx")]
 pub static ENCLAVE_CONTEXT: Lazy<AnonifyEnclaveContext> = Lazy::new(|| {
     backtrace::enable_backtrace(
         &*frame_config::ENCLAVE_SIGNED_SO,
@@ -22,3 +31,18 @@ pub static ENCLAVE_CONTEXT: Lazy<AnonifyEnclaveContext> = Lazy::new(|| {
     AnonifyEnclaveContext::new(ANONIFY_MRENCLAVE_VERSION)
         .expect("Failed to instantiate ENCLAVE_CONTEXT")
 });
+
+/// Instantiate an enclave running in the host process and return its ecall handler,
+/// which takes a command, an input and the max length of the output.
+/// Each enclave has its own context, as with enclaves created by the SGX runtime.
+#[cfg(feature = "software-enclave")]
+pub fn software_enclave(
+) -> anyhow::Result<impl Fn(u32, &mut [u8], usize) -> (EnclaveStatus, Vec<u8>) + Send + Sync> {
+    let ctx = AnonifyEnclaveContext::new(ANONIFY_MRENCLAVE_VERSION)?;
+
+    Ok(
+        move |command: u32, input: &mut [u8], output_max_len: usize| {
+            ecalls::handle_ecall(&ctx, command, input, output_max_len)
+        },
+    )
+}
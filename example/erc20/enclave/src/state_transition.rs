@@ -2,157 +2,171 @@ use frame_runtime::prelude::*;
 
 pub const MAX_MEM_SIZE: usize = 100;
 
-impl_memory! {
-    (0, "Balance", U64),
-    (1, "Approved", Approved),
-    (2, "TotalSupply", U64),
-    (3, "Owner", AccountId)
-}
-
-impl_runtime! {
-    pub fn construct(
-        self,
-        sender: AccountId,
-        total_supply: U64
-    ) {
-        let owner_account_id = update!(*OWNER_ACCOUNT_ID, "Owner", sender, AccountId);
-        let sender_balance = update!(sender, "Balance", total_supply, U64);
-        let total_supply = update!(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply, U64);
-
-        return_update![owner_account_id, sender_balance, total_supply]
-    }
-
-    pub fn transfer(
-        self,
-        sender: AccountId,
-        recipient: AccountId,
-        amount: U64
-    ) {
-        let sender_balance = self.get_map::<U64>(sender, "Balance")?;
-        let recipient_balance = self.get_map::<U64>(recipient, "Balance")?;
-
-        ensure!(sender_balance > amount, "transfer amount ({:?}) exceeds balance ({:?}).", amount, sender_balance);
-
-        let sender_update = update!(sender, "Balance", sender_balance - amount, U64);
-        let recipient_update = update!(recipient, "Balance", recipient_balance + amount, U64);
-
-        return_update![sender_update, recipient_update]
-    }
-
-    pub fn approve(
-        self,
-        owner: AccountId,
-        spender: AccountId,
-        amount: U64
-    ) {
-        let owner_balance = self.get_map::<U64>(owner, "Balance")?;
-        let mut owner_approved = self.get_map::<Approved>(owner, "Approved")?;
-
-        ensure!(
-            owner_approved.total() + amount <= owner_balance,
-            "approving amount exceeds balance and already approved."
-        );
-
-        owner_approved.approve(spender, amount);
-        let owner_approved_update = update!(owner, "Approved", owner_approved, Approved);
-        return_update![owner_approved_update]
-    }
-
-    pub fn transfer_from(
-        self,
-        sender: AccountId,
-        owner: AccountId,
-        recipient: AccountId,
-        amount: U64
-    ) {
-        let owner_balance = self.get_map::<U64>(owner, "Balance")?;
-        ensure!(
-            amount <= owner_balance,
-            "transferring amount exceeds owner's balance."
-        );
-
-        let mut owner_approved = self.get_map::<Approved>(owner, "Approved")?;
-        let approved_amount = owner_approved.allowance(&sender)
-            .ok_or_else(|| anyhow!("not enough amount approved."))?;
-        ensure!(
-            amount <= *approved_amount,
-            "transferring amount exceeds approved amount of sender."
-        );
-
-        owner_approved.consume(sender, amount)?;
-        let owner_approved_update = update!(owner, "Approved", owner_approved, Approved);
-
-        let recipient_balance = self.get_map::<U64>(recipient, "Balance")?;
-
-        let owner_balance_update = update!(owner, "Balance", owner_balance - amount, U64);
-        let recipient_balance_update = update!(recipient, "Balance", recipient_balance + amount, U64);
-
-        return_update![owner_approved_update, owner_balance_update, recipient_balance_update]
-    }
-
-    pub fn mint(
-        self,
-        executer: AccountId,
-        recipient: AccountId,
-        amount: U64
-    ) {
-        let owner_account_id = self.get_map::<AccountId>(*OWNER_ACCOUNT_ID, "Owner")?;
-        ensure!(executer == owner_account_id, "only owner can mint");
-
-        let recipient_balance = self.get_map::<U64>(recipient, "Balance")?;
-        let recipient_balance_update = update!(recipient, "Balance", recipient_balance + amount, U64);
-
-        let total_supply = self.get_map::<U64>(*OWNER_ACCOUNT_ID, "TotalSupply")?;
-        let total_supply_update = update!(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply + amount, U64);
-
-        return_update![recipient_balance_update, total_supply_update]
-    }
-
-    pub fn burn(
-        self,
-        sender: AccountId,
-        amount: U64
-    ) {
-        let balance = self.get_map::<U64>(sender, "Balance")?;
-        ensure!(balance >= amount, "not enough balance to burn");
-        let balance_update = update!(sender, "Balance", balance - amount, U64);
-
-        let total_supply = self.get_map::<U64>(*OWNER_ACCOUNT_ID, "TotalSupply")?;
-        let total_supply_update = update!(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply - amount, U64);
-
-        return_update![balance_update, total_supply_update]
-    }
-
-    pub fn balance_of(
-        self,
-        caller: AccountId
-    ) {
-        let balance = self.get_map::<U64>(caller, "Balance")?;
-        get_state![balance]
-    }
-
-    pub fn approved(
-        self,
-        caller: AccountId,
-        spender: AccountId
-    ) {
-        let approved = self.get_map::<Approved>(caller, "Approved")?;
-        get_state![approved.get(spender)]
-    }
-
-    pub fn total_supply(
-        self,
-        caller: AccountId
-    ) {
-        let total_supply = self.get_map::<U64>(*OWNER_ACCOUNT_ID, "TotalSupply")?;
-        get_state![total_supply]
+#[anonify_runtime]
+pub mod erc20 {
+    use super::*;
+
+    #[memory]
+    pub enum Memory {
+        #[mem(id = 0, notify)]
+        Balance(U64),
+        #[mem(id = 1)]
+        Approved(Approved),
+        #[mem(id = 2, notify)]
+        TotalSupply(U64),
+        #[mem(id = 3, notify)]
+        Owner(AccountId),
     }
 
-    pub fn owner(
-        self,
-        caller: AccountId
-    ) {
-        let owner = self.get_map::<AccountId>(*OWNER_ACCOUNT_ID, "Owner")?;
-        get_state![owner]
+    impl Runtime {
+        #[command]
+        pub fn construct(
+            self,
+            sender: AccountId,
+            total_supply: U64,
+        ) -> Result<ReturnState<StateType>> {
+            let owner_account_id = self.update(*OWNER_ACCOUNT_ID, "Owner", sender)?;
+            let sender_balance = self.update(sender, "Balance", total_supply)?;
+            let total_supply = self.update(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply)?;
+
+            return_update![owner_account_id, sender_balance, total_supply]
+        }
+
+        #[command]
+        pub fn transfer(
+            self,
+            sender: AccountId,
+            recipient: AccountId,
+            amount: U64,
+        ) -> Result<ReturnState<StateType>> {
+            let sender_balance = self.get_map(sender, "Balance")?;
+            let recipient_balance = self.get_map(recipient, "Balance")?;
+
+            ensure!(
+                sender_balance > amount,
+                "transfer amount ({:?}) exceeds balance ({:?}).",
+                amount,
+                sender_balance
+            );
+
+            let sender_update = self.update(sender, "Balance", sender_balance - amount)?;
+            let recipient_update = self.update(recipient, "Balance", recipient_balance + amount)?;
+
+            return_update![sender_update, recipient_update]
+        }
+
+        #[command]
+        pub fn approve(
+            self,
+            owner: AccountId,
+            spender: AccountId,
+            amount: U64,
+        ) -> Result<ReturnState<StateType>> {
+            let owner_balance = self.get_map(owner, "Balance")?;
+            let mut owner_approved = self.get_map(owner, "Approved")?;
+
+            ensure!(
+                owner_approved.total() + amount <= owner_balance,
+                "approving amount exceeds balance and already approved."
+            );
+
+            owner_approved.approve(spender, amount);
+            let owner_approved_update = self.update(owner, "Approved", owner_approved)?;
+            return_update![owner_approved_update]
+        }
+
+        #[command]
+        pub fn transfer_from(
+            self,
+            sender: AccountId,
+            owner: AccountId,
+            recipient: AccountId,
+            amount: U64,
+        ) -> Result<ReturnState<StateType>> {
+            let owner_balance = self.get_map(owner, "Balance")?;
+            ensure!(
+                amount <= owner_balance,
+                "transferring amount exceeds owner's balance."
+            );
+
+            let mut owner_approved = self.get_map(owner, "Approved")?;
+            let approved_amount = owner_approved
+                .allowance(&sender)
+                .ok_or_else(|| anyhow!("not enough amount approved."))?;
+            ensure!(
+                amount <= *approved_amount,
+                "transferring amount exceeds approved amount of sender."
+            );
+
+            owner_approved.consume(sender, amount)?;
+            let owner_approved_update = self.update(owner, "Approved", owner_approved)?;
+
+            let recipient_balance = self.get_map(recipient, "Balance")?;
+
+            let owner_balance_update = self.update(owner, "Balance", owner_balance - amount)?;
+            let recipient_balance_update =
+                self.update(recipient, "Balance", recipient_balance + amount)?;
+
+            return_update![
+                owner_approved_update,
+                owner_balance_update,
+                recipient_balance_update
+            ]
+        }
+
+        #[command]
+        pub fn mint(
+            self,
+            executer: AccountId,
+            recipient: AccountId,
+            amount: U64,
+        ) -> Result<ReturnState<StateType>> {
+            let owner_account_id = self.get_map(*OWNER_ACCOUNT_ID, "Owner")?;
+            ensure!(executer == owner_account_id, "only owner can mint");
+
+            let recipient_balance = self.get_map(recipient, "Balance")?;
+            let recipient_balance_update =
+                self.update(recipient, "Balance", recipient_balance + amount)?;
+
+            let total_supply = self.get_map(*OWNER_ACCOUNT_ID, "TotalSupply")?;
+            let total_supply_update =
+                self.update(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply + amount)?;
+
+            return_update![recipient_balance_update, total_supply_update]
+        }
+
+        #[command]
+        pub fn burn(self, sender: AccountId, amount: U64) -> Result<ReturnState<StateType>> {
+            let balance = self.get_map(sender, "Balance")?;
+            ensure!(balance >= amount, "not enough balance to burn");
+            let balance_update = self.update(sender, "Balance", balance - amount)?;
+
+            let total_supply = self.get_map(*OWNER_ACCOUNT_ID, "TotalSupply")?;
+            let total_supply_update =
+                self.update(*OWNER_ACCOUNT_ID, "TotalSupply", total_supply - amount)?;
+
+            return_update![balance_update, total_supply_update]
+        }
+
+        #[getter]
+        pub fn balance_of(self, caller: AccountId) -> Result<U64> {
+            self.get_map(caller, "Balance")
+        }
+
+        #[getter]
+        pub fn approved(self, caller: AccountId, spender: AccountId) -> Result<U64> {
+            let approved = self.get_map(caller, "Approved")?;
+            Ok(approved.get(spender))
+        }
+
+        #[getter]
+        pub fn total_supply(self, _caller: AccountId) -> Result<U64> {
+            self.get_map(*OWNER_ACCOUNT_ID, "TotalSupply")
+        }
+
+        #[getter]
+        pub fn owner(self, _caller: AccountId) -> Result<AccountId> {
+            self.get_map(*OWNER_ACCOUNT_ID, "Owner")
+        }
     }
 }
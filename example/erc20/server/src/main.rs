@@ -1,6 +1,7 @@
 use actix_web::{web, App, HttpServer};
 use anonify_eth_driver::eth::*;
 use frame_host::EnclaveDir;
+use futures::future::try_join;
 use state_runtime_node_server::{handlers::*, Server};
 use std::{env, io, sync::Arc};
 
@@ -19,8 +20,13 @@ async fn main() -> io::Result<()> {
         .expect("Failed to initialize enclave.");
     let eid = enclave.geteid();
     let server = Arc::new(Server::<EthDeployer, EthSender, EventWatcher>::new(eid));
+    // The operator endpoints are served only on the admin address, which must not be exposed.
+    let admin_url = env::var("ANONIFY_ADMIN_URL")
+        .ok()
+        .filter(|url| !url.is_empty());
+    let admin_server = server.clone();
 
-    HttpServer::new(move || {
+    let public = HttpServer::new(move || {
         App::new()
             .data(server.clone())
             .route(
@@ -43,6 +49,10 @@ async fn main() -> io::Result<()> {
                 "/api/v1/state",
                 web::get().to(handle_get_state::<EthDeployer, EthSender, EventWatcher>),
             )
+            .route(
+                "/api/v1/schema",
+                web::get().to(handle_get_schema::<EthDeployer, EthSender, EventWatcher>),
+            )
             .route(
                 "/api/v1/key_rotation",
                 web::post().to(handle_key_rotation::<EthDeployer, EthSender, EventWatcher>),
@@ -60,6 +70,19 @@ async fn main() -> io::Result<()> {
                 web::post()
                     .to(handle_register_notification::<EthDeployer, EthSender, EventWatcher>),
             )
+            .route(
+                "/api/v1/register_notification",
+                web::delete().to(handle_unregister_notification::<
+                    EthDeployer,
+                    EthSender,
+                    EventWatcher,
+                >),
+            )
+            .route(
+                "/api/v1/subscribe_notification",
+                web::get()
+                    .to(handle_subscribe_notification::<EthDeployer, EthSender, EventWatcher>),
+            )
             .route(
                 "/api/v1/enclave_encryption_key",
                 web::get()
@@ -69,9 +92,64 @@ async fn main() -> io::Result<()> {
                 "/api/v1/register_report",
                 web::post().to(handle_register_report::<EthDeployer, EthSender, EventWatcher>),
             )
+            .route(
+                "/api/v1/reload_trusted_measurements",
+                web::post().to(handle_reload_trusted_measurements::<
+                    EthDeployer,
+                    EthSender,
+                    EventWatcher,
+                >),
+            )
+            .route(
+                "/api/v1/state_snapshot",
+                web::get()
+                    .to(handle_export_state_snapshot::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/state_snapshot",
+                web::post()
+                    .to(handle_import_state_snapshot::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/state_root",
+                web::get().to(handle_get_state_root::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/state_root",
+                web::post().to(handle_verify_state_root::<EthDeployer, EthSender, EventWatcher>),
+            )
     })
     .bind(anonify_url)?
     .workers(num_workers)
-    .run()
-    .await
+    .run();
+
+    let admin_url = match admin_url {
+        Some(admin_url) => admin_url,
+        None => return public.await,
+    };
+    let admin = HttpServer::new(move || {
+        App::new()
+            .data(admin_server.clone())
+            .route(
+                "/api/v1/dead_letters",
+                web::get().to(handle_list_dead_letters::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/dead_letters/metrics",
+                web::get().to(handle_dead_letter_metrics::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/dead_letter/{id}",
+                web::get().to(handle_get_dead_letter::<EthDeployer, EthSender, EventWatcher>),
+            )
+            .route(
+                "/api/v1/dead_letter/{id}",
+                web::post().to(handle_retry_dead_letter::<EthDeployer, EthSender, EventWatcher>),
+            )
+    })
+    .bind(admin_url)?
+    .workers(1)
+    .run();
+
+    try_join(public, admin).await.map(|_| ())
 }
@@ -0,0 +1,93 @@
+//! Chain-neutral types exchanged with blockchain backends,
+//! so that the host side doesn't depend on a specific blockchain.
+
+use crate::local_anyhow::{anyhow, Error};
+use crate::localstd::{fmt, str::FromStr, string::String, vec::Vec};
+use crate::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+macro_rules! impl_hex_bytes {
+    ($name:ident) => {
+        impl $name {
+            pub fn from_bytes(bytes: &[u8]) -> Self {
+                $name(bytes.to_vec())
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0[..]
+            }
+
+            pub fn into_vec(self) -> Vec<u8> {
+                self.0
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(bytes: Vec<u8>) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "0x{}", hex::encode(&self.0))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}(0x{})", stringify!($name), hex::encode(&self.0))
+            }
+        }
+
+        /// Parse a hex string with or without the `0x` prefix.
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.strip_prefix("0x").unwrap_or(s);
+                let bytes = hex::decode(s)
+                    .map_err(|e| anyhow!("Invalid {}: {:?}", stringify!($name), e))?;
+                Ok($name(bytes))
+            }
+        }
+
+        /// Serialized as a `0x`-prefixed hex string, which is compatible with the hash types of web3.
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+/// An address of an account or a contract on a blockchain.
+/// Its length depends on the blockchain, e.g. 20 bytes on Ethereum.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChainAddress(Vec<u8>);
+
+impl_hex_bytes!(ChainAddress);
+
+impl From<web3::types::Address> for ChainAddress {
+    fn from(address: web3::types::Address) -> Self {
+        ChainAddress::from_bytes(address.as_bytes())
+    }
+}
+
+/// A hash of a transaction sent to a blockchain.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct TxHash(Vec<u8>);
+
+impl_hex_bytes!(TxHash);
+
+impl From<web3::types::H256> for TxHash {
+    fn from(hash: web3::types::H256) -> Self {
+        TxHash::from_bytes(hash.as_bytes())
+    }
+}
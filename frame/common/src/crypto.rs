@@ -1,4 +1,5 @@
 use crate::bincode;
+use crate::error::EcallError;
 use crate::local_anyhow::{anyhow, Error};
 use crate::local_once_cell::sync::Lazy;
 use crate::localstd::{
@@ -117,7 +118,9 @@ impl StateDecoder for AccountId {
 impl AccountId {
     /// Get a user account_id only if the verification of signature returns true.
     pub fn from_sig(msg: &[u8], sig: &Signature, pubkey: &PublicKey) -> Result<Self, Error> {
-        pubkey.verify(msg, &sig).map_err(|e| anyhow!("{}", e))?;
+        pubkey
+            .verify(msg, &sig)
+            .map_err(|e| EcallError::InvalidSignature(format!("{}", e)))?;
 
         Ok(Self::from_pubkey(&pubkey))
     }
@@ -266,6 +269,10 @@ impl AccessPolicy for NoAuth {
     fn into_account_id(&self) -> AccountId {
         self.account_id
     }
+
+    fn verify_challenge(&self, _msg: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 impl NoAuth {
@@ -308,6 +315,15 @@ impl AccessPolicy for Ed25519ChallengeResponse {
     fn into_account_id(&self) -> AccountId {
         AccountId::from_pubkey(&self.pubkey())
     }
+
+    /// The challenge must be the sha256 hash of the message.
+    fn verify_challenge(&self, msg: &[u8]) -> Result<(), Error> {
+        if self.challenge != Sha256::hash(msg).as_array() {
+            return Err(anyhow!("The challenge doesn't commit to the message"));
+        }
+
+        Ok(())
+    }
 }
 
 impl Ed25519ChallengeResponse {
@@ -320,6 +336,15 @@ impl Ed25519ChallengeResponse {
         Self::new(sig, keypair.public, challenge)
     }
 
+    /// Sign the challenge committing to the message, which is verified by `verify_challenge`.
+    #[cfg(feature = "std")]
+    pub fn new_with_message(keypair: &Keypair, msg: &[u8]) -> Self {
+        let challenge = Sha256::hash(msg).as_array();
+        let sig = keypair.sign(&challenge[..]);
+
+        Self::new(sig, keypair.public, challenge)
+    }
+
     #[cfg(feature = "std")]
     fn inner_new_from_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         let keypair = Keypair::generate(rng);
@@ -381,9 +406,12 @@ impl Ed25519ChallengeResponse {
     }
 
     pub fn verify_sig(&self) -> Result<(), Error> {
-        self.pubkey()
-            .verify(&self.challenge, &self.sig())
-            .map_err(|e| anyhow!("{:?}", e))?;
+        let invalid_signature = |e| EcallError::InvalidSignature(format!("{}", e));
+        let pubkey = PublicKey::from_bytes(&self.pubkey).map_err(invalid_signature)?;
+        let sig = Signature::from_bytes(&self.sig).map_err(invalid_signature)?;
+        pubkey
+            .verify(&self.challenge, &sig)
+            .map_err(invalid_signature)?;
 
         Ok(())
     }
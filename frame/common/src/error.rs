@@ -0,0 +1,101 @@
+//! Errors of ecalls which are returned to the host through the output buffer,
+//! so that the host can tell why the enclave rejected a request.
+
+use crate::local_anyhow::Error;
+use crate::localstd::{fmt, string::String};
+use crate::serde::{Deserialize, Serialize};
+use crate::state_types::UserCounter;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub enum EcallError {
+    /// The user counter of the command is not the next one of the user
+    InvalidUserCounter {
+        received: UserCounter,
+        expected: UserCounter,
+    },
+    /// The signature of the access policy is invalid
+    InvalidSignature(String),
+    /// The input couldn't be decrypted or decoded
+    Decode(String),
+    /// The access policy rejected the request
+    PolicyFailure(String),
+    /// The state runtime returned an error, e.g. by `ensure!` in a command or a getter
+    StateTransition(String),
+    /// The group key's keychain couldn't be ratcheted to the generation of the message
+    RatchetDesync(String),
+    Internal(String),
+}
+
+impl EcallError {
+    /// A stable identifier of the kind of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            EcallError::InvalidUserCounter { .. } => "invalid_user_counter",
+            EcallError::InvalidSignature(_) => "invalid_signature",
+            EcallError::Decode(_) => "decode_failure",
+            EcallError::PolicyFailure(_) => "policy_failure",
+            EcallError::StateTransition(_) => "state_transition_failure",
+            EcallError::RatchetDesync(_) => "ratchet_desync",
+            EcallError::Internal(_) => "internal",
+        }
+    }
+
+    /// The HTTP status code the servers respond with for the error
+    pub fn http_status(&self) -> u16 {
+        match self {
+            EcallError::InvalidUserCounter { .. } => 409,
+            EcallError::InvalidSignature(_) => 401,
+            EcallError::Decode(_) => 400,
+            EcallError::PolicyFailure(_) => 403,
+            EcallError::StateTransition(_) => 422,
+            // The node needs to catch up with the group key before processing messages.
+            EcallError::RatchetDesync(_) => 503,
+            EcallError::Internal(_) => 500,
+        }
+    }
+
+    /// Find the `EcallError` in the chain of causes.
+    pub fn find(err: &Error) -> Option<&EcallError> {
+        err.chain().find_map(|e| e.downcast_ref::<EcallError>())
+    }
+
+    /// Classify the error by `kind` unless it has already been classified.
+    pub fn classify<F>(err: Error, kind: F) -> Error
+    where
+        F: FnOnce(String) -> EcallError,
+    {
+        if Self::find(&err).is_some() {
+            err
+        } else {
+            kind(format!("{:#}", err)).into()
+        }
+    }
+
+    /// Extract the `EcallError` from the error. Unclassified errors are internal errors.
+    pub fn from_error(err: &Error) -> EcallError {
+        Self::find(err)
+            .cloned()
+            .unwrap_or_else(|| EcallError::Internal(format!("{:#}", err)))
+    }
+}
+
+impl fmt::Display for EcallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EcallError::InvalidUserCounter { received, expected } => write!(
+                f,
+                "Received UserCounter is {:?}, but expected is {:?}",
+                received, expected
+            ),
+            EcallError::InvalidSignature(msg) => write!(f, "Invalid signature: {}", msg),
+            EcallError::Decode(msg) => write!(f, "Failed to decode the input: {}", msg),
+            EcallError::PolicyFailure(msg) => write!(f, "Access policy failure: {}", msg),
+            EcallError::StateTransition(msg) => write!(f, "State transition failure: {}", msg),
+            EcallError::RatchetDesync(msg) => write!(f, "Ratchet desync: {}", msg),
+            EcallError::Internal(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl crate::localstd::error::Error for EcallError {}
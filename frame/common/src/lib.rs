@@ -35,7 +35,11 @@ use serde_std as serde;
 #[cfg(feature = "sgx")]
 use sgx_anyhow as local_anyhow;
 
+#[cfg(feature = "std")]
+pub mod chain_types;
 pub mod crypto;
+pub mod error;
+pub mod schema;
 pub mod state_types;
 pub mod traits;
 
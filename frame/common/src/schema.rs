@@ -0,0 +1,174 @@
+//! A machine-readable schema of a state runtime.
+//! The types of parameters, return values and memories are described as JSON Schema,
+//! so that clients can validate commands before encrypting them.
+
+use crate::crypto::AccountId;
+use crate::localstd::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::serde::{Deserialize, Serialize};
+use crate::serde_json::{json, Map};
+
+pub use crate::serde_json::Value;
+
+/// A type which describes its JSON representation as JSON Schema.
+pub trait JsonSchema {
+    fn json_schema() -> Value;
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct RuntimeSchema {
+    pub commands: Vec<FnSchema>,
+    pub getters: Vec<FnSchema>,
+    pub memories: Vec<MemorySchema>,
+}
+
+impl RuntimeSchema {
+    pub fn command(&self, name: &str) -> Option<&FnSchema> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    pub fn getter(&self, name: &str) -> Option<&FnSchema> {
+        self.getters.iter().find(|g| g.name == name)
+    }
+}
+
+/// A command or a getter of the runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct FnSchema {
+    pub name: String,
+    /// The parameters given as `runtime_params`, excluding the sender's account id
+    pub params: Vec<ParamSchema>,
+    /// The type returned by a getter
+    pub returns: Option<TypeSchema>,
+}
+
+impl FnSchema {
+    /// JSON Schema of the `runtime_params` object.
+    pub fn params_json_schema(&self) -> Value {
+        let mut properties = Map::new();
+        for param in &self.params {
+            properties.insert(param.name.clone(), param.ty.json_schema.clone());
+        }
+        let required: Vec<&str> = self.params.iter().map(|p| p.name.as_str()).collect();
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct ParamSchema {
+    pub name: String,
+    pub ty: TypeSchema,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct MemorySchema {
+    pub name: String,
+    pub id: u32,
+    /// Whether updates of the memory are notified to the registered users
+    pub notify: bool,
+    pub ty: TypeSchema,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct TypeSchema {
+    /// The type name in the runtime definition
+    pub rust_type: String,
+    pub json_schema: Value,
+}
+
+impl TypeSchema {
+    pub fn of<T: JsonSchema>(rust_type: &str) -> Self {
+        TypeSchema {
+            rust_type: rust_type.to_string(),
+            json_schema: T::json_schema(),
+        }
+    }
+}
+
+pub fn integer_schema(max: u64) -> Value {
+    json!({
+        "type": "integer",
+        "minimum": 0,
+        "maximum": max,
+    })
+}
+
+pub fn array_schema(items: Value, len: Option<usize>) -> Value {
+    match len {
+        Some(len) => json!({
+            "type": "array",
+            "items": items,
+            "minItems": len,
+            "maxItems": len,
+        }),
+        None => json!({
+            "type": "array",
+            "items": items,
+        }),
+    }
+}
+
+/// An object whose keys are arbitrary and values follow the schema.
+pub fn map_schema(values: Value) -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": values,
+    })
+}
+
+macro_rules! impl_uint_schema {
+    ($($t:ty),*) => {
+        $(
+            impl JsonSchema for $t {
+                fn json_schema() -> Value {
+                    integer_schema(<$t>::MAX as u64)
+                }
+            }
+        )*
+    };
+}
+
+impl_uint_schema!(u8, u16, u32, u64);
+
+impl JsonSchema for bool {
+    fn json_schema() -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+impl JsonSchema for String {
+    fn json_schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> Value {
+        array_schema(T::json_schema(), None)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Value {
+        json!({ "anyOf": [T::json_schema(), { "type": "null" }] })
+    }
+}
+
+impl JsonSchema for AccountId {
+    fn json_schema() -> Value {
+        array_schema(u8::json_schema(), Some(AccountId::default().0.len()))
+    }
+}
@@ -1,11 +1,11 @@
 use crate::bincode;
-use crate::crypto::AccountId;
+use crate::crypto::{AccountId, Sha256};
 use crate::local_anyhow::Result;
 use crate::localstd::vec::Vec;
 use crate::serde::{Deserialize, Serialize};
 use crate::serde_bytes;
 use crate::serde_json;
-use crate::traits::State;
+use crate::traits::{Hash256, State};
 
 pub trait RawState: Clone + Default {}
 
@@ -127,6 +127,62 @@ impl StateCounter {
     pub fn is_increment(self, other: StateCounter) -> bool {
         self.increment() == other
     }
+
+    pub fn as_raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// A root of the Merkle tree over all user states.
+/// Enclaves which reached the same state have the same root.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialOrd, PartialEq, Default, Eq, Ord, Hash,
+)]
+#[serde(crate = "crate::serde")]
+pub struct StateRoot([u8; 32]);
+
+impl StateRoot {
+    pub fn new(root: [u8; 32]) -> Self {
+        StateRoot(root)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    pub fn into_array(self) -> [u8; 32] {
+        self.0
+    }
+}
+
+/// A hash chain over the payloads of the events consumed by the enclave,
+/// `H(previous hash || state counter || payload)`, so that the host can check
+/// that the enclave has consumed the same events as it recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, Eq, Hash)]
+#[serde(crate = "crate::serde")]
+pub struct PayloadsHash([u8; 32]);
+
+impl PayloadsHash {
+    pub fn new(hash: [u8; 32]) -> Self {
+        PayloadsHash(hash)
+    }
+
+    /// Chain the payload of the event at the state counter.
+    pub fn next(&self, state_counter: StateCounter, payload: &[u8]) -> Self {
+        let mut inp = self.0.to_vec();
+        inp.extend_from_slice(&state_counter.as_raw().to_be_bytes());
+        inp.extend_from_slice(payload);
+
+        PayloadsHash(Sha256::hash(&inp).as_array())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    pub fn into_array(self) -> [u8; 32] {
+        self.0
+    }
 }
 
 /// A counter that guarantees idempotency and order of messages from users.
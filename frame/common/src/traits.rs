@@ -12,6 +12,10 @@ pub trait AccessPolicy: Clone + Debug + DeserializeOwned + Serialize + Default {
     fn verify(&self) -> Result<()>;
 
     fn into_account_id(&self) -> AccountId;
+
+    /// Verify that the signed challenge commits to the message,
+    /// so that the parameters in the message can't be replaced by others.
+    fn verify_challenge(&self, msg: &[u8]) -> Result<()>;
 }
 
 pub trait EcallInput {}
@@ -48,7 +52,11 @@ pub trait StateDecoder: State {
 
 /// A converter from memory name to memory id
 pub trait MemNameConverter: Debug {
-    fn as_id(name: &str) -> MemId;
+    /// Returns `None` if the memory name is not declared.
+    fn as_id(name: &str) -> Option<MemId>;
+
+    /// Whether updates of the memory are notified to the registered users.
+    fn is_notified(mem_id: MemId) -> bool;
 }
 
 pub trait IntoVec {
@@ -5,6 +5,7 @@ use crate::localstd::{
     env,
     ffi::OsStr,
     path::PathBuf,
+    str::FromStr,
     string::{String, ToString},
 };
 #[cfg(feature = "sgx")]
@@ -24,9 +25,84 @@ pub static RETRY_DELAY_MILLS: Lazy<u64> = Lazy::new(|| {
         .unwrap()
 });
 
+/// The type of remote attestation to attest the enclaves, "epid" (default) or "dcap".
+/// Both types are accepted when verifying the attestations of others.
+/// An invalid value is returned as an error when attesting.
+pub static ATTESTATION_TYPE: Lazy<Result<AttestationType, String>> = Lazy::new(|| {
+    env::var("ATTESTATION_TYPE")
+        .ok()
+        .filter(|ty| !ty.is_empty())
+        .map_or_else(|| Ok(AttestationType::default()), |ty| ty.parse())
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationType {
+    /// EPID attestation verified by Intel Attestation Service
+    Epid,
+    /// ECDSA attestation verified against the collateral of Intel SGX DCAP
+    Dcap,
+}
+
+impl Default for AttestationType {
+    fn default() -> Self {
+        AttestationType::Epid
+    }
+}
+
+impl FromStr for AttestationType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "epid" => Ok(AttestationType::Epid),
+            "dcap" => Ok(AttestationType::Dcap),
+            _ => Err(format!("Invalid attestation type: {}", s)),
+        }
+    }
+}
+
+/// The seconds for which an attested certificate of mutual attested TLS is used before attesting again.
+/// It is bounded by ATTESTATION_MAX_REPORT_AGE_SECS.
+pub static ATTESTATION_CACHE_TTL_SECS: Lazy<u64> = Lazy::new(|| {
+    env::var("ATTESTATION_CACHE_TTL_SECS")
+        .unwrap_or_else(|_| "3600".to_string())
+        .parse::<u64>()
+        .unwrap()
+});
+
+/// The number of worker threads serving the sessions of mutual attested TLS concurrently.
+/// TCSNum of the enclave has to cover them in addition to the thread accepting the sessions.
+pub static MRA_TLS_SERVER_WORKERS: Lazy<usize> = Lazy::new(|| {
+    env::var("MRA_TLS_SERVER_WORKERS")
+        .unwrap_or_else(|_| "2".to_string())
+        .parse::<usize>()
+        .unwrap()
+});
+
+/// The maximum bytes of a request or a response of mutual attested TLS, which are split into 4 KB frames.
+pub static MRA_TLS_MAX_MESSAGE_LEN: Lazy<usize> = Lazy::new(|| {
+    env::var("MRA_TLS_MAX_MESSAGE_LEN")
+        .unwrap_or_else(|_| "524288".to_string())
+        .parse::<usize>()
+        .unwrap()
+});
+
 pub static PATH_SECRETS_DIR: Lazy<String> =
     Lazy::new(|| env::var("PATH_SECRETS_DIR").unwrap_or(".anonify/pathsecrets".to_string()));
 
+/// The directory persisting sealed user states relative to PJ_ROOT_DIR.
+/// If it is not set, user states are kept only in memory.
+pub static USER_STATE_DIR: Lazy<Option<String>> =
+    Lazy::new(|| env::var("USER_STATE_DIR").ok().filter(|dir| !dir.is_empty()));
+
+/// The number of state transitions between snapshots of persisted user states.
+pub static USER_STATE_SNAPSHOT_INTERVAL: Lazy<u32> = Lazy::new(|| {
+    env::var("USER_STATE_SNAPSHOT_INTERVAL")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse::<u32>()
+        .unwrap()
+});
+
 pub static PJ_ROOT_DIR: Lazy<PathBuf> = Lazy::new(|| {
     let mut current_dir = env::current_dir().unwrap();
     loop {
@@ -90,6 +166,50 @@ pub static KEY_VAULT_ENCLAVE_MEASUREMENT: Lazy<EnclaveMeasurement> = Lazy::new(|
     EnclaveMeasurement::new_from_dumpfile(content)
 });
 
+/// The file relative to PJ_ROOT_DIR listing the measurements of the anonify enclaves trusted by the key vault.
+/// If it is not set, only ANONIFY_ENCLAVE_MEASUREMENT is trusted.
+pub static TRUSTED_ANONIFY_MEASUREMENTS_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| path_from_root("TRUSTED_ANONIFY_MEASUREMENTS_PATH"));
+
+/// The file relative to PJ_ROOT_DIR listing the measurements of the key vault enclaves trusted by the anonify nodes.
+/// If it is not set, only KEY_VAULT_ENCLAVE_MEASUREMENT is trusted.
+pub static TRUSTED_KEY_VAULT_MEASUREMENTS_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| path_from_root("TRUSTED_KEY_VAULT_MEASUREMENTS_PATH"));
+
+/// The file relative to PJ_ROOT_DIR persisting the event cache of the state runtime node.
+/// If it is not set, the event cache is kept only in memory.
+pub static EVENT_CACHE_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| path_from_root("EVENT_CACHE_PATH"));
+
+/// The file relative to PJ_ROOT_DIR persisting the events skipped because of errors in the enclave.
+/// If it is not set, they are kept only in memory.
+pub static DEAD_LETTER_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| path_from_root("DEAD_LETTER_PATH"));
+
+/// The number of fetched events waiting for their preceding events at most.
+/// If it is exceeded, the events furthest from being processed are dropped.
+pub static EVENT_REORDER_LIMIT: Lazy<usize> = Lazy::new(|| {
+    env::var("EVENT_REORDER_LIMIT")
+        .unwrap_or_else(|_| "1024".to_string())
+        .parse::<usize>()
+        .unwrap()
+});
+
+/// The seconds for which a fetched event waits for its preceding events at most before being dropped.
+pub static EVENT_REORDER_TIMEOUT_SECS: Lazy<u64> = Lazy::new(|| {
+    env::var("EVENT_REORDER_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse::<u64>()
+        .unwrap()
+});
+
+fn path_from_root(key: &str) -> Option<PathBuf> {
+    let path = env::var(key).ok().filter(|path| !path.is_empty())?;
+    let mut file_path = PJ_ROOT_DIR.clone();
+    file_path.push(path);
+    Some(file_path)
+}
+
 #[cfg(feature = "sgx")]
 pub static IAS_ROOT_CERT: Lazy<Vec<u8>> = Lazy::new(|| {
     let ias_root_cert_path = env::var("IAS_ROOT_CERT_PATH").expect("IAS_ROOT_CERT_PATH is not set");
@@ -100,3 +220,38 @@ pub static IAS_ROOT_CERT: Lazy<Vec<u8>> = Lazy::new(|| {
     let pem = pem::parse(ias_root_cert).expect("Cannot parse PEM File");
     pem.contents
 });
+
+/// The root certificate of DCAP attestations (Intel SGX Provisioning Certification Root CA).
+/// If DCAP_ROOT_CERT_PATH is not set, DCAP attestations are rejected.
+/// A certificate which can't be read is returned as an error when verifying attestations.
+#[cfg(feature = "sgx")]
+pub static DCAP_ROOT_CERT: Lazy<Result<Option<Vec<u8>>, String>> = Lazy::new(|| {
+    let dcap_root_cert_path = match env::var("DCAP_ROOT_CERT_PATH") {
+        Ok(path) if !path.is_empty() => path,
+        _ => return Ok(None),
+    };
+    let mut file_path = PJ_ROOT_DIR.clone();
+    file_path.push(&dcap_root_cert_path);
+
+    let dcap_root_cert = crate::localstd::untrusted::fs::read(file_path).map_err(|e| {
+        format!(
+            "Failed to read DCAP_ROOT_CERT_PATH {}: {}",
+            dcap_root_cert_path, e
+        )
+    })?;
+    let pem = pem::parse(dcap_root_cert).map_err(|e| {
+        format!(
+            "Failed to parse DCAP_ROOT_CERT_PATH {}: {:?}",
+            dcap_root_cert_path, e
+        )
+    })?;
+    Ok(Some(pem.contents))
+});
+
+/// The directory relative to PJ_ROOT_DIR which has the collateral of DCAP attestations,
+/// `tcb_info.json`, `qe_identity.json` and `tcb_signing_chain.pem` fetched from Intel PCS or PCCS.
+pub static DCAP_COLLATERAL_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    let mut dir = PJ_ROOT_DIR.clone();
+    dir.push(env::var("DCAP_COLLATERAL_DIR").unwrap_or_else(|_| ".anonify/dcap".to_string()));
+    dir
+});
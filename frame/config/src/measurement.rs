@@ -1,7 +1,7 @@
 use crate::localstd::{string::String, vec::Vec};
 
 #[cfg(feature = "sgx")]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EnclaveMeasurement {
     mr_signer: [u8; 32],
     mr_enclave: [u8; 32],
@@ -9,6 +9,13 @@ pub struct EnclaveMeasurement {
 
 #[cfg(feature = "sgx")]
 impl EnclaveMeasurement {
+    pub fn new(mr_signer: [u8; 32], mr_enclave: [u8; 32]) -> Self {
+        Self {
+            mr_signer,
+            mr_enclave,
+        }
+    }
+
     pub fn new_from_dumpfile(content: String) -> Self {
         let lines: Vec<&str> = content.split("\n").collect();
         let mr_signer_index = lines
@@ -0,0 +1,263 @@
+#!/usr/bin/env python3
+"""Generate a DCAP quote and its collateral signed by a test root CA instead of Intel SGX Root CA.
+
+The certificates and the signed JSON follow the formats of Intel PCS (TCB info v2, QE identity v2),
+so that the verifier can be tested without SGX machines or the access to PCS.
+Requires the `cryptography` package.
+"""
+
+import datetime
+import hashlib
+import json
+import os
+import struct
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.x509.oid import NameOID
+
+OUT_DIR = os.path.dirname(os.path.abspath(__file__))
+NOT_BEFORE = datetime.datetime(2021, 1, 1, tzinfo=datetime.timezone.utc)
+NOT_AFTER = datetime.datetime(2121, 1, 1, tzinfo=datetime.timezone.utc)
+
+SGX_EXTENSIONS_OID = "1.2.840.113741.1.13.1"
+INTEL_QE_VENDOR_ID = bytes.fromhex("939a7233f79c4ca9940a0db3957f0607")
+FMSPC = bytes.fromhex("00906ea10000")
+PCE_ID = bytes.fromhex("0000")
+CPUSVN = bytes([2, 2, 2, 2, 4, 1, 128, 4, 0, 0, 0, 0, 0, 0, 0, 0])
+PCESVN = 10
+QE_MRSIGNER = bytes.fromhex("8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff")
+QE_ISVSVN = 5
+
+
+def name(cn):
+    return x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, cn)])
+
+
+def cert(subject, key, issuer, issuer_key, ca, extensions=()):
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(name(subject))
+        .issuer_name(name(issuer))
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(NOT_BEFORE)
+        .not_valid_after(NOT_AFTER)
+        .add_extension(x509.BasicConstraints(ca=ca, path_length=None), critical=True)
+        .add_extension(
+            x509.KeyUsage(
+                digital_signature=not ca,
+                content_commitment=not ca,
+                key_encipherment=False,
+                data_encipherment=False,
+                key_agreement=False,
+                key_cert_sign=ca,
+                crl_sign=ca,
+                encipher_only=False,
+                decipher_only=False,
+            ),
+            critical=True,
+        )
+    )
+    for ext in extensions:
+        builder = builder.add_extension(ext, critical=False)
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def pem(*certs):
+    return b"".join(c.public_bytes(serialization.Encoding.PEM) for c in certs).decode()
+
+
+def der_len(n):
+    if n < 0x80:
+        return bytes([n])
+    b = n.to_bytes((n.bit_length() + 7) // 8, "big")
+    return bytes([0x80 | len(b)]) + b
+
+
+def tlv(tag, content):
+    return bytes([tag]) + der_len(len(content)) + content
+
+
+def oid(dotted):
+    parts = [int(p) for p in dotted.split(".")]
+    body = bytes([parts[0] * 40 + parts[1]])
+    for p in parts[2:]:
+        enc = [p & 0x7F]
+        p >>= 7
+        while p:
+            enc.append(0x80 | (p & 0x7F))
+            p >>= 7
+        body += bytes(reversed(enc))
+    return tlv(0x06, body)
+
+
+def integer(n):
+    b = n.to_bytes((n.bit_length() + 8) // 8, "big")
+    return tlv(0x02, b)
+
+
+def sgx_extensions():
+    seq = lambda *items: tlv(0x30, b"".join(items))
+    tcb = [seq(oid(SGX_EXTENSIONS_OID + ".2.%d" % (i + 1)), integer(svn)) for i, svn in enumerate(CPUSVN)]
+    tcb.append(seq(oid(SGX_EXTENSIONS_OID + ".2.17"), integer(PCESVN)))
+    tcb.append(seq(oid(SGX_EXTENSIONS_OID + ".2.18"), tlv(0x04, CPUSVN)))
+    return seq(
+        seq(oid(SGX_EXTENSIONS_OID + ".1"), tlv(0x04, bytes(16))),
+        seq(oid(SGX_EXTENSIONS_OID + ".2"), seq(*tcb)),
+        seq(oid(SGX_EXTENSIONS_OID + ".3"), tlv(0x04, PCE_ID)),
+        seq(oid(SGX_EXTENSIONS_OID + ".4"), tlv(0x04, FMSPC)),
+        seq(oid(SGX_EXTENSIONS_OID + ".5"), tlv(0x0A, b"\x00")),
+    )
+
+
+def raw_sign(key, msg):
+    r, s = decode_dss_signature(key.sign(msg, ec.ECDSA(hashes.SHA256())))
+    return r.to_bytes(32, "big") + s.to_bytes(32, "big")
+
+
+def raw_pubkey(key):
+    return key.public_key().public_bytes(
+        serialization.Encoding.X962, serialization.PublicFormat.UncompressedPoint
+    )[1:]
+
+
+def report_body(cpusvn, attributes, mrenclave, mrsigner, isv_prod_id, isv_svn, report_data):
+    body = (
+        cpusvn
+        + struct.pack("<I", 0)
+        + bytes(12)
+        + bytes(16)
+        + attributes
+        + mrenclave
+        + bytes(32)
+        + mrsigner
+        + bytes(32)
+        + bytes(64)
+        + struct.pack("<HHH", isv_prod_id, isv_svn, 0)
+        + bytes(42)
+        + bytes(16)
+        + report_data
+    )
+    assert len(body) == 384
+    return body
+
+
+def signed_json(field, body, key):
+    # The signature is calculated over the exact bytes of the body
+    raw = json.dumps(body, separators=(",", ":"))
+    sig = raw_sign(key, raw.encode()).hex()
+    return '{"%s":%s,"signature":"%s"}' % (field, raw, sig)
+
+
+def tcb_level(svns, pcesvn, status):
+    tcb = {"sgxtcbcomp%02dsvn" % (i + 1): svn for i, svn in enumerate(svns)}
+    tcb["pcesvn"] = pcesvn
+    return {"tcb": tcb, "tcbDate": "2020-11-11T00:00:00Z", "tcbStatus": status}
+
+
+def main():
+    curve = ec.SECP256R1()
+    root_key = ec.generate_private_key(curve)
+    root = cert("Anonify Test SGX Root CA", root_key, "Anonify Test SGX Root CA", root_key, True)
+    platform_ca_key = ec.generate_private_key(curve)
+    platform_ca = cert("Anonify Test SGX PCK Platform CA", platform_ca_key, "Anonify Test SGX Root CA", root_key, True)
+    pck_key = ec.generate_private_key(curve)
+    pck = cert(
+        "Anonify Test SGX PCK Certificate",
+        pck_key,
+        "Anonify Test SGX PCK Platform CA",
+        platform_ca_key,
+        False,
+        [x509.UnrecognizedExtension(x509.ObjectIdentifier(SGX_EXTENSIONS_OID), sgx_extensions())],
+    )
+    tcb_signing_key = ec.generate_private_key(curve)
+    tcb_signing = cert("Anonify Test SGX TCB Signing", tcb_signing_key, "Anonify Test SGX Root CA", root_key, False)
+
+    # The quote of the enclave
+    attest_key = ec.generate_private_key(curve)
+    attest_pubkey = raw_pubkey(attest_key)
+    qe_auth_data = bytes(range(32))
+    qe_report_data = hashlib.sha256(attest_pubkey + qe_auth_data).digest() + bytes(32)
+    qe_report = report_body(CPUSVN, bytes.fromhex("1100000000000000e700000000000000"), bytes(32), QE_MRSIGNER, 1, QE_ISVSVN, qe_report_data)
+    header = struct.pack("<HHIHH", 3, 2, 0, QE_ISVSVN, PCESVN) + INTEL_QE_VENDOR_ID + bytes(20)
+    isv_report = report_body(
+        CPUSVN,
+        bytes.fromhex("0700000000000000e700000000000000"),
+        bytes(range(32)),
+        bytes(range(32, 64)),
+        0,
+        1,
+        bytes(range(64)),
+    )
+    cert_data = pem(pck, platform_ca, root).encode()
+    signature_data = (
+        raw_sign(attest_key, header + isv_report)
+        + attest_pubkey
+        + qe_report
+        + raw_sign(pck_key, qe_report)
+        + struct.pack("<H", len(qe_auth_data))
+        + qe_auth_data
+        + struct.pack("<HI", 5, len(cert_data))
+        + cert_data
+    )
+    quote = header + isv_report + struct.pack("<I", len(signature_data)) + signature_data
+
+    def tcb_info(levels):
+        return {
+            "version": 2,
+            "issueDate": "2021-01-01T00:00:00Z",
+            "nextUpdate": "2100-01-01T00:00:00Z",
+            "fmspc": FMSPC.hex(),
+            "pceId": PCE_ID.hex(),
+            "tcbType": 0,
+            "tcbEvaluationDataNumber": 10,
+            "tcbLevels": levels,
+        }
+
+    # The TCB of the platform is up to date
+    up_to_date = [
+        tcb_level(CPUSVN, PCESVN, "UpToDate"),
+        tcb_level([1] * 2 + [0] * 14, 5, "OutOfDate"),
+    ]
+    # A newer TCB is released, so the TCB of the platform is out of date
+    out_of_date = [
+        tcb_level([3] + list(CPUSVN[1:]), PCESVN, "UpToDate"),
+        tcb_level(CPUSVN, PCESVN, "OutOfDate"),
+    ]
+    qe_identity = {
+        "id": "QE",
+        "version": 2,
+        "issueDate": "2021-01-01T00:00:00Z",
+        "nextUpdate": "2100-01-01T00:00:00Z",
+        "tcbEvaluationDataNumber": 10,
+        "miscselect": "00000000",
+        "miscselectMask": "FFFFFFFF",
+        "attributes": "11000000000000000000000000000000",
+        "attributesMask": "FBFFFFFFFFFFFFFF0000000000000000",
+        "mrsigner": QE_MRSIGNER.hex().upper(),
+        "isvprodid": 1,
+        "tcbLevels": [
+            {"tcb": {"isvsvn": QE_ISVSVN}, "tcbDate": "2020-11-11T00:00:00Z", "tcbStatus": "UpToDate"},
+            {"tcb": {"isvsvn": 1}, "tcbDate": "2019-11-13T00:00:00Z", "tcbStatus": "OutOfDate"},
+        ],
+    }
+
+    files = {
+        "root_ca.pem": pem(root),
+        "quote.dat": quote,
+        "tcb_info.json": signed_json("tcbInfo", tcb_info(up_to_date), tcb_signing_key),
+        "tcb_info_out_of_date.json": signed_json("tcbInfo", tcb_info(out_of_date), tcb_signing_key),
+        "qe_identity.json": signed_json("enclaveIdentity", qe_identity, tcb_signing_key),
+        "tcb_signing_chain.pem": pem(tcb_signing, root),
+    }
+    for file_name, content in files.items():
+        mode = "wb" if isinstance(content, bytes) else "w"
+        with open(os.path.join(OUT_DIR, file_name), mode) as f:
+            f.write(content)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,286 @@
+use crate::error::{DcapError, Result};
+use crate::localstd::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::serde::{Deserialize, Serialize};
+use crate::serde_json::{self, value::RawValue};
+
+/// Collateral to verify DCAP quotes, which is fetched from Intel PCS or PCCS.
+/// Each JSON is kept as it is, because the signature is calculated over the exact bytes of the body.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct DcapCollateral {
+    /// The response of the TCB info API (v2) for the FMSPC of the platform
+    pub tcb_info: String,
+    /// The response of the QE identity API (v2)
+    pub qe_identity: String,
+    /// The certificate chain in PEM signing the TCB info and the QE identity,
+    /// which is returned in `SGX-TCB-Info-Issuer-Chain` header
+    pub tcb_signing_chain: String,
+}
+
+/// The TCB status of the platform, which is the result of the quote verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub enum TcbStatus {
+    UpToDate,
+    SWHardeningNeeded,
+    ConfigurationNeeded,
+    ConfigurationAndSWHardeningNeeded,
+    OutOfDate,
+    OutOfDateConfigurationNeeded,
+    Revoked,
+}
+
+impl TcbStatus {
+    /// The status in the same vocabulary as `isvEnclaveQuoteStatus` of IAS
+    pub fn as_quote_status(&self) -> &'static str {
+        match self {
+            TcbStatus::UpToDate => "OK",
+            TcbStatus::SWHardeningNeeded => "SW_HARDENING_NEEDED",
+            TcbStatus::ConfigurationNeeded => "CONFIGURATION_NEEDED",
+            TcbStatus::ConfigurationAndSWHardeningNeeded => "CONFIGURATION_AND_SW_HARDENING_NEEDED",
+            TcbStatus::OutOfDate => "OUT_OF_DATE",
+            TcbStatus::OutOfDateConfigurationNeeded => "OUT_OF_DATE_CONFIGURATION_NEEDED",
+            TcbStatus::Revoked => "REVOKED",
+        }
+    }
+
+    /// Converge the TCB status of the platform with the one of the quoting enclave.
+    pub(crate) fn converge(self, qe_status: TcbStatus) -> TcbStatus {
+        match (qe_status, self) {
+            (TcbStatus::Revoked, _) => TcbStatus::Revoked,
+            (TcbStatus::OutOfDate, TcbStatus::UpToDate)
+            | (TcbStatus::OutOfDate, TcbStatus::SWHardeningNeeded) => TcbStatus::OutOfDate,
+            (TcbStatus::OutOfDate, TcbStatus::ConfigurationNeeded)
+            | (TcbStatus::OutOfDate, TcbStatus::ConfigurationAndSWHardeningNeeded) => {
+                TcbStatus::OutOfDateConfigurationNeeded
+            }
+            (_, platform_status) => platform_status,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "crate::serde")]
+struct SignedTcbInfo<'a> {
+    #[serde(rename = "tcbInfo", borrow)]
+    tcb_info: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "crate::serde")]
+struct SignedQeIdentity<'a> {
+    #[serde(rename = "enclaveIdentity", borrow)]
+    enclave_identity: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub(crate) struct TcbInfo {
+    pub version: u32,
+    pub next_update: String,
+    pub fmspc: String,
+    pub pce_id: String,
+    pub tcb_levels: Vec<TcbLevel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub(crate) struct TcbLevel {
+    pub tcb: Tcb,
+    pub tcb_status: TcbStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub(crate) struct Tcb {
+    sgxtcbcomp01svn: u8,
+    sgxtcbcomp02svn: u8,
+    sgxtcbcomp03svn: u8,
+    sgxtcbcomp04svn: u8,
+    sgxtcbcomp05svn: u8,
+    sgxtcbcomp06svn: u8,
+    sgxtcbcomp07svn: u8,
+    sgxtcbcomp08svn: u8,
+    sgxtcbcomp09svn: u8,
+    sgxtcbcomp10svn: u8,
+    sgxtcbcomp11svn: u8,
+    sgxtcbcomp12svn: u8,
+    sgxtcbcomp13svn: u8,
+    sgxtcbcomp14svn: u8,
+    sgxtcbcomp15svn: u8,
+    sgxtcbcomp16svn: u8,
+    pub pcesvn: u16,
+}
+
+impl Tcb {
+    pub fn cpusvn(&self) -> [u8; 16] {
+        [
+            self.sgxtcbcomp01svn,
+            self.sgxtcbcomp02svn,
+            self.sgxtcbcomp03svn,
+            self.sgxtcbcomp04svn,
+            self.sgxtcbcomp05svn,
+            self.sgxtcbcomp06svn,
+            self.sgxtcbcomp07svn,
+            self.sgxtcbcomp08svn,
+            self.sgxtcbcomp09svn,
+            self.sgxtcbcomp10svn,
+            self.sgxtcbcomp11svn,
+            self.sgxtcbcomp12svn,
+            self.sgxtcbcomp13svn,
+            self.sgxtcbcomp14svn,
+            self.sgxtcbcomp15svn,
+            self.sgxtcbcomp16svn,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub(crate) struct QeIdentity {
+    pub id: String,
+    pub version: u32,
+    pub next_update: String,
+    pub miscselect: String,
+    pub miscselect_mask: String,
+    pub attributes: String,
+    pub attributes_mask: String,
+    pub mrsigner: String,
+    pub isvprodid: u16,
+    pub tcb_levels: Vec<QeTcbLevel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub(crate) struct QeTcbLevel {
+    pub tcb: QeTcb,
+    pub tcb_status: TcbStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub(crate) struct QeTcb {
+    pub isvsvn: u16,
+}
+
+impl DcapCollateral {
+    /// Returns the body of the TCB info, the signed bytes and the signature.
+    pub(crate) fn tcb_info(&self) -> Result<(TcbInfo, &[u8], [u8; 64])> {
+        let signed: SignedTcbInfo = serde_json::from_str(&self.tcb_info).map_err(invalid)?;
+        let tcb_info: TcbInfo = serde_json::from_str(signed.tcb_info.get()).map_err(invalid)?;
+        if tcb_info.version != 2 {
+            return Err(DcapError::InvalidCollateral(
+                "TCB info version must be 2".to_string(),
+            ));
+        }
+
+        let mut signature = [0u8; 64];
+        decode_hex(&signed.signature, &mut signature)?;
+
+        Ok((tcb_info, signed.tcb_info.get().as_bytes(), signature))
+    }
+
+    /// Returns the body of the QE identity, the signed bytes and the signature.
+    pub(crate) fn qe_identity(&self) -> Result<(QeIdentity, &[u8], [u8; 64])> {
+        let signed: SignedQeIdentity = serde_json::from_str(&self.qe_identity).map_err(invalid)?;
+        let qe_identity: QeIdentity =
+            serde_json::from_str(signed.enclave_identity.get()).map_err(invalid)?;
+        if qe_identity.id != "QE" || qe_identity.version != 2 {
+            return Err(DcapError::InvalidCollateral(
+                "QE identity must be the one of QE and version 2".to_string(),
+            ));
+        }
+
+        let mut signature = [0u8; 64];
+        decode_hex(&signed.signature, &mut signature)?;
+
+        Ok((
+            qe_identity,
+            signed.enclave_identity.get().as_bytes(),
+            signature,
+        ))
+    }
+}
+
+/// Decode the hex string whose length must be the twice of the output.
+pub(crate) fn decode_hex(hex_str: &str, out: &mut [u8]) -> Result<()> {
+    hex::decode_to_slice(hex_str, out)
+        .map_err(|e| DcapError::InvalidCollateral(format!("invalid hex {}: {}", hex_str, e)))
+}
+
+/// Parse the date time in ISO 8601 (e.g. "2021-01-01T00:00:00Z") into seconds since the unix epoch.
+pub(crate) fn parse_date_time(date_time: &str) -> Result<u64> {
+    let err = || DcapError::InvalidCollateral(format!("invalid date time: {}", date_time));
+    let bytes = date_time.as_bytes();
+    if bytes.len() != 20 || bytes[19] != b'Z' {
+        return Err(err());
+    }
+    let num = |start: usize, end: usize| -> Result<i64> {
+        date_time[start..end].parse::<i64>().map_err(|_| err())
+    };
+    let (year, month, day) = (num(0, 4)?, num(5, 7)?, num(8, 10)?);
+    let (hour, min, sec) = (num(11, 13)?, num(14, 16)?, num(17, 19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(err());
+    }
+
+    // Days from the unix epoch to the civil date
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86400 + hour * 3600 + min * 60 + sec;
+    if secs < 0 {
+        return Err(err());
+    }
+    Ok(secs as u64)
+}
+
+fn invalid(err: serde_json::Error) -> DcapError {
+    DcapError::InvalidCollateral(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_time() {
+        assert_eq!(parse_date_time("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            parse_date_time("2021-03-01T12:34:56Z").unwrap(),
+            1_614_602_096
+        );
+        assert!(parse_date_time("2021-03-01T12:34:56").is_err());
+        assert!(parse_date_time("2021-13-01T12:34:56Z").is_err());
+    }
+
+    #[test]
+    fn test_converge_tcb_status() {
+        assert_eq!(
+            TcbStatus::UpToDate.converge(TcbStatus::UpToDate),
+            TcbStatus::UpToDate
+        );
+        assert_eq!(
+            TcbStatus::SWHardeningNeeded.converge(TcbStatus::OutOfDate),
+            TcbStatus::OutOfDate
+        );
+        assert_eq!(
+            TcbStatus::ConfigurationNeeded.converge(TcbStatus::OutOfDate),
+            TcbStatus::OutOfDateConfigurationNeeded
+        );
+        assert_eq!(
+            TcbStatus::UpToDate.converge(TcbStatus::Revoked),
+            TcbStatus::Revoked
+        );
+    }
+}
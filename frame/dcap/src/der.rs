@@ -0,0 +1,168 @@
+//! A minimal DER reader to extract the public keys and the SGX extensions of PCK certificates,
+//! which are not exposed by webpki.
+
+use crate::error::{DcapError, Result};
+
+pub const SEQUENCE: u8 = 0x30;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OID: u8 = 0x06;
+const VERSION: u8 = 0xA0;
+const EXTENSIONS: u8 = 0xA3;
+const BOOLEAN: u8 = 0x01;
+
+/// Reads DER-encoded values one by one.
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Reader { input }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    /// Read a value of any tag, and returns the tag and the content.
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8])> {
+        let (&tag, rest) = self
+            .input
+            .split_first()
+            .ok_or(DcapError::InvalidCertificate("malformed DER"))?;
+        let (&first, rest) = rest
+            .split_first()
+            .ok_or(DcapError::InvalidCertificate("malformed DER"))?;
+
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let num_bytes = (first & 0x7F) as usize;
+            if num_bytes == 0 || num_bytes > 4 || rest.len() < num_bytes {
+                return Err(DcapError::InvalidCertificate("malformed DER length"));
+            }
+            let len = rest[..num_bytes]
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (len, &rest[num_bytes..])
+        };
+        if rest.len() < len {
+            return Err(DcapError::InvalidCertificate("truncated DER"));
+        }
+
+        self.input = &rest[len..];
+        Ok((tag, &rest[..len]))
+    }
+
+    /// Read a value of the tag, and returns the content.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8]> {
+        match self.read_any()? {
+            (t, content) if t == tag => Ok(content),
+            _ => Err(DcapError::InvalidCertificate("unexpected DER tag")),
+        }
+    }
+
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+
+    /// Read a non-negative integer which fits in u32.
+    pub fn read_u32(&mut self) -> Result<u32> {
+        let content = self.read(INTEGER)?;
+        let content = match content {
+            [0, rest @ ..] => rest,
+            _ => content,
+        };
+        if content.len() > 4 {
+            return Err(DcapError::InvalidCertificate("too large integer"));
+        }
+        Ok(content.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32))
+    }
+}
+
+/// Get the uncompressed EC public key in the SubjectPublicKeyInfo of the certificate.
+pub fn subject_public_key(cert: &[u8]) -> Result<&[u8]> {
+    let mut tbs = tbs_certificate(cert)?;
+    // issuer, validity and subject
+    for _ in 0..3 {
+        tbs.read(SEQUENCE)?;
+    }
+    let mut spki = tbs.read_sequence()?;
+    spki.read(SEQUENCE)?;
+    match spki.read(BIT_STRING)? {
+        [0, key @ ..] => Ok(key),
+        _ => Err(DcapError::InvalidCertificate("invalid public key")),
+    }
+}
+
+/// Get the value of the extension of the OID (DER-encoded content) in the certificate.
+pub fn extension<'a>(cert: &'a [u8], oid: &[u8]) -> Result<Option<&'a [u8]>> {
+    let mut tbs = tbs_certificate(cert)?;
+    // issuer, validity, subject and subjectPublicKeyInfo
+    for _ in 0..4 {
+        tbs.read(SEQUENCE)?;
+    }
+    while !tbs.is_empty() {
+        let (tag, content) = tbs.read_any()?;
+        if tag != EXTENSIONS {
+            continue;
+        }
+        let mut extensions = Reader::new(content).read_sequence()?;
+        while !extensions.is_empty() {
+            let mut extension = extensions.read_sequence()?;
+            let extn_id = extension.read(OID)?;
+            if extension.peek_tag() == Some(BOOLEAN) {
+                extension.read(BOOLEAN)?;
+            }
+            let extn_value = extension.read(OCTET_STRING)?;
+            if extn_id == oid {
+                return Ok(Some(extn_value));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the reader of tbsCertificate positioned at the issuer.
+fn tbs_certificate(cert: &[u8]) -> Result<Reader<'_>> {
+    let mut tbs = Reader::new(cert).read_sequence()?.read_sequence()?;
+    if tbs.peek_tag() == Some(VERSION) {
+        tbs.read(VERSION)?;
+    }
+    // serialNumber and signature
+    tbs.read(INTEGER)?;
+    tbs.read(SEQUENCE)?;
+
+    Ok(tbs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_long_length() {
+        let mut value = vec![OCTET_STRING, 0x81, 0x80];
+        value.extend_from_slice(&[1u8; 0x80]);
+        value.extend_from_slice(&[INTEGER, 0x02, 0x00, 0xFF]);
+
+        let mut reader = Reader::new(&value);
+        assert_eq!(reader.read(OCTET_STRING).unwrap(), &[1u8; 0x80][..]);
+        assert_eq!(reader.read_u32().unwrap(), 0xFF);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_reject_truncated_value() {
+        let mut reader = Reader::new(&[SEQUENCE, 0x03, 0x02, 0x01]);
+        assert!(reader.read_sequence().is_err());
+    }
+}
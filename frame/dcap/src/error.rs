@@ -0,0 +1,43 @@
+use crate::localstd::{fmt, string::String};
+use crate::webpki;
+
+pub type Result<T> = core::result::Result<T, DcapError>;
+
+#[derive(Debug)]
+pub enum DcapError {
+    /// The quote is malformed or not supported
+    InvalidQuote(&'static str),
+    /// The certificate is malformed or doesn't have the expected extensions
+    InvalidCertificate(&'static str),
+    /// The certificate chain is not issued by the trusted root certificate
+    CertificateChain(webpki::Error),
+    /// The TCB info or the QE identity is malformed
+    InvalidCollateral(String),
+    /// The collateral doesn't match the quote
+    CollateralMismatch(&'static str),
+    /// The collateral is not updated after its `nextUpdate`
+    ExpiredCollateral(&'static str),
+    /// The signature of the target is invalid
+    InvalidSignature(&'static str),
+    /// No TCB level of the TCB info covers the TCB of the platform
+    TcbNotSupported,
+}
+
+impl fmt::Display for DcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DcapError::InvalidQuote(msg) => write!(f, "Invalid quote: {}", msg),
+            DcapError::InvalidCertificate(msg) => write!(f, "Invalid certificate: {}", msg),
+            DcapError::CertificateChain(err) => write!(f, "Invalid certificate chain: {:?}", err),
+            DcapError::InvalidCollateral(msg) => write!(f, "Invalid collateral: {}", msg),
+            DcapError::CollateralMismatch(msg) => {
+                write!(f, "The collateral doesn't match the quote: {}", msg)
+            }
+            DcapError::ExpiredCollateral(target) => write!(f, "Expired collateral: {}", target),
+            DcapError::InvalidSignature(target) => write!(f, "Invalid signature of {}", target),
+            DcapError::TcbNotSupported => write!(f, "The TCB of the platform is not supported"),
+        }
+    }
+}
+
+impl crate::localstd::error::Error for DcapError {}
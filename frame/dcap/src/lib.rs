@@ -0,0 +1,45 @@
+//! Verification of DCAP (ECDSA) quotes against locally supplied collateral.
+//!
+//! This crate is written in pure Rust without any SGX runtime, so that quotes can be verified
+//! both in enclaves (`sgx` feature) and on hosts (`std` feature) with the same code.
+//! The collateral (TCB info, QE identity and the certificate chain signing them) is fetched from
+//! Intel PCS or PCCS by the operators in advance, and verified against the trusted root certificate.
+//! Revocation of the PCK certificates by CRLs is not checked.
+
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+#[cfg(feature = "sgx")]
+#[macro_use]
+extern crate sgx_tstd as localstd;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use pem_sgx as pem;
+#[cfg(feature = "std")]
+use pem_std as pem;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use ring_sgx as ring;
+#[cfg(feature = "std")]
+use ring_std as ring;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use serde_json_sgx as serde_json;
+#[cfg(feature = "std")]
+use serde_json_std as serde_json;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use serde_sgx as serde;
+#[cfg(feature = "std")]
+use serde_std as serde;
+#[cfg(feature = "std")]
+use std as localstd;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use webpki_sgx as webpki;
+#[cfg(feature = "std")]
+use webpki_std as webpki;
+
+mod collateral;
+mod der;
+mod error;
+mod quote;
+mod verifier;
+
+pub use crate::collateral::{DcapCollateral, TcbStatus};
+pub use crate::error::{DcapError, Result};
+pub use crate::quote::{DcapQuote, QUOTE_BODY_LEN};
+pub use crate::verifier::{verify_quote, VerifiedQuote};
@@ -0,0 +1,178 @@
+use crate::error::{DcapError, Result};
+use crate::localstd::{convert::TryInto, vec::Vec};
+use crate::pem;
+
+/// The length of the quote header and the ISV enclave report body,
+/// which is signed by the attestation key and has the same layout as the body of EPID quotes.
+pub const QUOTE_BODY_LEN: usize = HEADER_LEN + REPORT_BODY_LEN;
+
+const HEADER_LEN: usize = 48;
+const REPORT_BODY_LEN: usize = 384;
+const SIGNATURE_LEN: usize = 64;
+const ATTESTATION_KEY_LEN: usize = 64;
+
+const QUOTE_VERSION: u16 = 3;
+const ATTESTATION_KEY_TYPE_ECDSA_P256: u16 = 2;
+const INTEL_QE_VENDOR_ID: [u8; 16] = [
+    0x93, 0x9a, 0x72, 0x33, 0xf7, 0x9c, 0x4c, 0xa9, 0x94, 0x0a, 0x0d, 0xb3, 0x95, 0x7f, 0x06, 0x07,
+];
+/// The certification data type of the PCK certificate chain in PEM
+const PCK_CERT_CHAIN: u16 = 5;
+
+/// A DCAP quote of version 3 whose attestation key type is ECDSA-256-with-P-256 curve.
+/// For the layout, see "Intel® SGX ECDSA Quote Library API".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DcapQuote<'a> {
+    /// The header and the ISV enclave report body
+    body: &'a [u8],
+    isv_report_sig: &'a [u8],
+    attestation_key: &'a [u8],
+    qe_report: &'a [u8],
+    qe_report_sig: &'a [u8],
+    qe_auth_data: &'a [u8],
+    pck_cert_chain: &'a [u8],
+}
+
+impl<'a> DcapQuote<'a> {
+    pub fn parse(quote: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader(quote);
+        let body = reader.read(QUOTE_BODY_LEN)?;
+
+        if u16::from_le_bytes([body[0], body[1]]) != QUOTE_VERSION {
+            return Err(DcapError::InvalidQuote("unsupported quote version"));
+        }
+        if u16::from_le_bytes([body[2], body[3]]) != ATTESTATION_KEY_TYPE_ECDSA_P256 {
+            return Err(DcapError::InvalidQuote("unsupported attestation key type"));
+        }
+        if body[12..28] != INTEL_QE_VENDOR_ID {
+            return Err(DcapError::InvalidQuote("unknown QE vendor"));
+        }
+
+        let sig_data_len = reader.read_u32()? as usize;
+        let mut reader = Reader(reader.read(sig_data_len)?);
+        let isv_report_sig = reader.read(SIGNATURE_LEN)?;
+        let attestation_key = reader.read(ATTESTATION_KEY_LEN)?;
+        let qe_report = reader.read(REPORT_BODY_LEN)?;
+        let qe_report_sig = reader.read(SIGNATURE_LEN)?;
+        let qe_auth_data_len = reader.read_u16()? as usize;
+        let qe_auth_data = reader.read(qe_auth_data_len)?;
+
+        if reader.read_u16()? != PCK_CERT_CHAIN {
+            return Err(DcapError::InvalidQuote(
+                "certification data must be PCK certificate chain",
+            ));
+        }
+        let cert_data_len = reader.read_u32()? as usize;
+        let pck_cert_chain = reader.read(cert_data_len)?;
+
+        Ok(DcapQuote {
+            body,
+            isv_report_sig,
+            attestation_key,
+            qe_report,
+            qe_report_sig,
+            qe_auth_data,
+            pck_cert_chain,
+        })
+    }
+
+    /// The header and the ISV enclave report body
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    pub fn isv_report_sig(&self) -> &'a [u8] {
+        self.isv_report_sig
+    }
+
+    /// The uncompressed point of the attestation key
+    pub fn attestation_key(&self) -> Vec<u8> {
+        let mut key = Vec::with_capacity(ATTESTATION_KEY_LEN + 1);
+        key.push(0x04);
+        key.extend_from_slice(self.attestation_key);
+        key
+    }
+
+    pub fn qe_report(&self) -> ReportBody<'a> {
+        ReportBody(self.qe_report)
+    }
+
+    pub fn qe_report_sig(&self) -> &'a [u8] {
+        self.qe_report_sig
+    }
+
+    /// The data expected to be the first half of the QE report data
+    pub fn qe_report_data_preimage(&self) -> Vec<u8> {
+        [self.attestation_key, self.qe_auth_data].concat()
+    }
+
+    /// The PCK certificate chain in PEM, which begins with the PCK certificate
+    pub fn pck_cert_chain(&self) -> &'a [u8] {
+        self.pck_cert_chain
+    }
+
+    /// The concatenated DER certificates of the PCK certificate chain,
+    /// which are registered with the contract so that it doesn't have to decode PEM.
+    pub fn pck_cert_chain_der(&self) -> Vec<u8> {
+        pem::parse_many(self.pck_cert_chain)
+            .into_iter()
+            .flat_map(|cert| cert.contents)
+            .collect()
+    }
+}
+
+/// A report body of enclaves (sgx_report_body_t)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportBody<'a>(&'a [u8]);
+
+impl<'a> ReportBody<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn misc_select(&self) -> u32 {
+        u32::from_le_bytes(self.0[16..20].try_into().unwrap())
+    }
+
+    pub fn attributes(&self) -> &'a [u8] {
+        &self.0[48..64]
+    }
+
+    pub fn mr_signer(&self) -> &'a [u8] {
+        &self.0[128..160]
+    }
+
+    pub fn isv_prod_id(&self) -> u16 {
+        u16::from_le_bytes([self.0[256], self.0[257]])
+    }
+
+    pub fn isv_svn(&self) -> u16 {
+        u16::from_le_bytes([self.0[258], self.0[259]])
+    }
+
+    pub fn report_data(&self) -> &'a [u8] {
+        &self.0[320..384]
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(DcapError::InvalidQuote("truncated quote"));
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        self.read(2).map(|v| u16::from_le_bytes([v[0], v[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read(4)
+            .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+    }
+}
@@ -0,0 +1,416 @@
+use crate::collateral::{decode_hex, parse_date_time, DcapCollateral, TcbStatus};
+use crate::der::{self, Reader};
+use crate::error::{DcapError, Result};
+use crate::localstd::vec::Vec;
+use crate::quote::DcapQuote;
+use crate::{pem, ring, webpki};
+use ring::{digest, signature};
+
+type SignatureAlgorithms = &'static [&'static webpki::SignatureAlgorithm];
+static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+];
+
+/// OID of SGX extensions in PCK certificates: 1.2.840.113741.1.13.1
+const SGX_EXTENSIONS_OID: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF8, 0x4D, 0x01, 0x0D, 0x01];
+const TCB_OID_INDEX: u8 = 2;
+const PCE_ID_OID_INDEX: u8 = 3;
+const FMSPC_OID_INDEX: u8 = 4;
+/// Index of PCESVN in the TCB extension. 1 to 16 are the CPUSVN components.
+const PCESVN_OID_INDEX: u8 = 17;
+
+/// The result of the verification of a DCAP quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedQuote {
+    pub tcb_status: TcbStatus,
+    /// The header and the ISV enclave report body
+    pub quote_body: Vec<u8>,
+}
+
+/// Verify that
+/// 1. the PCK certificate chain and the TCB signing chain are issued by the root certificate
+/// 2. the TCB info and the QE identity are signed, and not expired
+/// 3. the quoting enclave matches the QE identity, and its report is signed by the PCK
+/// 4. the attestation key is bound to the QE report, and the quote is signed by the attestation key
+///
+/// and then evaluate the TCB status of the platform.
+/// `now` is the seconds since the unix epoch.
+pub fn verify_quote(
+    quote: &[u8],
+    collateral: &DcapCollateral,
+    root_cert: &[u8],
+    now: u64,
+) -> Result<VerifiedQuote> {
+    let quote = DcapQuote::parse(quote)?;
+    let time = webpki::Time::from_seconds_since_unix_epoch(now);
+
+    let pck_cert = verify_cert_chain(quote.pck_cert_chain(), root_cert, time)?;
+    let tcb_signing_cert =
+        verify_cert_chain(collateral.tcb_signing_chain.as_bytes(), root_cert, time)?;
+    let pck = PckExtensions::parse(&pck_cert)?;
+
+    // TCB info
+    let (tcb_info, tcb_info_body, tcb_info_sig) = collateral.tcb_info()?;
+    verify_signature(&tcb_signing_cert, tcb_info_body, &tcb_info_sig, "TCB info")?;
+    if parse_date_time(&tcb_info.next_update)? < now {
+        return Err(DcapError::ExpiredCollateral("TCB info"));
+    }
+    let mut fmspc = [0u8; 6];
+    decode_hex(&tcb_info.fmspc, &mut fmspc)?;
+    let mut pce_id = [0u8; 2];
+    decode_hex(&tcb_info.pce_id, &mut pce_id)?;
+    if fmspc != pck.fmspc || pce_id != pck.pce_id {
+        return Err(DcapError::CollateralMismatch(
+            "FMSPC or PCEID of TCB info is not the one of the platform",
+        ));
+    }
+
+    // QE identity
+    let (qe_identity, qe_identity_body, qe_identity_sig) = collateral.qe_identity()?;
+    verify_signature(
+        &tcb_signing_cert,
+        qe_identity_body,
+        &qe_identity_sig,
+        "QE identity",
+    )?;
+    if parse_date_time(&qe_identity.next_update)? < now {
+        return Err(DcapError::ExpiredCollateral("QE identity"));
+    }
+    let qe_report = quote.qe_report();
+    let mut miscselect = [0u8; 4];
+    decode_hex(&qe_identity.miscselect, &mut miscselect)?;
+    let mut miscselect_mask = [0u8; 4];
+    decode_hex(&qe_identity.miscselect_mask, &mut miscselect_mask)?;
+    let mut attributes = [0u8; 16];
+    decode_hex(&qe_identity.attributes, &mut attributes)?;
+    let mut attributes_mask = [0u8; 16];
+    decode_hex(&qe_identity.attributes_mask, &mut attributes_mask)?;
+    let mut mrsigner = [0u8; 32];
+    decode_hex(&qe_identity.mrsigner, &mut mrsigner)?;
+    let masked_attributes = qe_report
+        .attributes()
+        .iter()
+        .zip(attributes_mask.iter())
+        .map(|(a, m)| a & m);
+    if qe_report.misc_select() & u32::from_be_bytes(miscselect_mask)
+        != u32::from_be_bytes(miscselect)
+        || !masked_attributes.eq(attributes.iter().copied())
+        || qe_report.mr_signer() != mrsigner
+        || qe_report.isv_prod_id() != qe_identity.isvprodid
+    {
+        return Err(DcapError::CollateralMismatch(
+            "the quoting enclave doesn't match QE identity",
+        ));
+    }
+    let qe_status = qe_identity
+        .tcb_levels
+        .iter()
+        .find(|level| level.tcb.isvsvn <= qe_report.isv_svn())
+        .map(|level| level.tcb_status)
+        .ok_or(DcapError::TcbNotSupported)?;
+
+    // QE report and the attestation key
+    verify_signature(
+        &pck_cert,
+        qe_report.as_bytes(),
+        quote.qe_report_sig(),
+        "QE report",
+    )?;
+    let hash = digest::digest(&digest::SHA256, &quote.qe_report_data_preimage());
+    let report_data = qe_report.report_data();
+    if &report_data[..32] != hash.as_ref() || report_data[32..].iter().any(|b| *b != 0) {
+        return Err(DcapError::InvalidQuote(
+            "the attestation key is not bound to the QE report",
+        ));
+    }
+    signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, quote.attestation_key())
+        .verify(quote.body(), quote.isv_report_sig())
+        .map_err(|_| DcapError::InvalidSignature("ISV enclave report"))?;
+
+    // The first TCB level which the TCB of the platform is higher than or equal to
+    let platform_status = tcb_info
+        .tcb_levels
+        .iter()
+        .find(|level| {
+            level
+                .tcb
+                .cpusvn()
+                .iter()
+                .zip(pck.cpusvn.iter())
+                .all(|(level_svn, svn)| level_svn <= svn)
+                && level.tcb.pcesvn <= pck.pcesvn
+        })
+        .map(|level| level.tcb_status)
+        .ok_or(DcapError::TcbNotSupported)?;
+
+    Ok(VerifiedQuote {
+        tcb_status: platform_status.converge(qe_status),
+        quote_body: quote.body().to_vec(),
+    })
+}
+
+/// Verify the certificate chain in PEM which begins with the end entity certificate,
+/// and returns the DER of the end entity certificate.
+fn verify_cert_chain(chain: &[u8], root_cert: &[u8], time: webpki::Time) -> Result<Vec<u8>> {
+    let certs = pem::parse_many(chain);
+    let (ee_cert, intermediates) = certs
+        .split_first()
+        .ok_or(DcapError::InvalidCertificate("empty certificate chain"))?;
+    let intermediates: Vec<&[u8]> = intermediates
+        .iter()
+        .map(|cert| cert.contents.as_slice())
+        .collect();
+
+    let trust_anchors = [
+        webpki::trust_anchor_util::cert_der_as_trust_anchor(root_cert)
+            .map_err(DcapError::CertificateChain)?,
+    ];
+    webpki::EndEntityCert::from(&ee_cert.contents)
+        .and_then(|cert| {
+            cert.verify_is_valid_tls_server_cert(
+                SUPPORTED_SIG_ALGS,
+                &webpki::TLSServerTrustAnchors(&trust_anchors),
+                &intermediates,
+                time,
+            )
+        })
+        .map_err(DcapError::CertificateChain)?;
+
+    Ok(ee_cert.contents.clone())
+}
+
+/// Verify the ECDSA signature (r || s) with the public key of the certificate.
+fn verify_signature(cert: &[u8], msg: &[u8], sig: &[u8], target: &'static str) -> Result<()> {
+    let public_key = der::subject_public_key(cert)?;
+    signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(msg, sig)
+        .map_err(|_| DcapError::InvalidSignature(target))
+}
+
+/// SGX extensions of the PCK certificate, which identify the TCB of the platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PckExtensions {
+    cpusvn: [u8; 16],
+    pcesvn: u16,
+    pce_id: [u8; 2],
+    fmspc: [u8; 6],
+}
+
+impl PckExtensions {
+    fn parse(pck_cert: &[u8]) -> Result<Self> {
+        let extensions = der::extension(pck_cert, SGX_EXTENSIONS_OID)?.ok_or(
+            DcapError::InvalidCertificate("SGX extensions are not found"),
+        )?;
+
+        let mut cpusvn = None;
+        let mut pcesvn = None;
+        let mut pce_id = None;
+        let mut fmspc = None;
+        let mut extensions = Reader::new(extensions).read_sequence()?;
+        while !extensions.is_empty() {
+            let mut extension = extensions.read_sequence()?;
+            match sgx_oid_index(extension.read(der::OID)?, SGX_EXTENSIONS_OID) {
+                Some(TCB_OID_INDEX) => {
+                    let (svns, svn) = parse_tcb(extension.read_sequence()?)?;
+                    cpusvn = Some(svns);
+                    pcesvn = Some(svn);
+                }
+                Some(PCE_ID_OID_INDEX) => {
+                    pce_id = Some(to_array(extension.read(der::OCTET_STRING)?)?)
+                }
+                Some(FMSPC_OID_INDEX) => {
+                    fmspc = Some(to_array(extension.read(der::OCTET_STRING)?)?)
+                }
+                _ => {}
+            }
+        }
+
+        match (cpusvn, pcesvn, pce_id, fmspc) {
+            (Some(cpusvn), Some(pcesvn), Some(pce_id), Some(fmspc)) => Ok(PckExtensions {
+                cpusvn,
+                pcesvn,
+                pce_id,
+                fmspc,
+            }),
+            _ => Err(DcapError::InvalidCertificate("missing SGX extensions")),
+        }
+    }
+}
+
+/// Parse the TCB extension into the CPUSVN components and PCESVN.
+fn parse_tcb(mut tcb: Reader<'_>) -> Result<([u8; 16], u16)> {
+    let mut tcb_oid = SGX_EXTENSIONS_OID.to_vec();
+    tcb_oid.push(TCB_OID_INDEX);
+
+    let mut cpusvn = [0u8; 16];
+    let mut pcesvn = None;
+    while !tcb.is_empty() {
+        let mut component = tcb.read_sequence()?;
+        match sgx_oid_index(component.read(der::OID)?, &tcb_oid) {
+            Some(i) if (1..=16).contains(&i) => {
+                cpusvn[i as usize - 1] = component.read_u32()? as u8;
+            }
+            Some(PCESVN_OID_INDEX) => pcesvn = Some(component.read_u32()? as u16),
+            _ => {}
+        }
+    }
+
+    pcesvn
+        .map(|pcesvn| (cpusvn, pcesvn))
+        .ok_or(DcapError::InvalidCertificate("PCESVN is not found"))
+}
+
+/// Returns the last arc of the OID if it's a child of the parent.
+fn sgx_oid_index(oid: &[u8], parent: &[u8]) -> Option<u8> {
+    match oid.split_last() {
+        Some((&index, prefix)) if prefix == parent && index < 0x80 => Some(index),
+        _ => None,
+    }
+}
+
+fn to_array<T: Default + AsMut<[u8]>>(value: &[u8]) -> Result<T> {
+    let mut array = T::default();
+    if array.as_mut().len() != value.len() {
+        return Err(DcapError::InvalidCertificate(
+            "invalid length of SGX extension",
+        ));
+    }
+    array.as_mut().copy_from_slice(value);
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::localstd::string::ToString;
+
+    const QUOTE: &[u8] = include_bytes!("../fixtures/quote.dat");
+    const ROOT_CERT: &str = include_str!("../fixtures/root_ca.pem");
+    const TCB_INFO: &str = include_str!("../fixtures/tcb_info.json");
+    const TCB_INFO_OUT_OF_DATE: &str = include_str!("../fixtures/tcb_info_out_of_date.json");
+    const QE_IDENTITY: &str = include_str!("../fixtures/qe_identity.json");
+    const TCB_SIGNING_CHAIN: &str = include_str!("../fixtures/tcb_signing_chain.pem");
+    // 2021-06-01T00:00:00Z
+    const NOW: u64 = 1_622_505_600;
+
+    fn collateral() -> DcapCollateral {
+        DcapCollateral {
+            tcb_info: TCB_INFO.to_string(),
+            qe_identity: QE_IDENTITY.to_string(),
+            tcb_signing_chain: TCB_SIGNING_CHAIN.to_string(),
+        }
+    }
+
+    fn root_cert() -> Vec<u8> {
+        pem::parse(ROOT_CERT).unwrap().contents
+    }
+
+    #[test]
+    fn test_verify_quote() {
+        let verified = verify_quote(QUOTE, &collateral(), &root_cert(), NOW).unwrap();
+
+        assert_eq!(verified.tcb_status, TcbStatus::UpToDate);
+        assert_eq!(verified.quote_body, &QUOTE[..crate::QUOTE_BODY_LEN]);
+        // MRENCLAVE at the same offset as EPID quotes
+        assert_eq!(
+            &verified.quote_body[112..144],
+            &(0..32).collect::<Vec<u8>>()[..]
+        );
+    }
+
+    #[test]
+    fn test_out_of_date_tcb() {
+        let collateral = DcapCollateral {
+            tcb_info: TCB_INFO_OUT_OF_DATE.to_string(),
+            ..collateral()
+        };
+        let verified = verify_quote(QUOTE, &collateral, &root_cert(), NOW).unwrap();
+
+        assert_eq!(verified.tcb_status, TcbStatus::OutOfDate);
+        assert_eq!(verified.tcb_status.as_quote_status(), "OUT_OF_DATE");
+    }
+
+    #[test]
+    fn test_reject_untrusted_root() {
+        let other_root = pem::parse_many(TCB_SIGNING_CHAIN)[0].contents.clone();
+        match verify_quote(QUOTE, &collateral(), &other_root, NOW) {
+            Err(DcapError::CertificateChain(_)) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_reject_modified_quote() {
+        let mut quote = QUOTE.to_vec();
+        // MRENCLAVE
+        quote[112] ^= 1;
+        match verify_quote(&quote, &collateral(), &root_cert(), NOW) {
+            Err(DcapError::InvalidSignature("ISV enclave report")) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        assert!(verify_quote(&QUOTE[..1000], &collateral(), &root_cert(), NOW).is_err());
+    }
+
+    #[test]
+    fn test_reject_modified_collateral() {
+        let collateral = DcapCollateral {
+            tcb_info: TCB_INFO.replace("\"OutOfDate\"", "\"UpToDate\""),
+            ..collateral()
+        };
+        match verify_quote(QUOTE, &collateral, &root_cert(), NOW) {
+            Err(DcapError::InvalidSignature("TCB info")) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_reject_expired_collateral() {
+        // 2100-01-02T00:00:00Z, after the nextUpdate of the collateral
+        let now = 4_102_531_200;
+        match verify_quote(QUOTE, &collateral(), &root_cert(), now) {
+            Err(DcapError::ExpiredCollateral("TCB info")) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_pck_cert_chain_der() {
+        let quote = DcapQuote::parse(QUOTE).unwrap();
+        let certs = pem::parse_many(quote.pck_cert_chain());
+        let der = quote.pck_cert_chain_der();
+
+        assert_eq!(certs.len(), 3);
+        assert_eq!(
+            der,
+            certs
+                .iter()
+                .flat_map(|cert| cert.contents.clone())
+                .collect::<Vec<u8>>()
+        );
+        // The first certificate is the PCK certificate, which is a DER sequence
+        assert_eq!(der[0], 0x30);
+        assert_eq!(
+            &der[der.len() - certs[2].contents.len()..],
+            &root_cert()[..]
+        );
+    }
+
+    #[test]
+    fn test_parse_pck_extensions() {
+        let quote = DcapQuote::parse(QUOTE).unwrap();
+        let pck_cert = &pem::parse_many(quote.pck_cert_chain())[0].contents;
+        let pck = PckExtensions::parse(pck_cert).unwrap();
+
+        assert_eq!(
+            pck.cpusvn,
+            [2, 2, 2, 2, 4, 1, 128, 4, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(pck.pcesvn, 10);
+        assert_eq!(pck.pce_id, [0, 0]);
+        assert_eq!(pck.fmspc, [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00]);
+    }
+}
@@ -0,0 +1,11 @@
+use frame_common::error::EcallError;
+use std::vec::Vec;
+
+/// Encode the error of an ecall to be written to the output buffer.
+/// An empty buffer is returned if it doesn't fit, then the host only knows the ecall failed.
+pub fn ecall_error_output(err: &anyhow::Error, output_max_len: usize) -> Vec<u8> {
+    match bincode::serialize(&EcallError::from_error(err)) {
+        Ok(buf) if buf.len() <= output_max_len => buf,
+        _ => Vec::new(),
+    }
+}
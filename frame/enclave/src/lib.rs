@@ -1,7 +1,17 @@
-#![no_std]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "software-enclave")]
+extern crate anyhow_std as anyhow;
+#[cfg(feature = "software-enclave")]
+extern crate bincode_std as bincode;
+#[cfg(feature = "software-enclave")]
+extern crate serde_std as serde;
+#[cfg(feature = "sgx")]
 extern crate sgx_tstd as std;
 
 pub mod engine;
+mod error;
 mod register;
 
 pub use crate::engine::*;
+pub use crate::error::ecall_error_output;
+pub use frame_common::error::EcallError;
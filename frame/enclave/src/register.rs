@@ -6,34 +6,62 @@ macro_rules! register_ecall {
         $ctx_ops: ty,
         $( ($cmd: path, $handler: ty), )*
     ) => {
-        fn ecall_handler(cmd: u32, input: &mut [u8]) -> anyhow::Result<Vec<u8>> {
+        fn ecall_handler(ctx: &$ctx_ops, cmd: u32, input: &mut [u8]) -> anyhow::Result<Vec<u8>> {
             match cmd {
                 $(
-                    $cmd => inner_ecall_handler::<$handler>(input),
+                    $cmd => inner_ecall_handler::<$handler>(ctx, input),
                 )*
                 _ => anyhow::bail!("Not registered the ecall command"),
             }
         }
 
-        fn inner_ecall_handler<EE>(input_payload: &[u8]) -> anyhow::Result<Vec<u8>>
+        fn inner_ecall_handler<EE>(ctx: &$ctx_ops, input_payload: &[u8]) -> anyhow::Result<Vec<u8>>
         where
             EE: EnclaveEngine,
         {
             #[cfg(feature = "runtime_enabled")]
             let res = {
                 let ciphertext = bincode::deserialize(&input_payload[..])
-                    .map_err(|e| anyhow!("{:?}", e))?;
-                let input = EE::decrypt::<$ctx_ops>(ciphertext, $ctx)?;
-                EE::eval_policy(&input)?;
-                EE::handle::<$runtime_exec, $ctx_ops>(input, $ctx, $max_mem)?
+                    .map_err(|e| $crate::EcallError::Decode(format!("{:?}", e)))?;
+                let input = EE::decrypt::<$ctx_ops>(ciphertext, ctx)
+                    .map_err(|e| $crate::EcallError::classify(e, $crate::EcallError::Decode))?;
+                EE::eval_policy(&input).map_err(|e| {
+                    $crate::EcallError::classify(e, $crate::EcallError::PolicyFailure)
+                })?;
+                EE::handle::<$runtime_exec, $ctx_ops>(input, ctx, $max_mem)?
             };
 
             #[cfg(not(feature = "runtime_enabled"))]
-            let res = EE::handle_without_runtime::<$ctx_ops>($ctx)?;
+            let res = EE::handle_without_runtime::<$ctx_ops>(ctx)?;
 
             bincode::serialize(&res).map_err(Into::into)
         }
 
+        /// Run the ecall handler on the context and encode its result into the output.
+        /// This is also the entry point of software enclaves linked into the host process,
+        /// each of which has its own context.
+        pub fn handle_ecall(
+            ctx: &$ctx_ops,
+            command: u32,
+            input: &mut [u8],
+            output_max_len: usize,
+        ) -> (frame_types::EnclaveStatus, Vec<u8>) {
+            // On failure, the error is written to the output buffer so that the host can tell the reason.
+            match ecall_handler(ctx, command, input) {
+                Ok(out) if out.len() <= output_max_len => (frame_types::EnclaveStatus::success(), out),
+                Ok(out) => {
+                    println!("Result buffer length is over output_max: output_max={}, res_len={}", output_max_len, out.len());
+                    let e = anyhow::anyhow!("Result buffer length is over output_max: output_max={}, res_len={}", output_max_len, out.len());
+                    (frame_types::EnclaveStatus::error(), $crate::ecall_error_output(&e, output_max_len))
+                }
+                Err(e) => {
+                    println!("Error in enclave (ecall_entry_point): command: {:?}, error: {:?}", command, e);
+                    (frame_types::EnclaveStatus::error(), $crate::ecall_error_output(&e, output_max_len))
+                }
+            }
+        }
+
+        #[cfg(not(feature = "software-enclave"))]
         #[no_mangle]
         pub extern "C" fn ecall_entry_point(
             command: u32,
@@ -44,28 +72,15 @@ macro_rules! register_ecall {
             output_len: &mut usize,
         ) -> frame_types::EnclaveStatus {
             let input = unsafe { std::slice::from_raw_parts_mut(input_buf, input_len) };
-            let res = unsafe {
-                match ecall_handler(command, input) {
-                    Ok(out) => out,
-                    Err(e) => {
-                        println!("Error in enclave (ecall_entry_point): command: {:?}, error: {:?}", command, e);
-                        return frame_types::EnclaveStatus::error();
-                    }
-                }
-            };
+            let (status, res) = handle_ecall($ctx, command, input, output_max_len);
 
             let res_len = res.len();
             *output_len = res_len;
-
-            if res_len > output_max_len {
-                println!("Result buffer length is over output_max: output_max={}, res_len={}", output_max_len, res_len);
-                return frame_types::EnclaveStatus::error();
-            }
             unsafe {
                 ptr::copy_nonoverlapping(res.as_ptr(), output_buf, res_len);
             }
 
-            frame_types::EnclaveStatus::success()
+            status
         }
     }
 }
@@ -1,6 +1,11 @@
 use std::env;
 
 fn main() {
+    // Software enclaves are linked into the host process, so neither the SGX runtime nor the untrusted bridge is needed.
+    if env::var("CARGO_FEATURE_SOFTWARE_ENCLAVE").is_ok() {
+        return;
+    }
+
     let sdk_dir = env::var("SGX_SDK").unwrap_or_else(|_| "/opt/intel/sgxsdk".to_string());
     let rust_sgx_sdk = env::var("SGX_SDK_RUST")
         .unwrap_or_else(|_| format!("{}/sgx", dirs::home_dir().unwrap().display()));
@@ -20,6 +25,10 @@ fn main() {
         }
     }
 
+    if env::var("CARGO_FEATURE_DCAP").is_ok() {
+        println!("cargo:rustc-link-lib=dylib=sgx_dcap_ql");
+    }
+
     let edl = format!("{}/edl", rust_sgx_sdk);
     let test_u_c_path = format!("{}/Anonify_test_u.c", build_dir);
     let common_u_c_path = format!("{}/Anonify_common_u.c", build_dir);
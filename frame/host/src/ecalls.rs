@@ -1,9 +1,14 @@
 use crate::error::{FrameHostError, Result};
+#[cfg(feature = "software-enclave")]
+use crate::software_enclave;
 use frame_common::{EcallInput, EcallOutput};
 use frame_types::EnclaveStatus;
 use serde::{de::DeserializeOwned, Serialize};
-use sgx_types::{sgx_enclave_id_t, sgx_status_t};
+use sgx_types::sgx_enclave_id_t;
+#[cfg(not(feature = "software-enclave"))]
+use sgx_types::sgx_status_t;
 
+#[cfg(not(feature = "software-enclave"))]
 extern "C" {
     fn ecall_entry_point(
         eid: sgx_enclave_id_t,
@@ -40,7 +45,29 @@ impl EnclaveConnector {
         bincode::deserialize(&result[..]).map_err(Into::into)
     }
 
-    fn inner_invoke_ecall(&self, cmd: u32, mut input: Vec<u8>) -> Result<Vec<u8>> {
+    fn inner_invoke_ecall(&self, cmd: u32, input: Vec<u8>) -> Result<Vec<u8>> {
+        let (ret, output) = self.ecall_entry_point(cmd, input)?;
+        if ret.is_err() {
+            // The enclave writes the reason of the failure to the output buffer if it fits.
+            if output.is_empty() {
+                return Err(FrameHostError::EnclaveError {
+                    status: ret,
+                    function: "ecall_entry_point",
+                    cmd,
+                });
+            }
+            return Err(FrameHostError::EcallError {
+                error: bincode::deserialize(&output[..])?,
+                function: "ecall_entry_point",
+                cmd,
+            });
+        }
+
+        Ok(output)
+    }
+
+    #[cfg(not(feature = "software-enclave"))]
+    fn ecall_entry_point(&self, cmd: u32, mut input: Vec<u8>) -> Result<(EnclaveStatus, Vec<u8>)> {
         let input_ptr = input.as_mut_ptr();
         let input_len = input.len();
         let output_max = self.output_max_len;
@@ -71,18 +98,29 @@ impl EnclaveConnector {
             });
         }
         if ret.is_err() {
-            return Err(FrameHostError::EnclaveError {
-                status: ret,
-                function: "ecall_entry_point",
-                cmd,
-            });
+            if output_len > output_max {
+                output_len = 0;
+            }
+        } else {
+            assert!(output_len < output_max);
         }
-        assert!(output_len < output_max);
 
         unsafe {
             output_buf.set_len(output_len);
         }
 
-        Ok(output_buf)
+        Ok((ret, output_buf))
+    }
+
+    /// Call the ecall handler linked into the host process instead of the SGX runtime.
+    #[cfg(feature = "software-enclave")]
+    fn ecall_entry_point(&self, cmd: u32, mut input: Vec<u8>) -> Result<(EnclaveStatus, Vec<u8>)> {
+        let (ret, output) =
+            software_enclave::ecall_entry_point(self.eid, cmd, &mut input, self.output_max_len)?;
+        if !ret.is_err() {
+            assert!(output.len() < self.output_max_len);
+        }
+
+        Ok((ret, output))
     }
 }
@@ -1,3 +1,4 @@
+use frame_common::error::EcallError;
 use frame_types::EnclaveStatus;
 use sgx_types::sgx_status_t;
 use thiserror::Error;
@@ -20,6 +21,13 @@ pub enum FrameHostError {
         function: &'static str,
         cmd: u32,
     },
+    #[error("Enclave ecall failed function: {function:?}, command: {cmd:?}, error: {error}")]
+    EcallError {
+        #[source]
+        error: EcallError,
+        function: &'static str,
+        cmd: u32,
+    },
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Bincode error: {0}")]
@@ -29,3 +37,14 @@ pub enum FrameHostError {
     #[error("SerdeJsonError error: {0}")]
     SerdeJsonError(#[from] serde_json::Error),
 }
+
+impl FrameHostError {
+    /// The reason the enclave rejected the ecall
+    pub fn ecall_error(&self) -> Option<&EcallError> {
+        match self {
+            FrameHostError::EcallError { error, .. } => Some(error),
+            FrameHostError::Error(err) => EcallError::find(err),
+            _ => None,
+        }
+    }
+}
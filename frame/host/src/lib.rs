@@ -1,8 +1,15 @@
 pub mod ecalls;
 pub mod engine;
 mod error;
+#[cfg(not(feature = "software-enclave"))]
 pub mod init_enclave;
+#[cfg(not(feature = "software-enclave"))]
 mod ocalls;
+#[cfg(feature = "software-enclave")]
+pub mod software_enclave;
 
 pub use error::FrameHostError as Error;
+#[cfg(not(feature = "software-enclave"))]
 pub use init_enclave::EnclaveDir;
+#[cfg(feature = "software-enclave")]
+pub use software_enclave::{EnclaveDir, SoftwareEnclave};
@@ -64,3 +64,70 @@ pub extern "C" fn ocall_get_quote(
 
     UntrustedStatus::success()
 }
+
+#[no_mangle]
+pub extern "C" fn ocall_qe_get_target_info(ret_ti: *mut sgx_target_info_t) -> UntrustedStatus {
+    #[cfg(feature = "dcap")]
+    {
+        let ret = unsafe { sgx_qe_get_target_info(ret_ti) };
+        if ret != sgx_quote3_error_t::SGX_QL_SUCCESS {
+            println!("sgx_qe_get_target_info returned {:?}", ret);
+            return UntrustedStatus::error();
+        }
+
+        UntrustedStatus::success()
+    }
+
+    #[cfg(not(feature = "dcap"))]
+    {
+        let _ = ret_ti;
+        println!("DCAP attestation is not supported. Build with `dcap` feature to enable it.");
+        UntrustedStatus::error()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn ocall_qe_get_quote(
+    p_report: *const sgx_report_t,
+    p_quote: *mut u8,
+    maxlen: u32,
+    p_quote_len: *mut u32,
+) -> UntrustedStatus {
+    #[cfg(feature = "dcap")]
+    {
+        let mut real_quote_len: u32 = 0;
+
+        let ret = unsafe { sgx_qe_get_quote_size(&mut real_quote_len as *mut u32) };
+        if ret != sgx_quote3_error_t::SGX_QL_SUCCESS {
+            println!("sgx_qe_get_quote_size returned {:?}", ret);
+            return UntrustedStatus::error();
+        }
+        if real_quote_len > maxlen {
+            println!(
+                "quote size ({}) exceeds the buffer ({})",
+                real_quote_len, maxlen
+            );
+            return UntrustedStatus::error();
+        }
+
+        println!("quote size = {}", real_quote_len);
+        unsafe {
+            *p_quote_len = real_quote_len;
+        }
+
+        let ret = unsafe { sgx_qe_get_quote(p_report, real_quote_len, p_quote) };
+        if ret != sgx_quote3_error_t::SGX_QL_SUCCESS {
+            println!("sgx_qe_get_quote returned {:?}", ret);
+            return UntrustedStatus::error();
+        }
+
+        UntrustedStatus::success()
+    }
+
+    #[cfg(not(feature = "dcap"))]
+    {
+        let _ = (p_report, p_quote, maxlen, p_quote_len);
+        println!("DCAP attestation is not supported. Build with `dcap` feature to enable it.");
+        UntrustedStatus::error()
+    }
+}
@@ -0,0 +1,147 @@
+//! An in-process backend of enclaves for machines without SGX, enabled by the `software-enclave` feature.
+//!
+//! The enclave crate built with its `software-enclave` feature is linked into the host process,
+//! and the ecall handler generated by `register_ecall!` is called directly
+//! instead of `ecall_entry_point` through the SGX runtime.
+//! The attestation reports of such enclaves are mocked without signatures, so they are accepted only by ledgers which don't verify them.
+//! Nothing is protected by hardware, so this must be used only for testing.
+
+use crate::error::{FrameHostError, Result};
+use frame_types::EnclaveStatus;
+use once_cell::sync::Lazy;
+use sgx_types::{sgx_enclave_id_t, sgx_status_t};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+use tracing::info;
+
+/// The ecall handler of an enclave, which takes a command, an input and the max length of the output.
+/// It calls `handle_ecall` generated by `register_ecall!` with the context of the enclave.
+pub type EcallHandler =
+    Arc<dyn Fn(u32, &mut [u8], usize) -> (EnclaveStatus, Vec<u8>) + Send + Sync>;
+
+/// A function instantiating an enclave with its own context and returning its ecall handler.
+pub type EnclaveFactory = fn() -> anyhow::Result<EcallHandler>;
+
+static ENCLAVE_FACTORY: Lazy<RwLock<Option<EnclaveFactory>>> = Lazy::new(Default::default);
+static ENCLAVES: Lazy<RwLock<HashMap<sgx_enclave_id_t, EcallHandler>>> =
+    Lazy::new(Default::default);
+static NEXT_ENCLAVE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Set the factory of the enclaves initialized by `EnclaveDir`.
+/// It has to be set before initializing enclaves, e.g. at the beginning of main function.
+pub fn set_enclave_factory(factory: EnclaveFactory) {
+    *ENCLAVE_FACTORY.write().expect("Poisoned lock") = Some(factory);
+}
+
+/// An enclave running in the host process. The enclave is destroyed when this is dropped.
+#[derive(Debug)]
+pub struct SoftwareEnclave {
+    eid: sgx_enclave_id_t,
+}
+
+impl SoftwareEnclave {
+    pub fn new(handler: EcallHandler) -> Self {
+        let eid = NEXT_ENCLAVE_ID.fetch_add(1, Ordering::SeqCst);
+        ENCLAVES
+            .write()
+            .expect("Poisoned lock")
+            .insert(eid, handler);
+        info!("Software enclave is initialized: eid: {}", eid);
+
+        SoftwareEnclave { eid }
+    }
+
+    pub fn geteid(&self) -> sgx_enclave_id_t {
+        self.eid
+    }
+
+    pub fn destroy(self) {}
+}
+
+impl Drop for SoftwareEnclave {
+    fn drop(&mut self) {
+        ENCLAVES.write().expect("Poisoned lock").remove(&self.eid);
+    }
+}
+
+/// Initialize software enclaves with the same interface as the SGX backend.
+#[derive(Debug, Default)]
+pub struct EnclaveDir;
+
+impl EnclaveDir {
+    pub fn new() -> Self {
+        EnclaveDir
+    }
+
+    /// Debug mode has no effect on software enclaves.
+    pub fn init_enclave(&self, _is_debug: bool) -> Result<SoftwareEnclave> {
+        let factory = ENCLAVE_FACTORY
+            .read()
+            .expect("Poisoned lock")
+            .ok_or_else(|| anyhow::anyhow!("The factory of software enclaves is not set"))?;
+
+        Ok(SoftwareEnclave::new(factory()?))
+    }
+}
+
+pub(crate) fn ecall_entry_point(
+    eid: sgx_enclave_id_t,
+    cmd: u32,
+    input: &mut [u8],
+    output_max_len: usize,
+) -> Result<(EnclaveStatus, Vec<u8>)> {
+    let handler = ENCLAVES
+        .read()
+        .expect("Poisoned lock")
+        .get(&eid)
+        .cloned()
+        .ok_or_else(|| FrameHostError::SgxStatus {
+            status: sgx_status_t::SGX_ERROR_INVALID_ENCLAVE_ID,
+            function: "software_enclave::ecall_entry_point",
+            cmd,
+        })?;
+
+    Ok(handler(cmd, input, output_max_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_handler() -> EcallHandler {
+        Arc::new(|cmd, input, _output_max_len| match cmd {
+            0 => (EnclaveStatus::success(), input.to_vec()),
+            _ => (EnclaveStatus::error(), vec![]),
+        })
+    }
+
+    #[test]
+    fn test_ecall_to_software_enclave() {
+        let enclave = SoftwareEnclave::new(echo_handler());
+        let eid = enclave.geteid();
+
+        let (status, output) = ecall_entry_point(eid, 0, &mut [1, 2, 3], 10).unwrap();
+        assert!(!status.is_err());
+        assert_eq!(output, vec![1, 2, 3]);
+        let (status, _) = ecall_entry_point(eid, 1, &mut [], 10).unwrap();
+        assert!(status.is_err());
+
+        enclave.destroy();
+        assert!(ecall_entry_point(eid, 0, &mut [], 10).is_err());
+    }
+
+    #[test]
+    fn test_enclave_ids_are_distinct() {
+        let enclave = SoftwareEnclave::new(echo_handler());
+        let other = SoftwareEnclave::new(echo_handler());
+        assert_ne!(enclave.geteid(), other.geteid());
+
+        drop(other);
+        assert!(ecall_entry_point(enclave.geteid(), 0, &mut [], 10).is_ok());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::local_anyhow::Result;
+use crate::localstd::fmt::Debug;
+use crate::types::{Snapshot, WalEntry};
+
+/// Storage behind the enclave's key-value tables.
+/// Every state transition is appended as a `WalEntry`, and the whole tables are
+/// occasionally checkpointed as a `Snapshot` which supersedes the preceding entries.
+pub trait KvsBackend: Send + Sync + Debug {
+    /// Load the latest snapshot with all subsequent entries applied.
+    /// Returns `None` if nothing has been persisted yet.
+    fn load(&self) -> Result<Option<Snapshot>>;
+
+    fn append(&self, entry: &WalEntry) -> Result<()>;
+
+    fn checkpoint(&self, snapshot: &Snapshot) -> Result<()>;
+}
+
+/// A backend which persists nothing. All state is lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend;
+
+impl KvsBackend for MemoryBackend {
+    fn load(&self) -> Result<Option<Snapshot>> {
+        Ok(None)
+    }
+
+    fn append(&self, _entry: &WalEntry) -> Result<()> {
+        Ok(())
+    }
+
+    fn checkpoint(&self, _snapshot: &Snapshot) -> Result<()> {
+        Ok(())
+    }
+}
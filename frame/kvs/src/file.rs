@@ -0,0 +1,284 @@
+use crate::backend::KvsBackend;
+use crate::bincode;
+use crate::local_anyhow::{anyhow, Result};
+#[cfg(feature = "std")]
+use crate::localstd::sync::Mutex;
+#[cfg(feature = "sgx")]
+use crate::localstd::sync::SgxMutex as Mutex;
+use crate::localstd::{
+    convert::TryInto,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    vec::Vec,
+};
+use crate::seal::SealingKey;
+use crate::types::{Snapshot, WalEntry};
+
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+const WAL_FILE: &str = "wal";
+const SNAPSHOT_AAD: &[u8] = b"anonify-kvs-snapshot";
+const WAL_AAD: &[u8] = b"anonify-kvs-wal";
+const FRAME_LEN_SIZE: usize = 4;
+
+/// A backend persisting sealed data in a directory of the untrusted filesystem.
+///
+/// The directory contains two files.
+///  - `snapshot`: a sealed `Snapshot`. It is replaced atomically via a temporary file.
+///  - `wal`: a sequence of frames, each of them is a 4 bytes big-endian length followed by
+///    a sealed `WalEntry`. It is truncated after every checkpoint.
+///
+/// A partially written frame at the tail of the wal, which is left by a crash while appending,
+/// is ignored on loading. Any frame failing authentication is treated as an error.
+#[derive(Debug)]
+pub struct SealedFileBackend {
+    dir: PathBuf,
+    key: SealingKey,
+    wal: Mutex<()>,
+}
+
+impl SealedFileBackend {
+    pub fn new<P: AsRef<Path>>(dir: P, key: SealingKey) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        Ok(SealedFileBackend {
+            dir,
+            key,
+            wal: Mutex::new(()),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Snapshot>> {
+        let sealed = match fs::read(self.dir.join(SNAPSHOT_FILE)) {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let plaintext = self.key.unseal(&sealed, SNAPSHOT_AAD)?;
+        let snapshot = bincode::deserialize(&plaintext)?;
+
+        Ok(Some(snapshot))
+    }
+
+    fn load_wal(&self) -> Result<Vec<WalEntry>> {
+        let mut buf = vec![];
+        match File::open(self.dir.join(WAL_FILE)) {
+            Ok(mut file) => {
+                file.read_to_end(&mut buf)?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut entries = vec![];
+        let mut rest = &buf[..];
+        while rest.len() >= FRAME_LEN_SIZE {
+            let (len, body) = rest.split_at(FRAME_LEN_SIZE);
+            let len = u32::from_be_bytes(len.try_into()?) as usize;
+            if body.len() < len {
+                // torn write at the tail
+                break;
+            }
+            let (sealed, next) = body.split_at(len);
+            let plaintext = self.key.unseal(sealed, WAL_AAD)?;
+            entries.push(bincode::deserialize(&plaintext)?);
+            rest = next;
+        }
+
+        Ok(entries)
+    }
+}
+
+impl KvsBackend for SealedFileBackend {
+    fn load(&self) -> Result<Option<Snapshot>> {
+        let _guard = self
+            .wal
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock wal: {:?}", e))?;
+        let snapshot = self.load_snapshot()?;
+        let entries = self.load_wal()?;
+        if snapshot.is_none() && entries.is_empty() {
+            return Ok(None);
+        }
+
+        let mut snapshot = snapshot.unwrap_or_default();
+        for entry in entries {
+            snapshot.apply(entry);
+        }
+
+        Ok(Some(snapshot))
+    }
+
+    fn append(&self, entry: &WalEntry) -> Result<()> {
+        let sealed = self.key.seal(&bincode::serialize(entry)?, WAL_AAD)?;
+        let mut frame = Vec::with_capacity(FRAME_LEN_SIZE + sealed.len());
+        frame.extend_from_slice(&(sealed.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&sealed);
+
+        let _guard = self
+            .wal
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock wal: {:?}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(WAL_FILE))?;
+        file.write_all(&frame)?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, snapshot: &Snapshot) -> Result<()> {
+        let sealed = self.key.seal(&bincode::serialize(snapshot)?, SNAPSHOT_AAD)?;
+
+        let _guard = self
+            .wal
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock wal: {:?}", e))?;
+        let tmp_path = self.dir.join(SNAPSHOT_TMP_FILE);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&sealed)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE))?;
+
+        // Entries up to the snapshot are no longer needed.
+        // If a crash happens before truncating, they are skipped on loading.
+        File::create(self.dir.join(WAL_FILE))?.sync_all()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Write as KvsWrite;
+    use frame_common::{crypto::rand_assign, state_types::StateCounter};
+    use std::env;
+
+    fn temp_dir() -> PathBuf {
+        let mut suffix = [0u8; 8];
+        rand_assign(&mut suffix).unwrap();
+        env::temp_dir().join(format!("frame-kvs-{}", hex_encode(&suffix)))
+    }
+
+    fn hex_encode(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn entry(counter: u32, key: u8, value: Option<u8>) -> WalEntry {
+        let write = match value {
+            Some(v) => KvsWrite::put("t", vec![key], vec![v]),
+            None => KvsWrite::delete("t", vec![key]),
+        };
+        WalEntry {
+            state_counter: StateCounter::new(counter),
+            block_num: counter as u64 * 10,
+            writes: vec![write],
+        }
+    }
+
+    #[test]
+    fn test_load_empty() {
+        let backend = SealedFileBackend::new(temp_dir(), SealingKey::new([1u8; 32])).unwrap();
+        assert!(backend.load().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_wal_roundtrip() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        backend.append(&entry(1, 0, Some(1))).unwrap();
+        backend.append(&entry(2, 1, Some(2))).unwrap();
+        backend.append(&entry(3, 0, None)).unwrap();
+
+        let reopened = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        let snapshot = reopened.load().unwrap().unwrap();
+        assert_eq!(snapshot.state_counter, StateCounter::new(3));
+        assert_eq!(snapshot.block_num, 30);
+        let table = snapshot.table("t").unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&vec![1]), Some(&vec![2]));
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_wal() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        backend.append(&entry(1, 0, Some(1))).unwrap();
+        backend.append(&entry(2, 1, Some(2))).unwrap();
+        let snapshot = backend.load().unwrap().unwrap();
+        backend.checkpoint(&snapshot).unwrap();
+        assert_eq!(fs::metadata(dir.join(WAL_FILE)).unwrap().len(), 0);
+
+        backend.append(&entry(3, 2, Some(3))).unwrap();
+        let loaded = backend.load().unwrap().unwrap();
+        assert_eq!(loaded.state_counter, StateCounter::new(3));
+        assert_eq!(loaded.table("t").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_skip_entries_covered_by_snapshot() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        let mut snapshot = Snapshot::default();
+        snapshot.apply(entry(1, 0, Some(1)));
+        snapshot.apply(entry(2, 0, Some(2)));
+        backend.checkpoint(&snapshot).unwrap();
+        // emulate a crash before the wal was truncated
+        backend.append(&entry(1, 0, Some(1))).unwrap();
+
+        let loaded = backend.load().unwrap().unwrap();
+        assert_eq!(loaded.state_counter, StateCounter::new(2));
+        assert_eq!(loaded.table("t").unwrap().get(&vec![0]), Some(&vec![2]));
+    }
+
+    #[test]
+    fn test_ignore_torn_tail() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        backend.append(&entry(1, 0, Some(1))).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(WAL_FILE))
+            .unwrap();
+        file.write_all(&[0, 0, 0, 100, 1, 2, 3]).unwrap();
+
+        let loaded = backend.load().unwrap().unwrap();
+        assert_eq!(loaded.state_counter, StateCounter::new(1));
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        backend.append(&entry(1, 0, Some(1))).unwrap();
+
+        let other = SealedFileBackend::new(&dir, SealingKey::new([2u8; 32])).unwrap();
+        assert!(other.load().is_err());
+    }
+
+    #[test]
+    fn test_tampered_snapshot() {
+        let dir = temp_dir();
+        let backend = SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap();
+        let mut snapshot = Snapshot::default();
+        snapshot.apply(entry(1, 0, Some(1)));
+        backend.checkpoint(&snapshot).unwrap();
+
+        let path = dir.join(SNAPSHOT_FILE);
+        let mut sealed = fs::read(&path).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        fs::write(&path, sealed).unwrap();
+
+        assert!(backend.load().is_err());
+    }
+}
@@ -0,0 +1,37 @@
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+#[cfg(feature = "std")]
+use std as localstd;
+#[cfg(feature = "sgx")]
+#[macro_use]
+extern crate sgx_tstd as localstd;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use bincode_sgx as bincode;
+#[cfg(feature = "std")]
+use bincode_std as bincode;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use serde_sgx as serde;
+#[cfg(feature = "std")]
+use serde_std as serde;
+
+#[cfg(feature = "std")]
+use anyhow as local_anyhow;
+#[cfg(feature = "sgx")]
+use sgx_anyhow as local_anyhow;
+#[cfg(feature = "sgx")]
+use sgx_xsalsa20poly1305 as xsalsa20poly1305;
+#[cfg(feature = "std")]
+use std_xsalsa20poly1305 as xsalsa20poly1305;
+
+mod backend;
+mod file;
+pub mod merkle;
+mod seal;
+mod staging;
+mod types;
+
+pub use backend::{KvsBackend, MemoryBackend};
+pub use file::SealedFileBackend;
+pub use seal::{SealingKey, SEALING_KEY_SIZE};
+pub use staging::{Staging, Transaction};
+pub use types::{Snapshot, Tables, WalEntry, Write};
@@ -0,0 +1,285 @@
+use crate::localstd::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+    vec::Vec,
+};
+use frame_common::{crypto::Sha256, state_types::StateRoot, traits::Hash256};
+
+pub type MerkleHash = [u8; 32];
+
+// Domain separation prefixes so that a leaf can never be interpreted as an inner node.
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hash an entry of a table.
+/// The key length is included so that the boundary between the key and the value is unambiguous.
+pub fn leaf_hash(key: &[u8], value: &[u8]) -> MerkleHash {
+    let mut buf = Vec::with_capacity(1 + 4 + key.len() + value.len());
+    buf.push(LEAF_PREFIX);
+    buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    buf.extend_from_slice(key);
+    buf.extend_from_slice(value);
+    Sha256::hash(&buf).as_array()
+}
+
+fn node_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    let mut buf = Vec::with_capacity(1 + 32 * 2);
+    buf.push(NODE_PREFIX);
+    buf.extend_from_slice(left);
+    buf.extend_from_slice(right);
+    Sha256::hash(&buf).as_array()
+}
+
+/// Compute the root of a binary Merkle tree over the ordered leaves.
+/// An odd node at the end of a level is promoted to the next level as it is.
+/// The root of no leaves is all zero.
+pub fn merkle_root<'a, I>(leaves: I) -> StateRoot
+where
+    I: IntoIterator<Item = &'a MerkleHash>,
+{
+    let mut level: Vec<MerkleHash> = leaves.into_iter().cloned().collect();
+    if level.is_empty() {
+        return StateRoot::default();
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [odd] => *odd,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    StateRoot::new(level[0])
+}
+
+/// Compute the root over all entries of a table in the order of the keys.
+pub fn table_root(table: &BTreeMap<Vec<u8>, Vec<u8>>) -> StateRoot {
+    let leaves: Vec<MerkleHash> = table.iter().map(|(k, v)| leaf_hash(k, v)).collect();
+    merkle_root(&leaves)
+}
+
+/// A Merkle tree over a table which keeps all levels of the tree between calls of `root`.
+/// Updating the value of an existing key recomputes only the path from its leaf to the root.
+/// Inserting or removing a key shifts the leaves after it, so only the nodes on the right of it are recomputed.
+/// The root is equal to `table_root` over the same entries.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// The keys in the order of the leaves
+    keys: Vec<Vec<u8>>,
+    /// `levels[0]` is the leaves and the last level is the root
+    levels: Vec<Vec<MerkleHash>>,
+    /// The indices of the leaves updated in place since the last call of `root`
+    dirty: BTreeSet<usize>,
+    /// The smallest index of the leaves shifted by insertions or removals since the last call of `root`
+    shifted_from: Option<usize>,
+}
+
+impl Default for MerkleTree {
+    fn default() -> Self {
+        MerkleTree {
+            keys: vec![],
+            levels: vec![vec![]],
+            dirty: BTreeSet::new(),
+            shifted_from: None,
+        }
+    }
+}
+
+impl MerkleTree {
+    pub fn from_table(table: &BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        let keys = table.keys().cloned().collect();
+        let leaves = table.iter().map(|(k, v)| leaf_hash(k, v)).collect();
+        MerkleTree {
+            keys,
+            levels: vec![leaves],
+            dirty: BTreeSet::new(),
+            shifted_from: Some(0),
+        }
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, value: &[u8]) {
+        let leaf = leaf_hash(&key, value);
+        match self.keys.binary_search(&key) {
+            Ok(idx) => {
+                if self.levels[0][idx] != leaf {
+                    self.levels[0][idx] = leaf;
+                    self.dirty.insert(idx);
+                }
+            }
+            Err(idx) => {
+                self.keys.insert(idx, key);
+                self.levels[0].insert(idx, leaf);
+                self.shift_from(idx);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        if let Ok(idx) = self.keys.binary_search_by(|k| k[..].cmp(key)) {
+            self.keys.remove(idx);
+            self.levels[0].remove(idx);
+            self.shift_from(idx);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Return the root, recomputing only the inner nodes affected by the updates since the last call.
+    pub fn root(&mut self) -> StateRoot {
+        let mut dirty = mem::take(&mut self.dirty);
+        let mut shifted_from = self.shifted_from.take();
+
+        let mut level = 0;
+        while self.levels[level].len() > 1 {
+            if self.levels.len() == level + 1 {
+                self.levels.push(vec![]);
+            }
+            let (lower, upper) = self.levels.split_at_mut(level + 1);
+            let (children, parents) = (&lower[level], &mut upper[0]);
+            let parents_len = children.chunks(2).len();
+            let parent = |idx: usize| match children.get(idx * 2 + 1) {
+                Some(right) => node_hash(&children[idx * 2], right),
+                None => children[idx * 2],
+            };
+
+            // The parents on the right of the shifted leaves are all recomputed,
+            // including those of the dirty leaves.
+            let rebuild_from = shifted_from.map(|idx| idx / 2).unwrap_or(parents_len);
+            parents.truncate(rebuild_from.min(parents_len));
+            dirty = dirty
+                .into_iter()
+                .map(|idx| idx / 2)
+                .filter(|idx| *idx < parents.len())
+                .collect();
+            for idx in &dirty {
+                parents[*idx] = parent(*idx);
+            }
+            for idx in parents.len()..parents_len {
+                parents.push(parent(idx));
+            }
+
+            shifted_from = shifted_from.map(|_| rebuild_from);
+            level += 1;
+        }
+        self.levels.truncate(level + 1);
+
+        self.levels[level]
+            .first()
+            .map(|root| StateRoot::new(*root))
+            .unwrap_or_default()
+    }
+
+    fn shift_from(&mut self, idx: usize) {
+        self.shifted_from = Some(self.shifted_from.map_or(idx, |from| from.min(idx)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(u8, u8)]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        entries.iter().map(|(k, v)| (vec![*k], vec![*v])).collect()
+    }
+
+    #[test]
+    fn test_empty_root() {
+        assert_eq!(table_root(&BTreeMap::new()), StateRoot::default());
+    }
+
+    #[test]
+    fn test_root_is_independent_of_insertion_order() {
+        let a = table(&[(1, 10), (2, 20), (3, 30)]);
+        let b = table(&[(3, 30), (1, 10), (2, 20)]);
+        assert_eq!(table_root(&a), table_root(&b));
+    }
+
+    #[test]
+    fn test_root_changes_with_values() {
+        let a = table(&[(1, 10), (2, 20), (3, 30)]);
+        let b = table(&[(1, 10), (2, 21), (3, 30)]);
+        assert_ne!(table_root(&a), table_root(&b));
+    }
+
+    #[test]
+    fn test_odd_leaf_is_promoted() {
+        let leaves = [
+            leaf_hash(&[1], &[10]),
+            leaf_hash(&[2], &[20]),
+            leaf_hash(&[3], &[30]),
+        ];
+        let expected = node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(merkle_root(&leaves), StateRoot::new(expected));
+    }
+
+    #[test]
+    fn test_tree_matches_table_root() {
+        let mut entries = table(&[(1, 10), (2, 20), (3, 30), (4, 40)]);
+        let mut tree = MerkleTree::from_table(&entries);
+        assert_eq!(tree.root(), table_root(&entries));
+
+        tree.insert(vec![5], &[50]);
+        entries.insert(vec![5], vec![50]);
+        assert_eq!(tree.root(), table_root(&entries));
+
+        tree.insert(vec![2], &[21]);
+        entries.insert(vec![2], vec![21]);
+        assert_eq!(tree.root(), table_root(&entries));
+
+        tree.remove(&[1]);
+        entries.remove(&vec![1]);
+        assert_eq!(tree.root(), table_root(&entries));
+
+        tree.remove(&[9]);
+        assert_eq!(tree.root(), table_root(&entries));
+        assert_eq!(tree.len(), entries.len());
+    }
+
+    #[test]
+    fn test_incremental_root_matches_full_recompute() {
+        let mut entries = BTreeMap::new();
+        let mut tree = MerkleTree::default();
+        // A deterministic sequence of inserts, updates and removals over a small key space
+        let mut seed: u32 = 7;
+        for i in 0..2000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let key = vec![(seed >> 16) as u8 % 64];
+            match seed % 5 {
+                0 => {
+                    tree.remove(&key);
+                    entries.remove(&key);
+                }
+                _ => {
+                    let value = vec![(seed >> 8) as u8];
+                    tree.insert(key.clone(), &value);
+                    entries.insert(key, value);
+                }
+            }
+            // Check both after each write and after batches of writes
+            if i % 3 != 0 {
+                assert_eq!(tree.root(), table_root(&entries));
+            }
+        }
+        assert_eq!(tree.len(), entries.len());
+
+        for key in entries.keys().cloned().collect::<Vec<_>>() {
+            tree.remove(&key);
+        }
+        assert_eq!(tree.root(), StateRoot::default());
+    }
+
+    #[test]
+    fn test_key_value_boundary() {
+        assert_ne!(leaf_hash(&[1, 2], &[3]), leaf_hash(&[1], &[2, 3]));
+    }
+}
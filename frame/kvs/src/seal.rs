@@ -0,0 +1,81 @@
+use crate::local_anyhow::{anyhow, ensure, Result};
+use crate::localstd::{fmt, vec::Vec};
+use crate::xsalsa20poly1305::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    XSalsa20Poly1305, NONCE_SIZE,
+};
+use frame_common::{
+    crypto::{rand_assign, Sha256},
+    traits::Hash256,
+};
+
+pub const SEALING_KEY_SIZE: usize = 32;
+
+/// A symmetric key used to encrypt and authenticate the persisted tables.
+/// Inside the enclave it is derived from the SGX seal key so that the files can only be opened
+/// by the same enclave signer on the same platform. In std mode any 32 bytes key can be used
+/// as a software stand-in.
+#[derive(Clone, PartialEq)]
+pub struct SealingKey([u8; SEALING_KEY_SIZE]);
+
+impl fmt::Debug for SealingKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SealingKey(..)")
+    }
+}
+
+impl SealingKey {
+    pub fn new(key: [u8; SEALING_KEY_SIZE]) -> Self {
+        SealingKey(key)
+    }
+
+    /// Derive a key from arbitrary length key material.
+    pub fn derive(material: &[u8]) -> Self {
+        SealingKey(Sha256::hash(material).as_array())
+    }
+
+    /// Encrypt `plaintext` bound to `aad`. The returned bytes are `nonce || ciphertext`.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand_assign(&mut nonce)?;
+
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|e| anyhow!("Failed to seal: {:?}", e))?;
+
+        let mut res = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        res.extend_from_slice(&nonce);
+        res.extend_from_slice(&ciphertext);
+        Ok(res)
+    }
+
+    pub fn unseal(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        ensure!(
+            sealed.len() > NONCE_SIZE,
+            "sealed data is too short: {}",
+            sealed.len()
+        );
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+
+        self.cipher()
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|e| anyhow!("Failed to unseal: {:?}", e))
+    }
+
+    fn cipher(&self) -> XSalsa20Poly1305 {
+        XSalsa20Poly1305::new(GenericArray::from_slice(&self.0))
+    }
+}
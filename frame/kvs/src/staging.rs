@@ -0,0 +1,118 @@
+use crate::local_anyhow::{anyhow, Result};
+#[cfg(feature = "std")]
+use crate::localstd::sync::{Mutex, MutexGuard};
+#[cfg(feature = "sgx")]
+use crate::localstd::sync::{SgxMutex as Mutex, SgxMutexGuard as MutexGuard};
+use crate::localstd::{mem, sync::Arc, vec::Vec};
+use crate::types::Write;
+
+/// Writes staged by the state transition in progress.
+///
+/// Only one transition is open at a time. Writes are accepted only while a transition is open,
+/// so that a write made outside of any transition is rejected rather than persisted
+/// as a part of the next one.
+#[derive(Debug, Clone, Default)]
+pub struct Staging {
+    writes: Arc<Mutex<Option<Vec<Write>>>>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl Staging {
+    /// Open a state transition, blocking until the previous one is closed.
+    pub fn begin(&self) -> Transaction<'_> {
+        let lock = self.lock.lock().unwrap();
+        *self.writes.lock().unwrap() = Some(vec![]);
+        Transaction {
+            staging: self,
+            _lock: lock,
+        }
+    }
+
+    pub fn put(&self, table: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.stage(Write::put(table, key, value))
+    }
+
+    pub fn delete(&self, table: &str, key: Vec<u8>) -> Result<()> {
+        self.stage(Write::delete(table, key))
+    }
+
+    fn stage(&self, write: Write) -> Result<()> {
+        self.writes
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or_else(|| anyhow!("No state transition is open to stage the write"))?
+            .push(write);
+        Ok(())
+    }
+}
+
+/// A state transition in progress. The staged writes are discarded when it is dropped,
+/// unless they have been taken to be persisted.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    staging: &'a Staging,
+    _lock: MutexGuard<'a, ()>,
+}
+
+impl Transaction<'_> {
+    /// Take the writes staged so far. The transition stays open until it is dropped.
+    pub fn take_writes(&mut self) -> Vec<Write> {
+        self.staging
+            .writes
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(mem::take)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        self.staging.writes.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_staged_writes() {
+        let staging = Staging::default();
+        let mut transaction = staging.begin();
+        staging.put("t", vec![0], vec![1]).unwrap();
+        staging.delete("t", vec![1]).unwrap();
+
+        assert_eq!(
+            transaction.take_writes(),
+            vec![
+                Write::put("t", vec![0], vec![1]),
+                Write::delete("t", vec![1])
+            ]
+        );
+        assert!(transaction.take_writes().is_empty());
+    }
+
+    #[test]
+    fn test_reject_write_outside_transaction() {
+        let staging = Staging::default();
+        assert!(staging.put("t", vec![0], vec![1]).is_err());
+
+        drop(staging.begin());
+        assert!(staging.delete("t", vec![0]).is_err());
+    }
+
+    #[test]
+    fn test_discard_on_drop() {
+        let staging = Staging::default();
+        {
+            let _transaction = staging.begin();
+            staging.put("t", vec![0], vec![1]).unwrap();
+        }
+
+        let mut transaction = staging.begin();
+        assert!(transaction.take_writes().is_empty());
+    }
+}
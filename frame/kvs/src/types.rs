@@ -0,0 +1,82 @@
+use crate::localstd::{collections::BTreeMap, string::String, vec::Vec};
+use crate::serde::{Deserialize, Serialize};
+use frame_common::state_types::StateCounter;
+
+/// Key-value pairs of every table, keyed by the table name.
+pub type Tables = BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// A single mutation of a table. `None` value means the key is deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct Write {
+    pub table: String,
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+impl Write {
+    pub fn put(table: impl Into<String>, key: Vec<u8>, value: Vec<u8>) -> Self {
+        Write {
+            table: table.into(),
+            key,
+            value: Some(value),
+        }
+    }
+
+    pub fn delete(table: impl Into<String>, key: Vec<u8>) -> Self {
+        Write {
+            table: table.into(),
+            key,
+            value: None,
+        }
+    }
+}
+
+/// An entry of the write-ahead log.
+/// It contains all writes which were made by the state transition identified by `state_counter`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct WalEntry {
+    pub state_counter: StateCounter,
+    pub block_num: u64,
+    pub writes: Vec<Write>,
+}
+
+/// A whole image of the tables at the time `state_counter` was applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct Snapshot {
+    pub state_counter: StateCounter,
+    pub block_num: u64,
+    pub tables: Tables,
+}
+
+impl Snapshot {
+    /// Apply the entry if it is newer than this snapshot.
+    /// Returns false if the entry is already covered.
+    pub fn apply(&mut self, entry: WalEntry) -> bool {
+        if entry.state_counter <= self.state_counter {
+            return false;
+        }
+
+        for write in entry.writes {
+            let table = self.tables.entry(write.table).or_insert_with(BTreeMap::new);
+            match write.value {
+                Some(value) => {
+                    table.insert(write.key, value);
+                }
+                None => {
+                    table.remove(&write.key);
+                }
+            }
+        }
+        self.state_counter = entry.state_counter;
+        self.block_num = entry.block_num;
+
+        true
+    }
+
+    pub fn table(&self, name: &str) -> Option<&BTreeMap<Vec<u8>, Vec<u8>>> {
+        self.tables.get(name)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::AttestedTlsConfig;
+use crate::error::Result;
+use frame_config::ATTESTATION_CACHE_TTL_SECS;
+use remote_attestation::ATTESTATION_POLICY;
+use std::{
+    prelude::v1::*,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, SgxRwLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::{error, info, warn};
+
+/// A cache of the attested certificates, so that the remote attestation is not performed for every TLS config.
+/// The certificates are used for `ttl`, which is bounded by the maximum report age of `ATTESTATION_POLICY`.
+/// Once the cached one is older than half of `ttl`, a new certificate is attested in the background,
+/// and it is attested synchronously only if the cached one is expired.
+/// The clones share the same cache.
+#[derive(Clone)]
+pub struct AttestedTlsConfigCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    spid: String,
+    ias_url: String,
+    sub_key: String,
+    root_cert: Vec<u8>,
+    ttl: Duration,
+    cached: SgxRwLock<Option<CachedConfig>>,
+    refreshing: AtomicBool,
+}
+
+#[derive(Clone)]
+struct CachedConfig {
+    config: AttestedTlsConfig,
+    certified_key: rustls::sign::CertifiedKey,
+    attested_at: SystemTime,
+}
+
+impl AttestedTlsConfigCache {
+    /// Create the cache whose ttl is `ATTESTATION_CACHE_TTL_SECS`.
+    pub fn new(spid: &str, ias_url: &str, sub_key: &str, root_cert: Vec<u8>) -> Self {
+        Self::with_ttl(
+            spid,
+            ias_url,
+            sub_key,
+            root_cert,
+            Duration::from_secs(*ATTESTATION_CACHE_TTL_SECS),
+        )
+    }
+
+    pub fn with_ttl(
+        spid: &str,
+        ias_url: &str,
+        sub_key: &str,
+        root_cert: Vec<u8>,
+        ttl: Duration,
+    ) -> Self {
+        // An invalid policy fails every attestation, so the ttl doesn't matter in that case.
+        let max_report_age_secs = ATTESTATION_POLICY
+            .as_ref()
+            .ok()
+            .and_then(|policy| policy.max_report_age_secs());
+        let ttl = match max_report_age_secs {
+            Some(max_age) if Duration::from_secs(max_age) < ttl => {
+                warn!(
+                    "The ttl of attested certificates is bounded by the maximum report age: {} secs",
+                    max_age
+                );
+                Duration::from_secs(max_age)
+            }
+            _ => ttl,
+        };
+
+        AttestedTlsConfigCache {
+            inner: Arc::new(Inner {
+                spid: spid.to_string(),
+                ias_url: ias_url.to_string(),
+                sub_key: sub_key.to_string(),
+                root_cert,
+                ttl,
+                cached: SgxRwLock::new(None),
+                refreshing: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Get the cached config, which is attested if it is expired.
+    pub fn get(&self) -> Result<AttestedTlsConfig> {
+        self.cached().map(|cached| cached.config)
+    }
+
+    /// Attest a new config and replace the cached one.
+    pub fn refresh(&self) -> Result<AttestedTlsConfig> {
+        self.attest().map(|cached| cached.config)
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.inner.ttl
+    }
+
+    fn cached(&self) -> Result<CachedConfig> {
+        if let Some(cached) = self.inner.cached.read().expect("Poisoned lock").as_ref() {
+            // A certificate attested ahead of the local clock is regarded as a fresh one
+            let age = cached.attested_at.elapsed().unwrap_or_default();
+            if age < self.inner.ttl {
+                if age >= self.inner.ttl / 2 {
+                    self.refresh_in_background();
+                }
+                return Ok(cached.clone());
+            }
+        }
+
+        self.attest()
+    }
+
+    fn attest(&self) -> Result<CachedConfig> {
+        let config = AttestedTlsConfig::new_by_ra(
+            &self.inner.spid,
+            &self.inner.ias_url,
+            &self.inner.sub_key,
+            self.inner.root_cert.clone(),
+        )?;
+        let certified_key = config.certified_key()?;
+        let cached = CachedConfig {
+            config,
+            certified_key,
+            attested_at: SystemTime::now(),
+        };
+        *self.inner.cached.write().expect("Poisoned lock") = Some(cached.clone());
+        info!("The attested certificate is refreshed");
+
+        Ok(cached)
+    }
+
+    fn refresh_in_background(&self) {
+        if self
+            .inner
+            .refreshing
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return;
+        }
+
+        let cache = self.clone();
+        let spawned = thread::Builder::new().spawn(move || {
+            if let Err(e) = cache.attest() {
+                error!("Failed to refresh the attested certificate: {:?}", e);
+            }
+            cache.inner.refreshing.store(false, Ordering::SeqCst);
+        });
+        // The certificate is attested synchronously once it is expired
+        if let Err(e) = spawned {
+            warn!(
+                "Failed to spawn a thread refreshing the attested certificate: {:?}",
+                e
+            );
+            self.inner.refreshing.store(false, Ordering::SeqCst);
+        }
+    }
+
+    fn certified_key(&self) -> Option<rustls::sign::CertifiedKey> {
+        self.cached()
+            .map(|cached| cached.certified_key)
+            .map_err(|e| error!("Failed to get the attested certificate: {:?}", e))
+            .ok()
+    }
+}
+
+impl rustls::ResolvesClientCert for AttestedTlsConfigCache {
+    fn resolve(
+        &self,
+        _acceptable_issuers: &[&[u8]],
+        _sigschemes: &[rustls::SignatureScheme],
+    ) -> Option<rustls::sign::CertifiedKey> {
+        self.certified_key()
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+impl rustls::ResolvesServerCert for AttestedTlsConfigCache {
+    fn resolve(&self, _client_hello: rustls::ClientHello) -> Option<rustls::sign::CertifiedKey> {
+        self.certified_key()
+    }
+}
@@ -1,36 +1,87 @@
 use crate::config::ClientConfig;
-use crate::connection::Connection;
+use crate::connection::{self, ClientConnection};
 use anyhow::{anyhow, Result};
 use frame_config::{REQUEST_RETRIES, RETRY_DELAY_MILLS};
 use frame_retrier::{strategy, Retry};
 use http::Uri;
 use serde::{de::DeserializeOwned, Serialize};
-use std::sync::Arc;
+use std::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use tracing::info;
 
+/// A client of mutual attested TLS, which reuses the session for the subsequent requests
+/// while the server keeps it alive.
 pub struct Client {
-    connection: Connection<rustls::ClientSession>,
+    address: String,
+    hostname: webpki::DNSName,
+    tls: Arc<rustls::ClientConfig>,
+    connection: Option<ClientConnection>,
 }
 
 impl Client {
     pub fn new(address: &str, client_config: &ClientConfig) -> Result<Self> {
         let uri = address.parse::<Uri>()?;
         let hostname = uri.host().ok_or_else(|| anyhow!("Invalid hostname"))?;
-        let hostname = webpki::DNSNameRef::try_from_ascii_str(hostname)?;
+        let hostname = webpki::DNSNameRef::try_from_ascii_str(hostname)?.to_owned();
 
-        let session = rustls::ClientSession::new(&Arc::new(client_config.tls().clone()), hostname);
-        let stream = std::net::TcpStream::connect(address)?;
-        let connection = Connection::new(session, stream);
+        let mut client = Client {
+            address: address.to_string(),
+            hostname,
+            tls: Arc::new(client_config.tls().clone()),
+            connection: None,
+        };
+        client.connection = Some(client.connect()?);
 
-        Ok(Client { connection })
+        Ok(client)
     }
 
+    /// Send the request over the current session. If the session has been closed, e.g. by the idle timeout of the server,
+    /// the request is sent again over a new session, so the requests should be idempotent.
     pub fn send_json<SE, DE>(&mut self, json: SE) -> Result<DE>
     where
         SE: Serialize,
         DE: DeserializeOwned,
     {
-        let wrt = serde_json::to_vec(&json)?;
-        Retry::new(
+        self.send_json_all(&[json])?
+            .pop()
+            .expect("A response is returned for each request")
+    }
+
+    /// Send all the requests before waiting for the responses, which are returned in the order of the requests.
+    /// The requests failed in the server don't affect the others.
+    pub fn send_json_all<SE, DE>(&mut self, jsons: &[SE]) -> Result<Vec<Result<DE>>>
+    where
+        SE: Serialize,
+        DE: DeserializeOwned,
+    {
+        let requests = jsons
+            .iter()
+            .map(serde_json::to_vec)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        let responses = self
+            .request_all(&requests)?
+            .into_iter()
+            .map(|response| Ok(serde_json::from_slice(&response?)?))
+            .collect();
+
+        Ok(responses)
+    }
+
+    fn request_all(&mut self, requests: &[Vec<u8>]) -> Result<Vec<frame_mux::Result<Vec<u8>>>> {
+        if let Some(mut connection) = self.connection.take() {
+            match connection.request_all(requests) {
+                Ok(responses) => {
+                    self.connection = Some(connection);
+                    return Ok(responses);
+                }
+                Err(e) => info!("Reconnecting to {}: {}", self.address, e),
+            }
+        }
+
+        let mut connection = Retry::new(
             "mutual_attested_tls",
             *REQUEST_RETRIES,
             strategy::FixedDelay::new(*RETRY_DELAY_MILLS),
@@ -39,9 +90,21 @@ impl Client {
             Ok(_) => false,
             Err(_) => true,
         })
-        .spawn(|| self.connection.write_frame(&wrt))?;
+        .spawn(|| {
+            let mut connection = self.connect()?;
+            connection::handshake(&mut connection)?;
+            Ok::<_, anyhow::Error>(connection)
+        })?;
+
+        let responses = connection.request_all(requests)?;
+        self.connection = Some(connection);
+        Ok(responses)
+    }
+
+    fn connect(&self) -> Result<ClientConnection> {
+        let session = rustls::ClientSession::new(&self.tls, self.hostname.as_ref());
+        let stream = std::net::TcpStream::connect(&self.address)?;
 
-        let rd = self.connection.read_frame()?;
-        serde_json::from_slice(&rd).map_err(Into::into)
+        Ok(connection::client_connection(session, stream))
     }
 }
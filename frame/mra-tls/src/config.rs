@@ -1,8 +1,10 @@
+use crate::cache::AttestedTlsConfigCache;
 use crate::error::Result;
 use crate::key::NistP256KeyPair;
+use crate::measurement::MeasurementAllowList;
 use crate::verifier::AttestedReportVerifier;
-use frame_config::EnclaveMeasurement;
-use remote_attestation::QuoteTarget;
+use anyhow::anyhow;
+use remote_attestation::attestation_policy;
 use std::{sync::Arc, vec::Vec};
 
 const CERT_ISSUER: &str = "Anonify";
@@ -15,20 +17,41 @@ pub struct AttestedTlsConfig {
 }
 
 impl AttestedTlsConfig {
+    /// Create the certificate whose extension has the attestation evidence of the type configured by `ATTESTATION_TYPE`.
+    /// The evidence must conform to `ATTESTATION_POLICY`, which is also applied to the peers.
+    /// The IAS parameters are used only for EPID.
     pub fn new_by_ra(spid: &str, ias_url: &str, sub_key: &str, root_cert: Vec<u8>) -> Result<Self> {
         let key_pair = NistP256KeyPair::new()?;
         let report_data = key_pair.report_data();
-        let attested_report = QuoteTarget::new()?
-            .set_enclave_report(&report_data)?
-            .create_quote(&spid)?
-            .remote_attestation(ias_url, sub_key, root_cert)?;
-
-        let extension = serde_json::to_vec(&attested_report)?;
+        let evidence = remote_attestation::attest(
+            &report_data,
+            spid,
+            ias_url,
+            sub_key,
+            root_cert,
+            attestation_policy()?,
+        )?;
+
+        let extension = serde_json::to_vec(&evidence)?;
         let ee_cert = key_pair.create_cert_with_extension(CERT_ISSUER, CERT_SUBJECT, &extension);
         let priv_key = key_pair.priv_key_into_der();
 
         Ok(Self { ee_cert, priv_key })
     }
+
+    /// The DER-encoded certificate with the attestation evidence
+    pub fn ee_cert(&self) -> &[u8] {
+        &self.ee_cert
+    }
+
+    pub(crate) fn certified_key(&self) -> Result<rustls::sign::CertifiedKey> {
+        let key = rustls::sign::any_ecdsa_type(&rustls::PrivateKey(self.priv_key.clone()))
+            .map_err(|_| anyhow!("Invalid private key of the attested certificate"))?;
+        Ok(rustls::sign::CertifiedKey::new(
+            vec![rustls::Certificate(self.ee_cert.clone())],
+            Arc::new(key),
+        ))
+    }
 }
 
 #[derive(Clone)]
@@ -49,16 +72,27 @@ impl ClientConfig {
         Ok(client_config)
     }
 
+    /// The client certificate is resolved from the cache for each handshake,
+    /// so that the sessions are established with the latest attestation.
+    pub fn from_attested_tls_config_cache(cache: AttestedTlsConfigCache) -> Self {
+        let mut client_config = ClientConfig::default();
+        client_config.tls.client_auth_cert_resolver = Arc::new(cache);
+
+        client_config
+    }
+
     pub fn tls(&self) -> &rustls::ClientConfig {
         &self.tls
     }
 
+    /// Verify the peers are attested and in the trusted measurements,
+    /// which is either a single `EnclaveMeasurement` or a shared `MeasurementAllowList`.
     pub fn set_attestation_report_verifier(
         mut self,
         root_cert: Vec<u8>,
-        measurement: EnclaveMeasurement,
+        measurements: impl Into<MeasurementAllowList>,
     ) -> Self {
-        let verifier = Arc::new(AttestedReportVerifier::new(root_cert, measurement));
+        let verifier = Arc::new(AttestedReportVerifier::new(root_cert, measurements.into()));
         self.tls.dangerous().set_certificate_verifier(verifier);
 
         self
@@ -99,16 +133,27 @@ impl ServerConfig {
         Ok(server_config)
     }
 
+    /// The server certificate is resolved from the cache for each handshake,
+    /// so that the sessions are established with the latest attestation.
+    pub fn from_attested_tls_config_cache(cache: AttestedTlsConfigCache) -> Self {
+        let mut server_config = ServerConfig::default();
+        server_config.tls.cert_resolver = Arc::new(cache);
+
+        server_config
+    }
+
     pub fn tls(&self) -> &rustls::ServerConfig {
         &self.tls
     }
 
+    /// Verify the peers are attested and in the trusted measurements,
+    /// which is either a single `EnclaveMeasurement` or a shared `MeasurementAllowList`.
     pub fn set_attestation_report_verifier(
         mut self,
         root_cert: Vec<u8>,
-        measurement: EnclaveMeasurement,
+        measurements: impl Into<MeasurementAllowList>,
     ) -> Self {
-        let verifier = Arc::new(AttestedReportVerifier::new(root_cert, measurement));
+        let verifier = Arc::new(AttestedReportVerifier::new(root_cert, measurements.into()));
         self.tls.set_client_certificate_verifier(verifier);
 
         self
@@ -1,59 +1,41 @@
 use crate::server::RequestHandler;
-use anyhow::{ensure, Result};
-use std::io::{Read, Write};
+use frame_config::MRA_TLS_MAX_MESSAGE_LEN;
+use frame_mux::{FramedStream, MuxClient, Service};
+use std::io::Write;
 use std::net::TcpStream;
+use std::string::{String, ToString};
 use std::vec::Vec;
-use tracing::{info, warn};
+use tracing::{error, info};
 
-const MAX_FRAME_LEN: u64 = 4096;
+pub type TlsStream<S> = rustls::StreamOwned<S, TcpStream>;
 
-pub struct Connection<S: rustls::Session> {
-    stream: rustls::StreamOwned<S, TcpStream>,
-    max_frame_len: u64,
-}
-
-impl<S: rustls::Session> Connection<S> {
-    pub fn new(sess: S, sock: TcpStream) -> Self {
-        Connection {
-            stream: rustls::StreamOwned::new(sess, sock),
-            max_frame_len: MAX_FRAME_LEN,
-        }
-    }
-
-    pub fn read_frame(&mut self) -> Result<Vec<u8>> {
-        let mut header = [0u8; 8];
-        self.stream.read_exact(&mut header)?;
-        let frame_len = u64::from_be_bytes(header);
-
-        ensure!(frame_len <= self.max_frame_len, "Exceed max frame length");
-
-        let mut frame = vec![0u8; frame_len as usize];
-        self.stream.read_exact(&mut frame)?;
-        info!("read {} bytes frame via m-attested-tls", frame_len);
+/// A session of mutual attested TLS sending the requests to the server
+pub type ClientConnection = MuxClient<TlsStream<rustls::ClientSession>>;
 
-        Ok(frame)
-    }
+/// A session of mutual attested TLS carrying the framed messages
+pub fn framed<S: rustls::Session>(sess: S, sock: TcpStream) -> FramedStream<TlsStream<S>> {
+    FramedStream::new(rustls::StreamOwned::new(sess, sock))
+        .set_max_message_len(*MRA_TLS_MAX_MESSAGE_LEN)
+}
 
-    pub fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
-        let frame_len = frame.len() as u64;
-        let header = frame_len.to_be_bytes();
+pub fn client_connection(sess: rustls::ClientSession, sock: TcpStream) -> ClientConnection {
+    MuxClient::new(framed(sess, sock))
+}
 
-        self.stream.write(&header)?;
-        self.stream.write_all(&frame)?;
-        self.stream.flush()?;
-        info!("write {} bytes frame via m-attested-tls", frame_len);
+/// Complete the handshake, so that the failures of the remote attestation are reported before any requests are sent.
+pub fn handshake(connection: &mut ClientConnection) -> std::io::Result<()> {
+    connection.get_mut().get_mut().flush()
+}
 
-        Ok(())
-    }
+/// Serve the requests by the handler, whose errors are sent to the client as error responses.
+pub struct HandlerService<H>(pub H);
 
-    pub fn serve_json<H: RequestHandler>(&mut self, handler: H) -> Result<()> {
-        let req = self.read_frame()?;
-        if req.len() == 0 {
-            warn!("request's length is 0");
-            return Ok(());
-        }
-        let resp = handler.handle_json(&req)?;
-        self.write_frame(&resp)?;
-        Ok(())
+impl<H: RequestHandler> Service for HandlerService<H> {
+    fn call(&self, request: &[u8]) -> Result<Vec<u8>, String> {
+        info!("read {} bytes request via m-attested-tls", request.len());
+        self.0.handle_json(request).map_err(|e| {
+            error!("Failed to handle the request: {:?}", e);
+            e.to_string()
+        })
     }
 }
@@ -20,4 +20,7 @@ pub enum MraTLSError {
 
     #[error("{0}")]
     SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    MuxError(#[from] frame_mux::MuxError),
 }
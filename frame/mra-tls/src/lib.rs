@@ -2,18 +2,25 @@
 #[macro_use]
 extern crate sgx_tstd as std;
 
+mod cache;
 mod cert;
 pub mod client;
 pub mod config;
 mod connection;
 mod error;
 mod key;
+pub mod measurement;
 pub mod server;
 #[cfg(debug_assertions)]
 pub mod tests;
 mod verifier;
 
+pub use cache::AttestedTlsConfigCache;
 pub use client::Client;
 pub use config::{AttestedTlsConfig, ClientConfig, ServerConfig};
 pub use error::MraTLSError;
+pub use measurement::{
+    EnclaveIdentity, MeasurementAllowList, SignerRule, TrustedMeasurements,
+    TRUSTED_ANONIFY_MEASUREMENTS, TRUSTED_KEY_VAULT_MEASUREMENTS,
+};
 pub use server::{RequestHandler, Server};
@@ -0,0 +1,260 @@
+use crate::error::Result;
+use anyhow::anyhow;
+use frame_config::{
+    EnclaveMeasurement, ANONIFY_ENCLAVE_MEASUREMENT, ANONIFY_PARAMS_DIR,
+    KEY_VAULT_ENCLAVE_MEASUREMENT, TRUSTED_ANONIFY_MEASUREMENTS_PATH,
+    TRUSTED_KEY_VAULT_MEASUREMENTS_PATH,
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    prelude::v1::*,
+    sync::{Arc, SgxRwLock},
+    untrusted::fs,
+};
+
+/// The anonify enclaves trusted by the key vault, which are listed in TRUSTED_ANONIFY_MEASUREMENTS_PATH
+/// or only ANONIFY_ENCLAVE_MEASUREMENT if it is not set.
+pub static TRUSTED_ANONIFY_MEASUREMENTS: Lazy<MeasurementAllowList> = Lazy::new(|| {
+    MeasurementAllowList::from_file_or(
+        TRUSTED_ANONIFY_MEASUREMENTS_PATH.clone(),
+        *ANONIFY_ENCLAVE_MEASUREMENT,
+    )
+    .expect("Invalid trusted anonify measurements")
+});
+
+/// The key vault enclaves trusted by the anonify nodes, which are listed in TRUSTED_KEY_VAULT_MEASUREMENTS_PATH
+/// or only KEY_VAULT_ENCLAVE_MEASUREMENT if it is not set.
+pub static TRUSTED_KEY_VAULT_MEASUREMENTS: Lazy<MeasurementAllowList> = Lazy::new(|| {
+    MeasurementAllowList::from_file_or(
+        TRUSTED_KEY_VAULT_MEASUREMENTS_PATH.clone(),
+        *KEY_VAULT_ENCLAVE_MEASUREMENT,
+    )
+    .expect("Invalid trusted key vault measurements")
+});
+
+/// The identity of an enclave in the quote body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnclaveIdentity {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+}
+
+/// A rule to trust any enclaves signed by `mr_signer` with the product ID
+/// and the security version not less than `min_isv_svn`, whatever their MRENCLAVE are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignerRule {
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub min_isv_svn: u16,
+}
+
+impl SignerRule {
+    fn matches(&self, identity: &EnclaveIdentity) -> bool {
+        self.mr_signer == identity.mr_signer
+            && self.isv_prod_id == identity.isv_prod_id
+            && self.min_isv_svn <= identity.isv_svn
+    }
+}
+
+/// A set of trusted enclaves, each of which is trusted if it matches
+/// either one of the exact measurements or one of the signer rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedMeasurements {
+    measurements: Vec<EnclaveMeasurement>,
+    signers: Vec<SignerRule>,
+}
+
+impl TrustedMeasurements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_measurement(mut self, measurement: EnclaveMeasurement) -> Self {
+        self.measurements.push(measurement);
+        self
+    }
+
+    pub fn add_signer(mut self, rule: SignerRule) -> Self {
+        self.signers.push(rule);
+        self
+    }
+
+    /// Parse the JSON list of trusted enclaves, e.g.
+    /// ```json
+    /// {
+    ///   "measurement_files": ["erc20_measurement.txt"],
+    ///   "measurements": [{ "mr_enclave": "<hex>", "mr_signer": "<hex>" }],
+    ///   "signers": [{ "mr_signer": "<hex>", "isv_prod_id": 0, "min_isv_svn": 1 }]
+    /// }
+    /// ```
+    /// `measurement_files` are the dump files of `sgx_sign` relative to ANONIFY_PARAMS_DIR.
+    /// All the fields are optional.
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        let file: TrustedMeasurementsFile = serde_json::from_slice(json)?;
+        let mut trusted = TrustedMeasurements::new();
+        for file_name in file.measurement_files {
+            let content = fs::read_to_string(ANONIFY_PARAMS_DIR.join(&file_name))?;
+            if !content.contains("mrsigner->value:")
+                || !content.contains("metadata->enclave_css.body.enclave_hash.m:")
+            {
+                return Err(anyhow!("Invalid measurement file: {}", file_name).into());
+            }
+            trusted = trusted.add_measurement(EnclaveMeasurement::new_from_dumpfile(content));
+        }
+        for m in file.measurements {
+            trusted = trusted.add_measurement(EnclaveMeasurement::new(
+                decode_hash(&m.mr_signer)?,
+                decode_hash(&m.mr_enclave)?,
+            ));
+        }
+        for s in file.signers {
+            trusted = trusted.add_signer(SignerRule {
+                mr_signer: decode_hash(&s.mr_signer)?,
+                isv_prod_id: s.isv_prod_id,
+                min_isv_svn: s.min_isv_svn,
+            });
+        }
+
+        Ok(trusted)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let json = fs::read(path)?;
+        Self::from_json(&json)
+    }
+
+    pub fn is_trusted(&self, identity: &EnclaveIdentity) -> bool {
+        self.measurements
+            .iter()
+            .any(|m| m.mr_enclave() == identity.mr_enclave && m.mr_signer() == identity.mr_signer)
+            || self.signers.iter().any(|rule| rule.matches(identity))
+    }
+}
+
+impl From<EnclaveMeasurement> for TrustedMeasurements {
+    fn from(measurement: EnclaveMeasurement) -> Self {
+        TrustedMeasurements::new().add_measurement(measurement)
+    }
+}
+
+/// A shared set of trusted enclaves which can be replaced at runtime.
+/// The clones share the same set, so that the verifiers of the existing TLS configs
+/// apply the new set to the subsequent handshakes, e.g. during rolling upgrades of the enclaves.
+#[derive(Debug, Clone)]
+pub struct MeasurementAllowList {
+    trusted: Arc<SgxRwLock<TrustedMeasurements>>,
+    path: Option<PathBuf>,
+}
+
+impl MeasurementAllowList {
+    pub fn new(trusted: TrustedMeasurements) -> Self {
+        MeasurementAllowList {
+            trusted: Arc::new(SgxRwLock::new(trusted)),
+            path: None,
+        }
+    }
+
+    /// Load the list from the file, which is read again by `reload`.
+    pub fn from_file(path: PathBuf) -> Result<Self> {
+        let trusted = TrustedMeasurements::from_file(&path)?;
+        Ok(MeasurementAllowList {
+            trusted: Arc::new(SgxRwLock::new(trusted)),
+            path: Some(path),
+        })
+    }
+
+    /// Load the list from the file if it is set, otherwise trust only the measurement.
+    pub fn from_file_or(path: Option<PathBuf>, measurement: EnclaveMeasurement) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => Ok(Self::new(measurement.into())),
+        }
+    }
+
+    /// Read the file again and replace the list. If the file is invalid, the current list is kept.
+    pub fn reload(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("The trusted measurements are not loaded from any files"))?;
+        let trusted = TrustedMeasurements::from_file(path)?;
+        self.set(trusted);
+
+        Ok(())
+    }
+
+    pub fn set(&self, trusted: TrustedMeasurements) {
+        *self.trusted.write().expect("Poisoned lock") = trusted;
+    }
+
+    pub fn get(&self) -> TrustedMeasurements {
+        self.trusted.read().expect("Poisoned lock").clone()
+    }
+
+    pub fn verify(&self, identity: &EnclaveIdentity) -> Result<()> {
+        if !self
+            .trusted
+            .read()
+            .expect("Poisoned lock")
+            .is_trusted(identity)
+        {
+            return Err(anyhow!(
+                "Untrusted enclave: mr_enclave: {:?}, mr_signer: {:?}, isv_prod_id: {}, isv_svn: {}",
+                identity.mr_enclave,
+                identity.mr_signer,
+                identity.isv_prod_id,
+                identity.isv_svn
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+impl From<TrustedMeasurements> for MeasurementAllowList {
+    fn from(trusted: TrustedMeasurements) -> Self {
+        MeasurementAllowList::new(trusted)
+    }
+}
+
+impl From<EnclaveMeasurement> for MeasurementAllowList {
+    fn from(measurement: EnclaveMeasurement) -> Self {
+        MeasurementAllowList::new(measurement.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct TrustedMeasurementsFile {
+    #[serde(default)]
+    measurement_files: Vec<String>,
+    #[serde(default)]
+    measurements: Vec<MeasurementEntry>,
+    #[serde(default)]
+    signers: Vec<SignerEntry>,
+}
+
+#[derive(Deserialize)]
+struct MeasurementEntry {
+    mr_enclave: String,
+    mr_signer: String,
+}
+
+#[derive(Deserialize)]
+struct SignerEntry {
+    mr_signer: String,
+    isv_prod_id: u16,
+    #[serde(default)]
+    min_isv_svn: u16,
+}
+
+fn decode_hash(hex_str: &str) -> Result<[u8; 32]> {
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(hex_str.trim_start_matches("0x"), &mut hash)
+        .map_err(|e| anyhow!("Invalid hash {}: {:?}", hex_str, e))?;
+    Ok(hash)
+}
@@ -1,8 +1,11 @@
 use crate::config::ServerConfig;
-use crate::connection::Connection;
+use crate::connection::{self, HandlerService};
 use crate::error::Result;
+use frame_config::MRA_TLS_SERVER_WORKERS;
+use frame_mux::WorkerPool;
 use std::string::String;
 use std::sync::Arc;
+use std::time::Duration;
 use std::vec::Vec;
 use tracing::error;
 
@@ -10,35 +13,60 @@ pub trait RequestHandler {
     fn handle_json(&self, msg: &[u8]) -> anyhow::Result<Vec<u8>>;
 }
 
+/// The duration for which a session is kept alive without any requests.
+/// Each session occupies a worker while it is alive, so the idle sessions block the others for this duration at most.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub struct Server {
     address: String,
     config: ServerConfig,
+    idle_timeout: Duration,
+    num_workers: usize,
 }
 
 impl Server {
     pub fn new(address: String, config: ServerConfig) -> Self {
-        Server { address, config }
+        Server {
+            address,
+            config,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            num_workers: *MRA_TLS_SERVER_WORKERS,
+        }
     }
 
-    pub fn run<H: RequestHandler + Clone>(&mut self, handler: H) -> Result<()> {
-        let listener = std::net::TcpListener::bind(&self.address)?;
+    pub fn set_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
 
-        #[cfg(not(test))]
-        for stream in listener.incoming() {
-            let session = rustls::ServerSession::new(&Arc::new(self.config.tls().clone()));
-            match Connection::new(session, stream?).serve_json(handler.clone()) {
-                Ok(_) => {}
-                Err(e) => error!("{:?}", e),
-            }
-        }
+    /// Set the number of sessions served concurrently. The enclave requires a TCS for each worker.
+    pub fn set_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers;
+        self
+    }
+
+    /// Accept the sessions and serve them by the workers.
+    /// Accepting blocks while all the workers are busy and as many sessions are queued.
+    pub fn run<H>(&mut self, handler: H) -> Result<()>
+    where
+        H: RequestHandler + Clone + Send + 'static,
+    {
+        let listener = std::net::TcpListener::bind(&self.address)?;
+        let tls = Arc::new(self.config.tls().clone());
+        let pool = WorkerPool::new(self.num_workers, self.num_workers)?;
+        let max_sessions = if cfg!(test) { 1 } else { usize::MAX };
 
-        #[cfg(test)]
-        for stream in listener.incoming().take(1) {
-            let session = rustls::ServerSession::new(&Arc::new(self.config.tls().clone()));
-            match Connection::new(session, stream?).serve_json(handler.clone()) {
-                Ok(_) => {}
-                Err(e) => error!("{:?}", e),
-            }
+        for stream in listener.incoming().take(max_sessions) {
+            let session = rustls::ServerSession::new(&tls);
+            let stream = stream?;
+            stream.set_read_timeout(Some(self.idle_timeout))?;
+            let service = HandlerService(handler.clone());
+            pool.execute(move || {
+                let mut stream = connection::framed(session, stream);
+                if let Err(e) = frame_mux::serve(&mut stream, &service) {
+                    error!("{:?}", e);
+                }
+            })?;
         }
 
         Ok(())
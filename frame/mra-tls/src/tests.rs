@@ -1,8 +1,14 @@
-use crate::{AttestedTlsConfig, Client, ClientConfig, RequestHandler, Server, ServerConfig};
+use crate::{
+    AttestedTlsConfig, AttestedTlsConfigCache, Client, ClientConfig, EnclaveIdentity,
+    MeasurementAllowList, MraTLSError, RequestHandler, Server, ServerConfig, SignerRule,
+    TrustedMeasurements,
+};
 use anyhow::Result;
-use frame_config::{ENCLAVE_MEASUREMENT, IAS_ROOT_CERT};
+use frame_config::{EnclaveMeasurement, ENCLAVE_MEASUREMENT, IAS_ROOT_CERT, PJ_ROOT_DIR};
 use once_cell::sync::Lazy;
+use remote_attestation::{AttestationEvidence, AttestationPolicy};
 use serde_json::Value;
+use sgx_types::sgx_report_data_t;
 use std::{
     env,
     string::{String, ToString},
@@ -17,9 +23,43 @@ static SERVER_ADDRESS: Lazy<String> = Lazy::new(|| {
     format!("{}:12345", host)
 });
 const LISTEN_ADDRESS: &str = "0.0.0.0:12345";
+static MOCK_IAS_SERVER_ADDRESS: Lazy<String> = Lazy::new(|| {
+    let host = env::var("HOSTNAME").expect("failed to get env 'HOSTNAME'");
+    format!("{}:12346", host)
+});
+const MOCK_IAS_LISTEN_ADDRESS: &str = "0.0.0.0:12346";
+static CACHE_SERVER_ADDRESS: Lazy<String> = Lazy::new(|| {
+    let host = env::var("HOSTNAME").expect("failed to get env 'HOSTNAME'");
+    format!("{}:12347", host)
+});
+const CACHE_LISTEN_ADDRESS: &str = "0.0.0.0:12347";
+
+static MOCK_IAS_ROOT_CERT: Lazy<Vec<u8>> = Lazy::new(|| {
+    let mut file_path = PJ_ROOT_DIR.clone();
+    file_path.push(MOCK_IAS_ROOT_CERT_PATH);
+
+    let root_cert = std::untrusted::fs::read(file_path).unwrap();
+    pem::parse(root_cert).unwrap().contents
+});
 
 pub fn run_tests() -> bool {
-    check_all_passed!(run_tests!(test_request_response,),)
+    check_all_passed!(run_tests!(
+        test_request_response,
+        test_mock_ias_ok,
+        test_mock_ias_group_out_of_date,
+        test_mock_ias_revoked,
+        test_mock_ias_request_response,
+        test_policy_quote_status,
+        test_policy_default_quote_status,
+        test_policy_advisory_ids,
+        test_policy_report_age,
+        test_policy_quote_body,
+        test_trusted_measurements,
+        test_measurement_allow_list_reload,
+        test_attested_tls_config_cache,
+        test_session_reuse,
+        test_multiplexed_requests,
+    ),)
 }
 
 #[derive(Default, Clone)]
@@ -41,7 +81,11 @@ fn test_request_response() {
     let attested_tls_config =
         AttestedTlsConfig::new_by_ra(&spid, &ias_url, &sub_key, IAS_ROOT_CERT.to_vec()).unwrap();
 
-    start_server(attested_tls_config.clone(), IAS_ROOT_CERT.to_vec());
+    start_server(
+        attested_tls_config.clone(),
+        IAS_ROOT_CERT.to_vec(),
+        LISTEN_ADDRESS,
+    );
     let client_config = ClientConfig::from_attested_tls_config(attested_tls_config)
         .unwrap()
         .set_attestation_report_verifier(IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
@@ -54,12 +98,349 @@ fn test_request_response() {
     assert_eq!(msg, resp);
 }
 
-fn start_server(attested_tls_config: AttestedTlsConfig, ias_root_cert: Vec<u8>) {
+/// The quote status is configured by the path of the mock IAS
+fn mock_ias_url(quote_status: &str) -> String {
+    format!(
+        "{}/status/{}/sgx/dev/attestation/v3/report",
+        MOCK_IAS_URL, quote_status
+    )
+}
+
+fn attest_by_mock_ias(quote_status: &str) -> crate::error::Result<AttestedTlsConfig> {
+    set_env_vars();
+    let spid = env::var("SPID").unwrap();
+    let ias_url = mock_ias_url(quote_status);
+
+    AttestedTlsConfig::new_by_ra(&spid, &ias_url, "", MOCK_IAS_ROOT_CERT.to_vec())
+}
+
+fn attest_with_policy(
+    quote_status: &str,
+    policy: &AttestationPolicy,
+) -> std::result::Result<AttestationEvidence, remote_attestation::Error> {
+    set_env_vars();
+    let spid = env::var("SPID").unwrap();
+    let ias_url = mock_ias_url(quote_status);
+
+    remote_attestation::attest(
+        &sgx_report_data_t::default(),
+        &spid,
+        &ias_url,
+        "",
+        MOCK_IAS_ROOT_CERT.to_vec(),
+        policy,
+    )
+}
+
+fn test_mock_ias_ok() {
+    assert!(attest_by_mock_ias("OK").is_ok());
+}
+
+fn test_mock_ias_group_out_of_date() {
+    // GROUP_OUT_OF_DATE is accepted with a warning
+    assert!(attest_by_mock_ias("GROUP_OUT_OF_DATE").is_ok());
+}
+
+fn test_mock_ias_revoked() {
+    match attest_by_mock_ias("REVOKED") {
+        Err(MraTLSError::RAError(remote_attestation::Error::QuoteStatusError(status))) => {
+            assert_eq!(status, "REVOKED")
+        }
+        res => panic!("The revoked quote must be rejected: {:?}", res.map(|_| ())),
+    }
+}
+
+fn test_mock_ias_request_response() {
+    let attested_tls_config = attest_by_mock_ias("OK").unwrap();
+    start_server(
+        attested_tls_config.clone(),
+        MOCK_IAS_ROOT_CERT.to_vec(),
+        MOCK_IAS_LISTEN_ADDRESS,
+    );
+    let msg = r#"{
+        "message": "Hello test_mock_ias_request_response"
+    }"#;
+
+    let client_config = ClientConfig::from_attested_tls_config(attested_tls_config.clone())
+        .unwrap()
+        .set_attestation_report_verifier(MOCK_IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
+    let mut client = Client::new(&*MOCK_IAS_SERVER_ADDRESS, &client_config).unwrap();
+    let resp: String = client.send_json(msg).unwrap();
+    assert_eq!(msg, resp);
+
+    // Reports signed by the mock IAS are rejected by the verifier trusting the root of IAS
+    let client_config = ClientConfig::from_attested_tls_config(attested_tls_config)
+        .unwrap()
+        .set_attestation_report_verifier(IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
+    let mut client = Client::new(&*MOCK_IAS_SERVER_ADDRESS, &client_config).unwrap();
+    let res: Result<String> = client.send_json(msg);
+    assert!(res.is_err());
+}
+
+fn test_policy_quote_status() {
+    let policy = AttestationPolicy::default().set_allowed_quote_statuses(vec!["OK".to_string()]);
+    assert!(attest_with_policy("OK", &policy).is_ok());
+    match attest_with_policy("GROUP_OUT_OF_DATE", &policy) {
+        Err(remote_attestation::Error::QuoteStatusError(status)) => {
+            assert_eq!(status, "GROUP_OUT_OF_DATE")
+        }
+        res => panic!("GROUP_OUT_OF_DATE must be rejected: {:?}", res.map(|_| ())),
+    }
+}
+
+fn test_policy_default_quote_status() {
+    // OUT_OF_DATE is accepted only as the TCB status of DCAP by default
+    let policy = AttestationPolicy::default();
+    match attest_with_policy("OUT_OF_DATE", &policy) {
+        Err(remote_attestation::Error::QuoteStatusError(status)) => {
+            assert_eq!(status, "OUT_OF_DATE")
+        }
+        res => panic!(
+            "OUT_OF_DATE of EPID must be rejected: {:?}",
+            res.map(|_| ())
+        ),
+    }
+    assert!(policy.verify_tcb_status("OUT_OF_DATE", &[]).is_ok());
+    assert!(policy.verify_tcb_status("GROUP_OUT_OF_DATE", &[]).is_err());
+}
+
+fn test_policy_advisory_ids() {
+    // The mock IAS for the unit tests reports INTEL-SA-00334 with statuses except OK
+    let policy = AttestationPolicy::default()
+        .set_allowed_quote_statuses(vec!["OK".to_string(), "SW_HARDENING_NEEDED".to_string()]);
+    assert!(attest_with_policy("SW_HARDENING_NEEDED", &policy).is_ok());
+
+    let allowed = policy
+        .clone()
+        .set_allowed_advisory_ids(vec!["INTEL-SA-00334".to_string()]);
+    assert!(attest_with_policy("SW_HARDENING_NEEDED", &allowed).is_ok());
+
+    let not_allowed = policy.set_allowed_advisory_ids(vec!["INTEL-SA-00161".to_string()]);
+    match attest_with_policy("SW_HARDENING_NEEDED", &not_allowed) {
+        Err(remote_attestation::Error::AdvisoryIdError(id)) => assert_eq!(id, "INTEL-SA-00334"),
+        res => panic!("INTEL-SA-00334 must be rejected: {:?}", res.map(|_| ())),
+    }
+}
+
+fn test_policy_report_age() {
+    let policy = AttestationPolicy::default().set_max_report_age_secs(3600);
+    assert!(attest_with_policy("OK", &policy).is_ok());
+    match policy.verify_report_age("2021-01-01T00:00:00.000000") {
+        Err(remote_attestation::Error::ReportTooOldError { max_age, .. }) => {
+            assert_eq!(max_age, 3600)
+        }
+        res => panic!("The old report must be rejected: {:?}", res),
+    }
+}
+
+fn test_policy_quote_body() {
+    let mut quote_body = vec![0u8; 432];
+    quote_body[96] = 0x02; // SGX_FLAGS_DEBUG
+    quote_body[304..306].copy_from_slice(&1u16.to_le_bytes()); // ISVPRODID
+    quote_body[306..308].copy_from_slice(&3u16.to_le_bytes()); // ISVSVN
+
+    assert!(AttestationPolicy::default()
+        .verify_quote_body(&quote_body)
+        .is_ok());
+    assert!(matches!(
+        AttestationPolicy::default()
+            .set_allow_debug(false)
+            .verify_quote_body(&quote_body),
+        Err(remote_attestation::Error::DebugEnclaveError)
+    ));
+    assert!(matches!(
+        AttestationPolicy::default()
+            .set_isv_prod_id(2)
+            .verify_quote_body(&quote_body),
+        Err(remote_attestation::Error::IsvProdIdError {
+            expected: 2,
+            actual: 1
+        })
+    ));
+    assert!(matches!(
+        AttestationPolicy::default()
+            .set_min_isv_svn(4)
+            .verify_quote_body(&quote_body),
+        Err(remote_attestation::Error::IsvSvnError { min: 4, actual: 3 })
+    ));
+    assert!(AttestationPolicy::default()
+        .set_isv_prod_id(1)
+        .set_min_isv_svn(3)
+        .verify_quote_body(&quote_body)
+        .is_ok());
+}
+
+fn test_trusted_measurements() {
+    let identity = EnclaveIdentity {
+        mr_enclave: [1u8; 32],
+        mr_signer: [2u8; 32],
+        isv_prod_id: 1,
+        isv_svn: 3,
+    };
+    let signer = |isv_prod_id, min_isv_svn| SignerRule {
+        mr_signer: [2u8; 32],
+        isv_prod_id,
+        min_isv_svn,
+    };
+
+    assert!(TrustedMeasurements::new()
+        .add_measurement(EnclaveMeasurement::new([2u8; 32], [1u8; 32]))
+        .is_trusted(&identity));
+    assert!(!TrustedMeasurements::new()
+        .add_measurement(EnclaveMeasurement::new([2u8; 32], [0u8; 32]))
+        .is_trusted(&identity));
+    assert!(TrustedMeasurements::new()
+        .add_signer(signer(1, 3))
+        .is_trusted(&identity));
+    assert!(!TrustedMeasurements::new()
+        .add_signer(signer(1, 4))
+        .is_trusted(&identity));
+    assert!(!TrustedMeasurements::new()
+        .add_signer(signer(2, 0))
+        .is_trusted(&identity));
+
+    let json = format!(
+        r#"{{
+            "measurements": [{{ "mr_enclave": "{}", "mr_signer": "{}" }}],
+            "signers": [{{ "mr_signer": "0x{}", "isv_prod_id": 1, "min_isv_svn": 3 }}]
+        }}"#,
+        "01".repeat(32),
+        "02".repeat(32),
+        "02".repeat(32)
+    );
+    assert_eq!(
+        TrustedMeasurements::from_json(json.as_bytes()).unwrap(),
+        TrustedMeasurements::new()
+            .add_measurement(EnclaveMeasurement::new([2u8; 32], [1u8; 32]))
+            .add_signer(signer(1, 3))
+    );
+    assert!(TrustedMeasurements::from_json(
+        br#"{ "measurements": [{ "mr_enclave": "01", "mr_signer": "02" }] }"#
+    )
+    .is_err());
+}
+
+fn test_measurement_allow_list_reload() {
+    // Connect to the server started by test_mock_ias_request_response
+    let attested_tls_config = attest_by_mock_ias("OK").unwrap();
+    let signer = |min_isv_svn| SignerRule {
+        mr_signer: ENCLAVE_MEASUREMENT.mr_signer(),
+        isv_prod_id: 0,
+        min_isv_svn,
+    };
+    let allow_list = MeasurementAllowList::new(TrustedMeasurements::new().add_signer(signer(1)));
+    let client_config = ClientConfig::from_attested_tls_config(attested_tls_config)
+        .unwrap()
+        .set_attestation_report_verifier(MOCK_IAS_ROOT_CERT.to_vec(), allow_list.clone());
+    let msg = r#"{
+        "message": "Hello test_measurement_allow_list_reload"
+    }"#;
+
+    // The server enclave of ISVSVN 0 is rejected
+    let mut client = Client::new(&*MOCK_IAS_SERVER_ADDRESS, &client_config).unwrap();
+    let res: Result<String> = client.send_json(msg);
+    assert!(res.is_err());
+
+    // The new list is applied to the existing config
+    allow_list.set(TrustedMeasurements::new().add_signer(signer(0)));
+    let mut client = Client::new(&*MOCK_IAS_SERVER_ADDRESS, &client_config).unwrap();
+    let resp: String = client.send_json(msg).unwrap();
+    assert_eq!(msg, resp);
+
+    // Reloading fails without any files, and the current list is kept
+    assert!(allow_list.reload().is_err());
+    assert_eq!(
+        allow_list.get(),
+        TrustedMeasurements::new().add_signer(signer(0))
+    );
+}
+
+fn attested_tls_config_cache(ttl: Duration) -> AttestedTlsConfigCache {
+    set_env_vars();
+    let spid = env::var("SPID").unwrap();
+
+    AttestedTlsConfigCache::with_ttl(
+        &spid,
+        &mock_ias_url("OK"),
+        "",
+        MOCK_IAS_ROOT_CERT.to_vec(),
+        ttl,
+    )
+}
+
+fn test_attested_tls_config_cache() {
+    let cache = attested_tls_config_cache(Duration::from_secs(3600));
+    let cached = cache.get().unwrap();
+    assert_eq!(cache.get().unwrap().ee_cert(), cached.ee_cert());
+
+    // The clones share the refreshed certificate
+    let refreshed = cache.clone().refresh().unwrap();
+    assert_ne!(refreshed.ee_cert(), cached.ee_cert());
+    assert_eq!(cache.get().unwrap().ee_cert(), refreshed.ee_cert());
+
+    // Expired certificates are attested again
+    let cache = attested_tls_config_cache(Duration::from_secs(0));
+    let cached = cache.get().unwrap();
+    assert_ne!(cache.get().unwrap().ee_cert(), cached.ee_cert());
+}
+
+fn test_session_reuse() {
+    let cache = attested_tls_config_cache(Duration::from_secs(3600));
+    let server_config = ServerConfig::from_attested_tls_config_cache(cache.clone())
+        .set_attestation_report_verifier(MOCK_IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
+    let mut server = Server::new(CACHE_LISTEN_ADDRESS.to_string(), server_config)
+        .set_idle_timeout(Duration::from_millis(500))
+        .set_num_workers(1);
+    thread::spawn(move || server.run(EchoHandler::default()).unwrap());
+    thread::sleep(Duration::from_secs(1));
+
+    let client_config = ClientConfig::from_attested_tls_config_cache(cache)
+        .set_attestation_report_verifier(MOCK_IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
+    let mut client = Client::new(&*CACHE_SERVER_ADDRESS, &client_config).unwrap();
+    for i in 0..3 {
+        let msg = format!(r#"{{ "message": "Hello test_session_reuse {}" }}"#, i);
+        let resp: String = client.send_json(&msg).unwrap();
+        assert_eq!(msg, resp);
+    }
+
+    // The request is sent over a new session after the server closes the idle one
+    thread::sleep(Duration::from_secs(1));
+    let msg = r#"{ "message": "Hello test_session_reuse again" }"#;
+    let resp: String = client.send_json(msg).unwrap();
+    assert_eq!(msg, resp);
+}
+
+fn test_multiplexed_requests() {
+    // Connect to the server started by test_session_reuse
+    let cache = attested_tls_config_cache(Duration::from_secs(3600));
+    let client_config = ClientConfig::from_attested_tls_config_cache(cache)
+        .set_attestation_report_verifier(MOCK_IAS_ROOT_CERT.to_vec(), *ENCLAVE_MEASUREMENT);
+    let mut client = Client::new(&*CACHE_SERVER_ADDRESS, &client_config).unwrap();
+
+    // The messages larger than a frame are split into multiple frames
+    let large = format!(r#"{{ "message": "{}" }}"#, "a".repeat(10 * 4096));
+    let msgs = vec![
+        large.clone(),
+        r#"{ "message": "Hello test_multiplexed_requests" }"#.to_string(),
+        large,
+    ];
+    let resps: Vec<Result<String>> = client.send_json_all(&msgs).unwrap();
+    assert_eq!(resps.len(), msgs.len());
+    for (msg, resp) in msgs.iter().zip(resps) {
+        assert_eq!(msg, &resp.unwrap());
+    }
+}
+
+fn start_server(
+    attested_tls_config: AttestedTlsConfig,
+    ias_root_cert: Vec<u8>,
+    listen_address: &str,
+) {
     let server_config = ServerConfig::from_attested_tls_config(attested_tls_config)
         .unwrap()
         .set_attestation_report_verifier(ias_root_cert, *ENCLAVE_MEASUREMENT);
 
-    let mut server = Server::new(LISTEN_ADDRESS.to_string(), server_config);
+    let mut server = Server::new(listen_address.to_string(), server_config).set_num_workers(1);
     let handler = EchoHandler::default();
     thread::spawn(move || server.run(handler).unwrap());
     thread::sleep(Duration::from_secs(1));
@@ -1,25 +1,46 @@
 use crate::cert::*;
 use crate::error::{MraTLSError, Result};
+use crate::measurement::{EnclaveIdentity, MeasurementAllowList};
 use anyhow::anyhow;
-use frame_config::EnclaveMeasurement;
-use remote_attestation::AttestedReport;
+use frame_config::DCAP_ROOT_CERT;
+use remote_attestation::{AttestationEvidence, AttestationPolicy, ATTESTATION_POLICY};
 use std::io::{Cursor, Read};
+use std::string::String;
 use std::vec::Vec;
 
+/// A verifier of the attestation evidence in the certificates, which accepts both EPID and DCAP.
+/// `root_cert` is the root certificate of IAS, and the one of DCAP is `DCAP_ROOT_CERT` by default.
+/// If `DCAP_ROOT_CERT` can't be read, the error is returned for every certificate to be verified.
+/// The evidence must conform to `ATTESTATION_POLICY` by default, and the enclave must be in `measurements`.
+/// If `ATTESTATION_POLICY` is invalid, the error is returned for every certificate to be verified as well.
 #[derive(Clone, Debug)]
 pub struct AttestedReportVerifier {
     root_cert: Vec<u8>,
-    measurement: EnclaveMeasurement,
+    dcap_root_cert: std::result::Result<Option<Vec<u8>>, String>,
+    measurements: MeasurementAllowList,
+    policy: std::result::Result<AttestationPolicy, String>,
 }
 
 impl AttestedReportVerifier {
-    pub fn new(root_cert: Vec<u8>, measurement: EnclaveMeasurement) -> Self {
+    pub fn new(root_cert: Vec<u8>, measurements: MeasurementAllowList) -> Self {
         Self {
             root_cert,
-            measurement,
+            dcap_root_cert: DCAP_ROOT_CERT.clone(),
+            measurements,
+            policy: ATTESTATION_POLICY.clone(),
         }
     }
 
+    pub fn set_dcap_root_cert(mut self, dcap_root_cert: Vec<u8>) -> Self {
+        self.dcap_root_cert = Ok(Some(dcap_root_cert));
+        self
+    }
+
+    pub fn set_attestation_policy(mut self, policy: AttestationPolicy) -> Self {
+        self.policy = Ok(policy);
+        self
+    }
+
     fn verify_cert(&self, ee_cert: &[u8]) -> Result<()> {
         // Parse DER formatted x.509 end entity certificate
         let x509 = yasna::parse_der(&ee_cert, X509::load)?;
@@ -29,25 +50,45 @@ impl AttestedReportVerifier {
         let cert_ext: <SgxRaCertExt as Asn1Ty>::ValueTy = (((((((tbs_cert.1).1).1).1).1).1).1).0;
         let cert_ext_payload: Vec<u8> = ((cert_ext.0).1).0;
 
-        // Verify the deserialized attested_report which is included in extension field of X.509 cert
-        let attested_report = serde_json::from_slice::<AttestedReport>(&cert_ext_payload)?
-            .verify_attested_report(self.root_cert.to_vec())?;
-
-        let mut quote = Cursor::new(attested_report.get_quote_body()?);
+        // Verify the deserialized evidence which is included in extension field of X.509 cert
+        let dcap_root_cert = self.dcap_root_cert.as_ref().map_err(|e| anyhow!("{}", e))?;
+        let policy = self
+            .policy
+            .as_ref()
+            .map_err(|e| anyhow!("Invalid attestation policy: {}", e))?;
+        let evidence = serde_json::from_slice::<AttestationEvidence>(&cert_ext_payload)?.verify(
+            self.root_cert.to_vec(),
+            dcap_root_cert.as_deref(),
+            policy,
+        )?;
+
+        let mut quote = Cursor::new(evidence.get_quote_body()?);
         let mut mr_enclave = [0u8; 32];
         let mut mr_signer = [0u8; 32];
+        let mut isv_prod_id = [0u8; 2];
+        let mut isv_svn = [0u8; 2];
         let mut report_data = [0u8; 64];
 
         // Offsets are defined in "Attestation Service for Intel® Software Guard Extensions (Intel® SGX): API Documentation version 6.0"
+        // The header and the report body of DCAP quotes have the same layout.
         quote.set_position(112);
         quote.read_exact(&mut mr_enclave)?;
         quote.set_position(176);
         quote.read_exact(&mut mr_signer)?;
+        quote.set_position(304);
+        quote.read_exact(&mut isv_prod_id)?;
+        quote.set_position(306);
+        quote.read_exact(&mut isv_svn)?;
         quote.set_position(368);
         quote.read_exact(&mut report_data)?;
 
         Self::verify_pubkey_eq(pubkey, report_data)?;
-        self.verify_measurement(mr_enclave, mr_signer)?;
+        self.measurements.verify(&EnclaveIdentity {
+            mr_enclave,
+            mr_signer,
+            isv_prod_id: u16::from_le_bytes(isv_prod_id),
+            isv_svn: u16::from_le_bytes(isv_svn),
+        })?;
 
         Ok(())
     }
@@ -63,25 +104,6 @@ impl AttestedReportVerifier {
 
         Ok(())
     }
-
-    fn verify_measurement(&self, mr_enclave: [u8; 32], mr_signer: [u8; 32]) -> Result<()> {
-        if self.measurement.mr_enclave() != mr_enclave {
-            return Err(MraTLSError::Error(anyhow!(
-                "Invalid mr_enclave: local mr_enclave: {:?}, received mr_enclave: {:?}",
-                self.measurement.mr_enclave(),
-                mr_enclave
-            )));
-        }
-        if self.measurement.mr_signer() != mr_signer {
-            return Err(MraTLSError::Error(anyhow!(
-                "Invalid mr_signer: local mr_signer: {:?}, received mr_signer: {:?}",
-                self.measurement.mr_signer(),
-                mr_signer
-            )));
-        }
-
-        Ok(())
-    }
 }
 
 impl rustls::ClientCertVerifier for AttestedReportVerifier {
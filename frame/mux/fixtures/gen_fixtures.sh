@@ -0,0 +1,26 @@
+#!/usr/bin/env bash
+# Generate a test CA and the server and client certificates signed by it,
+# so that the protocol can be tested over plain rustls without remote attestation.
+set -eu
+cd "$(dirname "$0")"
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+openssl ecparam -name prime256v1 -genkey -noout -out "$tmp/ca.key"
+openssl req -x509 -new -key "$tmp/ca.key" -subj "/CN=Test Mux CA" -days 36500 \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign" \
+    -out ca.pem
+
+for name in server client; do
+    openssl ecparam -name prime256v1 -genkey -noout \
+        | openssl pkcs8 -topk8 -nocrypt -out "$name.key"
+    openssl req -new -key "$name.key" -subj "/CN=$name" -out "$tmp/$name.csr"
+    if [ "$name" = server ]; then
+        usage="serverAuth"
+    else
+        usage="clientAuth"
+    fi
+    printf "basicConstraints=critical,CA:FALSE\nsubjectAltName=DNS:localhost\nextendedKeyUsage=%s\n" "$usage" > "$tmp/$name.ext"
+    openssl x509 -req -in "$tmp/$name.csr" -CA ca.pem -CAkey "$tmp/ca.key" -CAcreateserial \
+        -CAserial "$tmp/ca.srl" -days 36500 -extfile "$tmp/$name.ext" -out "$name.pem"
+done
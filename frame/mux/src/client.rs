@@ -0,0 +1,103 @@
+use crate::error::{MuxError, Result};
+use crate::frame::FrameKind;
+use crate::localstd::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    string::String,
+    vec::Vec,
+};
+use crate::stream::{FramedStream, Message};
+
+/// A client sending multiple requests over a session.
+/// The requests can be pipelined, and the responses received ahead of their turns are buffered.
+pub struct MuxClient<S> {
+    stream: FramedStream<S>,
+    next_id: u64,
+    outstanding: HashSet<u64>,
+    responses: HashMap<u64, Message>,
+}
+
+impl<S: Read + Write> MuxClient<S> {
+    pub fn new(stream: FramedStream<S>) -> Self {
+        MuxClient {
+            stream,
+            next_id: 0,
+            outstanding: HashSet::new(),
+            responses: HashMap::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &FramedStream<S> {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut FramedStream<S> {
+        &mut self.stream
+    }
+
+    /// Send the request without waiting for the response, and return its ID.
+    pub fn send(&mut self, request: &[u8]) -> Result<u64> {
+        let request_id = self.next_id;
+        self.stream
+            .write_message(FrameKind::Request, request_id, request)?;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.outstanding.insert(request_id);
+
+        Ok(request_id)
+    }
+
+    /// Wait for the response to the request.
+    pub fn receive(&mut self, request_id: u64) -> Result<Vec<u8>> {
+        if let Some(response) = self.responses.remove(&request_id) {
+            return into_result(response);
+        }
+        if !self.outstanding.contains(&request_id) {
+            return Err(MuxError::UnexpectedResponse(request_id));
+        }
+
+        loop {
+            let response = self.stream.read_message()?;
+            if !self.outstanding.remove(&response.request_id) {
+                return Err(MuxError::UnexpectedResponse(response.request_id));
+            }
+            if response.request_id == request_id {
+                return into_result(response);
+            }
+            self.responses.insert(response.request_id, response);
+        }
+    }
+
+    pub fn request(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        let request_id = self.send(request)?;
+        self.receive(request_id)
+    }
+
+    /// Send all the requests before waiting for the responses, which are returned in the order of the requests.
+    /// Every response is received even if some of them are errors, so that the session can be used afterwards.
+    pub fn request_all<R: AsRef<[u8]>>(&mut self, requests: &[R]) -> Result<Vec<Result<Vec<u8>>>> {
+        let request_ids = requests
+            .iter()
+            .map(|request| self.send(request.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut responses = Vec::with_capacity(request_ids.len());
+        for request_id in request_ids {
+            match self.receive(request_id) {
+                Err(MuxError::Remote(msg)) => responses.push(Err(MuxError::Remote(msg))),
+                response => responses.push(Ok(response?)),
+            }
+        }
+
+        Ok(responses)
+    }
+}
+
+fn into_result(response: Message) -> Result<Vec<u8>> {
+    match response.kind {
+        FrameKind::Response => Ok(response.payload),
+        FrameKind::Error => Err(MuxError::Remote(
+            String::from_utf8_lossy(&response.payload).into_owned(),
+        )),
+        FrameKind::Request => Err(MuxError::InvalidFrame("expected a response")),
+    }
+}
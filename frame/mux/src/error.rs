@@ -0,0 +1,63 @@
+use crate::localstd::{fmt, io, string::String};
+
+pub type Result<T> = core::result::Result<T, MuxError>;
+
+#[derive(Debug)]
+pub enum MuxError {
+    /// The underlying stream failed
+    Io(io::Error),
+    /// The header of the frame is malformed or unexpected
+    InvalidFrame(&'static str),
+    /// The payload of the frame exceeds `MAX_CHUNK_LEN`
+    FrameTooLarge(usize),
+    /// The payload of the message exceeds the maximum message length
+    MessageTooLarge(usize),
+    /// The response doesn't correspond to any outstanding requests
+    UnexpectedResponse(u64),
+    /// The peer failed to handle the request
+    Remote(String),
+    /// No workers are left to handle the jobs
+    WorkerPoolClosed,
+}
+
+impl MuxError {
+    /// Whether the peer closed the session or it is idle for the read timeout of the stream.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            MuxError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for MuxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MuxError::Io(err) => write!(f, "I/O error: {}", err),
+            MuxError::InvalidFrame(msg) => write!(f, "Invalid frame: {}", msg),
+            MuxError::FrameTooLarge(len) => write!(f, "Too large frame: {} bytes", len),
+            MuxError::MessageTooLarge(len) => write!(f, "Too large message: {} bytes", len),
+            MuxError::UnexpectedResponse(id) => {
+                write!(f, "Unexpected response to the request {}", id)
+            }
+            MuxError::Remote(msg) => write!(f, "Remote error: {}", msg),
+            MuxError::WorkerPoolClosed => write!(f, "The worker pool is closed"),
+        }
+    }
+}
+
+impl crate::localstd::error::Error for MuxError {}
+
+impl From<io::Error> for MuxError {
+    fn from(err: io::Error) -> Self {
+        MuxError::Io(err)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::error::{MuxError, Result};
+
+/// The length of the frame header:
+/// version (1 byte), kind (1 byte), flags (1 byte), reserved (1 byte),
+/// request ID (8 bytes, big endian) and payload length (4 bytes, big endian).
+pub const HEADER_LEN: usize = 16;
+/// The maximum payload length of a single frame. Larger messages are split into multiple frames.
+pub const MAX_CHUNK_LEN: usize = 4096;
+
+const VERSION: u8 = 1;
+/// Set on the last frame of the message
+const FLAG_END: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Request,
+    Response,
+    /// A response reporting that the peer failed to handle the request
+    Error,
+}
+
+impl FrameKind {
+    fn to_u8(self) -> u8 {
+        match self {
+            FrameKind::Request => 0,
+            FrameKind::Response => 1,
+            FrameKind::Error => 2,
+        }
+    }
+
+    fn from_u8(kind: u8) -> Result<Self> {
+        match kind {
+            0 => Ok(FrameKind::Request),
+            1 => Ok(FrameKind::Response),
+            2 => Ok(FrameKind::Error),
+            _ => Err(MuxError::InvalidFrame("unknown kind")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub kind: FrameKind,
+    /// Whether the frame is the last one of the message
+    pub end: bool,
+    pub request_id: u64,
+    pub payload_len: u32,
+}
+
+impl FrameHeader {
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut buf = [0u8; HEADER_LEN];
+        buf[0] = VERSION;
+        buf[1] = self.kind.to_u8();
+        if self.end {
+            buf[2] |= FLAG_END;
+        }
+        buf[4..12].copy_from_slice(&self.request_id.to_be_bytes());
+        buf[12..16].copy_from_slice(&self.payload_len.to_be_bytes());
+        buf
+    }
+
+    pub fn decode(buf: &[u8; HEADER_LEN]) -> Result<Self> {
+        if buf[0] != VERSION {
+            return Err(MuxError::InvalidFrame("unsupported version"));
+        }
+        if buf[2] & !FLAG_END != 0 || buf[3] != 0 {
+            return Err(MuxError::InvalidFrame("unknown flags"));
+        }
+        let mut request_id = [0u8; 8];
+        request_id.copy_from_slice(&buf[4..12]);
+        let mut payload_len = [0u8; 4];
+        payload_len.copy_from_slice(&buf[12..16]);
+
+        Ok(FrameHeader {
+            kind: FrameKind::from_u8(buf[1])?,
+            end: buf[2] & FLAG_END != 0,
+            request_id: u64::from_be_bytes(request_id),
+            payload_len: u32::from_be_bytes(payload_len),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_roundtrip() {
+        let header = FrameHeader {
+            kind: FrameKind::Error,
+            end: true,
+            request_id: 0x0102_0304_0506_0708,
+            payload_len: 4096,
+        };
+        let encoded = header.encode();
+        assert_eq!(encoded, [1, 2, 1, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0x10, 0]);
+        assert_eq!(FrameHeader::decode(&encoded).unwrap(), header);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut encoded = FrameHeader {
+            kind: FrameKind::Request,
+            end: false,
+            request_id: 1,
+            payload_len: 0,
+        }
+        .encode();
+
+        let mut version = encoded;
+        version[0] = 2;
+        assert!(FrameHeader::decode(&version).is_err());
+
+        let mut kind = encoded;
+        kind[1] = 3;
+        assert!(FrameHeader::decode(&kind).is_err());
+
+        encoded[2] = 0x02;
+        assert!(FrameHeader::decode(&encoded).is_err());
+    }
+}
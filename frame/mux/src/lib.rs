@@ -0,0 +1,29 @@
+//! A multiplexed request/response protocol over any reliable byte streams, e.g. TLS sessions.
+//!
+//! Each message is tagged with a request ID and split into frames of `MAX_CHUNK_LEN` bytes at most,
+//! so that a session carries multiple requests and payloads larger than a single frame.
+//! The requests can be pipelined, and the responses are matched to the requests by their IDs.
+//! This crate doesn't depend on TLS libraries, so that it can be tested with plain rustls outside enclaves.
+
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+#[cfg(feature = "sgx")]
+#[macro_use]
+extern crate sgx_tstd as localstd;
+#[cfg(feature = "std")]
+use std as localstd;
+
+mod client;
+mod error;
+mod frame;
+mod pool;
+mod session;
+mod stream;
+#[cfg(test)]
+mod tests;
+
+pub use client::MuxClient;
+pub use error::{MuxError, Result};
+pub use frame::{FrameHeader, FrameKind, HEADER_LEN, MAX_CHUNK_LEN};
+pub use pool::WorkerPool;
+pub use session::{serve, Service};
+pub use stream::{FramedStream, Message, DEFAULT_MAX_MESSAGE_LEN};
@@ -0,0 +1,114 @@
+use crate::error::{MuxError, Result};
+#[cfg(feature = "std")]
+use crate::localstd::sync::Mutex;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
+use crate::localstd::sync::SgxMutex as Mutex;
+use crate::localstd::{
+    boxed::Box,
+    io,
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    vec::Vec,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed number of worker threads with a bounded queue of jobs.
+/// `execute` blocks while the queue is full, which applies back pressure to the acceptor of the sessions.
+/// Dropping the pool waits for the queued jobs to be done.
+pub struct WorkerPool {
+    sender: Option<SyncSender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(num_workers: usize, queue_len: usize) -> io::Result<Self> {
+        assert!(
+            num_workers > 0,
+            "The worker pool requires one worker at least"
+        );
+
+        let (sender, receiver) = sync_channel::<Job>(queue_len);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_workers)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::Builder::new().spawn(move || work(&receiver))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(WorkerPool {
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    pub fn num_workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn execute<F>(&self, job: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.sender
+            .as_ref()
+            .expect("The sender is taken only on drop")
+            .send(Box::new(job))
+            .map_err(|_| MuxError::WorkerPoolClosed)
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before the job runs, so that the other workers can receive the next jobs
+        let job = match receiver.lock().expect("Poisoned lock").recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        job();
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Disconnect the channel so that the workers exit after the queued jobs
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Barrier,
+    };
+
+    #[test]
+    fn test_jobs_run_concurrently() {
+        let num_workers = 4;
+        let pool = WorkerPool::new(num_workers, 0).unwrap();
+        // Every job waits for the others, so it deadlocks unless all the workers run concurrently
+        let barrier = Arc::new(Barrier::new(num_workers));
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..num_workers {
+            let barrier = barrier.clone();
+            let done = done.clone();
+            pool.execute(move || {
+                barrier.wait();
+                done.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+
+        drop(pool);
+        assert_eq!(done.load(Ordering::SeqCst), num_workers);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::error::{MuxError, Result};
+use crate::frame::FrameKind;
+use crate::localstd::{
+    io::{Read, Write},
+    string::String,
+    vec::Vec,
+};
+use crate::stream::FramedStream;
+
+/// A service handling the requests of a session.
+pub trait Service {
+    /// Handle the request, whose error is sent to the client as an error response.
+    fn call(&self, request: &[u8]) -> core::result::Result<Vec<u8>, String>;
+}
+
+/// Serve the requests over the session until the client closes it or it is idle for the read timeout of the stream.
+/// The requests are handled in the order they are completed, and each response has the same ID as its request.
+pub fn serve<S, H>(stream: &mut FramedStream<S>, service: &H) -> Result<()>
+where
+    S: Read + Write,
+    H: Service + ?Sized,
+{
+    loop {
+        let request = match stream.read_message() {
+            Ok(request) => request,
+            Err(e) if e.is_closed() => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if request.kind != FrameKind::Request {
+            return Err(MuxError::InvalidFrame("expected a request"));
+        }
+
+        match service.call(&request.payload) {
+            Ok(response) => {
+                stream.write_message(FrameKind::Response, request.request_id, &response)?
+            }
+            Err(msg) => {
+                stream.write_message(FrameKind::Error, request.request_id, msg.as_bytes())?
+            }
+        }
+    }
+}
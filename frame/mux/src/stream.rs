@@ -0,0 +1,247 @@
+use crate::error::{MuxError, Result};
+use crate::frame::{FrameHeader, FrameKind, HEADER_LEN, MAX_CHUNK_LEN};
+use crate::localstd::{
+    collections::HashMap,
+    io::{Read, Write},
+    vec::Vec,
+};
+
+/// The default maximum length of a message, which bounds the memory buffering the partially received messages.
+/// The enclave heap is limited, so it is far less than the whole heap.
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 512 * 1024;
+
+/// A message reassembled from its frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub kind: FrameKind,
+    pub request_id: u64,
+    pub payload: Vec<u8>,
+}
+
+/// A stream sending and receiving messages which are split into frames.
+/// The frames of different messages may be interleaved, and they are reassembled by their request IDs.
+pub struct FramedStream<S> {
+    inner: S,
+    max_message_len: usize,
+    partials: HashMap<u64, Message>,
+    buffered_len: usize,
+}
+
+impl<S: Read + Write> FramedStream<S> {
+    pub fn new(inner: S) -> Self {
+        FramedStream {
+            inner,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            partials: HashMap::new(),
+            buffered_len: 0,
+        }
+    }
+
+    /// Set the maximum length of a message, which also bounds the total length of the partially received messages.
+    pub fn set_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    pub fn max_message_len(&self) -> usize {
+        self.max_message_len
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub fn write_message(
+        &mut self,
+        kind: FrameKind,
+        request_id: u64,
+        payload: &[u8],
+    ) -> Result<()> {
+        if payload.len() > self.max_message_len {
+            return Err(MuxError::MessageTooLarge(payload.len()));
+        }
+
+        // An empty message is sent as a single empty frame
+        let mut offset = 0;
+        loop {
+            let chunk = &payload[offset..core::cmp::min(offset + MAX_CHUNK_LEN, payload.len())];
+            offset += chunk.len();
+            let header = FrameHeader {
+                kind,
+                end: offset == payload.len(),
+                request_id,
+                payload_len: chunk.len() as u32,
+            };
+            self.inner.write_all(&header.encode())?;
+            self.inner.write_all(chunk)?;
+            if header.end {
+                break;
+            }
+        }
+        self.inner.flush()?;
+
+        Ok(())
+    }
+
+    /// Read frames until any message is completed.
+    pub fn read_message(&mut self) -> Result<Message> {
+        loop {
+            let mut header = [0u8; HEADER_LEN];
+            self.inner.read_exact(&mut header)?;
+            let header = FrameHeader::decode(&header)?;
+
+            let chunk_len = header.payload_len as usize;
+            if chunk_len > MAX_CHUNK_LEN {
+                return Err(MuxError::FrameTooLarge(chunk_len));
+            }
+            if self.buffered_len + chunk_len > self.max_message_len {
+                let partial_len = self
+                    .partials
+                    .get(&header.request_id)
+                    .map_or(0, |m| m.payload.len());
+                return Err(MuxError::MessageTooLarge(partial_len + chunk_len));
+            }
+
+            let message = self
+                .partials
+                .entry(header.request_id)
+                .or_insert_with(|| Message {
+                    kind: header.kind,
+                    request_id: header.request_id,
+                    payload: Vec::new(),
+                });
+            if message.kind != header.kind {
+                return Err(MuxError::InvalidFrame(
+                    "the kind changed within the message",
+                ));
+            }
+            let offset = message.payload.len();
+            message.payload.resize(offset + chunk_len, 0);
+            self.buffered_len += chunk_len;
+            self.inner.read_exact(&mut message.payload[offset..])?;
+
+            if header.end {
+                let message = self
+                    .partials
+                    .remove(&header.request_id)
+                    .expect("the partial message must be buffered");
+                self.buffered_len -= message.payload.len();
+                return Ok(message);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_chunked_message() {
+        let mut stream = FramedStream::new(Cursor::new(Vec::new()));
+        let large = payload(MAX_CHUNK_LEN * 2 + 1);
+        stream.write_message(FrameKind::Request, 1, &large).unwrap();
+        stream.write_message(FrameKind::Request, 2, &[]).unwrap();
+        // 3 frames for the large message and 1 for the empty one
+        assert_eq!(
+            stream.get_ref().get_ref().len(),
+            HEADER_LEN * 4 + large.len()
+        );
+
+        stream.get_mut().set_position(0);
+        let message = stream.read_message().unwrap();
+        assert_eq!(message.request_id, 1);
+        assert_eq!(message.payload, large);
+        let message = stream.read_message().unwrap();
+        assert_eq!(message.request_id, 2);
+        assert!(message.payload.is_empty());
+        assert!(stream.read_message().unwrap_err().is_closed());
+    }
+
+    #[test]
+    fn test_interleaved_frames() {
+        let frame = |request_id: u64, end: bool, payload: &[u8]| {
+            let mut buf = FrameHeader {
+                kind: FrameKind::Response,
+                end,
+                request_id,
+                payload_len: payload.len() as u32,
+            }
+            .encode()
+            .to_vec();
+            buf.extend_from_slice(payload);
+            buf
+        };
+        let mut buf = frame(1, false, b"he");
+        buf.extend(frame(2, false, b"wor"));
+        buf.extend(frame(2, true, b"ld"));
+        buf.extend(frame(1, true, b"llo"));
+
+        let mut stream = FramedStream::new(Cursor::new(buf));
+        let message = stream.read_message().unwrap();
+        assert_eq!(
+            (message.request_id, &message.payload[..]),
+            (2, &b"world"[..])
+        );
+        let message = stream.read_message().unwrap();
+        assert_eq!(
+            (message.request_id, &message.payload[..]),
+            (1, &b"hello"[..])
+        );
+        assert_eq!(stream.buffered_len, 0);
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let mut writer = FramedStream::new(Cursor::new(Vec::new()));
+        writer
+            .write_message(FrameKind::Request, 1, &payload(MAX_CHUNK_LEN + 1))
+            .unwrap();
+        let buf = writer.into_inner().into_inner();
+
+        let mut writer =
+            FramedStream::new(Cursor::new(Vec::new())).set_max_message_len(MAX_CHUNK_LEN);
+        assert!(matches!(
+            writer.write_message(FrameKind::Request, 1, &payload(MAX_CHUNK_LEN + 1)),
+            Err(MuxError::MessageTooLarge(_))
+        ));
+
+        let mut reader = FramedStream::new(Cursor::new(buf)).set_max_message_len(MAX_CHUNK_LEN);
+        assert!(matches!(
+            reader.read_message(),
+            Err(MuxError::MessageTooLarge(len)) if len == MAX_CHUNK_LEN + 1
+        ));
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let mut buf = FrameHeader {
+            kind: FrameKind::Request,
+            end: true,
+            request_id: 1,
+            payload_len: MAX_CHUNK_LEN as u32 + 1,
+        }
+        .encode()
+        .to_vec();
+        buf.extend(payload(MAX_CHUNK_LEN + 1));
+
+        let mut stream = FramedStream::new(Cursor::new(buf));
+        assert!(matches!(
+            stream.read_message(),
+            Err(MuxError::FrameTooLarge(_))
+        ));
+    }
+}
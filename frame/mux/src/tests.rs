@@ -0,0 +1,169 @@
+//! End-to-end tests over mutually authenticated rustls sessions with the test certificates in `fixtures`.
+
+use crate::*;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Barrier};
+use std::time::Duration;
+
+const CA_CERT: &[u8] = include_bytes!("../fixtures/ca.pem");
+const SERVER_CERT: &[u8] = include_bytes!("../fixtures/server.pem");
+const SERVER_KEY: &[u8] = include_bytes!("../fixtures/server.key");
+const CLIENT_CERT: &[u8] = include_bytes!("../fixtures/client.pem");
+const CLIENT_KEY: &[u8] = include_bytes!("../fixtures/client.key");
+
+/// Echo the request, and fail if it is `fail`.
+/// If the request is `wait`, wait for the other session doing so.
+#[derive(Clone)]
+struct EchoService {
+    barrier: Arc<Barrier>,
+}
+
+impl Service for EchoService {
+    fn call(&self, request: &[u8]) -> core::result::Result<Vec<u8>, String> {
+        match request {
+            b"fail" => Err("failed".to_string()),
+            b"wait" => {
+                self.barrier.wait();
+                Ok(request.to_vec())
+            }
+            _ => Ok(request.to_vec()),
+        }
+    }
+}
+
+fn root_store() -> rustls::RootCertStore {
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.add_pem_file(&mut &CA_CERT[..]).unwrap();
+    root_store
+}
+
+fn server_config() -> Arc<rustls::ServerConfig> {
+    let mut config =
+        rustls::ServerConfig::new(rustls::AllowAnyAuthenticatedClient::new(root_store()));
+    config
+        .set_single_cert(
+            certs(&mut &SERVER_CERT[..]).unwrap(),
+            pkcs8_private_keys(&mut &SERVER_KEY[..]).unwrap().remove(0),
+        )
+        .unwrap();
+    Arc::new(config)
+}
+
+fn client_config() -> Arc<rustls::ClientConfig> {
+    let mut config = rustls::ClientConfig::new();
+    config.root_store = root_store();
+    config
+        .set_single_client_cert(
+            certs(&mut &CLIENT_CERT[..]).unwrap(),
+            pkcs8_private_keys(&mut &CLIENT_KEY[..]).unwrap().remove(0),
+        )
+        .unwrap();
+    Arc::new(config)
+}
+
+/// Serve the sessions by the worker pool, and return the address of the server.
+fn spawn_server(num_workers: usize, service: EchoService) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let config = server_config();
+
+    std::thread::spawn(move || {
+        let pool = WorkerPool::new(num_workers, num_workers).unwrap();
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let session = rustls::ServerSession::new(&config);
+            let service = service.clone();
+            pool.execute(move || {
+                let mut stream = FramedStream::new(rustls::StreamOwned::new(session, stream));
+                let _ = serve(&mut stream, &service);
+            })
+            .unwrap();
+        }
+    });
+
+    address
+}
+
+fn connect(address: &str) -> MuxClient<rustls::StreamOwned<rustls::ClientSession, TcpStream>> {
+    let hostname = webpki::DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let session = rustls::ClientSession::new(&client_config(), hostname);
+    let stream = TcpStream::connect(address).unwrap();
+    MuxClient::new(FramedStream::new(rustls::StreamOwned::new(session, stream)))
+}
+
+fn echo_service() -> EchoService {
+    EchoService {
+        barrier: Arc::new(Barrier::new(2)),
+    }
+}
+
+#[test]
+fn test_multiple_requests_per_session() {
+    let address = spawn_server(1, echo_service());
+    let mut client = connect(&address);
+
+    assert_eq!(client.request(b"hello").unwrap(), b"hello");
+    assert!(matches!(
+        client.request(b"fail"),
+        Err(MuxError::Remote(msg)) if msg == "failed"
+    ));
+    // The session is still available after the error
+    assert_eq!(client.request(b"world").unwrap(), b"world");
+}
+
+#[test]
+fn test_large_payload() {
+    let address = spawn_server(1, echo_service());
+    let mut client = connect(&address);
+
+    // e.g. `ManuallyRecoverAll` with many path secrets
+    let large: Vec<u8> = (0..100 * MAX_CHUNK_LEN + 123).map(|i| i as u8).collect();
+    assert_eq!(client.request(&large).unwrap(), large);
+
+    let too_large = vec![0u8; DEFAULT_MAX_MESSAGE_LEN + 1];
+    assert!(matches!(
+        client.request(&too_large),
+        Err(MuxError::MessageTooLarge(_))
+    ));
+}
+
+#[test]
+fn test_pipelined_requests() {
+    let address = spawn_server(1, echo_service());
+    let mut client = connect(&address);
+
+    let large = vec![7u8; MAX_CHUNK_LEN * 3];
+    let requests: Vec<&[u8]> = vec![b"first", b"fail", &large, b"last"];
+    let responses = client.request_all(&requests).unwrap();
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0].as_ref().unwrap(), b"first");
+    assert!(matches!(responses[1], Err(MuxError::Remote(_))));
+    assert_eq!(responses[2].as_ref().unwrap(), &large);
+    assert_eq!(responses[3].as_ref().unwrap(), b"last");
+
+    // The responses received out of order are buffered
+    let first = client.send(b"a").unwrap();
+    let second = client.send(b"b").unwrap();
+    assert_eq!(client.receive(second).unwrap(), b"b");
+    assert_eq!(client.receive(first).unwrap(), b"a");
+    assert!(matches!(
+        client.receive(first),
+        Err(MuxError::UnexpectedResponse(_))
+    ));
+}
+
+#[test]
+fn test_concurrent_sessions() {
+    let address = spawn_server(2, echo_service());
+    let mut first = connect(&address);
+    let mut second = connect(&address);
+
+    // Each request waits for the other one, so it deadlocks unless the sessions are served concurrently
+    let handle = std::thread::spawn(move || first.request(b"wait").unwrap());
+    assert_eq!(second.request(b"wait").unwrap(), b"wait");
+    assert_eq!(handle.join().unwrap(), b"wait");
+}
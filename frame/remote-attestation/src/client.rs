@@ -1,7 +1,11 @@
 use crate::error::{FrameRAError, Result};
+use crate::policy::AttestationPolicy;
 use anyhow::anyhow;
+#[cfg(feature = "sgx")]
 use frame_config::{REQUEST_RETRIES, RETRY_DELAY_MILLS};
+#[cfg(feature = "sgx")]
 use frame_retrier::{strategy, Retry};
+#[cfg(feature = "sgx")]
 use http_req::{
     request::{Method, Request},
     response::{Headers, Response},
@@ -9,7 +13,9 @@ use http_req::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{io::Write, prelude::v1::*, str, string::String, time::SystemTime};
+#[cfg(feature = "sgx")]
+use std::io::Write;
+use std::{prelude::v1::*, str, time::SystemTime};
 
 type SignatureAlgorithms = &'static [&'static webpki::SignatureAlgorithm];
 static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
@@ -27,11 +33,13 @@ static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
 ];
 
 /// A client for remote attestation with IAS
+#[cfg(feature = "sgx")]
 pub struct RAClient<'a> {
     request: Request<'a>,
     host: String,
 }
 
+#[cfg(feature = "sgx")]
 impl<'a> RAClient<'a> {
     pub fn new(uri: &'a Uri) -> Self {
         let host = uri.host_header().expect("Not found host in the uri");
@@ -100,6 +108,7 @@ impl<'a> RAClient<'a> {
 
 /// A response from IAS
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
 pub struct AttestedReport {
     /// A report returned from Attestation Service
     report: Vec<u8>,
@@ -110,6 +119,7 @@ pub struct AttestedReport {
 }
 
 impl AttestedReport {
+    #[cfg(feature = "sgx")]
     pub(crate) fn from_response(body: Vec<u8>, resp: Response) -> Result<Self> {
         if !resp.status_code().is_success() {
             return Err(FrameRAError::StatusCodeError(resp));
@@ -134,13 +144,39 @@ impl AttestedReport {
         })
     }
 
+    /// A report of the quote body as if it were returned from IAS, which has no signature and certificate.
+    /// It can't be verified by `verify_attested_report`, so it is accepted only by peers which don't verify reports.
+    #[cfg(feature = "software-enclave")]
+    pub(crate) fn mock(quote_body: &[u8]) -> Result<Self> {
+        let timestamp = chrono::Utc::now()
+            .naive_utc()
+            .format("%Y-%m-%dT%H:%M:%S%.6f")
+            .to_string();
+        let report = serde_json::json!({
+            "version": 3,
+            "timestamp": timestamp,
+            "isvEnclaveQuoteStatus": "OK",
+            "isvEnclaveQuoteBody": base64::encode(quote_body),
+        });
+
+        Ok(AttestedReport {
+            report: serde_json::to_vec(&report)?,
+            report_sig: vec![],
+            report_cert: vec![],
+        })
+    }
+
     /// Verify that
     /// 1. TLS server certificate
     /// 2. report's signature
     /// 3. report's version
-    /// 4. quote status
+    /// 4. quote status, report's age and quote body conform to the policy
     #[must_use]
-    pub fn verify_attested_report(self, root_cert: Vec<u8>) -> Result<Self> {
+    pub fn verify_attested_report(
+        self,
+        root_cert: Vec<u8>,
+        policy: &AttestationPolicy,
+    ) -> Result<Self> {
         let now_func = webpki::Time::try_from(SystemTime::now()).map_err(|e| anyhow!("{:?}", e))?;
 
         let mut root_store = rustls::RootCertStore::empty();
@@ -172,7 +208,9 @@ impl AttestedReport {
 
         let report = serde_json::from_slice(&self.report)?;
         Self::verify_version(&report)?;
-        Self::verify_quote_status(&report)?;
+        Self::verify_quote_status(&report, policy)?;
+        Self::verify_timestamp(&report, policy)?;
+        policy.verify_quote_body(&self.get_quote_body()?)?;
 
         Ok(self)
     }
@@ -208,23 +246,29 @@ impl AttestedReport {
         Ok(())
     }
 
-    /// Verify the quote status included the attestation report is OK
-    fn verify_quote_status(report: &Value) -> Result<()> {
+    /// Verify the quote status included the attestation report is allowed by the policy
+    fn verify_quote_status(report: &Value, policy: &AttestationPolicy) -> Result<()> {
         if let Value::String(quote_status) = &report["isvEnclaveQuoteStatus"] {
-            match quote_status.as_ref() {
-                "OK" => Ok(()),
-                "GROUP_OUT_OF_DATE" => {
-                    println!("Enclave Quote Status: GROUP_OUT_OF_DATE");
-                    Ok(())
-                }
-                _ => Err(FrameRAError::QuoteStatusError(quote_status.to_string())),
-            }
+            let advisory_ids: Vec<&str> = report["advisoryIDs"]
+                .as_array()
+                .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            policy.verify_quote_status(quote_status, &advisory_ids)
         } else {
             Err(FrameRAError::NotFoundisvEnclaveQuoteStatusError)
         }
     }
+
+    /// Verify the attestation report is not older than the maximum age of the policy
+    fn verify_timestamp(report: &Value, policy: &AttestationPolicy) -> Result<()> {
+        let timestamp = report["timestamp"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Not found timestamp in the attestation report"))?;
+        policy.verify_report_age(timestamp)
+    }
 }
 
+#[cfg(feature = "sgx")]
 fn percent_decode(orig: String) -> Result<Vec<u8>> {
     let v: Vec<&str> = orig.split('%').collect();
     if v.is_empty() {
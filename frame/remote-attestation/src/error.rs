@@ -18,8 +18,11 @@ pub enum FrameRAError {
     SerdeJsonError(#[from] serde_json::Error),
     #[error("web pki error: {0}")]
     WebpkiJsonError(#[from] webpki::Error),
+    #[cfg(feature = "sgx")]
     #[error("http req error: {0}")]
     HttpReqError(#[from] http_req::error::Error),
+    #[error("DCAP error: {0}")]
+    DcapError(#[from] frame_dcap::DcapError),
 
     #[error("Ocall Error: function: {function:?}, status: {status:?}")]
     OcallError {
@@ -32,14 +35,29 @@ pub enum FrameRAError {
         function: &'static str,
     },
 
+    #[cfg(feature = "sgx")]
     #[error("The status code indicates that it's not Successful, response: {0:?}")]
     StatusCodeError(http_req::response::Response),
     #[error("The Remote Attestation API version ({0}) is not supported")]
     ApiVersionError(u64),
     #[error("Invalid Enclave Quote Status: {0}")]
     QuoteStatusError(String),
+    #[error("Advisory ID is not allowed: {0}")]
+    AdvisoryIdError(String),
+    #[error("The attestation report is too old: age: {age} secs, max age: {max_age} secs")]
+    ReportTooOldError { age: u64, max_age: u64 },
+    #[error("Debug enclaves are not allowed")]
+    DebugEnclaveError,
+    #[error("Invalid ISV product ID: expected: {expected}, actual: {actual}")]
+    IsvProdIdError { expected: u16, actual: u16 },
+    #[error("ISV SVN is lower than the minimum: min: {min}, actual: {actual}")]
+    IsvSvnError { min: u16, actual: u16 },
     #[error("Failed to fetch isvEnclaveQuoteStatus from attestation report")]
     NotFoundisvEnclaveQuoteStatusError,
+    #[error("The root certificate of DCAP attestations is not set")]
+    NotFoundDcapRootCertError,
+    #[error("Invalid attestation configuration: {0}")]
+    ConfigError(String),
     #[error("Certificate is blank")]
     BlankCertError,
     #[error("qe_report is not valid: {0}")]
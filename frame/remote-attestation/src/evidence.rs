@@ -0,0 +1,193 @@
+use crate::client::AttestedReport;
+use crate::error::{FrameRAError, Result};
+use crate::policy::AttestationPolicy;
+#[cfg(feature = "software-enclave")]
+use crate::policy::{ATTRIBUTES_FLAGS_OFFSET, QUOTE_BODY_LEN, REPORT_DATA_OFFSET};
+#[cfg(feature = "sgx")]
+use crate::quote::QuoteTarget;
+use anyhow::anyhow;
+use frame_config::DCAP_COLLATERAL_DIR;
+#[cfg(feature = "sgx")]
+use frame_config::{AttestationType, ATTESTATION_TYPE, DCAP_ROOT_CERT};
+use frame_dcap::{DcapCollateral, DcapQuote};
+use serde::{Deserialize, Serialize};
+use sgx_types::sgx_report_data_t;
+#[cfg(feature = "software-enclave")]
+use std::fs;
+#[cfg(feature = "sgx")]
+use std::untrusted::fs;
+use std::{
+    prelude::v1::*,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// An evidence of remote attestation, which is an attestation report of IAS (EPID)
+/// or an ECDSA quote with its collateral (DCAP).
+/// It is untagged so that the serialized EPID evidence is the same as `AttestedReport`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+#[serde(untagged)]
+pub enum AttestationEvidence {
+    Epid(AttestedReport),
+    Dcap(DcapEvidence),
+}
+
+impl AttestationEvidence {
+    /// Verify the evidence against the root certificate of its type and the policy.
+    #[must_use]
+    pub fn verify(
+        self,
+        ias_root_cert: Vec<u8>,
+        dcap_root_cert: Option<&[u8]>,
+        policy: &AttestationPolicy,
+    ) -> Result<Self> {
+        match self {
+            AttestationEvidence::Epid(report) => report
+                .verify_attested_report(ias_root_cert, policy)
+                .map(AttestationEvidence::Epid),
+            AttestationEvidence::Dcap(evidence) => {
+                let root_cert = dcap_root_cert.ok_or(FrameRAError::NotFoundDcapRootCertError)?;
+                evidence
+                    .verify(root_cert, policy)
+                    .map(AttestationEvidence::Dcap)
+            }
+        }
+    }
+
+    /// The quote header and the report body of the enclave, whose layouts are the same in both types.
+    pub fn get_quote_body(&self) -> Result<Vec<u8>> {
+        match self {
+            AttestationEvidence::Epid(report) => report.get_quote_body(),
+            AttestationEvidence::Dcap(evidence) => evidence.get_quote_body(),
+        }
+    }
+
+    /// The report and its signature to be registered with the contract.
+    /// For DCAP, they are the quote and its PCK certificate chain in DER,
+    /// which `ReportHandle.sol` verifies against Intel SGX Root CA.
+    pub fn contract_report(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            AttestationEvidence::Epid(report) => {
+                Ok((report.report().to_vec(), report.report_sig().to_vec()))
+            }
+            AttestationEvidence::Dcap(evidence) => {
+                let pck_cert_chain = DcapQuote::parse(&evidence.quote)?.pck_cert_chain_der();
+                Ok((evidence.quote.clone(), pck_cert_chain))
+            }
+        }
+    }
+}
+
+/// An ECDSA quote of DCAP with the collateral to verify it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct DcapEvidence {
+    quote: Vec<u8>,
+    collateral: DcapCollateral,
+}
+
+impl DcapEvidence {
+    pub fn new(quote: Vec<u8>, collateral: DcapCollateral) -> Self {
+        DcapEvidence { quote, collateral }
+    }
+
+    /// Attach the collateral stored in `DCAP_COLLATERAL_DIR` to the quote.
+    pub fn with_local_collateral(quote: Vec<u8>) -> Result<Self> {
+        let read = |file_name: &str| fs::read_to_string(DCAP_COLLATERAL_DIR.join(file_name));
+        let collateral = DcapCollateral {
+            tcb_info: read("tcb_info.json")?,
+            qe_identity: read("qe_identity.json")?,
+            tcb_signing_chain: read("tcb_signing_chain.pem")?,
+        };
+
+        Ok(DcapEvidence::new(quote, collateral))
+    }
+
+    /// Verify that
+    /// 1. the quote and the collateral are issued by the root certificate
+    /// 2. TCB status and quote body conform to the policy
+    #[must_use]
+    pub fn verify(self, root_cert: &[u8], policy: &AttestationPolicy) -> Result<Self> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| anyhow!("{:?}", e))?
+            .as_secs();
+        let verified = frame_dcap::verify_quote(&self.quote, &self.collateral, root_cert, now)?;
+        // TCB info v2 has no advisory IDs
+        policy.verify_tcb_status(verified.tcb_status.as_quote_status(), &[])?;
+        policy.verify_quote_body(&verified.quote_body)?;
+
+        Ok(self)
+    }
+
+    pub fn get_quote_body(&self) -> Result<Vec<u8>> {
+        let quote = DcapQuote::parse(&self.quote)?;
+        Ok(quote.body().to_vec())
+    }
+
+    pub fn quote(&self) -> &[u8] {
+        &self.quote
+    }
+
+    pub fn collateral(&self) -> &DcapCollateral {
+        &self.collateral
+    }
+}
+
+/// Attest the enclave with the type configured by `ATTESTATION_TYPE`,
+/// and verify the evidence conforms to the policy so that it is accepted by others.
+/// The IAS parameters are used only for EPID.
+#[cfg(feature = "sgx")]
+pub fn attest(
+    report_data: &sgx_report_data_t,
+    spid: &str,
+    ias_url: &str,
+    sub_key: &str,
+    ias_root_cert: Vec<u8>,
+    policy: &AttestationPolicy,
+) -> Result<AttestationEvidence> {
+    let attestation_type = ATTESTATION_TYPE
+        .clone()
+        .map_err(FrameRAError::ConfigError)?;
+    match attestation_type {
+        AttestationType::Epid => QuoteTarget::new()?
+            .set_enclave_report(report_data)?
+            .create_quote(spid)?
+            .remote_attestation(ias_url, sub_key, ias_root_cert, policy)
+            .map(AttestationEvidence::Epid),
+        AttestationType::Dcap => {
+            let quote = QuoteTarget::new_dcap()?
+                .set_enclave_report(report_data)?
+                .create_dcap_quote()?;
+            let root_cert = DCAP_ROOT_CERT
+                .as_ref()
+                .map_err(|e| FrameRAError::ConfigError(e.clone()))?
+                .as_ref()
+                .ok_or(FrameRAError::NotFoundDcapRootCertError)?;
+            DcapEvidence::with_local_collateral(quote)?
+                .verify(root_cert, policy)
+                .map(AttestationEvidence::Dcap)
+        }
+    }
+}
+
+/// Mock the attestation of the enclave running in the host process, where neither the quote nor IAS is available.
+/// The evidence is an unsigned EPID report of the quote body with the report data, which is flagged as
+/// a debug enclave. It is verified against the policy, but only the ledgers which don't verify reports accept it.
+#[cfg(feature = "software-enclave")]
+pub fn attest(
+    report_data: &sgx_report_data_t,
+    _spid: &str,
+    _ias_url: &str,
+    _sub_key: &str,
+    _ias_root_cert: Vec<u8>,
+    policy: &AttestationPolicy,
+) -> Result<AttestationEvidence> {
+    let mut quote_body = vec![0u8; QUOTE_BODY_LEN];
+    quote_body[ATTRIBUTES_FLAGS_OFFSET..ATTRIBUTES_FLAGS_OFFSET + 8]
+        .copy_from_slice(&sgx_types::SGX_FLAGS_DEBUG.to_le_bytes());
+    quote_body[REPORT_DATA_OFFSET..].copy_from_slice(&report_data.d);
+    policy.verify_quote_body(&quote_body)?;
+
+    AttestedReport::mock(&quote_body).map(AttestationEvidence::Epid)
+}
@@ -1,11 +1,38 @@
-#![no_std]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "sgx")]
 #[macro_use]
 extern crate sgx_tstd as std;
+#[cfg(feature = "software-enclave")]
+extern crate anyhow_std as anyhow;
+#[cfg(feature = "software-enclave")]
+extern crate base64_std as base64;
+#[cfg(feature = "software-enclave")]
+extern crate chrono_std as chrono;
+#[cfg(feature = "software-enclave")]
+extern crate once_cell_std as once_cell;
+#[cfg(feature = "software-enclave")]
+extern crate rustls_std as rustls;
+#[cfg(feature = "software-enclave")]
+extern crate serde_json_std as serde_json;
+#[cfg(feature = "sgx")]
+extern crate serde_sgx as serde;
+#[cfg(feature = "software-enclave")]
+extern crate serde_std as serde;
+#[cfg(feature = "software-enclave")]
+extern crate thiserror_std as thiserror;
+#[cfg(feature = "software-enclave")]
+extern crate webpki_std as webpki;
 
 mod client;
 mod error;
+mod evidence;
+mod policy;
+#[cfg(feature = "sgx")]
 mod quote;
 
 pub use crate::client::AttestedReport;
 pub use crate::error::FrameRAError as Error;
+pub use crate::evidence::{attest, AttestationEvidence, DcapEvidence};
+pub use crate::policy::{attestation_policy, AttestationPolicy, ATTESTATION_POLICY};
+#[cfg(feature = "sgx")]
 pub use crate::quote::{EncodedQuote, QuoteTarget};
@@ -0,0 +1,261 @@
+use crate::error::{FrameRAError, Result};
+use anyhow::anyhow;
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use sgx_types::SGX_FLAGS_DEBUG;
+use std::{
+    convert::TryInto,
+    env,
+    prelude::v1::*,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The attestation policy configured by the environment variables:
+/// - ATTESTATION_ALLOWED_QUOTE_STATUSES: comma-separated quote statuses of EPID to be accepted
+/// - ATTESTATION_ALLOWED_TCB_STATUSES: comma-separated TCB statuses of DCAP to be accepted
+/// - ATTESTATION_ALLOWED_ADVISORY_IDS: comma-separated advisory IDs to be accepted with statuses except OK
+/// - ATTESTATION_MAX_REPORT_AGE_SECS: the maximum age of the attestation reports
+/// - ATTESTATION_ISV_PROD_ID: the product ID of the enclaves
+/// - ATTESTATION_MIN_ISV_SVN: the minimum security version of the enclaves
+/// - ATTESTATION_ALLOW_DEBUG: whether debug enclaves are accepted
+///
+/// An invalid value is kept as the error so that it is returned by `attestation_policy`
+/// instead of panicking in the enclave.
+pub static ATTESTATION_POLICY: Lazy<std::result::Result<AttestationPolicy, String>> =
+    Lazy::new(|| AttestationPolicy::from_env().map_err(|e| e.to_string()));
+
+pub(crate) const QUOTE_BODY_LEN: usize = 432;
+// Offsets are defined in "Attestation Service for Intel® Software Guard Extensions (Intel® SGX): API Documentation version 6.0"
+pub(crate) const ATTRIBUTES_FLAGS_OFFSET: usize = 96;
+const ISV_PROD_ID_OFFSET: usize = 304;
+const ISV_SVN_OFFSET: usize = 306;
+#[cfg(feature = "software-enclave")]
+pub(crate) const REPORT_DATA_OFFSET: usize = 368;
+
+/// Get `ATTESTATION_POLICY`, or the error if the environment variables are invalid.
+pub fn attestation_policy() -> Result<&'static AttestationPolicy> {
+    ATTESTATION_POLICY
+        .as_ref()
+        .map_err(|e| FrameRAError::ConfigError(format!("Invalid attestation policy: {}", e)))
+}
+
+/// A policy to accept remote attestations of EPID and DCAP.
+/// The TCB statuses of DCAP are in the same vocabulary as the quote statuses of IAS,
+/// e.g. `OUT_OF_DATE` and `SW_HARDENING_NEEDED`, but they are allowed separately.
+/// The default policy accepts `OK` and `GROUP_OUT_OF_DATE` of EPID, `OK` and `OUT_OF_DATE` of DCAP
+/// with any advisory IDs, and any enclaves including debug ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestationPolicy {
+    allowed_quote_statuses: Vec<String>,
+    allowed_tcb_statuses: Vec<String>,
+    /// If it is None, any advisory IDs are accepted.
+    allowed_advisory_ids: Option<Vec<String>>,
+    /// Only attestation reports of EPID have the timestamp, and DCAP quotes are bounded by
+    /// the `nextUpdate` of the collateral instead.
+    max_report_age_secs: Option<u64>,
+    isv_prod_id: Option<u16>,
+    min_isv_svn: u16,
+    allow_debug: bool,
+}
+
+impl Default for AttestationPolicy {
+    fn default() -> Self {
+        AttestationPolicy {
+            allowed_quote_statuses: vec!["OK".to_string(), "GROUP_OUT_OF_DATE".to_string()],
+            allowed_tcb_statuses: vec!["OK".to_string(), "OUT_OF_DATE".to_string()],
+            allowed_advisory_ids: None,
+            max_report_age_secs: None,
+            isv_prod_id: None,
+            min_isv_svn: 0,
+            allow_debug: true,
+        }
+    }
+}
+
+impl AttestationPolicy {
+    /// Build the policy from the environment variables. Unset variables are the default.
+    pub fn from_env() -> Result<Self> {
+        let mut policy = AttestationPolicy::default();
+        if let Some(statuses) = env_var("ATTESTATION_ALLOWED_QUOTE_STATUSES") {
+            policy.allowed_quote_statuses = split_list(&statuses);
+        }
+        if let Some(statuses) = env_var("ATTESTATION_ALLOWED_TCB_STATUSES") {
+            policy.allowed_tcb_statuses = split_list(&statuses);
+        }
+        if let Some(advisory_ids) = env_var("ATTESTATION_ALLOWED_ADVISORY_IDS") {
+            policy.allowed_advisory_ids = Some(split_list(&advisory_ids));
+        }
+        if let Some(max_age) = env_var("ATTESTATION_MAX_REPORT_AGE_SECS") {
+            policy.max_report_age_secs = Some(max_age.parse()?);
+        }
+        if let Some(isv_prod_id) = env_var("ATTESTATION_ISV_PROD_ID") {
+            policy.isv_prod_id = Some(isv_prod_id.parse()?);
+        }
+        if let Some(min_isv_svn) = env_var("ATTESTATION_MIN_ISV_SVN") {
+            policy.min_isv_svn = min_isv_svn.parse()?;
+        }
+        if let Some(allow_debug) = env_var("ATTESTATION_ALLOW_DEBUG") {
+            policy.allow_debug = allow_debug
+                .parse()
+                .map_err(|e| anyhow!("Invalid ATTESTATION_ALLOW_DEBUG: {:?}", e))?;
+        }
+
+        Ok(policy)
+    }
+
+    pub fn set_allowed_quote_statuses(mut self, statuses: Vec<String>) -> Self {
+        self.allowed_quote_statuses = statuses;
+        self
+    }
+
+    pub fn set_allowed_tcb_statuses(mut self, statuses: Vec<String>) -> Self {
+        self.allowed_tcb_statuses = statuses;
+        self
+    }
+
+    pub fn set_allowed_advisory_ids(mut self, advisory_ids: Vec<String>) -> Self {
+        self.allowed_advisory_ids = Some(advisory_ids);
+        self
+    }
+
+    pub fn set_max_report_age_secs(mut self, max_age: u64) -> Self {
+        self.max_report_age_secs = Some(max_age);
+        self
+    }
+
+    pub fn set_isv_prod_id(mut self, isv_prod_id: u16) -> Self {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    pub fn set_min_isv_svn(mut self, min_isv_svn: u16) -> Self {
+        self.min_isv_svn = min_isv_svn;
+        self
+    }
+
+    pub fn set_allow_debug(mut self, allow_debug: bool) -> Self {
+        self.allow_debug = allow_debug;
+        self
+    }
+
+    pub fn max_report_age_secs(&self) -> Option<u64> {
+        self.max_report_age_secs
+    }
+
+    /// Verify the quote status of EPID is allowed, and all the advisory IDs are allowed if the status is not OK.
+    pub fn verify_quote_status(&self, quote_status: &str, advisory_ids: &[&str]) -> Result<()> {
+        self.verify_status(&self.allowed_quote_statuses, quote_status, advisory_ids)
+    }
+
+    /// Verify the TCB status of DCAP, which is converted to the quote status, is allowed.
+    pub fn verify_tcb_status(&self, tcb_status: &str, advisory_ids: &[&str]) -> Result<()> {
+        self.verify_status(&self.allowed_tcb_statuses, tcb_status, advisory_ids)
+    }
+
+    fn verify_status(
+        &self,
+        allowed: &[String],
+        quote_status: &str,
+        advisory_ids: &[&str],
+    ) -> Result<()> {
+        if !allowed.iter().any(|s| s == quote_status) {
+            return Err(FrameRAError::QuoteStatusError(quote_status.to_string()));
+        }
+        if quote_status == "OK" {
+            return Ok(());
+        }
+
+        if let Some(allowed) = &self.allowed_advisory_ids {
+            if let Some(id) = advisory_ids
+                .iter()
+                .find(|id| !allowed.iter().any(|a| a == *id))
+            {
+                return Err(FrameRAError::AdvisoryIdError(id.to_string()));
+            }
+        }
+        println!(
+            "Enclave Quote Status: {}, advisory IDs: {:?}",
+            quote_status, advisory_ids
+        );
+
+        Ok(())
+    }
+
+    /// Verify the report is not older than the maximum age.
+    /// The timestamp is in UTC as `timestamp` of attestation reports, e.g. "2021-01-01T00:00:00.000000".
+    pub fn verify_report_age(&self, timestamp: &str) -> Result<()> {
+        let max_age = match self.max_report_age_secs {
+            Some(max_age) => max_age,
+            None => return Ok(()),
+        };
+        let issued_at = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f")
+            .map_err(|e| anyhow!("Invalid timestamp {}: {:?}", timestamp, e))?
+            .timestamp();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| anyhow!("{:?}", e))?
+            .as_secs() as i64;
+
+        // A report from a little ahead of the local clock is regarded as a fresh one
+        let age = (now - issued_at).max(0) as u64;
+        if age > max_age {
+            return Err(FrameRAError::ReportTooOldError { age, max_age });
+        }
+
+        Ok(())
+    }
+
+    /// Verify the attributes, the product ID and the security version of the enclave in the quote body.
+    pub fn verify_quote_body(&self, quote_body: &[u8]) -> Result<()> {
+        if quote_body.len() < QUOTE_BODY_LEN {
+            return Err(FrameRAError::AnyhowError(anyhow!(
+                "The quote body is too short: {}",
+                quote_body.len()
+            )));
+        }
+
+        let flags = u64::from_le_bytes(
+            quote_body[ATTRIBUTES_FLAGS_OFFSET..ATTRIBUTES_FLAGS_OFFSET + 8]
+                .try_into()
+                .unwrap(),
+        );
+        if !self.allow_debug && flags & SGX_FLAGS_DEBUG != 0 {
+            return Err(FrameRAError::DebugEnclaveError);
+        }
+
+        let isv_prod_id = u16::from_le_bytes([
+            quote_body[ISV_PROD_ID_OFFSET],
+            quote_body[ISV_PROD_ID_OFFSET + 1],
+        ]);
+        if let Some(expected) = self.isv_prod_id {
+            if isv_prod_id != expected {
+                return Err(FrameRAError::IsvProdIdError {
+                    expected,
+                    actual: isv_prod_id,
+                });
+            }
+        }
+
+        let isv_svn =
+            u16::from_le_bytes([quote_body[ISV_SVN_OFFSET], quote_body[ISV_SVN_OFFSET + 1]]);
+        if isv_svn < self.min_isv_svn {
+            return Err(FrameRAError::IsvSvnError {
+                min: self.min_isv_svn,
+                actual: isv_svn,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -1,5 +1,6 @@
 use crate::client::*;
 use crate::error::{FrameRAError, Result};
+use crate::policy::AttestationPolicy;
 use anyhow::anyhow;
 use frame_types::UntrustedStatus;
 use http_req::uri::Uri;
@@ -27,6 +28,19 @@ extern "C" {
         maxlen: u32,
         p_quote_len: *mut u32,
     ) -> sgx_status_t;
+
+    fn ocall_qe_get_target_info(
+        retval: *mut UntrustedStatus,
+        ret_ti: *mut sgx_target_info_t,
+    ) -> sgx_status_t;
+
+    fn ocall_qe_get_quote(
+        retval: *mut UntrustedStatus,
+        report: *const sgx_report_t,
+        p_quote: *mut u8,
+        maxlen: u32,
+        p_quote_len: *mut u32,
+    ) -> sgx_status_t;
 }
 
 /// The very high level service for remote attestations
@@ -45,6 +59,7 @@ impl EncodedQuote {
         uri: &str,
         ias_api_key: &str,
         root_cert: Vec<u8>,
+        policy: &AttestationPolicy,
     ) -> Result<AttestedReport> {
         let uri: Uri = uri.parse().expect("Invalid uri");
         let body = format!("{{\"isvEnclaveQuote\":\"{}\"}}\r\n", &self.base64_quote);
@@ -56,7 +71,7 @@ impl EncodedQuote {
             .send(&mut writer)?;
 
         AttestedReport::from_response(writer, response)?
-            .verify_attested_report(root_cert)
+            .verify_attested_report(root_cert, policy)
             .map_err(Into::into)
     }
 }
@@ -101,6 +116,37 @@ impl QuoteTarget {
         })
     }
 
+    /// Returns the target information of the quoting enclave of Intel SGX DCAP.
+    pub fn new_dcap() -> Result<Self> {
+        let mut rt = UntrustedStatus::default();
+        let mut target_info = sgx_target_info_t::default();
+
+        let status = unsafe {
+            ocall_qe_get_target_info(
+                &mut rt as *mut UntrustedStatus,
+                &mut target_info as *mut sgx_target_info_t,
+            )
+        };
+
+        if status != sgx_status_t::SGX_SUCCESS {
+            return Err(FrameRAError::OcallError {
+                status,
+                function: "ocall_qe_get_target_info",
+            });
+        }
+        if rt.is_err() {
+            return Err(FrameRAError::UntrustedError {
+                status: rt,
+                function: "ocall_qe_get_target_info",
+            });
+        }
+
+        Ok(Self {
+            target_info,
+            enclave_report: None,
+        })
+    }
+
     pub fn set_enclave_report(mut self, report_data: &sgx_report_data_t) -> Result<Self> {
         let enclave_report =
             sgx_tse::rsgx_create_report(&self.target_info, &report_data).map_err(|err| {
@@ -156,4 +202,39 @@ impl QuoteTarget {
         let _ = quote.split_off(quote_len as usize);
         Ok(EncodedQuote::new(base64::encode(&quote)))
     }
+
+    /// Create an ECDSA quote of DCAP with enclave's local report.
+    /// The target information must be the one of the quoting enclave of DCAP.
+    pub fn create_dcap_quote(self) -> Result<Vec<u8>> {
+        // The quote includes the PCK certificate chain in PEM
+        const RET_QUOTE_BUF_LEN: u32 = 8192;
+        let mut rt = UntrustedStatus::default();
+        let mut quote = vec![0u8; RET_QUOTE_BUF_LEN as usize];
+        let mut quote_len: u32 = 0;
+
+        let status = unsafe {
+            ocall_qe_get_quote(
+                &mut rt as *mut UntrustedStatus,
+                &self.enclave_report.unwrap() as *const sgx_report_t, // enclave_report must be set
+                quote.as_mut_ptr(),
+                RET_QUOTE_BUF_LEN, // maxlen
+                &mut quote_len as *mut u32,
+            )
+        };
+        if status != sgx_status_t::SGX_SUCCESS {
+            return Err(FrameRAError::OcallError {
+                status,
+                function: "ocall_qe_get_quote",
+            });
+        }
+        if rt.is_err() {
+            return Err(FrameRAError::UntrustedError {
+                status: rt,
+                function: "ocall_qe_get_quote",
+            });
+        }
+
+        quote.truncate(quote_len as usize);
+        Ok(quote)
+    }
 }
@@ -0,0 +1,246 @@
+//! A procedural macro to define a state runtime executed in the enclave.
+//!
+//! ```ignore
+//! #[anonify_runtime]
+//! pub mod erc20 {
+//!     use super::*;
+//!
+//!     #[memory]
+//!     pub enum Memory {
+//!         // Updates are notified to the registered users
+//!         #[mem(id = 0, notify)]
+//!         Balance(U64),
+//!         #[mem(id = 1)]
+//!         Approved(Approved),
+//!     }
+//!
+//!     impl Runtime {
+//!         #[command]
+//!         pub fn transfer(
+//!             self,
+//!             sender: AccountId,
+//!             recipient: AccountId,
+//!             amount: U64,
+//!         ) -> Result<ReturnState<StateType>> {
+//!             // Unknown memory names are compile errors and the value types are inferred.
+//!             let sender_balance = self.get_map(sender, "Balance")?;
+//!             ...
+//!         }
+//!
+//!         #[getter]
+//!         pub fn balance_of(self, caller: AccountId) -> Result<U64> {
+//!             self.get_map(caller, "Balance")
+//!         }
+//!     }
+//! }
+//! ```
+extern crate proc_macro;
+
+mod memory;
+mod runtime;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Error, Item, ItemMod, Result, Type};
+
+/// Derive `MemName`, `CallKind` and `Runtime` with their executor implementations
+/// from a module containing a `#[memory]` enum and `impl Runtime` blocks.
+///
+/// Functions in `impl Runtime` annotated with `#[command]` are state transitions
+/// and ones annotated with `#[getter]` return typed values.
+/// The other functions are kept as helpers.
+#[proc_macro_attribute]
+pub fn anonify_runtime(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(Span::call_site(), "`#[anonify_runtime]` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let module = parse_macro_input!(item as ItemMod);
+    expand(module)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand(mut module: ItemMod) -> Result<proc_macro2::TokenStream> {
+    let (_, items) = module.content.take().ok_or_else(|| {
+        Error::new(
+            module.span(),
+            "`#[anonify_runtime]` must be applied to an inline module",
+        )
+    })?;
+
+    let mut memory = None;
+    let mut impls = vec![];
+    let mut others = vec![];
+    for item in items {
+        match item {
+            Item::Enum(mut item_enum) if has_attr(&item_enum.attrs, "memory") => {
+                if memory.is_some() {
+                    return Err(Error::new(
+                        item_enum.span(),
+                        "only one `#[memory]` enum can be declared",
+                    ));
+                }
+                item_enum.attrs.retain(|attr| !attr.path.is_ident("memory"));
+                memory = Some(item_enum);
+            }
+            Item::Impl(item_impl) if is_runtime(&item_impl.self_ty) => impls.push(item_impl),
+            item => others.push(item),
+        }
+    }
+
+    let memory = memory.ok_or_else(|| Error::new(module.span(), "missing a `#[memory]` enum"))?;
+    let mems = memory::parse(&memory)?;
+
+    let mut fns = vec![];
+    for item_impl in &mut impls {
+        fns.extend(runtime::parse(item_impl, &mems)?);
+    }
+
+    let memory_items = memory::expand(&mems);
+    let runtime_items = runtime::expand(&fns, &mems);
+    let attrs = &module.attrs;
+    let vis = &module.vis;
+    let mod_token = &module.mod_token;
+    let ident = &module.ident;
+
+    Ok(quote! {
+        #( #attrs )*
+        #vis #mod_token #ident {
+            #( #others )*
+
+            #memory_items
+
+            #runtime_items
+
+            #( #impls )*
+        }
+    })
+}
+
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident(name))
+}
+
+fn is_runtime(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path.path.is_ident("Runtime"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand_err(module: ItemMod) -> String {
+        match expand(module) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_runtime() {
+        let module: ItemMod = parse_quote! {
+            mod runtime {
+                #[memory]
+                enum Memory {
+                    #[mem(id = 0, notify)]
+                    Balance(U64),
+                    #[mem(id = 1)]
+                    Approved(Approved),
+                }
+
+                impl Runtime {
+                    #[command]
+                    pub fn transfer(self, sender: AccountId, recipient: AccountId, amount: U64) -> Result<ReturnState<StateType>> {
+                        let balance = self.get_map(sender, "Balance")?;
+                        let update = self.update(sender, "Balance", balance - amount)?;
+                        return_update![update]
+                    }
+
+                    #[getter]
+                    pub fn balance_of(self, caller: AccountId) -> Result<U64> {
+                        self.get_map(caller, "Balance")
+                    }
+                }
+            }
+        };
+
+        let expanded = expand(module).unwrap().to_string();
+        assert!(expanded.contains("pub enum CallKind"));
+        assert!(expanded.contains("get_mem :: < mem :: Balance >"));
+        assert!(expanded.contains("update_mem :: < mem :: Balance >"));
+        assert!(expanded.contains("get_state :: < U64 >"));
+        assert!(expanded.contains("fn schema ()"));
+        assert!(expanded.contains("TypeSchema :: of :: < AccountId > (\"AccountId\")"));
+        assert!(!expanded.contains("# [command]"));
+    }
+
+    #[test]
+    fn test_unknown_memory_name() {
+        let module: ItemMod = parse_quote! {
+            mod runtime {
+                #[memory]
+                enum Memory {
+                    #[mem(id = 0)]
+                    Balance(U64),
+                }
+
+                impl Runtime {
+                    #[command]
+                    pub fn burn(self, sender: AccountId) -> Result<ReturnState<StateType>> {
+                        let balance = self.get_map(sender, "Balanse")?;
+                        return_update![]
+                    }
+                }
+            }
+        };
+
+        assert!(expand_err(module).contains("unknown memory name"));
+    }
+
+    #[test]
+    fn test_duplicate_memory_id() {
+        let module: ItemMod = parse_quote! {
+            mod runtime {
+                #[memory]
+                enum Memory {
+                    #[mem(id = 0)]
+                    Balance(U64),
+                    #[mem(id = 0)]
+                    TotalSupply(U64),
+                }
+            }
+        };
+
+        assert!(expand_err(module).contains("duplicate memory id"));
+    }
+
+    #[test]
+    fn test_getter_must_return_result() {
+        let module: ItemMod = parse_quote! {
+            mod runtime {
+                #[memory]
+                enum Memory {
+                    #[mem(id = 0)]
+                    Balance(U64),
+                }
+
+                impl Runtime {
+                    #[getter]
+                    pub fn balance_of(self, caller: AccountId) -> U64 {
+                        U64::zero()
+                    }
+                }
+            }
+        };
+
+        assert!(expand_err(module).contains("a getter must return"));
+    }
+}
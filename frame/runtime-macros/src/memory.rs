@@ -0,0 +1,149 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{
+    spanned::Spanned, Attribute, Error, Fields, Ident, ItemEnum, Lit, Meta, NestedMeta, Result,
+    Type,
+};
+
+/// A memory declared as a variant of the `#[memory]` enum, e.g.
+/// `#[mem(id = 0, notify)] Balance(U64)`.
+pub struct MemoryDef {
+    pub ident: Ident,
+    pub id: u32,
+    pub notify: bool,
+    pub ty: Type,
+}
+
+impl MemoryDef {
+    pub fn name(&self) -> String {
+        self.ident.to_string()
+    }
+}
+
+pub fn parse(item: &ItemEnum) -> Result<Vec<MemoryDef>> {
+    let mut defs = vec![];
+    let mut ids = HashSet::new();
+
+    for variant in &item.variants {
+        let ty = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => fields.unnamed[0].ty.clone(),
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "a memory must have exactly one value type, e.g. `Balance(U64)`",
+                ))
+            }
+        };
+
+        let (id, notify) = parse_mem_attr(&variant.attrs, variant.span())?;
+        if !ids.insert(id) {
+            return Err(Error::new(variant.span(), format!("duplicate memory id: {}", id)));
+        }
+
+        defs.push(MemoryDef {
+            ident: variant.ident.clone(),
+            id,
+            notify,
+            ty,
+        });
+    }
+
+    if defs.is_empty() {
+        return Err(Error::new(item.span(), "at least one memory must be declared"));
+    }
+
+    Ok(defs)
+}
+
+fn parse_mem_attr(attrs: &[Attribute], span: proc_macro2::Span) -> Result<(u32, bool)> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("mem"))
+        .ok_or_else(|| Error::new(span, "missing `#[mem(id = ..)]` attribute"))?;
+
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        meta => return Err(Error::new(meta.span(), "expected `#[mem(id = .., notify)]`")),
+    };
+
+    let mut id = None;
+    let mut notify = false;
+    for nested in list.nested {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("id") => match nv.lit {
+                Lit::Int(lit) => id = Some(lit.base10_parse::<u32>()?),
+                lit => return Err(Error::new(lit.span(), "memory id must be an integer")),
+            },
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("notify") => notify = true,
+            nested => {
+                return Err(Error::new(
+                    nested.span(),
+                    "unknown argument, expected `id = ..` or `notify`",
+                ))
+            }
+        }
+    }
+
+    let id = id.ok_or_else(|| Error::new(attr.span(), "missing memory id"))?;
+    Ok((id, notify))
+}
+
+/// Generate `MemName`, a marker type for each memory in the `mem` module and their `Memory` implementations.
+pub fn expand(defs: &[MemoryDef]) -> TokenStream {
+    let names: Vec<String> = defs.iter().map(MemoryDef::name).collect();
+    let idents: Vec<&Ident> = defs.iter().map(|d| &d.ident).collect();
+    let ids: Vec<u32> = defs.iter().map(|d| d.id).collect();
+    let notified_ids: Vec<u32> = defs
+        .iter()
+        .filter(|d| d.notify)
+        .map(|d| d.id)
+        .collect();
+    let notifies: Vec<bool> = defs.iter().map(|d| d.notify).collect();
+    let tys: Vec<&Type> = defs.iter().map(|d| &d.ty).collect();
+    let docs: Vec<String> = defs
+        .iter()
+        .map(|d| format!("Memory `{}` (id: {})", d.name(), d.id))
+        .collect();
+    let mem_mod = format_ident!("mem");
+
+    quote! {
+        #[derive(Debug, Clone)]
+        pub struct MemName;
+
+        impl ::frame_runtime::prelude::MemNameConverter for MemName {
+            fn as_id(name: &str) -> ::core::option::Option<::frame_runtime::prelude::MemId> {
+                match name {
+                    #( #names => ::core::option::Option::Some(::frame_runtime::prelude::MemId::from_raw(#ids)), )*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            fn is_notified(mem_id: ::frame_runtime::prelude::MemId) -> bool {
+                [#( #notified_ids ),*].contains(&mem_id.as_raw())
+            }
+        }
+
+        /// Marker types of the memories to access them with the value types checked at compile time.
+        pub mod #mem_mod {
+            #(
+                #[doc = #docs]
+                #[derive(Debug, Clone, Copy)]
+                pub struct #idents;
+            )*
+        }
+
+        #(
+            impl ::frame_runtime::traits::Memory for #mem_mod::#idents {
+                type Value = #tys;
+
+                const NAME: &'static str = #names;
+                const NOTIFY: bool = #notifies;
+
+                fn mem_id() -> ::frame_runtime::prelude::MemId {
+                    ::frame_runtime::prelude::MemId::from_raw(#ids)
+                }
+            }
+        )*
+    }
+}
@@ -0,0 +1,450 @@
+use crate::memory::MemoryDef;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_quote, spanned::Spanned, visit_mut::VisitMut, Error, Expr, ExprMethodCall, FnArg,
+    GenericArgument, Ident, ImplItem, ImplItemMethod, ItemImpl, Lit, Pat, PathArguments, Result,
+    ReturnType, Type,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FnKind {
+    /// A state transition function which returns updated states
+    Command,
+    /// A state getting function which returns a typed value
+    Getter,
+}
+
+/// A function annotated with `#[command]` or `#[getter]`.
+pub struct RuntimeFn {
+    pub kind: FnKind,
+    pub ident: Ident,
+    pub params: Vec<(Ident, Type)>,
+    /// The `T` of `Result<T>` returned by a getter
+    pub ret: Option<Type>,
+}
+
+/// Parse the functions in an `impl Runtime` block,
+/// and rewrite the impl block so that it is generic over the context.
+pub fn parse(item: &mut ItemImpl, mems: &[MemoryDef]) -> Result<Vec<RuntimeFn>> {
+    if !item.generics.params.is_empty() || item.trait_.is_some() {
+        return Err(Error::new(
+            item.span(),
+            "expected an inherent `impl Runtime` block without generics",
+        ));
+    }
+
+    let mut fns = vec![];
+    let mut checker = MemNameChecker {
+        mems,
+        errors: vec![],
+    };
+
+    for impl_item in &mut item.items {
+        if let ImplItem::Method(method) = impl_item {
+            checker.visit_impl_item_method_mut(method);
+            if let Some(kind) = take_kind_attr(method)? {
+                fns.push(parse_fn(kind, method)?);
+            }
+        }
+    }
+
+    let mut errors = checker.errors.into_iter();
+    if let Some(mut err) = errors.next() {
+        for e in errors {
+            err.combine(e);
+        }
+        return Err(err);
+    }
+
+    item.generics = parse_quote! {
+        <G: ::frame_runtime::traits::ContextOps<S = ::frame_runtime::prelude::StateType>>
+    };
+    item.self_ty = parse_quote!(Runtime<G>);
+
+    Ok(fns)
+}
+
+fn take_kind_attr(method: &mut ImplItemMethod) -> Result<Option<FnKind>> {
+    let mut kind = None;
+    let mut res = Ok(());
+    method.attrs.retain(|attr| {
+        let k = if attr.path.is_ident("command") {
+            FnKind::Command
+        } else if attr.path.is_ident("getter") {
+            FnKind::Getter
+        } else {
+            return true;
+        };
+        if kind.replace(k).is_some() {
+            res = Err(Error::new(
+                attr.span(),
+                "a function can be either `#[command]` or `#[getter]`",
+            ));
+        }
+        false
+    });
+
+    res.map(|_| kind)
+}
+
+fn parse_fn(kind: FnKind, method: &ImplItemMethod) -> Result<RuntimeFn> {
+    let sig = &method.sig;
+    let mut inputs = sig.inputs.iter();
+
+    match inputs.next() {
+        Some(FnArg::Receiver(_)) => {}
+        _ => return Err(Error::new(sig.span(), "the first parameter must be `self`")),
+    }
+    // The second parameter is the account id of the sender, which is given by the enclave.
+    match inputs.next() {
+        Some(FnArg::Typed(_)) => {}
+        _ => {
+            return Err(Error::new(
+                sig.span(),
+                "the second parameter must be the sender's `AccountId`",
+            ))
+        }
+    }
+
+    let mut params = vec![];
+    for input in inputs {
+        match input {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => {
+                    params.push((pat_ident.ident.clone(), (*pat_type.ty).clone()))
+                }
+                pat => return Err(Error::new(pat.span(), "parameters must be identifiers")),
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new(receiver.span(), "unexpected `self`"))
+            }
+        }
+    }
+
+    let ret = match kind {
+        FnKind::Command => None,
+        FnKind::Getter => Some(result_inner_type(&sig.output).ok_or_else(|| {
+            Error::new(sig.output.span(), "a getter must return `Result<T>`")
+        })?),
+    };
+
+    Ok(RuntimeFn {
+        kind,
+        ident: sig.ident.clone(),
+        params,
+        ret,
+    })
+}
+
+fn result_inner_type(output: &ReturnType) -> Option<Type> {
+    let ty = match output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => return None,
+    };
+    let segment = match &**ty {
+        Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Check the memory names given as string literals at compile time,
+/// and rewrite `self.get_map(key, "Name")` and `self.update(key, "Name", value)`
+/// into the accessors typed by the declared memories.
+struct MemNameChecker<'a> {
+    mems: &'a [MemoryDef],
+    errors: Vec<Error>,
+}
+
+impl<'a> VisitMut for MemNameChecker<'a> {
+    fn visit_expr_method_call_mut(&mut self, call: &mut ExprMethodCall) {
+        syn::visit_mut::visit_expr_method_call_mut(self, call);
+
+        let typed = match call.method.to_string().as_str() {
+            "get_map" => "get_mem",
+            "update" => "update_mem",
+            _ => return,
+        };
+        if !is_self(&call.receiver) {
+            return;
+        }
+        let lit = match call.args.iter().nth(1) {
+            Some(Expr::Lit(expr_lit)) => match &expr_lit.lit {
+                Lit::Str(lit) => lit.clone(),
+                _ => return,
+            },
+            // Names given dynamically are checked at runtime.
+            _ => return,
+        };
+
+        let mem = match self.mems.iter().find(|m| m.name() == lit.value()) {
+            Some(mem) => &mem.ident,
+            None => {
+                self.errors.push(Error::new(
+                    lit.span(),
+                    format!("unknown memory name: {:?}", lit.value()),
+                ));
+                return;
+            }
+        };
+        if call.turbofish.is_some() {
+            self.errors.push(Error::new(
+                call.turbofish.span(),
+                "the value type is given by the memory declaration",
+            ));
+            return;
+        }
+
+        let args: Vec<Expr> = call
+            .args
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, arg)| arg.clone())
+            .collect();
+        let method = Ident::new(typed, call.method.span());
+        *call = parse_quote!(self.#method::<mem::#mem>(#(#args),*));
+    }
+}
+
+fn is_self(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(expr_path) => expr_path.path.is_ident("self"),
+        _ => false,
+    }
+}
+
+/// Generate the command structs, `CallKind` and `Runtime` with their executor implementations.
+pub fn expand(fns: &[RuntimeFn], mems: &[MemoryDef]) -> TokenStream {
+    let idents: Vec<&Ident> = fns.iter().map(|f| &f.ident).collect();
+    let names: Vec<String> = idents.iter().map(|i| i.to_string()).collect();
+    let param_names: Vec<Vec<&Ident>> = fns
+        .iter()
+        .map(|f| f.params.iter().map(|(name, _)| name).collect())
+        .collect();
+    let param_tys: Vec<Vec<&Type>> = fns
+        .iter()
+        .map(|f| f.params.iter().map(|(_, ty)| ty).collect())
+        .collect();
+    let executes: Vec<TokenStream> = fns
+        .iter()
+        .map(|f| {
+            let ident = &f.ident;
+            let params = f.params.iter().map(|(name, _)| name);
+            let call = quote!(runtime.#ident(my_account_id, #( cmd.#params ),*));
+            match (f.kind, &f.ret) {
+                (FnKind::Getter, Some(ret)) => {
+                    quote!(::frame_runtime::impls::get_state::<#ret>(&#call?))
+                }
+                _ => call,
+            }
+        })
+        .collect();
+
+    let schema = expand_schema(fns, mems);
+
+    quote! {
+        #(
+            #[derive(::frame_runtime::serde::Serialize, ::frame_runtime::serde::Deserialize, Debug, Clone, Default)]
+            #[serde(crate = "frame_runtime::serde")]
+            #[allow(non_camel_case_types)]
+            pub struct #idents {
+                #( pub #param_names: #param_tys, )*
+            }
+        )*
+
+        #[derive(::frame_runtime::serde::Serialize, ::frame_runtime::serde::Deserialize, Debug, Clone)]
+        #[serde(crate = "frame_runtime::serde")]
+        #[allow(non_camel_case_types)]
+        pub enum CallKind {
+            #( #idents(#idents), )*
+        }
+
+        impl<G> ::frame_runtime::traits::CallKindExecutor<G> for CallKind
+        where
+            G: ::frame_runtime::traits::ContextOps<S = ::frame_runtime::prelude::StateType>,
+        {
+            type R = Runtime<G>;
+            type S = ::frame_runtime::prelude::StateType;
+
+            fn new(
+                cmd_name: &str,
+                cmd: ::frame_runtime::prelude::serde_json::Value,
+            ) -> ::frame_runtime::prelude::Result<Self> {
+                match cmd_name {
+                    #( #names => {
+                        if cmd.is_null() {
+                            Ok(CallKind::#idents(#idents::default()))
+                        } else {
+                            Ok(CallKind::#idents(::frame_runtime::prelude::serde_json::from_value(cmd)?))
+                        }
+                    }, )*
+                    _ => Err(::frame_runtime::prelude::anyhow!("Invalid command name: {}", cmd_name)),
+                }
+            }
+
+            fn execute(
+                self,
+                runtime: Self::R,
+                my_account_id: ::frame_runtime::prelude::AccountId,
+            ) -> ::frame_runtime::prelude::Result<::frame_runtime::prelude::ReturnState<Self::S>> {
+                match self {
+                    #( CallKind::#idents(cmd) => #executes, )*
+                }
+            }
+        }
+
+        pub struct Runtime<G: ::frame_runtime::traits::ContextOps<S = ::frame_runtime::prelude::StateType>> {
+            db: G,
+        }
+
+        impl<G> ::frame_runtime::traits::RuntimeExecutor<G> for Runtime<G>
+        where
+            G: ::frame_runtime::traits::ContextOps<S = ::frame_runtime::prelude::StateType>,
+        {
+            type C = CallKind;
+            type S = ::frame_runtime::prelude::StateType;
+
+            fn new(db: G) -> Self {
+                Runtime { db }
+            }
+
+            fn execute(
+                self,
+                kind: Self::C,
+                my_account_id: ::frame_runtime::prelude::AccountId,
+            ) -> ::frame_runtime::prelude::Result<::frame_runtime::prelude::ReturnState<Self::S>> {
+                ::frame_runtime::traits::CallKindExecutor::execute(kind, self, my_account_id)
+            }
+
+            fn schema() -> ::frame_runtime::prelude::schema::RuntimeSchema {
+                #schema
+            }
+        }
+
+        impl<G> Runtime<G>
+        where
+            G: ::frame_runtime::traits::ContextOps<S = ::frame_runtime::prelude::StateType>,
+        {
+            /// Get the state by the memory name, which is checked at compile time if it is a literal.
+            pub fn get_map<S: ::frame_runtime::prelude::State>(
+                &self,
+                key: ::frame_runtime::prelude::AccountId,
+                name: &str,
+            ) -> ::frame_runtime::prelude::Result<S> {
+                ::frame_runtime::impls::get_map::<MemName, _, _>(&self.db, key, name)
+            }
+
+            pub fn get_mem<M: ::frame_runtime::traits::Memory>(
+                &self,
+                key: ::frame_runtime::prelude::AccountId,
+            ) -> ::frame_runtime::prelude::Result<M::Value> {
+                ::frame_runtime::impls::get_mem::<M, _>(&self.db, key)
+            }
+
+            /// Update the state by the memory name, which is checked at compile time if it is a literal.
+            pub fn update<S>(
+                &self,
+                key: ::frame_runtime::prelude::AccountId,
+                name: &str,
+                value: S,
+            ) -> ::frame_runtime::prelude::Result<::frame_runtime::impls::Update>
+            where
+                S: ::frame_runtime::prelude::State + Into<::frame_runtime::prelude::StateType>,
+            {
+                ::frame_runtime::impls::update_map::<MemName, _>(key, name, value)
+            }
+
+            pub fn update_mem<M: ::frame_runtime::traits::Memory>(
+                &self,
+                key: ::frame_runtime::prelude::AccountId,
+                value: M::Value,
+            ) -> ::frame_runtime::prelude::Result<::frame_runtime::impls::Update> {
+                ::frame_runtime::impls::update_mem::<M>(key, value)
+            }
+
+            pub fn values<S: ::frame_runtime::prelude::State>(
+                self,
+            ) -> ::frame_runtime::prelude::Result<::frame_runtime::prelude::Vec<S>> {
+                ::frame_runtime::traits::StateOps::values(self.db)
+                    .into_iter()
+                    .map(|e| S::decode_s(e.as_bytes()))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn expand_schema(fns: &[RuntimeFn], mems: &[MemoryDef]) -> TokenStream {
+    let fn_schema = |f: &RuntimeFn| {
+        let name = f.ident.to_string();
+        let param_names = f.params.iter().map(|(name, _)| name.to_string());
+        let param_tys = f.params.iter().map(|(_, ty)| ty);
+        let param_ty_names = f.params.iter().map(|(_, ty)| type_name(ty));
+        let returns = match &f.ret {
+            Some(ret) => {
+                let ret_name = type_name(ret);
+                quote!(Some(::frame_runtime::prelude::schema::TypeSchema::of::<#ret>(#ret_name)))
+            }
+            None => quote!(None),
+        };
+
+        quote! {
+            ::frame_runtime::prelude::schema::FnSchema {
+                name: ::frame_runtime::prelude::String::from(#name),
+                params: vec![
+                    #( ::frame_runtime::prelude::schema::ParamSchema {
+                        name: ::frame_runtime::prelude::String::from(#param_names),
+                        ty: ::frame_runtime::prelude::schema::TypeSchema::of::<#param_tys>(#param_ty_names),
+                    }, )*
+                ],
+                returns: #returns,
+            }
+        }
+    };
+    let commands = fns
+        .iter()
+        .filter(|f| f.kind == FnKind::Command)
+        .map(fn_schema);
+    let getters = fns
+        .iter()
+        .filter(|f| f.kind == FnKind::Getter)
+        .map(fn_schema);
+    let memories = mems.iter().map(|m| {
+        let name = m.name();
+        let id = m.id;
+        let notify = m.notify;
+        let ty = &m.ty;
+        let ty_name = type_name(ty);
+        quote! {
+            ::frame_runtime::prelude::schema::MemorySchema {
+                name: ::frame_runtime::prelude::String::from(#name),
+                id: #id,
+                notify: #notify,
+                ty: ::frame_runtime::prelude::schema::TypeSchema::of::<#ty>(#ty_name),
+            }
+        }
+    });
+
+    quote! {
+        ::frame_runtime::prelude::schema::RuntimeSchema {
+            commands: vec![ #( #commands, )* ],
+            getters: vec![ #( #getters, )* ],
+            memories: vec![ #( #memories, )* ],
+        }
+    }
+}
+
+fn type_name(ty: &Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
@@ -1,201 +1,85 @@
-#[macro_export]
-macro_rules! impl_memory {
-    ( $( $t:tt )* ) => {
-        $crate::__impl_inner_memory!(@normalize $( $t )* );
-    };
+//! Helpers called from the runtime generated by `#[anonify_runtime]`.
+
+use crate::local_anyhow::{anyhow, Result};
+use crate::localstd::vec::Vec;
+use crate::serde::Serialize;
+use crate::serde_json;
+use crate::traits::{Memory, StateOps};
+use frame_common::{
+    crypto::AccountId,
+    state_types::{MemId, NotifyState, ReturnState, StateType, UpdatedState},
+    traits::{MemNameConverter, State},
+};
+
+/// An updated state and the notification of it if the memory is notified.
+pub type Update = (UpdatedState<StateType>, Option<NotifyState>);
+
+/// Get the state of the memory. The default value is returned if it has never been updated.
+pub fn get_mem<M, G>(db: &G, key: AccountId) -> Result<M::Value>
+where
+    M: Memory,
+    G: StateOps<S = StateType>,
+{
+    decode_state(db, key, M::mem_id())
 }
 
-#[macro_export]
-macro_rules! __impl_inner_memory {
-    (@normalize
-        $( ($id:expr, $name:expr, Address => $value:ty) ),*
-    ) => {
-        $crate::__impl_inner_memory!(@normalize $( ($id, $name, $value) ),* );
-    };
-
-    (@normalize
-        $( ($id:expr, $name:expr, $value:ty) ),*
-    ) => {
-        $crate::__impl_inner_memory!(@imp $( ($id, $name, $value) ),* );
-    };
-
-    (@imp
-        $( ($id:expr, $name:expr, $value:ty) ),*
-    ) => {
-        #[derive(Debug, Clone)]
-        pub struct MemName;
-
-        impl MemNameConverter for MemName {
-            fn as_id(name: &str) -> MemId {
-                match name {
-                    $( $name => MemId::from_raw($id), )*
-                    _ => panic!("invalid mem name"),
-                }
-            }
-        }
-
-        /// Return maximum size of mem values
-        fn max_size() -> usize {
-            *[ $( <$value>::default().size(), )* ]
-                .iter()
-                .max()
-                .expect("Iterator should not be empty.")
-        }
-    };
+/// Get the state by the memory name.
+pub fn get_map<N, G, S>(db: &G, key: AccountId, name: &str) -> Result<S>
+where
+    N: MemNameConverter,
+    G: StateOps<S = StateType>,
+    S: State,
+{
+    decode_state(db, key, mem_id::<N>(name)?)
 }
 
-#[macro_export]
-macro_rules! impl_runtime {
-    (
-        $( $t:tt )*
-    ) => {
-        $crate::__impl_inner_runtime!(@imp
-            $($t)*
-        );
-    };
+pub fn update_mem<M: Memory>(key: AccountId, value: M::Value) -> Result<Update> {
+    update_state(key, M::mem_id(), M::NOTIFY, value)
 }
 
-#[macro_export]
-macro_rules! __impl_inner_runtime {
-    (@imp
-        $(
-            pub fn $cmd_name:ident(
-                $runtime:ident,
-                $sender:ident : $account_id:ty
-                $(, $param_name:ident : $param:ty )*
-            ) {
-                $( $impl:tt )*
-            }
-        )*
-    ) => {
-        $(
-            #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-            #[serde(crate = "frame_runtime::serde")]
-            #[allow(non_camel_case_types)]
-            pub struct $cmd_name {
-                $( pub $param_name: $param, )*
-            }
-
-        )*
-
-        #[derive(Debug, Clone, Serialize, Deserialize)]
-        #[serde(crate = "frame_runtime::serde")]
-        pub enum CallKind {
-            $(
-                #[allow(non_camel_case_types)]
-                $cmd_name($cmd_name),
-            )*
-        }
-
-        impl<G> CallKindExecutor<G> for CallKind
-        where
-            G: ContextOps<S=StateType>,
-        {
-            type R = Runtime<G>;
-            type S = StateType;
-
-            fn new(cmd_name: &str, cmd: serde_json::Value) -> Result<Self> {
-                match cmd_name {
-                    $( stringify!($cmd_name) => {
-                        if cmd.is_null() {
-                            Ok(CallKind::$cmd_name($cmd_name::default()))
-                        } else {
-                            Ok(CallKind::$cmd_name(serde_json::from_value(cmd)?))
-                        }
-                    },)*
-                    _ => return Err(anyhow!("Invalid Command Name")),
-                }
-            }
-
-            fn execute(self, runtime: Self::R, my_account_id: AccountId) -> Result<ReturnState<Self::S>> {
-                match self {
-                    $( CallKind::$cmd_name($cmd_name) => {
-                        runtime.$cmd_name(
-                            my_account_id,
-                            $( $cmd_name.$param_name, )*
-                        )
-                    }, )*
-                    _ => unimplemented!()
-                }
-            }
-        }
-
-        pub struct Runtime<G: ContextOps<S=StateType>> {
-            db: G,
-        }
-
-        impl<G> RuntimeExecutor<G> for Runtime<G>
-        where
-            G: ContextOps<S=StateType>,
-        {
-            type C = CallKind;
-            type S = StateType;
-
-            fn new(db: G) -> Self {
-                Runtime {
-                    db,
-                }
-            }
-
-            fn execute(self, kind: Self::C, my_account_id: AccountId) -> Result<ReturnState<Self::S>> {
-                kind.execute(self, my_account_id)
-            }
-        }
+/// Update the state by the memory name.
+pub fn update_map<N, S>(key: AccountId, name: &str, value: S) -> Result<Update>
+where
+    N: MemNameConverter,
+    S: State + Into<StateType>,
+{
+    let mem_id = mem_id::<N>(name)?;
+    update_state(key, mem_id, N::is_notified(mem_id), value)
+}
 
-        impl<G> Runtime<G>
-        where
-            G: ContextOps<S=StateType>,
-        {
-            pub fn get_map<S: State>(
-                &self,
-                key: AccountId,
-                name: &str
-            ) -> Result<S> {
-                let mem_id = MemName::as_id(name);
-                let mut tmp = self.db.get_state_by_mem_id(key, mem_id).into_vec();
-                if tmp.is_empty() {
-                    Ok(S::default())
-                } else {
-                    S::decode_s(&mut tmp)
-                }
-            }
+/// Encode a value returned from a getter as JSON, which is described by the getter's schema.
+pub fn get_state<T: Serialize>(value: &T) -> Result<ReturnState<StateType>> {
+    Ok(ReturnState::Get(StateType::new(serde_json::to_vec(value)?)))
+}
 
-            pub fn values<S: State>(mut self) -> Result<Vec<S>> {
-                self.db.values().into_iter().map(|e| S::decode_s(&mut e.into_vec())).collect()
-            }
+fn mem_id<N: MemNameConverter>(name: &str) -> Result<MemId> {
+    N::as_id(name).ok_or_else(|| anyhow!("Invalid memory name: {}", name))
+}
 
-            $(
-                pub fn $cmd_name (
-                    $runtime,
-                    $sender: $account_id
-                    $(, $param_name : $param )*
-                ) -> Result<ReturnState<StateType>> {
-                    $( $impl )*
-                }
-            )*
-        }
-    };
+fn decode_state<G, S>(db: &G, key: AccountId, mem_id: MemId) -> Result<S>
+where
+    G: StateOps<S = StateType>,
+    S: State,
+{
+    let tmp: Vec<u8> = db.get_state_by_mem_id(key, mem_id).into_vec();
+    if tmp.is_empty() {
+        Ok(S::default())
+    } else {
+        S::decode_s(&tmp)
+    }
 }
 
-#[macro_export]
-macro_rules! update {
-    ($account_id:expr, $mem_name:expr, $value:expr, $state_type:ty) => {
-        if stringify!($state_type) == "Approved" {
-            (
-                UpdatedState::new($account_id, MemName::as_id($mem_name), $value.clone())?,
-                None,
-            )
-        } else {
-            (
-                UpdatedState::new($account_id, MemName::as_id($mem_name), $value.clone())?,
-                Some(NotifyState::new(
-                    $account_id,
-                    MemName::as_id($mem_name),
-                    serde_json::to_value::<$state_type>($value)?,
-                )),
-            )
-        }
+fn update_state<S>(key: AccountId, mem_id: MemId, notify: bool, value: S) -> Result<Update>
+where
+    S: State + Into<StateType>,
+{
+    let notify_state = if notify {
+        Some(NotifyState::new(key, mem_id, serde_json::to_value(&value)?))
+    } else {
+        None
     };
+
+    Ok((UpdatedState::new(key, mem_id, value)?, notify_state))
 }
 
 #[macro_export]
@@ -206,12 +90,3 @@ macro_rules! return_update {
         )
     };
 }
-
-#[macro_export]
-macro_rules! get_state {
-    ( $state:expr ) => {
-        Ok(ReturnState::Get(StateType::new(bincode::serialize(
-            &serde_json::to_vec(&$state)?,
-        )?)))
-    };
-}
@@ -20,16 +20,20 @@ use serde_bytes_sgx as serde_bytes;
 #[cfg(feature = "std")]
 use serde_bytes_std as serde_bytes;
 #[cfg(all(feature = "sgx", not(feature = "std")))]
+pub use serde_json_sgx as serde_json;
+#[cfg(feature = "software-enclave")]
+pub use serde_json_std as serde_json;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
 pub use serde_sgx as serde;
 #[cfg(feature = "std")]
 pub use serde_std as serde;
 
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub mod impls;
 pub mod prelude;
 pub mod primitives;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub mod traits;
 
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::traits::*;
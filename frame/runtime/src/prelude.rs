@@ -3,18 +3,17 @@ pub use crate::local_anyhow::{anyhow, ensure, Result};
 pub use crate::localstd::marker::PhantomData;
 pub use crate::localstd::prelude::v1::*;
 pub use crate::primitives::*;
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
+pub use crate::return_update;
 pub use crate::serde::{self, de::DeserializeOwned, Deserialize, Serialize};
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
+pub use crate::serde_json;
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::traits::*;
-#[cfg(feature = "sgx")]
-pub use crate::{
-    __impl_inner_memory, __impl_inner_runtime, get_state, impl_memory, impl_runtime, return_update,
-    update,
-};
 pub use frame_common::{
     crypto::{AccountId, OWNER_ACCOUNT_ID},
+    schema::{self, JsonSchema},
     state_types::*,
     traits::*,
 };
-#[cfg(feature = "sgx")]
-pub use serde_json;
+pub use frame_runtime_macros::anonify_runtime;
@@ -11,6 +11,7 @@ use crate::serde::{Deserialize, Serialize};
 use crate::serde_bytes;
 use frame_common::{
     crypto::AccountId,
+    schema::{map_schema, JsonSchema, Value},
     state_types::StateType,
     traits::{State, StateDecoder},
 };
@@ -104,6 +105,12 @@ macro_rules! impl_uint {
             }
         }
 
+        impl JsonSchema for $name {
+            fn json_schema() -> Value {
+                <$raw>::json_schema()
+            }
+        }
+
         impl $name {
             pub fn as_raw(&self) -> $raw {
                 self.0
@@ -165,6 +172,12 @@ impl StateDecoder for Bytes {
     }
 }
 
+impl JsonSchema for Bytes {
+    fn json_schema() -> Value {
+        Vec::<u8>::json_schema()
+    }
+}
+
 impl From<Bytes> for StateType {
     fn from(bs: Bytes) -> Self {
         StateType::new(bs.0.encode_s())
@@ -225,6 +238,12 @@ impl Approved {
     }
 }
 
+impl JsonSchema for Approved {
+    fn json_schema() -> Value {
+        map_schema(U64::json_schema())
+    }
+}
+
 impl From<Approved> for StateType {
     fn from(a: Approved) -> Self {
         StateType::new(a.0.encode_s())
@@ -1,17 +1,27 @@
 use crate::local_anyhow::Result;
-use crate::localstd::{
-    fmt::Debug,
-    sync::{SgxRwLockReadGuard, SgxRwLockWriteGuard},
-    vec::Vec,
+#[cfg(feature = "std")]
+use crate::localstd::sync::{RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "sgx")]
+use crate::localstd::sync::{
+    SgxRwLockReadGuard as RwLockReadGuard, SgxRwLockWriteGuard as RwLockWriteGuard,
 };
+use crate::localstd::{fmt::Debug, vec::Vec};
 use crate::serde::{de::DeserializeOwned, Serialize};
+use crate::serde_json;
 use frame_common::{
     crypto::{AccountId, BackupPathSecret, Ciphertext, RecoverAllRequest, RecoveredPathSecret},
-    state_types::{MemId, NotifyState, ReturnState, StateCounter, UpdatedState, UserCounter},
+    schema::RuntimeSchema,
+    state_types::{
+        MemId, NotifyState, PayloadsHash, ReturnState, StateCounter, StateRoot, StateType,
+        UpdatedState, UserCounter,
+    },
     traits::*,
 };
+use frame_kvs::{Snapshot, Transaction};
 use frame_sodium::{SodiumCiphertext, SodiumPubKey};
 use frame_treekem::{handshake::HandshakeParams, PathSecret, StorePathSecrets};
+use remote_attestation::AttestationEvidence;
+#[cfg(feature = "sgx")]
 use remote_attestation::EncodedQuote;
 
 /// Execute state transition functions from runtime
@@ -21,6 +31,9 @@ pub trait RuntimeExecutor<G: ContextOps>: Sized {
 
     fn new(db: G) -> Self;
     fn execute(self, kind: Self::C, my_account_id: AccountId) -> Result<ReturnState<Self::S>>;
+
+    /// The schema of all commands, getters and memories of the runtime
+    fn schema() -> RuntimeSchema;
 }
 
 /// Execute state transition functions from call kind
@@ -34,6 +47,17 @@ pub trait CallKindExecutor<G: ContextOps>:
     fn execute(self, runtime: Self::R, my_account_id: AccountId) -> Result<ReturnState<Self::S>>;
 }
 
+/// A memory declared in the runtime, generated by `#[anonify_runtime]`
+pub trait Memory {
+    type Value: State + Into<StateType>;
+
+    const NAME: &'static str;
+    /// Whether updates of the memory are notified to the registered users.
+    const NOTIFY: bool;
+
+    fn mem_id() -> MemId;
+}
+
 /// A trait for all context operations
 pub trait ContextOps:
     StateOps
@@ -94,30 +118,87 @@ pub trait StateOps {
         R: RuntimeExecutor<CTX, S = Self::S>,
         CTX: ContextOps<S = Self::S>;
 
-    /// Returns a updated state of registered account_id in notification.
+    /// Returns all updated states of registered account_ids in notification.
+    /// Fails if no state transition is open to stage the updated states.
     fn update_state(
         &self,
         updated_state_iter: impl Iterator<Item = UpdatedState<Self::S>>,
         notify_state_iter: impl Iterator<Item = Option<NotifyState>>,
-    ) -> Option<NotifyState>;
+    ) -> Result<Vec<NotifyState>>;
+
+    /// Open a state transition. It must be held from verifying the state counter
+    /// until committing the states, so that snapshots never observe a half-applied transition.
+    /// The writes staged by it are discarded if it is dropped without being committed.
+    fn lock_state_transition(&self) -> Transaction<'_>;
 
-    fn verify_state_counter_increment(&self, received_state_counter: StateCounter) -> Result<()>;
+    /// Verify that the state counter of the event is the next one, and chain its payload
+    /// into the payloads hash. Both are persisted by committing the transition.
+    fn verify_state_counter_increment(
+        &self,
+        received_state_counter: StateCounter,
+        payload: &[u8],
+    ) -> Result<()>;
 
     fn verify_user_counter_increment(&self, user: AccountId, received: UserCounter) -> Result<()>;
+
+    /// Persist all writes staged by the transition as the one identified by `state_counter`,
+    /// which was emitted at `block_num`.
+    fn commit_state(
+        &self,
+        transition: Transaction<'_>,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> Result<()>;
+
+    /// Returns the latest committed state counter, the block number it was emitted at
+    /// and the hash chain over the payloads consumed until it.
+    fn committed_state_counter(&self) -> (StateCounter, u64, PayloadsHash);
+
+    /// Export all user states and counters at the latest committed state counter
+    /// with the state root over the user states.
+    fn export_snapshot(&self) -> Result<(Snapshot, StateRoot)>;
+
+    /// Replace all user states and counters with the snapshot and persist it.
+    /// A snapshot older than the latest committed state counter is rejected.
+    fn import_snapshot(&self, snapshot: Snapshot) -> Result<()>;
+
+    /// The root of the Merkle tree over the current user states.
+    fn state_root(&self) -> StateRoot;
 }
 
 pub trait GroupKeyGetter {
     type GK: GroupKeyOps;
 
-    fn read_group_key(&self) -> SgxRwLockReadGuard<Self::GK>;
+    fn read_group_key(&self) -> RwLockReadGuard<Self::GK>;
 
-    fn write_group_key(&self) -> SgxRwLockWriteGuard<Self::GK>;
+    fn write_group_key(&self) -> RwLockWriteGuard<Self::GK>;
+
+    /// Stage the group key into the open state transition, so that it is sealed with the states
+    /// and the enclave can rejoin the group after restarting.
+    fn stage_group_key(&self, group_key: &Self::GK) -> Result<()>;
+
+    /// Seal the generation of the sender keychain apart from the states,
+    /// since it is ratcheted by sending messages outside of state transitions.
+    fn seal_sender_generation(&self, group_key: &Self::GK) -> Result<()>;
 }
 
 pub trait NotificationOps {
-    fn set_notification(&self, account_id: AccountId) -> bool;
+    /// Register the account to be notified until `expires_at` in Unix time seconds.
+    /// Returns false if it has already been registered.
+    fn set_notification(
+        &self,
+        account_id: AccountId,
+        notification_key: SodiumPubKey,
+        expires_at: u64,
+    ) -> Result<bool>;
+
+    /// Returns false if the account has not been registered.
+    fn unset_notification(&self, account_id: &AccountId) -> Result<bool>;
 
     fn is_notified(&self, account_id: &AccountId) -> bool;
+
+    /// Encrypt the notified state to the notification key registered by the owner of the state.
+    fn encrypt_notification(&self, notify_state: &NotifyState) -> Result<SodiumCiphertext>;
 }
 
 pub trait EnclaveKeyOps {
@@ -154,13 +235,23 @@ pub trait GroupKeyOps: Sized {
     fn sync_ratchet(&mut self, roster_idx: usize, msg_gen: u32) -> Result<()>;
 
     fn my_roster_idx(&self) -> u32;
+
+    /// The epoch of the group key which messages are currently received in.
+    fn epoch(&self) -> u32;
+
+    /// Derive a secret shared by all group members in the current epoch for the given label.
+    fn exporter_secret(&self, label: &[u8]) -> Result<Vec<u8>>;
 }
 
 pub trait QuoteGetter: Sized {
     /// Generate Base64-encoded QUOTE data structure.
     /// QUOTE will be sent to Attestation Service to verify SGX's status.
     /// For more information: https://api.trustedservices.intel.com/documents/sgx-attestation-api-spec.pdf
+    #[cfg(feature = "sgx")]
     fn quote(&self) -> Result<EncodedQuote>;
+
+    /// Attest the enclave with the type configured by `ATTESTATION_TYPE`, EPID or DCAP.
+    fn attestation_evidence(&self) -> Result<AttestationEvidence>;
 }
 
 pub trait KeyVaultOps {
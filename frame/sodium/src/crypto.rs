@@ -306,7 +306,6 @@ pub struct SodiumCiphertext {
 impl frame_common::EcallInput for SodiumCiphertext {}
 
 impl SodiumCiphertext {
-    #[cfg(feature = "std")]
     pub fn encrypt<T>(
         csprng: &mut T,
         others_pub_key: &SodiumPubKey,
@@ -331,7 +330,6 @@ impl SodiumCiphertext {
         })
     }
 
-    #[cfg(any(all(feature = "std", test), feature = "sgx"))]
     pub fn decrypt(self, my_priv_key: &SodiumPrivateKey) -> Result<Vec<u8>> {
         let cbox = CryptoBox::new(&self.ephemeral_public_key.0, &my_priv_key.0);
         let plaintext = cbox
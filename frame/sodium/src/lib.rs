@@ -36,7 +36,7 @@ use std_rand_core as rand_core;
 use std_xsalsa20poly1305 as xsalsa20poly1305;
 
 mod crypto;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub mod rng;
 
 pub use crypto::{SodiumCiphertext, SodiumPrivateKey, SodiumPubKey, SODIUM_PUBLIC_KEY_SIZE};
@@ -1,9 +1,15 @@
-#![cfg(feature = "sgx")]
+#![cfg(any(feature = "sgx", feature = "software-enclave"))]
 use crate::localstd::{io, mem};
 use crate::rand_core::{self, CryptoRng, RngCore};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+#[cfg(feature = "sgx")]
 use sgx_trts::trts::rsgx_read_rand;
+#[cfg(feature = "sgx")]
 use sgx_types::*;
 
+/// A random number generator of the enclave.
+/// In the software enclave mode, the random numbers are taken from the operating system.
 pub struct SgxRng;
 
 impl SgxRng {
@@ -43,17 +49,19 @@ fn next_u64(fill_buf: &mut dyn FnMut(&mut [u8])) -> u64 {
     unsafe { mem::transmute::<[u8; 8], u64>(buf) }
 }
 
-fn getrandom(buf: &mut [u8]) -> SgxError {
-    rsgx_read_rand(buf)
-}
-
 fn getrandom_fill_bytes(v: &mut [u8]) {
-    getrandom(v).expect("unexpected getrandom error");
+    try_getrandom_fill_bytes(v).expect("unexpected getrandom error");
 }
 
+#[cfg(feature = "sgx")]
 fn try_getrandom_fill_bytes(v: &mut [u8]) -> Result<(), rand_core::Error> {
-    match getrandom(v) {
+    match rsgx_read_rand(v) {
         Ok(_) => Ok(()),
         Err(ret) => Err(rand_core::Error::new(ret.from_key())),
     }
 }
+
+#[cfg(feature = "std")]
+fn try_getrandom_fill_bytes(v: &mut [u8]) -> Result<(), rand_core::Error> {
+    OsRng.try_fill_bytes(v)
+}
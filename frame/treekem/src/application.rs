@@ -13,14 +13,17 @@ use crate::local_ring::aead::{
 };
 use crate::localstd::{convert::TryFrom, prelude::v1::*};
 use crate::ratchet_tree::RatchetTreeNode;
-use crate::serde::Serialize;
+use crate::serde::{Deserialize, Serialize};
 use frame_common::crypto::Ciphertext;
 use tracing::warn;
 
 /// Application Keychain manages each member's `AppMemberSecret' and generation.
-#[derive(Debug, Clone, Default)]
+/// It is serialized only to be sealed, since it contains the secrets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
 pub struct AppKeyChain {
     member_secrets_and_gens: Vec<(AppMemberSecret, u32)>,
+    exporter_secret: Vec<u8>,
     epoch: u32,
 }
 
@@ -105,12 +108,36 @@ impl AppKeyChain {
             })
             .collect();
 
+        let mut exporter_secret = vec![0u8; SHA256_OUTPUT_LEN];
+        hkdf::expand_label(&prk, b"exporter", b"", exporter_secret.as_mut_slice())
+            .expect("Failed hkdf expand.");
+
         AppKeyChain {
             member_secrets_and_gens,
+            exporter_secret,
             epoch: group_state.epoch(),
         }
     }
 
+    /// Derive a secret shared by all members in the current epoch for the given label.
+    /// Unlike the member secrets, it is not ratcheted per message.
+    pub fn exporter_secret(&self, label: &[u8]) -> Result<Vec<u8>> {
+        ensure!(
+            !self.exporter_secret.is_empty(),
+            "The exporter secret is not derived because the keychain hasn't been initialized by a handshake"
+        );
+
+        let prk = HmacKey::from(&self.exporter_secret[..]);
+        let mut buf = vec![0u8; SHA256_OUTPUT_LEN];
+        hkdf::expand_label(&prk, label, &self.epoch.to_be_bytes(), buf.as_mut_slice())?;
+
+        Ok(buf)
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
     /// Ratchets a specific roster's AppMemberSecret forward.
     pub fn ratchet(&mut self, roster_idx: usize) -> Result<()> {
         let (member_secret, gen) = self
@@ -169,16 +196,16 @@ impl AppKeyChain {
     }
 }
 
+#[cfg(feature = "sgx")]
 #[cfg(debug_assertions)]
 pub(crate) mod tests {
     use super::*;
     use crate::handshake::{PathSecretKVS, PathSecretSource};
     use crate::test_funcs;
-    #[cfg(feature = "sgx")]
     use test_utils::*;
 
     pub(crate) fn run_tests() -> bool {
-        run_tests!(test_app_msg_correctness,)
+        run_tests!(test_app_msg_correctness, test_restore_sealed_group_state,)
     }
 
     fn test_app_msg_correctness() {
@@ -388,4 +415,60 @@ pub(crate) mod tests {
             &mut key_chain2_epoch6,
         );
     }
+
+    fn test_restore_sealed_group_state() {
+        crate::localstd::env::set_var("AUDITOR_ENDPOINT", "test");
+        let msg = b"restore sealed group state test";
+
+        let mut kvs = PathSecretKVS::new();
+        test_funcs::init_path_secret_kvs(&mut kvs, 10, 10);
+        let source = PathSecretSource::LocalTestKV(kvs);
+
+        let mut group_state1 = GroupState::new(0).unwrap();
+        let mut group_state2 = GroupState::new(1).unwrap();
+        let mut group_state3 = GroupState::new(2).unwrap();
+
+        // Add member1 and member2
+        test_funcs::do_handshake_three_party(
+            &mut group_state1,
+            &mut group_state2,
+            &mut group_state3,
+            &source,
+        );
+        let (mut key_chain1, mut key_chain2, _) = test_funcs::do_handshake_three_party(
+            &mut group_state2,
+            &mut group_state1,
+            &mut group_state3,
+            &source,
+        );
+        key_chain1.ratchet(0).unwrap();
+        key_chain2.ratchet(0).unwrap();
+
+        // Restart member2 from its sealed group state and keychain
+        let encoded_group_state = group_state2.encode_secrets().unwrap();
+        let encoded_key_chain = bincode::serialize(&key_chain2).unwrap();
+        let mut group_state2 = GroupState::decode_secrets(&encoded_group_state).unwrap();
+        let key_chain2: AppKeyChain = bincode::deserialize(&encoded_key_chain).unwrap();
+        assert_eq!(group_state2.epoch(), group_state1.epoch());
+        assert_eq!(group_state2.my_roster_idx(), 1);
+        assert_eq!(key_chain2.generation(0).unwrap(), 1);
+
+        // 1 --> 2
+        let app_msg = key_chain1.encrypt_msg(msg.to_vec(), &group_state1).unwrap();
+        let plaintext = key_chain2.decrypt_msg(&app_msg, &group_state2).unwrap();
+        assert_eq!(plaintext.unwrap().as_slice(), msg);
+
+        // Update member1, whose path secret is decrypted with the restored private keys
+        let (key_chain1, _, key_chain2) = test_funcs::do_handshake_three_party(
+            &mut group_state1,
+            &mut group_state3,
+            &mut group_state2,
+            &source,
+        );
+
+        // 1 --> 2
+        let app_msg = key_chain1.encrypt_msg(msg.to_vec(), &group_state1).unwrap();
+        let plaintext = key_chain2.decrypt_msg(&app_msg, &group_state2).unwrap();
+        assert_eq!(plaintext.unwrap().as_slice(), msg);
+    }
 }
@@ -3,7 +3,7 @@ use crate::local_ring::hmac::{Context, Key, HMAC_SHA256};
 use crate::localstd::vec::Vec;
 use crate::serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(crate = "crate::serde")]
 pub struct HmacKey(Vec<u8>);
 
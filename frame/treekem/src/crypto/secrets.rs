@@ -12,10 +12,11 @@ use super::{
     CryptoRng, SHA256_OUTPUT_LEN,
 };
 use crate::bincode;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 use crate::handshake::AccessKey;
 use crate::local_anyhow::{anyhow, Result};
 use crate::localstd::{fmt, vec::Vec};
+use crate::serde::{Deserialize, Serialize};
 use codec::{Decode, Encode, Input};
 use frame_common::crypto::rand_assign;
 use frame_common::crypto::{ExportPathSecret, EXPORT_ID_SIZE, SEALED_DATA_SIZE};
@@ -55,7 +56,8 @@ impl From<AppSecret> for HmacKey {
 }
 
 /// A secret hat is unique to a member of the group.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
 pub struct AppMemberSecret(HmacKey);
 
 impl From<Vec<u8>> for AppMemberSecret {
@@ -187,7 +189,7 @@ impl PathSecret {
         PathSecret(key)
     }
 
-    #[cfg(feature = "sgx")]
+    #[cfg(any(feature = "sgx", feature = "software-enclave"))]
     pub fn derive_next(self, access_key: AccessKey) -> Result<PathSecret> {
         let prk = HmacKey::from(self);
         let mut path_secret_buf = vec![0u8; SHA256_OUTPUT_LEN];
@@ -209,7 +211,7 @@ impl PathSecret {
         self.as_bytes().len()
     }
 
-    #[cfg(feature = "sgx")]
+    #[cfg(any(feature = "sgx", feature = "software-enclave"))]
     pub fn try_into_exporting(self, epoch: u32, id: &[u8]) -> Result<ExportPathSecret> {
         let encoded_sealed = UnsealedPathSecret::from(self).encoded_seal()?;
         let mut id_arr = [0u8; EXPORT_ID_SIZE];
@@ -218,7 +220,7 @@ impl PathSecret {
         Ok(ExportPathSecret::new(encoded_sealed, epoch, id_arr))
     }
 
-    #[cfg(feature = "sgx")]
+    #[cfg(any(feature = "sgx", feature = "software-enclave"))]
     pub fn try_from_importing(imp_path_secret: ExportPathSecret) -> Result<Self> {
         let sealed_path_secret = SealedPathSecret::decode(&mut imp_path_secret.encoded_sealed())
             .map_err(|e| anyhow!("error: {:?}", e))?
@@ -240,12 +242,17 @@ impl UnsealedPathSecret {
 
         Ok(SealedPathSecret::new(sealed_data).encode())
     }
+
+    #[cfg(feature = "software-enclave")]
+    pub fn encoded_seal(self) -> Result<Vec<u8>> {
+        Ok(SealedPathSecret::new(self).encode())
+    }
 }
 
 #[cfg(feature = "sgx")]
 unsafe impl sgx_types::marker::ContiguousMemory for UnsealedPathSecret {}
 
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 impl From<PathSecret> for UnsealedPathSecret {
     fn from(ps: PathSecret) -> Self {
         assert_eq!(ps.len(), SHA256_OUTPUT_LEN);
@@ -322,6 +329,52 @@ impl fmt::Debug for SealedPathSecret<'_> {
     }
 }
 
+/// A path secret which is not sealed in the software enclave mode, since there is no seal key.
+/// It is encoded to the same size as the sealed one.
+#[cfg(feature = "software-enclave")]
+#[derive(Clone)]
+pub struct SealedPathSecret(UnsealedPathSecret);
+
+#[cfg(feature = "software-enclave")]
+impl SealedPathSecret {
+    pub fn new(path_secret: UnsealedPathSecret) -> Self {
+        SealedPathSecret(path_secret)
+    }
+
+    pub fn unseal(&self) -> Result<UnsealedPathSecret> {
+        Ok(self.0)
+    }
+}
+
+#[cfg(feature = "software-enclave")]
+impl Encode for SealedPathSecret {
+    fn encode(&self) -> Vec<u8> {
+        let mut res = vec![0u8; SEALED_DATA_SIZE];
+        res[..SHA256_OUTPUT_LEN].copy_from_slice(&(self.0).0);
+
+        res
+    }
+}
+
+#[cfg(feature = "software-enclave")]
+impl Decode for SealedPathSecret {
+    fn decode<I: Input>(value: &mut I) -> Result<Self, codec::Error> {
+        let mut buf = [0u8; SEALED_DATA_SIZE];
+        value.read(&mut buf)?;
+        let mut path_secret = [0u8; SHA256_OUTPUT_LEN];
+        path_secret.copy_from_slice(&buf[..SHA256_OUTPUT_LEN]);
+
+        Ok(SealedPathSecret::new(UnsealedPathSecret(path_secret)))
+    }
+}
+
+#[cfg(feature = "software-enclave")]
+impl fmt::Debug for SealedPathSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SealedPathSecret").finish()
+    }
+}
+
 #[cfg(feature = "sgx")]
 #[cfg(debug_assertions)]
 pub(crate) mod tests {
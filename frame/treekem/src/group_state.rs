@@ -1,16 +1,16 @@
 use crate::application::AppKeyChain;
+use crate::bincode;
 use crate::crypto::{hkdf, hmac::HmacKey, secrets::*};
 use crate::handshake::{AccessKey, Handshake, HandshakeParams, PathSecretSource};
 use crate::local_anyhow::{anyhow, ensure, Result};
 use crate::localstd::{env, vec::Vec};
-use crate::ratchet_tree::{RatchetTree, RatchetTreeNode};
-use crate::serde::Serialize;
+use crate::ratchet_tree::{PersistedNode, RatchetTree, RatchetTreeNode};
+use crate::serde::{Deserialize, Serialize};
 use crate::store_path_secrets::StorePathSecrets;
 use crate::tree_math;
 use frame_common::crypto::{
     ExportPathSecret, KeyVaultCmd, KeyVaultRequest, RecoverRequest, RecoveredPathSecret,
 };
-use frame_mra_tls::{AttestedTlsConfig, Client, ClientConfig};
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(crate = "crate::serde")]
@@ -29,6 +29,17 @@ pub struct GroupState {
     init_secret: HmacKey,
 }
 
+/// All fields of `GroupState` including the secrets which are skipped by its serialization,
+/// since the serialization is used as the context of the key schedule.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+struct PersistedGroupState {
+    epoch: u32,
+    my_roster_idx: u32,
+    nodes: Vec<PersistedNode>,
+    init_secret: HmacKey,
+}
+
 impl Handshake for GroupState {
     fn create_handshake(&self, source: &PathSecretSource) -> Result<(HandshakeParams, PathSecret)> {
         let my_roster_idx = self.my_roster_idx;
@@ -283,6 +294,28 @@ impl GroupState {
             .ok_or_else(|| anyhow!("Invalid tree size."))
     }
 
+    /// Encode the group state including the private keys and the init secret,
+    /// so the encoded bytes must be sealed.
+    pub fn encode_secrets(&self) -> Result<Vec<u8>> {
+        let persisted = PersistedGroupState {
+            epoch: self.epoch,
+            my_roster_idx: self.my_roster_idx,
+            nodes: self.tree.to_persisted(),
+            init_secret: self.init_secret.clone(),
+        };
+        bincode::serialize(&persisted).map_err(Into::into)
+    }
+
+    pub fn decode_secrets(bytes: &[u8]) -> Result<Self> {
+        let persisted: PersistedGroupState = bincode::deserialize(bytes)?;
+        Ok(GroupState {
+            epoch: persisted.epoch,
+            my_roster_idx: persisted.my_roster_idx,
+            tree: RatchetTree::from_persisted(persisted.nodes),
+            init_secret: persisted.init_secret,
+        })
+    }
+
     pub fn epoch(&self) -> u32 {
         self.epoch
     }
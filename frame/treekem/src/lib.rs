@@ -25,6 +25,10 @@ use serde_bytes_sgx as serde_bytes;
 #[cfg(feature = "std")]
 use serde_bytes_std as serde_bytes;
 #[cfg(all(feature = "sgx", not(feature = "std")))]
+use serde_json_sgx as serde_json;
+#[cfg(feature = "software-enclave")]
+use serde_json_std as serde_json;
+#[cfg(all(feature = "sgx", not(feature = "std")))]
 use serde_sgx as serde;
 #[cfg(feature = "std")]
 use serde_std as serde;
@@ -47,36 +51,36 @@ use std_rand_core as local_rand_core;
 #[cfg(feature = "std")]
 use std_ring as local_ring;
 
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod application;
 mod crypto;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod group_state;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub mod handshake;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod ratchet_tree;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod tree_math;
 // #[cfg(debug_assertions)]
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod store_path_secrets;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 mod test_funcs;
 
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::application::AppKeyChain;
 #[cfg(all(feature = "sgx", no_std))]
 pub use crate::crypto::secrets::SealedPathSecret;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::crypto::secrets::{PathSecret, UnsealedPathSecret};
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::group_state::GroupState;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::handshake::Handshake;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use crate::test_funcs::init_path_secret_kvs;
-#[cfg(feature = "sgx")]
+#[cfg(any(feature = "sgx", feature = "software-enclave"))]
 pub use store_path_secrets::StorePathSecrets;
 
 #[cfg(feature = "sgx")]
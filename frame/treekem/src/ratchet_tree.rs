@@ -5,7 +5,7 @@ use crate::crypto::{
 };
 use crate::local_anyhow::{anyhow, ensure, Result};
 use crate::localstd::vec::Vec;
-use crate::serde::Serialize;
+use crate::serde::{Deserialize, Serialize};
 use crate::{
     handshake::{DirectPathMsg, DirectPathNodeMsg},
     tree_math,
@@ -22,6 +22,15 @@ impl RatchetTree {
         RatchetTree { nodes }
     }
 
+    /// The nodes including their private keys, which must be sealed.
+    pub(crate) fn to_persisted(&self) -> Vec<PersistedNode> {
+        self.nodes.iter().map(Into::into).collect()
+    }
+
+    pub(crate) fn from_persisted(nodes: Vec<PersistedNode>) -> Self {
+        RatchetTree::new(nodes.into_iter().map(Into::into).collect())
+    }
+
     pub fn new_empty() -> Self {
         RatchetTree { nodes: vec![] }
     }
@@ -310,6 +319,48 @@ pub enum RatchetTreeNode {
     },
 }
 
+/// A node of RatchetTree which keeps its private key when it is serialized,
+/// unlike `RatchetTreeNode`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub(crate) enum PersistedNode {
+    Blank,
+    Filled {
+        public_key: DhPubKey,
+        private_key: Option<DhPrivateKey>,
+    },
+}
+
+impl From<&RatchetTreeNode> for PersistedNode {
+    fn from(node: &RatchetTreeNode) -> Self {
+        match node {
+            RatchetTreeNode::Blank => PersistedNode::Blank,
+            RatchetTreeNode::Filled {
+                public_key,
+                private_key,
+            } => PersistedNode::Filled {
+                public_key: public_key.clone(),
+                private_key: private_key.clone(),
+            },
+        }
+    }
+}
+
+impl From<PersistedNode> for RatchetTreeNode {
+    fn from(node: PersistedNode) -> Self {
+        match node {
+            PersistedNode::Blank => RatchetTreeNode::Blank,
+            PersistedNode::Filled {
+                public_key,
+                private_key,
+            } => RatchetTreeNode::Filled {
+                public_key,
+                private_key,
+            },
+        }
+    }
+}
+
 impl RatchetTreeNode {
     #[allow(dead_code)]
     pub fn from_private_key(private_key: DhPrivateKey) -> Self {
@@ -5,6 +5,7 @@ use crate::localstd::{
     path::{Path, PathBuf},
     vec::Vec,
 };
+use crate::serde_json;
 use frame_common::crypto::ExportPathSecret;
 use frame_config::PJ_ROOT_DIR;
 use tracing::info;
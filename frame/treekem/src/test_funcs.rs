@@ -17,6 +17,8 @@ pub fn init_path_secret_kvs(kvs: &mut PathSecretKVS, until_roster_idx: usize, un
     }
 }
 
+// It recovers path secrets from the key vault over mutual attested TLS, which is available only in SGX.
+#[cfg(feature = "sgx")]
 pub fn do_handshake_three_party(
     my_group: &mut GroupState,
     others_group1: &mut GroupState,
@@ -88,6 +90,7 @@ pub fn encrypt_decrypt_helper(
     };
 }
 
+#[cfg(feature = "sgx")]
 fn recover_path_secret_from_key_vault_for_test(
     id: &[u8],
     roster_idx: u32,
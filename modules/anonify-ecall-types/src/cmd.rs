@@ -9,3 +9,9 @@ pub const GET_ENCLAVE_ENCRYPTION_KEY_CMD: u32 = 8;
 pub const SEND_REGISTER_REPORT_CMD: u32 = 9;
 pub const BACKUP_PATH_SECRET_ALL_CMD: u32 = 10;
 pub const RECOVER_PATH_SECRET_ALL_CMD: u32 = 11;
+pub const GET_STATE_COUNTER_CMD: u32 = 12;
+pub const EXPORT_STATE_SNAPSHOT_CMD: u32 = 13;
+pub const IMPORT_STATE_SNAPSHOT_CMD: u32 = 14;
+pub const GET_RUNTIME_SCHEMA_CMD: u32 = 15;
+pub const UNREGISTER_NOTIFICATION_CMD: u32 = 16;
+pub const RELOAD_TRUSTED_MEASUREMENTS_CMD: u32 = 17;
@@ -12,16 +12,20 @@ use crate::serde::{
 use crate::serde_bytes;
 use crate::serde_json;
 use frame_common::{
-    crypto::{Ciphertext, ExportHandshake},
-    state_types::{StateCounter, StateType, UserCounter},
+    crypto::{AccountId, Ciphertext, ExportHandshake},
+    state_types::{PayloadsHash, StateCounter, StateRoot, StateType, UserCounter},
     traits::AccessPolicy,
     EcallInput, EcallOutput,
 };
-use frame_sodium::SodiumPubKey;
+use frame_sodium::{SodiumCiphertext, SodiumPubKey};
 
 pub mod input {
     use super::*;
 
+    /// Prepended to the messages signed for the notification requests to separate their domains.
+    const REGISTER_NOTIFICATION_DOMAIN: &[u8] = b"anonify:register_notification";
+    const UNREGISTER_NOTIFICATION_DOMAIN: &[u8] = b"anonify:unregister_notification";
+
     #[derive(Debug, Clone, Deserialize, Serialize)]
     #[serde(crate = "crate::serde")]
     pub struct Command<AP: AccessPolicy> {
@@ -82,15 +86,17 @@ pub mod input {
     pub struct InsertCiphertext {
         ciphertext: Ciphertext,
         state_counter: StateCounter,
+        block_num: u64,
     }
 
     impl EcallInput for InsertCiphertext {}
 
     impl InsertCiphertext {
-        pub fn new(ciphertext: Ciphertext, state_counter: StateCounter) -> Self {
+        pub fn new(ciphertext: Ciphertext, state_counter: StateCounter, block_num: u64) -> Self {
             InsertCiphertext {
                 ciphertext,
                 state_counter,
+                block_num,
             }
         }
 
@@ -101,6 +107,10 @@ pub mod input {
         pub fn state_counter(&self) -> StateCounter {
             self.state_counter
         }
+
+        pub fn block_num(&self) -> u64 {
+            self.block_num
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -108,15 +118,21 @@ pub mod input {
     pub struct InsertHandshake {
         handshake: ExportHandshake,
         state_counter: StateCounter,
+        block_num: u64,
     }
 
     impl EcallInput for InsertHandshake {}
 
     impl InsertHandshake {
-        pub fn new(handshake: ExportHandshake, state_counter: StateCounter) -> Self {
+        pub fn new(
+            handshake: ExportHandshake,
+            state_counter: StateCounter,
+            block_num: u64,
+        ) -> Self {
             InsertHandshake {
                 handshake,
                 state_counter,
+                block_num,
             }
         }
 
@@ -127,6 +143,27 @@ pub mod input {
         pub fn state_counter(&self) -> StateCounter {
             self.state_counter
         }
+
+        pub fn block_num(&self) -> u64 {
+            self.block_num
+        }
+    }
+
+    /// A state snapshot exported by another node in the same group
+    #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct ImportStateSnapshot {
+        pub header: StateSnapshotHeader,
+        #[serde(with = "serde_bytes")]
+        pub sealed: Vec<u8>,
+    }
+
+    impl EcallInput for ImportStateSnapshot {}
+
+    impl ImportStateSnapshot {
+        pub fn new(header: StateSnapshotHeader, sealed: Vec<u8>) -> Self {
+            ImportStateSnapshot { header, sealed }
+        }
     }
 
     #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -180,20 +217,85 @@ pub mod input {
     #[derive(Serialize, Deserialize, Debug, Clone, Default)]
     #[serde(crate = "crate::serde")]
     pub struct RegisterNotification<AP: AccessPolicy> {
+        /// The challenge must commit to `challenge_message` of the notification key and the expiry
         #[serde(deserialize_with = "AP::deserialize")]
         access_policy: AP,
+        /// The user's key which notified states are encrypted to
+        notification_key: SodiumPubKey,
+        /// Unix time in seconds after which the registration is expired
+        expires_at: u64,
     }
 
     impl<AP: AccessPolicy> EcallInput for RegisterNotification<AP> {}
 
     impl<AP: AccessPolicy> RegisterNotification<AP> {
-        pub fn new(access_policy: AP) -> Self {
-            RegisterNotification { access_policy }
+        pub fn new(access_policy: AP, notification_key: SodiumPubKey, expires_at: u64) -> Self {
+            RegisterNotification {
+                access_policy,
+                notification_key,
+                expires_at,
+            }
+        }
+
+        pub fn access_policy(&self) -> &AP {
+            &self.access_policy
+        }
+
+        pub fn notification_key(&self) -> &SodiumPubKey {
+            &self.notification_key
+        }
+
+        pub fn expires_at(&self) -> u64 {
+            self.expires_at
+        }
+
+        /// The message which the challenge of the access policy commits to.
+        /// Binding them to the signature prevents the host from replaying the registration
+        /// with another key or a later expiry.
+        pub fn challenge_message(notification_key: &SodiumPubKey, expires_at: u64) -> Vec<u8> {
+            let mut msg = REGISTER_NOTIFICATION_DOMAIN.to_vec();
+            msg.extend_from_slice(&notification_key.to_bytes());
+            msg.extend_from_slice(&expires_at.to_be_bytes());
+            msg
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct UnregisterNotification<AP: AccessPolicy> {
+        /// The challenge must commit to `challenge_message` of the expiry
+        #[serde(deserialize_with = "AP::deserialize")]
+        access_policy: AP,
+        /// Unix time in seconds after which the request is rejected
+        expires_at: u64,
+    }
+
+    impl<AP: AccessPolicy> EcallInput for UnregisterNotification<AP> {}
+
+    impl<AP: AccessPolicy> UnregisterNotification<AP> {
+        pub fn new(access_policy: AP, expires_at: u64) -> Self {
+            UnregisterNotification {
+                access_policy,
+                expires_at,
+            }
         }
 
         pub fn access_policy(&self) -> &AP {
             &self.access_policy
         }
+
+        pub fn expires_at(&self) -> u64 {
+            self.expires_at
+        }
+
+        /// The message which the challenge of the access policy commits to.
+        /// The domain differs from the other requests,
+        /// so that their signatures can't be replayed to unregister the owner.
+        pub fn challenge_message(expires_at: u64) -> Vec<u8> {
+            let mut msg = UNREGISTER_NOTIFICATION_DOMAIN.to_vec();
+            msg.extend_from_slice(&expires_at.to_be_bytes());
+            msg
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -317,20 +419,48 @@ pub mod output {
     #[derive(Serialize, Deserialize, Debug, Clone)]
     #[serde(crate = "crate::serde")]
     pub struct ReturnNotifyState {
-        pub state: Option<serde_bytes::ByteBuf>,
+        /// `NotifyState`s of every registered account updated by the transition
+        pub states: Vec<EncryptedNotifyState>,
+        /// The Merkle root over all user states right after the transition at `state_counter`
+        pub state_root: Option<(StateCounter, StateRoot)>,
     }
 
     impl EcallOutput for ReturnNotifyState {}
 
     impl Default for ReturnNotifyState {
         fn default() -> Self {
-            ReturnNotifyState { state: None }
+            ReturnNotifyState {
+                states: Vec::new(),
+                state_root: None,
+            }
         }
     }
 
     impl ReturnNotifyState {
-        pub fn update(&mut self, state: Vec<u8>) {
-            self.state = Some(serde_bytes::ByteBuf::from(state))
+        pub fn push_state(&mut self, state: EncryptedNotifyState) {
+            self.states.push(state)
+        }
+
+        pub fn set_state_root(&mut self, state_counter: StateCounter, state_root: StateRoot) {
+            self.state_root = Some((state_counter, state_root))
+        }
+    }
+
+    /// A JSON-encoded `NotifyState` encrypted to the notification key registered by its owner.
+    /// Only `account_id` is left in plaintext so that the host can deliver it to the owner.
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[serde(crate = "crate::serde")]
+    pub struct EncryptedNotifyState {
+        pub account_id: AccountId,
+        pub ciphertext: SodiumCiphertext,
+    }
+
+    impl EncryptedNotifyState {
+        pub fn new(account_id: AccountId, ciphertext: SodiumCiphertext) -> Self {
+            EncryptedNotifyState {
+                account_id,
+                ciphertext,
+            }
         }
     }
 
@@ -374,6 +504,81 @@ pub mod output {
         }
     }
 
+    /// The runtime schema encoded as JSON, since it contains JSON Schema values
+    #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct ReturnRuntimeSchema {
+        #[serde(with = "serde_bytes")]
+        schema: Vec<u8>,
+    }
+
+    impl EcallOutput for ReturnRuntimeSchema {}
+
+    impl ReturnRuntimeSchema {
+        pub fn new(schema: Vec<u8>) -> Self {
+            ReturnRuntimeSchema { schema }
+        }
+
+        pub fn schema(&self) -> &[u8] {
+            &self.schema[..]
+        }
+    }
+
+    /// A state snapshot encrypted with a secret derived from the group key of `header.epoch`
+    #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct ReturnStateSnapshot {
+        pub header: StateSnapshotHeader,
+        #[serde(with = "serde_bytes")]
+        pub sealed: Vec<u8>,
+    }
+
+    impl EcallOutput for ReturnStateSnapshot {}
+
+    impl ReturnStateSnapshot {
+        pub fn new(header: StateSnapshotHeader, sealed: Vec<u8>) -> Self {
+            ReturnStateSnapshot { header, sealed }
+        }
+    }
+
+    /// The state counter and the block number which the persisted state covers,
+    /// with the hash chain over the payloads consumed until the state counter
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct ReturnStateCounter {
+        state_counter: StateCounter,
+        block_num: u64,
+        payloads_hash: PayloadsHash,
+    }
+
+    impl EcallOutput for ReturnStateCounter {}
+
+    impl ReturnStateCounter {
+        pub fn new(
+            state_counter: StateCounter,
+            block_num: u64,
+            payloads_hash: PayloadsHash,
+        ) -> Self {
+            ReturnStateCounter {
+                state_counter,
+                block_num,
+                payloads_hash,
+            }
+        }
+
+        pub fn state_counter(&self) -> StateCounter {
+            self.state_counter
+        }
+
+        pub fn block_num(&self) -> u64 {
+            self.block_num
+        }
+
+        pub fn payloads_hash(&self) -> PayloadsHash {
+            self.payloads_hash
+        }
+    }
+
     #[derive(Serialize, Deserialize, Clone, Default)]
     #[serde(crate = "crate::serde")]
     pub struct ReturnJoinGroup {
@@ -607,3 +812,45 @@ pub mod output {
         }
     }
 }
+
+/// Public metadata of an exported state snapshot.
+/// It is authenticated together with the encrypted snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(crate = "crate::serde")]
+pub struct StateSnapshotHeader {
+    pub state_counter: StateCounter,
+    pub block_num: u64,
+    pub state_root: StateRoot,
+    pub epoch: u32,
+}
+
+impl StateSnapshotHeader {
+    pub fn new(
+        state_counter: StateCounter,
+        block_num: u64,
+        state_root: StateRoot,
+        epoch: u32,
+    ) -> Self {
+        StateSnapshotHeader {
+            state_counter,
+            block_num,
+            state_root,
+            epoch,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(&self).unwrap() // must not fail
+    }
+}
+
+/// A notification of the state updated by the transition at `state_counter`,
+/// which is published by the host to the owner of the state.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "crate::serde")]
+pub struct Notification {
+    pub state_counter: StateCounter,
+    #[serde(flatten)]
+    pub state: output::EncryptedNotifyState,
+}
@@ -3,6 +3,7 @@ use anonify_ecall_types::*;
 use anyhow::anyhow;
 use frame_common::{
     crypto::{AccountId, Ciphertext, Sha256},
+    error::EcallError,
     state_types::{NotifyState, ReturnState, StateType, UpdatedState, UserCounter},
     AccessPolicy,
 };
@@ -46,10 +47,16 @@ where
         let group_key = &mut *enclave_context.write_group_key();
         let roster_idx = group_key.my_roster_idx();
         // ratchet sender's app keychain per tx.
-        group_key.sender_ratchet(roster_idx as usize)?;
+        group_key
+            .sender_ratchet(roster_idx as usize)
+            .map_err(|e| EcallError::classify(e, EcallError::RatchetDesync))?;
+        // The generation must be sealed before the message leaves the enclave,
+        // so that it is never reused for encryption after restarting.
+        enclave_context.seal_sender_generation(group_key)?;
 
         let my_account_id = self.ecall_input.access_policy().into_account_id();
-        let ciphertext = Commands::<R, C, AP>::new(my_account_id, self.ecall_input)?
+        let ciphertext = Commands::<R, C, AP>::new(my_account_id, self.ecall_input)
+            .map_err(|e| EcallError::classify(e.into(), EcallError::Decode))?
             .encrypt(group_key, max_mem_size)?;
 
         let msg = Sha256::hash_for_attested_tx(
@@ -67,6 +74,7 @@ where
 
 /// A message receiver that decrypt commands and make state transition
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(crate = "crate::serde")]
 pub struct CmdReceiver<AP> {
     ecall_input: input::InsertCiphertext,
     ap: PhantomData<AP>,
@@ -94,18 +102,51 @@ where
     /// 2. Ratchet keychains
     /// 3. Verify the order of transactions for each user (verify_user_counter_increment)
     /// 4. State transitions
+    /// 5. Persist the updated states and counters (commit_state)
+    /// 6. Publish the state root at the state counter so that other nodes can compare it
     fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
     where
         R: RuntimeExecutor<C, S = StateType>,
         C: ContextOps<S = StateType> + Clone,
     {
+        // The group key must be locked before the state transition, as snapshots are exported in the same order.
         let group_key = &mut *enclave_context.write_group_key();
+        // The writes staged by the transition are discarded if it returns before committing.
+        let transition = enclave_context.lock_state_transition();
+        let state_counter = self.ecall_input.state_counter();
+        // Even if group_key's ratchet operations and state transitions fail, state_counter must be incremented so it doesn't get stuck.
+        enclave_context.verify_state_counter_increment(
+            state_counter,
+            &self.ecall_input.ciphertext().encode(),
+        )?;
+
+        let res = self.receive::<R, C>(enclave_context, group_key);
+        // The incremented counters and the ratcheted keychains must be persisted whether the state transition succeeded or not.
+        enclave_context.stage_group_key(group_key)?;
+        enclave_context.commit_state(transition, state_counter, self.ecall_input.block_num())?;
+
+        let mut output = res?;
+        output.set_state_root(state_counter, enclave_context.state_root());
+        Ok(output)
+    }
+}
+
+impl<AP> CmdReceiver<AP>
+where
+    AP: AccessPolicy,
+{
+    fn receive<R, C>(
+        &self,
+        enclave_context: &C,
+        group_key: &mut C::GK,
+    ) -> anyhow::Result<output::ReturnNotifyState>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
         let roster_idx = self.ecall_input.ciphertext().roster_idx() as usize;
         let msg_gen = self.ecall_input.ciphertext().generation();
 
-        // Even if group_key's ratchet operations and state transitions fail, state_counter must be incremented so it doesn't get stuck.
-        enclave_context.verify_state_counter_increment(self.ecall_input.state_counter())?;
-
         // Since the sender's keychain has already ratcheted,
         // even if an error occurs in the state transition, the receiver's keychain also ratchet.
         // `receiver_ratchet` fails if
@@ -115,23 +156,37 @@ where
         // In addition to these, `sync_ratchet` fails even if the receiver generation is larger than that of the sender
         // So if you run `sync_ratchet` first,
         // it will either succeed or both fail for the mutable `app_keychain`, so it will be atomic.
-        group_key.sync_ratchet(roster_idx, msg_gen)?;
-        group_key.receiver_ratchet(roster_idx)?;
+        group_key
+            .sync_ratchet(roster_idx, msg_gen)
+            .and_then(|_| group_key.receiver_ratchet(roster_idx))
+            .map_err(|e| EcallError::classify(e, EcallError::RatchetDesync))?;
 
         let mut output = output::ReturnNotifyState::default();
         let decrypted_cmds =
-            Commands::<R, C, AP>::decrypt(self.ecall_input.ciphertext(), group_key)?;
+            Commands::<R, C, AP>::decrypt(self.ecall_input.ciphertext(), group_key)
+                .map_err(|e| EcallError::classify(e.into(), EcallError::Decode))?;
         if let Some(cmds) = decrypted_cmds {
             // Since the command data is valid for the error at the time of state transition,
             // `user_counter` must be verified and incremented before the state transition.
             enclave_context.verify_user_counter_increment(cmds.my_account_id, cmds.counter)?;
             // Even if an error occurs in the state transition logic here, there is no problem because the state of `app_keychain` is consistent.
-            let state_iter = cmds.state_transition(enclave_context.clone())?;
+            let state_iter = cmds
+                .state_transition(enclave_context.clone())
+                .map_err(|e| EcallError::classify(e.into(), EcallError::StateTransition))?;
 
-            if let Some(notify_state) = enclave_context.update_state(state_iter.0, state_iter.1) {
-                let json = serde_json::to_vec(&notify_state)?;
-                let bytes = bincode::serialize(&json[..])?;
-                output.update(bytes);
+            for notify_state in enclave_context.update_state(state_iter.0, state_iter.1)? {
+                // The states are already updated, so failing to notify must not fail the state transition,
+                // e.g. the registration expired after it was checked in `update_state`.
+                match enclave_context.encrypt_notification(&notify_state) {
+                    Ok(ciphertext) => output.push_state(output::EncryptedNotifyState::new(
+                        notify_state.account_id,
+                        ciphertext,
+                    )),
+                    Err(e) => println!(
+                        "[warn] failed to encrypt the notification to {:?}: {:?}",
+                        notify_state.account_id, e
+                    ),
+                }
             }
         }
 
@@ -141,6 +196,7 @@ where
 
 /// Command data which make state update
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
 pub struct Commands<R: RuntimeExecutor<CTX>, CTX: ContextOps<S = StateType>, AP> {
     my_account_id: AccountId,
     #[serde(deserialize_with = "R::C::deserialize")]
@@ -1,38 +1,53 @@
 use crate::{
     enclave_key::EnclaveKey,
-    error::Result,
-    group_key::GroupKey,
-    kvs::{UserCounterDB, UserStateDB},
+    error::{EnclaveError, Result},
+    group_key::{GroupKey, SenderGeneration, GROUP_KEY_TABLE, SENDER_GENERATION_TABLE},
+    kvs::{
+        store::{self, PAYLOADS_HASH_TABLE},
+        user_counter::USER_COUNTER_TABLE,
+        user_state::USER_STATE_TABLE,
+        StateStore, UserCounterDB, UserStateDB,
+    },
     notify::Notifier,
 };
 use anonify_ecall_types::*;
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, ensure};
 use frame_common::{
     crypto::{
         AccountId, BackupPathSecret, KeyVaultCmd, KeyVaultRequest, RecoverAllRequest,
         RecoverRequest, RecoveredPathSecret,
     },
+    error::EcallError,
     state_types::{
-        MemId, NotifyState, ReturnState, StateCounter, StateType, UpdatedState, UserCounter,
+        MemId, NotifyState, PayloadsHash, ReturnState, StateCounter, StateRoot, StateType,
+        UpdatedState, UserCounter,
     },
     AccessPolicy,
 };
-use frame_config::{IAS_ROOT_CERT, KEY_VAULT_ENCLAVE_MEASUREMENT, PATH_SECRETS_DIR};
+#[cfg(feature = "sgx")]
+use frame_config::IAS_ROOT_CERT;
+use frame_config::PATH_SECRETS_DIR;
 use frame_enclave::EnclaveEngine;
-use frame_mra_tls::{AttestedTlsConfig, Client, ClientConfig};
+use frame_kvs::{KvsBackend, Snapshot, Transaction};
+#[cfg(feature = "sgx")]
+use frame_mra_tls::{AttestedTlsConfigCache, Client, ClientConfig, TRUSTED_KEY_VAULT_MEASUREMENTS};
 use frame_runtime::traits::*;
 use frame_sodium::{rng::SgxRng, SodiumCiphertext, SodiumPubKey};
 use frame_treekem::{
     handshake::{PathSecretKVS, PathSecretSource},
     init_path_secret_kvs, PathSecret, StorePathSecrets,
 };
+use remote_attestation::{attestation_policy, AttestationEvidence};
+#[cfg(feature = "sgx")]
 use remote_attestation::{EncodedQuote, QuoteTarget};
-use std::{
-    env,
-    prelude::v1::*,
-    sync::{Arc, SgxRwLock, SgxRwLockReadGuard, SgxRwLockWriteGuard},
-    vec::Vec,
+#[cfg(feature = "software-enclave")]
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "sgx")]
+use std::sync::{
+    SgxMutex as Mutex, SgxRwLock as RwLock, SgxRwLockReadGuard as RwLockReadGuard,
+    SgxRwLockWriteGuard as RwLockWriteGuard,
 };
+use std::{collections::BTreeMap, env, prelude::v1::*, sync::Arc, vec::Vec};
 
 /// spid: Service provider ID for the ISV.
 #[derive(Clone)]
@@ -46,11 +61,20 @@ pub struct AnonifyEnclaveContext {
     user_state_db: UserStateDB,
     user_counter_db: UserCounterDB,
     notifier: Notifier,
-    group_key: Arc<SgxRwLock<GroupKey>>,
+    group_key: Arc<RwLock<GroupKey>>,
+    // The group key as of the last state transition, which is carried over to checkpoints.
+    sealed_group_key: Arc<RwLock<Option<Vec<u8>>>>,
+    sender_generation_backend: Arc<dyn KvsBackend>,
+    #[cfg(feature = "sgx")]
     client_config: ClientConfig,
+    #[cfg(feature = "sgx")]
+    key_vault_client: Arc<Mutex<Option<Client>>>,
     store_path_secrets: StorePathSecrets,
     ias_root_cert: Vec<u8>,
-    state_counter: Arc<SgxRwLock<StateCounter>>,
+    state_counter: Arc<RwLock<StateCounter>>,
+    // Must be locked after `state_counter` so that the hash always reflects the counter.
+    payloads_hash: Arc<RwLock<PayloadsHash>>,
+    state_store: StateStore,
 }
 
 impl ConfigGetter for AnonifyEnclaveContext {
@@ -108,8 +132,11 @@ impl StateOps for AnonifyEnclaveContext {
         R: RuntimeExecutor<CTX, S = Self::S>,
         CTX: ContextOps<S = Self::S>,
     {
-        let call_kind = R::C::new(cmd_name, runtime_params)?;
-        let res = R::new(ctx).execute(call_kind, account_id.into())?;
+        let call_kind = R::C::new(cmd_name, runtime_params)
+            .map_err(|e| EcallError::classify(e, EcallError::Decode))?;
+        let res = R::new(ctx)
+            .execute(call_kind, account_id.into())
+            .map_err(|e| EcallError::classify(e, EcallError::StateTransition))?;
 
         match res {
             ReturnState::Updated(_) => Err(anyhow!(
@@ -119,29 +146,32 @@ impl StateOps for AnonifyEnclaveContext {
         }
     }
 
-    /// Returns a updated state of registerd account_id in notification.
-    // TODO: Enables to return multiple updated states.
+    /// Returns all updated states of registerd account_ids in notification.
     fn update_state(
         &self,
         updated_state_iter: impl Iterator<Item = UpdatedState<Self::S>>,
-        mut notify_state_iter: impl Iterator<Item = Option<NotifyState>>,
-    ) -> Option<NotifyState> {
-        updated_state_iter.for_each(|s| self.user_state_db.insert_by_updated_state(s));
-        notify_state_iter
-            .find(|state| {
-                if let Some(s) = state {
-                    self.is_notified(&s.account_id)
-                } else {
-                    // if the type of NotifyState is `Approved`
-                    false
-                }
-            })
-            .and_then(|e| e)
+        notify_state_iter: impl Iterator<Item = Option<NotifyState>>,
+    ) -> anyhow::Result<Vec<NotifyState>> {
+        for s in updated_state_iter {
+            self.user_state_db.insert_by_updated_state(s)?;
+        }
+        let notify_states = notify_state_iter
+            // `None` if the memory is not notified, e.g. `Approved`
+            .flatten()
+            .filter(|s| self.is_notified(&s.account_id))
+            .collect();
+
+        Ok(notify_states)
+    }
+
+    fn lock_state_transition(&self) -> Transaction<'_> {
+        self.state_store.begin()
     }
 
     fn verify_state_counter_increment(
         &self,
         received_state_counter: StateCounter,
+        payload: &[u8],
     ) -> anyhow::Result<()> {
         let mut curr_state_counter = self.state_counter.write().unwrap();
         if !curr_state_counter.is_increment(received_state_counter) {
@@ -151,6 +181,10 @@ impl StateOps for AnonifyEnclaveContext {
                 received_state_counter,
             );
         }
+        let mut payloads_hash = self.payloads_hash.write().unwrap();
+        let next_hash = payloads_hash.next(received_state_counter, payload);
+        self.state_store.put_payloads_hash(next_hash)?;
+        *payloads_hash = next_hash;
         *curr_state_counter = curr_state_counter.increment();
 
         Ok(())
@@ -163,30 +197,146 @@ impl StateOps for AnonifyEnclaveContext {
     ) -> anyhow::Result<()> {
         self.user_counter_db
             .increment(user, received)
-            .map_err(|e| anyhow!("{:?}", e))
+            .map_err(|e| match e {
+                EnclaveError::InvalidUserCounter { received, expected } => {
+                    EcallError::InvalidUserCounter { received, expected }.into()
+                }
+                e => anyhow!("{:?}", e),
+            })
+    }
+
+    fn commit_state(
+        &self,
+        mut transition: Transaction<'_>,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> anyhow::Result<()> {
+        if !self
+            .state_store
+            .commit(&mut transition, state_counter, block_num)?
+        {
+            return Ok(());
+        }
+
+        let mut snapshot = self.snapshot(state_counter, block_num)?;
+        self.put_sealed_group_key(&mut snapshot);
+        self.state_store.checkpoint(&snapshot).map_err(Into::into)
+    }
+
+    fn committed_state_counter(&self) -> (StateCounter, u64, PayloadsHash) {
+        let state_counter = self.state_counter.read().unwrap();
+        (
+            *state_counter,
+            self.state_store.block_num(),
+            *self.payloads_hash.read().unwrap(),
+        )
+    }
+
+    fn export_snapshot(&self) -> anyhow::Result<(Snapshot, StateRoot)> {
+        // Hold the transition lock so that the snapshot doesn't mix states from different transitions.
+        let _guard = self.lock_state_transition();
+        let state_counter = *self.state_counter.read().unwrap();
+        let snapshot = self.snapshot(state_counter, self.state_store.block_num())?;
+        let state_root = self.user_state_db.state_root();
+
+        Ok((snapshot, state_root))
+    }
+
+    fn import_snapshot(&self, mut snapshot: Snapshot) -> anyhow::Result<()> {
+        let _guard = self.lock_state_transition();
+        let mut state_counter = self.state_counter.write().unwrap();
+        ensure!(
+            snapshot.state_counter >= *state_counter,
+            "The snapshot's state counter ({:?}) is older than the current one ({:?})",
+            snapshot.state_counter,
+            *state_counter
+        );
+        let empty = BTreeMap::new();
+        self.user_state_db
+            .replace(snapshot.table(USER_STATE_TABLE).unwrap_or(&empty))?;
+        self.user_counter_db
+            .replace(snapshot.table(USER_COUNTER_TABLE).unwrap_or(&empty))?;
+        *self.payloads_hash.write().unwrap() = store::payloads_hash(Some(&snapshot))?;
+        // The imported states are checkpointed with our own group key, not the exporter's.
+        self.put_sealed_group_key(&mut snapshot);
+        self.state_store.checkpoint(&snapshot)?;
+        *state_counter = snapshot.state_counter;
+
+        Ok(())
+    }
+
+    fn state_root(&self) -> StateRoot {
+        self.user_state_db.state_root()
     }
 }
 
 impl GroupKeyGetter for AnonifyEnclaveContext {
     type GK = GroupKey;
 
-    fn read_group_key(&self) -> SgxRwLockReadGuard<Self::GK> {
+    fn read_group_key(&self) -> RwLockReadGuard<Self::GK> {
         self.group_key.read().unwrap()
     }
 
-    fn write_group_key(&self) -> SgxRwLockWriteGuard<Self::GK> {
+    fn write_group_key(&self) -> RwLockWriteGuard<Self::GK> {
         self.group_key.write().unwrap()
     }
+
+    fn stage_group_key(&self, group_key: &Self::GK) -> anyhow::Result<()> {
+        let encoded = group_key.encode_secrets()?;
+        self.state_store
+            .put(GROUP_KEY_TABLE, vec![], encoded.clone())?;
+        *self.sealed_group_key.write().unwrap() = Some(encoded);
+
+        Ok(())
+    }
+
+    fn seal_sender_generation(&self, group_key: &Self::GK) -> anyhow::Result<()> {
+        let mut table = BTreeMap::new();
+        table.insert(vec![], bincode::serialize(&group_key.sender_generation()?)?);
+        let mut snapshot = Snapshot::default();
+        snapshot
+            .tables
+            .insert(SENDER_GENERATION_TABLE.to_string(), table);
+
+        self.sender_generation_backend
+            .checkpoint(&snapshot)
+            .map_err(Into::into)
+    }
 }
 
 impl NotificationOps for AnonifyEnclaveContext {
-    fn set_notification(&self, account_id: AccountId) -> bool {
-        self.notifier.register(account_id)
+    fn set_notification(
+        &self,
+        account_id: AccountId,
+        notification_key: SodiumPubKey,
+        expires_at: u64,
+    ) -> anyhow::Result<bool> {
+        self.notifier
+            .register(account_id, notification_key, expires_at)
+            .map_err(Into::into)
+    }
+
+    fn unset_notification(&self, account_id: &AccountId) -> anyhow::Result<bool> {
+        self.notifier.unregister(account_id).map_err(Into::into)
     }
 
     fn is_notified(&self, account_id: &AccountId) -> bool {
         self.notifier.contains(&account_id)
     }
+
+    fn encrypt_notification(
+        &self,
+        notify_state: &NotifyState,
+    ) -> anyhow::Result<SodiumCiphertext> {
+        let notification_key = self
+            .notifier
+            .notification_key(&notify_state.account_id)
+            .ok_or_else(|| anyhow!("{:?} is not registered", notify_state.account_id))?;
+        let mut rng = SgxRng::new()?;
+        let plaintext = serde_json::to_vec(notify_state)?;
+
+        SodiumCiphertext::encrypt(&mut rng, &notification_key, plaintext)
+    }
 }
 
 impl EnclaveKeyOps for AnonifyEnclaveContext {
@@ -207,6 +357,7 @@ impl EnclaveKeyOps for AnonifyEnclaveContext {
 }
 
 impl QuoteGetter for AnonifyEnclaveContext {
+    #[cfg(feature = "sgx")]
     fn quote(&self) -> anyhow::Result<EncodedQuote> {
         let report_data = &self.enclave_key.report_data()?;
         QuoteTarget::new()?
@@ -214,23 +365,34 @@ impl QuoteGetter for AnonifyEnclaveContext {
             .create_quote(&self.spid)
             .map_err(|e| anyhow!("{:?}", e))
     }
+
+    fn attestation_evidence(&self) -> anyhow::Result<AttestationEvidence> {
+        let report_data = self.enclave_key.report_data()?;
+        let policy = attestation_policy().map_err(|e| anyhow!("{:?}", e))?;
+        remote_attestation::attest(
+            &report_data,
+            &self.spid,
+            &self.ias_url,
+            &self.sub_key,
+            self.ias_root_cert.clone(),
+            policy,
+        )
+        .map_err(|e| anyhow!("{:?}", e))
+    }
 }
 
 impl KeyVaultOps for AnonifyEnclaveContext {
     fn backup_path_secret(&self, backup_path_secret: BackupPathSecret) -> anyhow::Result<()> {
-        let mut mra_tls_client = Client::new(self.key_vault_endpoint(), &self.client_config)?;
         let key_vault_request = KeyVaultRequest::new(KeyVaultCmd::Store, backup_path_secret);
-        let _resp: serde_json::Value = mra_tls_client.send_json(key_vault_request)?;
+        let _resp: serde_json::Value = self.send_to_key_vault(key_vault_request)?;
 
         Ok(())
     }
 
     fn recover_path_secret(&self, ps_id: &[u8], roster_idx: u32) -> anyhow::Result<PathSecret> {
         let recover_request = RecoverRequest::new(roster_idx, ps_id.to_vec());
-        let mut mra_tls_client = Client::new(self.key_vault_endpoint(), &self.client_config)?;
         let backup_request = KeyVaultRequest::new(KeyVaultCmd::Recover, recover_request);
-        let recovered_path_secret: RecoveredPathSecret =
-            mra_tls_client.send_json(backup_request)?;
+        let recovered_path_secret: RecoveredPathSecret = self.send_to_key_vault(backup_request)?;
 
         Ok(PathSecret::from(recovered_path_secret.path_secret()))
     }
@@ -239,10 +401,9 @@ impl KeyVaultOps for AnonifyEnclaveContext {
         &self,
         backup_path_secrets: Vec<BackupPathSecret>,
     ) -> anyhow::Result<()> {
-        let mut mra_tls_client = Client::new(self.key_vault_endpoint(), &self.client_config)?;
         let key_vault_request =
             KeyVaultRequest::new(KeyVaultCmd::ManuallyStoreAll, backup_path_secrets);
-        let _resp: serde_json::Value = mra_tls_client.send_json(key_vault_request)?;
+        let _resp: serde_json::Value = self.send_to_key_vault(key_vault_request)?;
 
         Ok(())
     }
@@ -251,10 +412,9 @@ impl KeyVaultOps for AnonifyEnclaveContext {
         &self,
         recover_path_secrets_all: RecoverAllRequest,
     ) -> anyhow::Result<Vec<RecoveredPathSecret>> {
-        let mut mra_tls_client = Client::new(self.key_vault_endpoint(), &self.client_config)?;
         let key_vault_request =
             KeyVaultRequest::new(KeyVaultCmd::ManuallyRecoverAll, recover_path_secrets_all);
-        let path_secrets: Vec<RecoveredPathSecret> = mra_tls_client.send_json(key_vault_request)?;
+        let path_secrets: Vec<RecoveredPathSecret> = self.send_to_key_vault(key_vault_request)?;
 
         Ok(path_secrets)
     }
@@ -262,12 +422,92 @@ impl KeyVaultOps for AnonifyEnclaveContext {
 
 // TODO: Consider SGX_ERROR_BUSY.
 impl AnonifyEnclaveContext {
+    /// Send the request to the key vault over the session shared by the requests,
+    /// which is established on the first request.
+    #[cfg(feature = "sgx")]
+    fn send_to_key_vault<SE, DE>(&self, request: SE) -> anyhow::Result<DE>
+    where
+        SE: serde::Serialize,
+        DE: serde::de::DeserializeOwned,
+    {
+        let mut client = self
+            .key_vault_client
+            .lock()
+            .map_err(|e| anyhow!("{:?}", e))?;
+        if client.is_none() {
+            *client = Some(Client::new(self.key_vault_endpoint(), &self.client_config)?);
+        }
+
+        client
+            .as_mut()
+            .expect("The client is initialized above")
+            .send_json(request)
+    }
+
+    /// Software enclaves can't be attested by the key vault, so there is no key vault to send to.
+    #[cfg(feature = "software-enclave")]
+    fn send_to_key_vault<SE, DE>(&self, _request: SE) -> anyhow::Result<DE>
+    where
+        SE: serde::Serialize,
+        DE: serde::de::DeserializeOwned,
+    {
+        bail!("The key vault is not available in the software enclave mode")
+    }
+
+    /// Take a snapshot of all user states and counters.
+    fn snapshot(&self, state_counter: StateCounter, block_num: u64) -> Result<Snapshot> {
+        let mut snapshot = Snapshot {
+            state_counter,
+            block_num,
+            ..Default::default()
+        };
+        snapshot
+            .tables
+            .insert(USER_STATE_TABLE.to_string(), self.user_state_db.to_table());
+        snapshot.tables.insert(
+            USER_COUNTER_TABLE.to_string(),
+            self.user_counter_db.to_table()?,
+        );
+        let mut payloads_hash = BTreeMap::new();
+        payloads_hash.insert(
+            vec![],
+            self.payloads_hash.read().unwrap().as_bytes().to_vec(),
+        );
+        snapshot
+            .tables
+            .insert(PAYLOADS_HASH_TABLE.to_string(), payloads_hash);
+
+        Ok(snapshot)
+    }
+
+    /// Put the sealed group key into the snapshot to be checkpointed.
+    /// It is never exported since it contains the private keys of this enclave.
+    fn put_sealed_group_key(&self, snapshot: &mut Snapshot) {
+        snapshot.tables.remove(GROUP_KEY_TABLE);
+        if let Some(group_key) = &*self.sealed_group_key.read().unwrap() {
+            let mut table = BTreeMap::new();
+            table.insert(vec![], group_key.clone());
+            snapshot.tables.insert(GROUP_KEY_TABLE.to_string(), table);
+        }
+    }
+
     pub fn new(version: usize) -> Result<Self> {
         let mut rng = SgxRng::new()?;
 
         let enclave_key = EnclaveKey::new(&mut rng)?;
-        let user_state_db = UserStateDB::new();
-        let user_counter_db = UserCounterDB::new();
+        let state_store = StateStore::from_env()?;
+        let snapshot = state_store.load()?;
+        let user_state_db = UserStateDB::restore(state_store.clone(), snapshot.as_ref())?;
+        let user_counter_db = UserCounterDB::restore(state_store.clone(), snapshot.as_ref())?;
+        let payloads_hash = Arc::new(RwLock::new(store::payloads_hash(snapshot.as_ref())?));
+        let sealed_group_key = snapshot
+            .as_ref()
+            .and_then(|s| s.table(GROUP_KEY_TABLE))
+            .and_then(|table| table.get(&[][..]))
+            .cloned();
+        let state_counter = Arc::new(RwLock::new(
+            snapshot.map(|s| s.state_counter).unwrap_or_default(),
+        ));
 
         let source = match env::var("AUDITOR_ENDPOINT") {
             Err(_) => PathSecretSource::Local,
@@ -291,27 +531,41 @@ impl AnonifyEnclaveContext {
             .parse()
             .expect("Failed to parse MAX_ROSTER_IDX to usize");
 
-        let group_key = Arc::new(SgxRwLock::new(GroupKey::new(
-            my_roster_idx,
-            max_roster_idx,
-            source,
-        )?));
-        let notifier = Notifier::new();
+        let mut group_key = match &sealed_group_key {
+            Some(bytes) => GroupKey::restore(bytes, my_roster_idx, max_roster_idx, source)?,
+            None => GroupKey::new(my_roster_idx, max_roster_idx, source)?,
+        };
+        let sender_generation_backend = store::sender_generation_backend_from_env()?;
+        if let Some(bytes) = sender_generation_backend
+            .load()?
+            .as_ref()
+            .and_then(|s| s.table(SENDER_GENERATION_TABLE))
+            .and_then(|table| table.get(&[][..]))
+        {
+            let sender_generation: SenderGeneration = bincode::deserialize(bytes)?;
+            group_key.restore_sender_generation(sender_generation)?;
+        }
+        let group_key = Arc::new(RwLock::new(group_key));
+        let notifier = Notifier::from_env()?;
 
         let ias_url = env::var("IAS_URL").expect("IAS_URL is not set");
         let sub_key = env::var("SUB_KEY").expect("SUB_KEY is not set");
         let key_vault_endpoint =
             env::var("KEY_VAULT_ENDPOINT").expect("KEY_VAULT_ENDPOINT is not set");
 
-        let attested_tls_config =
-            AttestedTlsConfig::new_by_ra(&spid, &ias_url, &sub_key, IAS_ROOT_CERT.to_vec())?;
-        let client_config = ClientConfig::from_attested_tls_config(attested_tls_config)?
-            .set_attestation_report_verifier(
-                IAS_ROOT_CERT.to_vec(),
-                *KEY_VAULT_ENCLAVE_MEASUREMENT,
-            );
+        #[cfg(feature = "sgx")]
+        let client_config = {
+            let attested_tls_config_cache =
+                AttestedTlsConfigCache::new(&spid, &ias_url, &sub_key, IAS_ROOT_CERT.to_vec());
+            // Attest in advance so that the first request to the key vault does not wait for it
+            attested_tls_config_cache.get()?;
+            ClientConfig::from_attested_tls_config_cache(attested_tls_config_cache)
+                .set_attestation_report_verifier(
+                    IAS_ROOT_CERT.to_vec(),
+                    TRUSTED_KEY_VAULT_MEASUREMENTS.clone(),
+                )
+        };
         let store_path_secrets = StorePathSecrets::new(&*PATH_SECRETS_DIR);
-        let state_counter = Arc::new(SgxRwLock::new(StateCounter::default()));
 
         Ok(AnonifyEnclaveContext {
             spid,
@@ -320,14 +574,25 @@ impl AnonifyEnclaveContext {
             user_counter_db,
             notifier,
             group_key,
+            sealed_group_key: Arc::new(RwLock::new(sealed_group_key)),
+            sender_generation_backend,
             version,
             ias_url,
             sub_key,
             key_vault_endpoint,
+            #[cfg(feature = "sgx")]
             client_config,
+            #[cfg(feature = "sgx")]
+            key_vault_client: Arc::new(Mutex::new(None)),
             store_path_secrets,
+            #[cfg(feature = "sgx")]
             ias_root_cert: (&*IAS_ROOT_CERT).to_vec(),
+            // Mocked attestation reports are not signed by IAS.
+            #[cfg(feature = "software-enclave")]
+            ias_root_cert: vec![],
             state_counter,
+            payloads_hash,
+            state_store,
         })
     }
 }
@@ -389,22 +654,81 @@ impl EnclaveEngine for ReportRegistration {
         R: RuntimeExecutor<C, S = StateType>,
         C: ContextOps<S = StateType> + Clone,
     {
-        let ias_url = enclave_context.ias_url();
-        let sub_key = enclave_context.sub_key();
-        let attested_report = enclave_context.quote()?.remote_attestation(
-            ias_url,
-            sub_key,
-            IAS_ROOT_CERT.to_vec(),
-        )?;
+        let evidence = enclave_context.attestation_evidence()?;
+        let (report, report_sig) = evidence.contract_report()?;
 
         let mrenclave_ver = enclave_context.mrenclave_ver();
         let my_roster_idx = enclave_context.read_group_key().my_roster_idx();
 
         Ok(output::ReturnRegisterReport::new(
-            attested_report.report().to_vec(),
-            attested_report.report_sig().to_vec(),
+            report,
+            report_sig,
             mrenclave_ver,
             my_roster_idx,
         ))
     }
 }
+
+/// An engine returning the state counter and the block number which the persisted states cover,
+/// so that the host can resume fetching events from there.
+#[derive(Debug, Clone, Default)]
+pub struct GetStateCounter;
+
+impl EnclaveEngine for GetStateCounter {
+    type EI = input::Empty;
+    type EO = output::ReturnStateCounter;
+
+    fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        let (state_counter, block_num, payloads_hash) = enclave_context.committed_state_counter();
+        Ok(output::ReturnStateCounter::new(
+            state_counter,
+            block_num,
+            payloads_hash,
+        ))
+    }
+}
+
+/// An engine returning the schema of the runtime's commands, getters and memories.
+#[derive(Debug, Clone, Default)]
+pub struct GetRuntimeSchema;
+
+impl EnclaveEngine for GetRuntimeSchema {
+    type EI = input::Empty;
+    type EO = output::ReturnRuntimeSchema;
+
+    fn handle<R, C>(self, _enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        let schema = serde_json::to_vec(&R::schema())?;
+        Ok(output::ReturnRuntimeSchema::new(schema))
+    }
+}
+
+/// An engine reloading the measurements of the key vault enclaves trusted by this enclave,
+/// which are applied to the subsequent connections to the key vault.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedMeasurementsReloader;
+
+impl EnclaveEngine for TrustedMeasurementsReloader {
+    type EI = input::Empty;
+    type EO = output::Empty;
+
+    fn handle<R, C>(self, _enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        #[cfg(feature = "sgx")]
+        TRUSTED_KEY_VAULT_MEASUREMENTS.reload()?;
+        #[cfg(feature = "software-enclave")]
+        bail!("The key vault is not available in the software enclave mode");
+        #[cfg(feature = "sgx")]
+        Ok(output::Empty::default())
+    }
+}
@@ -15,6 +15,7 @@ pub enum EnclaveError {
     Secp256k1Error(secp256k1::Error),
     #[error("Anyhow error: {0}")]
     AnyhowError(#[from] anyhow::Error),
+    #[cfg(feature = "sgx")]
     #[error("Frame mra-tls error: {0}")]
     FrameMraTLSError(#[from] frame_mra_tls::MraTLSError),
     #[error("Bincode error: {0}")]
@@ -1,12 +1,18 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use frame_common::crypto::Ciphertext;
 use frame_runtime::traits::*;
 use frame_treekem::{
     handshake::{HandshakeParams, PathSecretSource},
     AppKeyChain, GroupState, Handshake, PathSecret, StorePathSecrets,
 };
+use serde::{Deserialize, Serialize};
 use std::vec::Vec;
 
+/// The table holding the group key sealed with the states under the empty key.
+pub const GROUP_KEY_TABLE: &str = "group_key";
+/// The table holding the generation of the sender keychain under the empty key.
+pub const SENDER_GENERATION_TABLE: &str = "sender_generation";
+
 #[derive(Clone, Debug)]
 pub struct GroupKey {
     group_state: GroupState,
@@ -34,6 +40,82 @@ impl GroupKey {
             source,
         })
     }
+
+    /// Restore the group key sealed with the states by `encode_secrets`.
+    pub fn restore(
+        bytes: &[u8],
+        my_roster_idx: usize,
+        max_roster_idx: usize,
+        source: PathSecretSource,
+    ) -> Result<Self> {
+        let sealed: SealedGroupKey = bincode::deserialize(bytes)?;
+        let group_state = GroupState::decode_secrets(&sealed.group_state)?;
+        ensure!(
+            group_state.my_roster_idx() as usize == my_roster_idx,
+            "The sealed group key belongs to the roster index {:?}, but MY_ROSTER_IDX is {:?}",
+            group_state.my_roster_idx(),
+            my_roster_idx
+        );
+
+        Ok(GroupKey {
+            group_state,
+            sender_keychain: sealed.sender_keychain,
+            receiver_keychain: sealed.receiver_keychain,
+            max_roster_idx,
+            source,
+        })
+    }
+
+    /// Encode the group key including the private keys and the keychains,
+    /// so the encoded bytes must be sealed.
+    pub fn encode_secrets(&self) -> Result<Vec<u8>> {
+        let sealed = SealedGroupKey {
+            group_state: self.group_state.encode_secrets()?,
+            sender_keychain: self.sender_keychain.clone(),
+            receiver_keychain: self.receiver_keychain.clone(),
+        };
+        bincode::serialize(&sealed).map_err(Into::into)
+    }
+
+    pub fn sender_generation(&self) -> Result<SenderGeneration> {
+        let roster_idx = self.my_roster_idx() as usize;
+        Ok(SenderGeneration {
+            epoch: self.sender_keychain.epoch(),
+            generation: self.sender_keychain.generation(roster_idx)?,
+        })
+    }
+
+    /// Ratchet the sender keychain forward to the generation sealed before restarting,
+    /// since messages may have been sent after the group key was sealed with the states.
+    /// It is ignored if the keychain has been updated by a handshake since then.
+    pub fn restore_sender_generation(&mut self, sealed: SenderGeneration) -> Result<()> {
+        if sealed.epoch != self.sender_keychain.epoch() {
+            return Ok(());
+        }
+        let roster_idx = self.my_roster_idx() as usize;
+        while self.sender_keychain.generation(roster_idx)? < sealed.generation {
+            self.sender_keychain.ratchet(roster_idx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+struct SealedGroupKey {
+    group_state: Vec<u8>,
+    sender_keychain: AppKeyChain,
+    receiver_keychain: AppKeyChain,
+}
+
+/// The generation of my own sender keychain, which is sealed on every sent message
+/// so that it is never reused for encryption after restarting.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+pub struct SenderGeneration {
+    epoch: u32,
+    generation: u32,
 }
 
 impl GroupKeyOps for GroupKey {
@@ -147,4 +229,96 @@ impl GroupKeyOps for GroupKey {
     fn my_roster_idx(&self) -> u32 {
         self.group_state.my_roster_idx()
     }
+
+    fn epoch(&self) -> u32 {
+        self.receiver_keychain.epoch()
+    }
+
+    fn exporter_secret(&self, label: &[u8]) -> Result<Vec<u8>> {
+        self.receiver_keychain.exporter_secret(label)
+    }
+}
+
+#[cfg(all(feature = "sgx", debug_assertions))]
+pub(crate) mod tests {
+    use super::*;
+    use frame_config::PATH_SECRETS_DIR;
+    use frame_treekem::{handshake::PathSecretKVS, init_path_secret_kvs};
+    use test_utils::*;
+
+    const MAX_ROSTER_IDX: usize = 2;
+
+    pub(crate) fn run_tests() -> bool {
+        run_tests!(
+            test_restore_sealed_group_key,
+            test_restore_sealed_sender_generation,
+        )
+    }
+
+    fn source() -> PathSecretSource {
+        let mut kvs = PathSecretKVS::new();
+        init_path_secret_kvs(&mut kvs, 10, 10);
+        PathSecretSource::LocalTestKV(kvs)
+    }
+
+    /// Members 0 and 1 join the group.
+    fn join_group() -> (GroupKey, GroupKey) {
+        let mut group_key0 = GroupKey::new(0, MAX_ROSTER_IDX, source()).unwrap();
+        let mut group_key1 = GroupKey::new(1, MAX_ROSTER_IDX, source()).unwrap();
+
+        let (handshake, _) = group_key0.create_handshake().unwrap();
+        process_handshake(&mut group_key0, &mut group_key1, &handshake);
+        let (handshake, _) = group_key1.create_handshake().unwrap();
+        process_handshake(&mut group_key0, &mut group_key1, &handshake);
+
+        (group_key0, group_key1)
+    }
+
+    fn process_handshake(
+        group_key0: &mut GroupKey,
+        group_key1: &mut GroupKey,
+        handshake: &HandshakeParams,
+    ) {
+        let store_path_secrets = StorePathSecrets::new(&*PATH_SECRETS_DIR);
+        for group_key in vec![group_key0, group_key1] {
+            group_key
+                .process_handshake(&store_path_secrets, handshake, |_, _| {
+                    Err(anyhow!("The path secret is not recovered in tests"))
+                })
+                .unwrap();
+        }
+    }
+
+    fn test_restore_sealed_group_key() {
+        let msg = b"restore sealed group key test".to_vec();
+        let (mut group_key0, group_key1) = join_group();
+
+        let sealed = group_key1.encode_secrets().unwrap();
+        let mut restored = GroupKey::restore(&sealed, 1, MAX_ROSTER_IDX, source()).unwrap();
+        assert_eq!(restored.epoch(), group_key1.epoch());
+        assert!(GroupKey::restore(&sealed, 0, MAX_ROSTER_IDX, source()).is_err());
+
+        group_key0.sender_ratchet(0).unwrap();
+        let ciphertext = group_key0.encrypt(msg.clone()).unwrap();
+        restored.sync_ratchet(0, ciphertext.generation()).unwrap();
+        restored.receiver_ratchet(0).unwrap();
+        assert_eq!(restored.decrypt(&ciphertext).unwrap(), Some(msg));
+    }
+
+    fn test_restore_sealed_sender_generation() {
+        let (_, mut group_key1) = join_group();
+        let sealed = group_key1.encode_secrets().unwrap();
+
+        group_key1.sender_ratchet(1).unwrap();
+        group_key1.sender_ratchet(1).unwrap();
+        let sender_generation = group_key1.sender_generation().unwrap();
+
+        let mut restored = GroupKey::restore(&sealed, 1, MAX_ROSTER_IDX, source()).unwrap();
+        restored
+            .restore_sender_generation(sender_generation)
+            .unwrap();
+        restored.sender_ratchet(1).unwrap();
+        let ciphertext = restored.encrypt(b"next message".to_vec()).unwrap();
+        assert_eq!(ciphertext.generation(), 3);
+    }
 }
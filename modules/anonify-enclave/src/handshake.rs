@@ -20,11 +20,8 @@ impl EnclaveEngine for JoinGroupSender {
         R: RuntimeExecutor<C, S = StateType>,
         C: ContextOps<S = StateType> + Clone,
     {
-        let attested_report = enclave_context.quote()?.remote_attestation(
-            enclave_context.ias_url(),
-            enclave_context.sub_key(),
-            enclave_context.ias_root_cert().to_vec(),
-        )?;
+        let evidence = enclave_context.attestation_evidence()?;
+        let (report, report_sig) = evidence.contract_report()?;
 
         let (handshake, path_secret) = (&*enclave_context.read_group_key()).create_handshake()?;
         let epoch = handshake.prior_epoch();
@@ -47,8 +44,8 @@ impl EnclaveEngine for JoinGroupSender {
         }
 
         Ok(output::ReturnJoinGroup::new(
-            attested_report.report().to_vec(),
-            attested_report.report_sig().to_vec(),
+            report,
+            report_sig,
             export_handshake.encode(),
             enclave_context.mrenclave_ver(),
             export_handshake.roster_idx(),
@@ -133,16 +130,26 @@ impl EnclaveEngine for HandshakeReceiver {
         C: ContextOps<S = StateType> + Clone,
     {
         let group_key = &mut *enclave_context.write_group_key();
-        let handshake = HandshakeParams::decode(&self.ecall_input.handshake().handshake()[..])
-            .map_err(|_| anyhow!("HandshakeParams::decode Error"))?;
-
-        // Even if `process_handshake` fails, state_counter must be incremented so it doesn't get stuck.
-        enclave_context.verify_state_counter_increment(self.ecall_input.state_counter())?;
-        group_key.process_handshake(
-            enclave_context.store_path_secrets(),
-            &handshake,
-            |ps_id, roster_idx| C::recover_path_secret(enclave_context, ps_id, roster_idx),
+        let transition = enclave_context.lock_state_transition();
+        // Even if decoding or `process_handshake` fails, state_counter must be incremented so it doesn't get stuck.
+        let state_counter = self.ecall_input.state_counter();
+        enclave_context.verify_state_counter_increment(
+            state_counter,
+            &self.ecall_input.handshake().encode(),
         )?;
+        let res = HandshakeParams::decode(&self.ecall_input.handshake().handshake()[..])
+            .map_err(|_| anyhow!("HandshakeParams::decode Error"))
+            .and_then(|handshake| {
+                group_key.process_handshake(
+                    enclave_context.store_path_secrets(),
+                    &handshake,
+                    |ps_id, roster_idx| C::recover_path_secret(enclave_context, ps_id, roster_idx),
+                )
+            });
+        // The group key is sealed with the transition so that it is not lost by restarting.
+        enclave_context.stage_group_key(group_key)?;
+        enclave_context.commit_state(transition, state_counter, self.ecall_input.block_num())?;
+        res?;
 
         Ok(output::Empty::default())
     }
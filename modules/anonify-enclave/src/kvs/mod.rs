@@ -1,5 +1,7 @@
+pub mod store;
 pub mod user_counter;
 pub mod user_state;
 
+pub use store::StateStore;
 pub use user_counter::UserCounterDB;
 pub use user_state::UserStateDB;
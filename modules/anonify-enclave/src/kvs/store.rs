@@ -0,0 +1,216 @@
+use crate::error::Result;
+use anyhow::anyhow;
+use frame_common::state_types::{PayloadsHash, StateCounter};
+use frame_config::{PJ_ROOT_DIR, USER_STATE_DIR, USER_STATE_SNAPSHOT_INTERVAL};
+use frame_kvs::{
+    KvsBackend, MemoryBackend, SealedFileBackend, SealingKey, Snapshot, Staging, Transaction,
+    WalEntry,
+};
+#[cfg(feature = "sgx")]
+use sgx_tse::{rsgx_get_key, rsgx_self_report};
+#[cfg(feature = "sgx")]
+use sgx_types::{
+    sgx_attributes_t, sgx_key_id_t, sgx_key_request_t, SGX_FLAGS_DEBUG, SGX_FLAGS_INITTED,
+    SGX_KEYPOLICY_MRSIGNER, SGX_KEYSELECT_SEAL,
+};
+#[cfg(feature = "software-enclave")]
+use std::sync::RwLock;
+#[cfg(feature = "sgx")]
+use std::sync::SgxRwLock as RwLock;
+use std::{prelude::v1::*, sync::Arc};
+
+/// Fixed key ids so that the same sealing keys are derived across restarts.
+const USER_STATE_KEY_ID: [u8; 32] = *b"anonify-user-state-sealing-key00";
+const NOTIFICATION_KEY_ID: [u8; 32] = *b"anonify-notify-sealing-key000000";
+const SENDER_GENERATION_KEY_ID: [u8; 32] = *b"anonify-sender-gen-sealing-key00";
+/// The directory under USER_STATE_DIR where notification registrations are persisted.
+const NOTIFICATION_DIR: &str = "notification";
+/// The directory under USER_STATE_DIR where the generation of the sender keychain is persisted.
+const SENDER_GENERATION_DIR: &str = "sender_generation";
+#[cfg(feature = "sgx")]
+const TSEAL_DEFAULT_MISCMASK: u32 = 0xF000_0000;
+/// The table holding the hash chain over the consumed payloads under the empty key.
+pub const PAYLOADS_HASH_TABLE: &str = "payloads_hash";
+
+/// A write-ahead store shared by the key-value databases in the enclave.
+/// Writes are staged by the open state transition until it is committed, and then they are appended
+/// to the backend as a single entry.
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    backend: Arc<dyn KvsBackend>,
+    staging: Staging,
+    committed: Arc<RwLock<Committed>>,
+    snapshot_interval: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Committed {
+    block_num: u64,
+    since_snapshot: u32,
+}
+
+impl StateStore {
+    pub fn new(backend: Arc<dyn KvsBackend>, snapshot_interval: u32) -> Self {
+        StateStore {
+            backend,
+            staging: Staging::default(),
+            committed: Arc::new(RwLock::new(Committed::default())),
+            snapshot_interval,
+        }
+    }
+
+    /// Create a store using USER_STATE_DIR. If it is not set, nothing is persisted.
+    pub fn from_env() -> Result<Self> {
+        let backend: Arc<dyn KvsBackend> = match &*USER_STATE_DIR {
+            Some(dir) => Arc::new(SealedFileBackend::new(
+                PJ_ROOT_DIR.join(dir),
+                sealing_key(USER_STATE_KEY_ID)?,
+            )?),
+            None => Arc::new(MemoryBackend),
+        };
+
+        Ok(Self::new(backend, *USER_STATE_SNAPSHOT_INTERVAL))
+    }
+
+    /// Open a state transition. Writes fail unless a transition is open.
+    pub fn begin(&self) -> Transaction<'_> {
+        self.staging.begin()
+    }
+
+    pub fn put(&self, table: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.staging.put(table, key, value).map_err(Into::into)
+    }
+
+    pub fn delete(&self, table: &str, key: Vec<u8>) -> Result<()> {
+        self.staging.delete(table, key).map_err(Into::into)
+    }
+
+    /// Stage the hash chain over the payloads consumed by the transition.
+    pub fn put_payloads_hash(&self, payloads_hash: PayloadsHash) -> Result<()> {
+        self.put(
+            PAYLOADS_HASH_TABLE,
+            vec![],
+            payloads_hash.as_bytes().to_vec(),
+        )
+    }
+
+    pub fn load(&self) -> Result<Option<Snapshot>> {
+        let snapshot = self.backend.load()?;
+        if let Some(s) = &snapshot {
+            self.committed.write().unwrap().block_num = s.block_num;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Append the writes staged by the transition to the backend.
+    /// Returns true if a snapshot should be taken.
+    pub fn commit(
+        &self,
+        transition: &mut Transaction<'_>,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> Result<bool> {
+        let writes = transition.take_writes();
+        let entry = WalEntry {
+            state_counter,
+            block_num,
+            writes,
+        };
+        self.backend.append(&entry)?;
+
+        let mut committed = self.committed.write().unwrap();
+        committed.block_num = block_num;
+        committed.since_snapshot += 1;
+        Ok(committed.since_snapshot >= self.snapshot_interval)
+    }
+
+    pub fn checkpoint(&self, snapshot: &Snapshot) -> Result<()> {
+        self.backend.checkpoint(snapshot)?;
+        let mut committed = self.committed.write().unwrap();
+        committed.block_num = snapshot.block_num;
+        committed.since_snapshot = 0;
+        Ok(())
+    }
+
+    /// The block number of the last committed state transition.
+    pub fn block_num(&self) -> u64 {
+        self.committed.read().unwrap().block_num
+    }
+}
+
+/// The hash chain over the payloads consumed until the snapshot.
+pub fn payloads_hash(snapshot: Option<&Snapshot>) -> Result<PayloadsHash> {
+    let hash = match snapshot
+        .and_then(|s| s.table(PAYLOADS_HASH_TABLE))
+        .and_then(|table| table.get(&[][..]))
+    {
+        Some(hash) => hash,
+        None => return Ok(PayloadsHash::default()),
+    };
+    if hash.len() != 32 {
+        return Err(anyhow!("Invalid length of the payloads hash: {}", hash.len()).into());
+    }
+
+    let mut res = [0u8; 32];
+    res.copy_from_slice(hash);
+    Ok(PayloadsHash::new(res))
+}
+
+/// Create a backend for notification registrations using USER_STATE_DIR.
+/// They are persisted apart from the states since they are not a part of state transitions.
+/// If USER_STATE_DIR is not set, nothing is persisted.
+pub fn notification_backend_from_env() -> Result<Arc<dyn KvsBackend>> {
+    backend_from_env(NOTIFICATION_DIR, NOTIFICATION_KEY_ID)
+}
+
+/// Create a backend for the generation of the sender keychain using USER_STATE_DIR.
+/// It is persisted apart from the states since sending messages is not a part of state transitions.
+/// If USER_STATE_DIR is not set, nothing is persisted.
+pub fn sender_generation_backend_from_env() -> Result<Arc<dyn KvsBackend>> {
+    backend_from_env(SENDER_GENERATION_DIR, SENDER_GENERATION_KEY_ID)
+}
+
+fn backend_from_env(sub_dir: &str, key_id: [u8; 32]) -> Result<Arc<dyn KvsBackend>> {
+    let backend: Arc<dyn KvsBackend> = match &*USER_STATE_DIR {
+        Some(dir) => Arc::new(SealedFileBackend::new(
+            PJ_ROOT_DIR.join(dir).join(sub_dir),
+            sealing_key(key_id)?,
+        )?),
+        None => Arc::new(MemoryBackend),
+    };
+
+    Ok(backend)
+}
+
+/// Derive the sealing key from the SGX seal key bound to MRSIGNER,
+/// so that the persisted states can be loaded by upgraded enclaves of the same signer.
+#[cfg(feature = "sgx")]
+fn sealing_key(key_id: [u8; 32]) -> Result<SealingKey> {
+    let report = rsgx_self_report();
+    let key_request = sgx_key_request_t {
+        key_name: SGX_KEYSELECT_SEAL,
+        key_policy: SGX_KEYPOLICY_MRSIGNER,
+        isv_svn: report.body.isv_svn,
+        cpu_svn: report.body.cpu_svn,
+        attribute_mask: sgx_attributes_t {
+            flags: SGX_FLAGS_INITTED | SGX_FLAGS_DEBUG,
+            xfrm: 0,
+        },
+        key_id: sgx_key_id_t { id: key_id },
+        misc_mask: TSEAL_DEFAULT_MISCMASK,
+        config_svn: report.body.config_svn,
+        ..Default::default()
+    };
+    let key = rsgx_get_key(&key_request)
+        .map_err(|e| anyhow!("Failed to get the seal key: {:?}", e))?;
+
+    Ok(SealingKey::derive(&key[..]))
+}
+
+/// Software enclaves have no seal key, so the sealing key is derived from the key id only
+/// and the persisted states are not protected.
+#[cfg(feature = "software-enclave")]
+fn sealing_key(key_id: [u8; 32]) -> Result<SealingKey> {
+    Ok(SealingKey::derive(&key_id[..]))
+}
@@ -1,23 +1,68 @@
+use super::store::StateStore;
 use crate::error::{EnclaveError, Result};
 use frame_common::{crypto::AccountId, state_types::UserCounter};
+use frame_kvs::Snapshot;
+#[cfg(feature = "software-enclave")]
+use std::sync::RwLock;
+#[cfg(feature = "sgx")]
+use std::sync::SgxRwLock as RwLock;
 use std::{
-    collections::hash_map::HashMap,
+    collections::{hash_map::HashMap, BTreeMap},
     prelude::v1::*,
-    sync::{Arc, SgxRwLock},
+    sync::Arc,
 };
 
+pub const USER_COUNTER_TABLE: &str = "user_counter";
+
 /// A counter that guarantees idempotency and order of messages from users.
 /// Verifying that it is incremented by 1 at the time of state transitions.
 #[derive(Debug, Clone)]
-pub struct UserCounterDB(Arc<SgxRwLock<HashMap<AccountId, UserCounter>>>);
+pub struct UserCounterDB {
+    db: Arc<RwLock<HashMap<AccountId, UserCounter>>>,
+    store: StateStore,
+}
 
 impl UserCounterDB {
-    pub fn new() -> Self {
-        UserCounterDB(Arc::new(SgxRwLock::new(HashMap::new())))
+    pub fn new(store: StateStore) -> Self {
+        UserCounterDB {
+            db: Arc::new(RwLock::new(HashMap::new())),
+            store,
+        }
+    }
+
+    /// Restore the counters from the persisted snapshot.
+    pub fn restore(store: StateStore, snapshot: Option<&Snapshot>) -> Result<Self> {
+        let db = match snapshot.and_then(|s| s.table(USER_COUNTER_TABLE)) {
+            Some(table) => Self::decode_table(table)?,
+            None => HashMap::new(),
+        };
+
+        Ok(UserCounterDB {
+            db: Arc::new(RwLock::new(db)),
+            store,
+        })
+    }
+
+    /// Replace all counters with the table without staging writes.
+    /// The caller is responsible for persisting the table.
+    pub fn replace(&self, table: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<()> {
+        let db = Self::decode_table(table)?;
+        *self.db.write().unwrap() = db;
+        Ok(())
+    }
+
+    fn decode_table(
+        table: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<HashMap<AccountId, UserCounter>> {
+        let mut db = HashMap::new();
+        for (k, v) in table {
+            db.insert(bincode::deserialize(k)?, bincode::deserialize(v)?);
+        }
+        Ok(db)
     }
 
     pub fn increment(&self, user: AccountId, received: UserCounter) -> Result<()> {
-        let mut db = self.0.write().unwrap();
+        let mut db = self.db.write().unwrap();
         let curr_counter = db.get(&user).map(|e| *e).unwrap_or_default();
         if !curr_counter.is_increment(received) {
             return Err(EnclaveError::InvalidUserCounter {
@@ -26,7 +71,77 @@ impl UserCounterDB {
             });
         }
 
+        // Staging fails unless a state transition is open, and then the counter is left as it is.
+        self.store.put(
+            USER_COUNTER_TABLE,
+            bincode::serialize(&user)?,
+            bincode::serialize(&received)?,
+        )?;
         db.insert(user, received);
         Ok(())
     }
+
+    pub fn to_table(&self) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let mut table = BTreeMap::new();
+        for (k, v) in self.db.read().unwrap().iter() {
+            table.insert(bincode::serialize(k)?, bincode::serialize(v)?);
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(all(feature = "sgx", debug_assertions))]
+pub(crate) mod tests {
+    use super::*;
+    use frame_kvs::MemoryBackend;
+    use test_utils::*;
+
+    pub(crate) fn run_tests() -> bool {
+        run_tests!(
+            test_stage_increment_in_transition,
+            test_reject_increment_outside_transition,
+            test_discard_staged_increment,
+        )
+    }
+
+    fn counter_db() -> (UserCounterDB, StateStore) {
+        let store = StateStore::new(Arc::new(MemoryBackend), 10);
+        (UserCounterDB::new(store.clone()), store)
+    }
+
+    fn test_stage_increment_in_transition() {
+        let (db, store) = counter_db();
+        let user = AccountId([1u8; 20]);
+
+        let mut transition = store.begin();
+        db.increment(user, UserCounter::new(1)).unwrap();
+        assert_eq!(transition.take_writes().len(), 1);
+        assert_eq!(db.to_table().unwrap().len(), 1);
+    }
+
+    fn test_reject_increment_outside_transition() {
+        let (db, store) = counter_db();
+        let user = AccountId([1u8; 20]);
+
+        assert!(db.increment(user, UserCounter::new(1)).is_err());
+        assert!(db.to_table().unwrap().is_empty());
+
+        // The counter is left as it is, so the same one is accepted in a transition.
+        let mut transition = store.begin();
+        db.increment(user, UserCounter::new(1)).unwrap();
+        assert_eq!(transition.take_writes().len(), 1);
+    }
+
+    fn test_discard_staged_increment() {
+        let (db, store) = counter_db();
+        let user = AccountId([1u8; 20]);
+
+        {
+            let _transition = store.begin();
+            db.increment(user, UserCounter::new(1)).unwrap();
+        }
+
+        let mut transition = store.begin();
+        assert!(transition.take_writes().is_empty());
+    }
 }
@@ -1,14 +1,25 @@
+use super::store::StateStore;
+use crate::error::Result;
 use frame_common::{
     crypto::AccountId,
-    state_types::{MemId, StateType, UpdatedState},
+    state_types::{MemId, StateRoot, StateType, UpdatedState},
 };
+use frame_kvs::{merkle::MerkleTree, Snapshot};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "software-enclave")]
+use std::sync::RwLock;
+#[cfg(feature = "sgx")]
+use std::sync::SgxRwLock as RwLock;
 use std::{
-    collections::hash_map::HashMap,
+    collections::{hash_map::HashMap, BTreeMap},
     prelude::v1::*,
-    sync::{Arc, SgxRwLock},
+    sync::Arc,
 };
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub const USER_STATE_TABLE: &str = "user_state";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
 pub struct DBKey((AccountId, MemId));
 
 // TODO: AccountId+MemId is not sufficient size for hash digest in terms of collision resistance.
@@ -16,19 +27,64 @@ impl DBKey {
     pub fn new(account_id: AccountId, mem_id: MemId) -> Self {
         DBKey((account_id, mem_id))
     }
+
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(&self).unwrap() // must not fail
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct UserStateDB(Arc<SgxRwLock<HashMap<DBKey, StateType>>>);
+pub struct UserStateDB {
+    db: Arc<RwLock<HashMap<DBKey, StateType>>>,
+    // Must be locked after `db` so that the tree always reflects the states.
+    tree: Arc<RwLock<MerkleTree>>,
+    store: StateStore,
+}
 
 impl UserStateDB {
-    pub fn new() -> Self {
-        UserStateDB(Arc::new(SgxRwLock::new(HashMap::new())))
+    pub fn new(store: StateStore) -> Self {
+        UserStateDB {
+            db: Arc::new(RwLock::new(HashMap::new())),
+            tree: Arc::new(RwLock::new(MerkleTree::default())),
+            store,
+        }
+    }
+
+    /// Restore the states from the persisted snapshot.
+    pub fn restore(store: StateStore, snapshot: Option<&Snapshot>) -> Result<Self> {
+        let (db, tree) = match snapshot.and_then(|s| s.table(USER_STATE_TABLE)) {
+            Some(table) => (Self::decode_table(table)?, MerkleTree::from_table(table)),
+            None => (HashMap::new(), MerkleTree::default()),
+        };
+
+        Ok(UserStateDB {
+            db: Arc::new(RwLock::new(db)),
+            tree: Arc::new(RwLock::new(tree)),
+            store,
+        })
+    }
+
+    /// Replace all states with the table without staging writes.
+    /// The caller is responsible for persisting the table.
+    pub fn replace(&self, table: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<()> {
+        let db = Self::decode_table(table)?;
+        let mut tmp = self.db.write().unwrap();
+        *self.tree.write().unwrap() = MerkleTree::from_table(table);
+        *tmp = db;
+        Ok(())
+    }
+
+    fn decode_table(table: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<HashMap<DBKey, StateType>> {
+        let mut db = HashMap::new();
+        for (k, v) in table {
+            db.insert(bincode::deserialize(k)?, StateType::new(v.clone()));
+        }
+        Ok(db)
     }
 
     pub fn get(&self, account_id: AccountId, mem_id: MemId) -> StateType {
         let key = DBKey::new(account_id, mem_id);
-        match self.0.read().unwrap().get(&key) {
+        match self.db.read().unwrap().get(&key) {
             Some(v) => v.clone(),
             None => StateType::default(),
         }
@@ -36,27 +92,56 @@ impl UserStateDB {
 
     pub fn values(&self) -> Vec<StateType> {
         let mut acc = vec![];
-        for v in self.0.read().unwrap().values() {
+        for v in self.db.read().unwrap().values() {
             acc.push(v.clone());
         }
         acc
     }
 
-    pub fn insert_by_updated_state(&self, updated_state: UpdatedState<StateType>) {
-        let mut tmp = self.0.write().unwrap();
-        let key = DBKey::new(updated_state.account_id, updated_state.mem_id);
-        tmp.insert(key, updated_state.state);
+    pub fn insert_by_updated_state(&self, updated_state: UpdatedState<StateType>) -> Result<()> {
+        self.insert(
+            updated_state.account_id,
+            updated_state.mem_id,
+            updated_state.state,
+        )
     }
 
-    pub fn insert(&self, account_id: AccountId, mem_id: MemId, state: StateType) {
-        let mut tmp = self.0.write().unwrap();
+    /// Fails unless a state transition is open to stage the write, and then the state is left as it is.
+    pub fn insert(&self, account_id: AccountId, mem_id: MemId, state: StateType) -> Result<()> {
+        let mut tmp = self.db.write().unwrap();
         let key = DBKey::new(account_id, mem_id);
+        let encoded = key.encode();
+        self.store
+            .put(USER_STATE_TABLE, encoded.clone(), state.as_bytes().to_vec())?;
+        self.tree.write().unwrap().insert(encoded, state.as_bytes());
         tmp.insert(key, state);
+        Ok(())
     }
 
-    pub fn delete(&self, account_id: AccountId, mem_id: MemId) {
-        let mut tmp = self.0.write().unwrap();
+    /// Fails unless a state transition is open to stage the write, and then the state is left as it is.
+    pub fn delete(&self, account_id: AccountId, mem_id: MemId) -> Result<()> {
+        let mut tmp = self.db.write().unwrap();
         let key = DBKey::new(account_id, mem_id);
+        let encoded = key.encode();
+        self.store.delete(USER_STATE_TABLE, encoded.clone())?;
+        self.tree.write().unwrap().remove(&encoded);
         tmp.remove(&key);
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.db
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.encode(), v.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// The root of the Merkle tree over all states in the order of the encoded keys.
+    /// Leaf hashes are updated on each write, so this only rehashes the inner nodes.
+    pub fn state_root(&self) -> StateRoot {
+        let _db = self.db.read().unwrap();
+        self.tree.write().unwrap().root()
     }
 }
@@ -1,9 +1,23 @@
-#![cfg_attr(not(target_env = "sgx"), no_std)]
+#![cfg_attr(all(feature = "sgx", not(target_env = "sgx")), no_std)]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
 
-#[cfg(not(target_env = "sgx"))]
+#[cfg(all(feature = "sgx", not(target_env = "sgx")))]
 #[macro_use]
 extern crate sgx_tstd as std;
+#[cfg(feature = "software-enclave")]
+extern crate anyhow_std as anyhow;
+#[cfg(feature = "software-enclave")]
+extern crate bincode_std as bincode;
+#[cfg(feature = "software-enclave")]
+extern crate rand_core_std as rand_core;
+#[cfg(feature = "software-enclave")]
+extern crate serde_json_std as serde_json;
+#[cfg(feature = "sgx")]
+extern crate serde_sgx as serde;
+#[cfg(feature = "software-enclave")]
+extern crate serde_std as serde;
+#[cfg(feature = "software-enclave")]
+extern crate thiserror_std as thiserror;
 
 #[cfg(feature = "backup-enable")]
 mod backup;
@@ -15,24 +29,33 @@ mod group_key;
 mod handshake;
 mod kvs;
 mod notify;
+mod snapshot;
 
 pub mod workflow {
     #[cfg(feature = "backup-enable")]
     pub use crate::backup::{PathSecretBackupper, PathSecretRecoverer};
     pub use crate::commands::{CmdReceiver, CmdSender};
-    pub use crate::context::{GetState, ReportRegistration};
+    pub use crate::context::{
+        GetRuntimeSchema, GetState, GetStateCounter, ReportRegistration,
+        TrustedMeasurementsReloader,
+    };
     pub use crate::enclave_key::EncryptionKeyGetter;
     pub use crate::handshake::{HandshakeReceiver, HandshakeSender, JoinGroupSender};
-    pub use crate::notify::RegisterNotification;
+    pub use crate::notify::{RegisterNotification, UnregisterNotification};
+    pub use crate::snapshot::{StateSnapshotExporter, StateSnapshotImporter};
 }
 
-#[cfg(debug_assertions)]
+#[cfg(all(feature = "sgx", debug_assertions))]
 pub mod tests {
     use super::*;
     use std::prelude::v1::*;
     use test_utils::*;
 
     pub fn run_tests() -> bool {
-        check_all_passed!(notify::tests::run_tests(),)
+        check_all_passed!(
+            notify::tests::run_tests(),
+            kvs::user_counter::tests::run_tests(),
+            group_key::tests::run_tests(),
+        )
     }
 }
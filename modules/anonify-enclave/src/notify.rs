@@ -1,36 +1,159 @@
+use crate::{error::Result, kvs::store::notification_backend_from_env};
 use anonify_ecall_types::*;
+use anyhow::ensure;
 use frame_common::{crypto::AccountId, state_types::StateType, AccessPolicy};
 use frame_enclave::EnclaveEngine;
+use frame_kvs::{KvsBackend, MemoryBackend, Snapshot};
 use frame_runtime::traits::*;
-use frame_sodium::SodiumCiphertext;
+use frame_sodium::{SodiumCiphertext, SodiumPubKey};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "software-enclave")]
+use std::sync::RwLock;
+#[cfg(feature = "sgx")]
+use std::sync::SgxRwLock as RwLock;
 use std::{
-    collections::HashSet,
-    sync::{Arc, SgxRwLock},
+    collections::{BTreeMap, HashMap},
+    prelude::v1::*,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+pub const NOTIFICATION_TABLE: &str = "notification";
+/// The longest time in seconds a registration is valid for,
+/// which bounds how long a leaked registration can be replayed after unregistering.
+const MAX_REGISTRATION_TTL_SECS: u64 = 3600;
+/// The longest time in seconds an unregistration request is valid for,
+/// which bounds how long a leaked request can be replayed.
+const MAX_UNREGISTER_TTL_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "crate::serde")]
+struct Registration {
+    notification_key: SodiumPubKey,
+    expires_at: u64,
+}
+
+impl Registration {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// Registered account_ids with the keys which their notified states are encrypted to.
+/// Every change of the registrations is persisted, and expired ones are dropped.
 #[derive(Debug, Clone)]
 pub struct Notifier {
-    account_ids: Arc<SgxRwLock<HashSet<AccountId>>>,
+    registrations: Arc<RwLock<HashMap<AccountId, Registration>>>,
+    backend: Arc<dyn KvsBackend>,
 }
 
 impl Notifier {
     pub fn new() -> Self {
-        let account_ids = HashSet::new();
         Notifier {
-            account_ids: Arc::new(SgxRwLock::new(account_ids)),
+            registrations: Arc::new(RwLock::new(HashMap::new())),
+            backend: Arc::new(MemoryBackend),
         }
     }
 
-    pub fn register(&self, account_id: AccountId) -> bool {
-        let mut tmp = self.account_ids.write().unwrap();
-        tmp.insert(account_id)
+    /// Restore the registrations persisted under USER_STATE_DIR.
+    pub fn from_env() -> Result<Self> {
+        Self::load(notification_backend_from_env()?)
+    }
+
+    /// Restore the registrations persisted in the backend.
+    fn load(backend: Arc<dyn KvsBackend>) -> Result<Self> {
+        let mut registrations = HashMap::new();
+        if let Some(table) = backend
+            .load()?
+            .as_ref()
+            .and_then(|s| s.table(NOTIFICATION_TABLE))
+        {
+            let now = now();
+            for (k, v) in table {
+                let registration: Registration = bincode::deserialize(v)?;
+                if !registration.is_expired(now) {
+                    registrations.insert(bincode::deserialize(k)?, registration);
+                }
+            }
+        }
+
+        Ok(Notifier {
+            registrations: Arc::new(RwLock::new(registrations)),
+            backend,
+        })
+    }
+
+    /// Returns false if the account_id has already been registered,
+    /// in which case the notification key and the expiry are replaced.
+    pub fn register(
+        &self,
+        account_id: AccountId,
+        notification_key: SodiumPubKey,
+        expires_at: u64,
+    ) -> Result<bool> {
+        let mut registrations = self.registrations.write().unwrap();
+        let now = now();
+        registrations.retain(|_, r| !r.is_expired(now));
+        let registration = Registration {
+            notification_key,
+            expires_at,
+        };
+        let is_new = registrations.insert(account_id, registration).is_none();
+        self.persist(&registrations)?;
+
+        Ok(is_new)
+    }
+
+    /// Returns false if the account_id has not been registered.
+    pub fn unregister(&self, account_id: &AccountId) -> Result<bool> {
+        let mut registrations = self.registrations.write().unwrap();
+        if registrations.remove(account_id).is_none() {
+            return Ok(false);
+        }
+        self.persist(&registrations)?;
+
+        Ok(true)
     }
 
     pub fn contains(&self, account_id: &AccountId) -> bool {
-        self.account_ids.read().unwrap().contains(&account_id)
+        self.notification_key(account_id).is_some()
+    }
+
+    pub fn notification_key(&self, account_id: &AccountId) -> Option<SodiumPubKey> {
+        self.registrations
+            .read()
+            .unwrap()
+            .get(account_id)
+            .filter(|r| !r.is_expired(now()))
+            .map(|r| r.notification_key.clone())
+    }
+
+    /// Replace the persisted registrations with the current ones.
+    /// The registrations are so small that they are checkpointed as a whole on every change.
+    fn persist(&self, registrations: &HashMap<AccountId, Registration>) -> Result<()> {
+        let mut table = BTreeMap::new();
+        for (k, v) in registrations.iter() {
+            table.insert(bincode::serialize(k)?, bincode::serialize(v)?);
+        }
+        let mut snapshot = Snapshot::default();
+        snapshot
+            .tables
+            .insert(NOTIFICATION_TABLE.to_string(), table);
+
+        self.backend.checkpoint(&snapshot).map_err(Into::into)
     }
 }
 
+/// The current Unix time in seconds.
+/// Since it is provided by the host, the host can delay expiries,
+/// but it can't make notifications readable for others than the owners.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct RegisterNotification<AP: AccessPolicy> {
     ecall_input: input::RegisterNotification<AP>,
@@ -50,7 +173,26 @@ impl<AP: AccessPolicy> EnclaveEngine for RegisterNotification<AP> {
     }
 
     fn eval_policy(&self) -> anyhow::Result<()> {
-        self.ecall_input.access_policy().verify()
+        let access_policy = self.ecall_input.access_policy();
+        let expires_at = self.ecall_input.expires_at();
+        access_policy.verify()?;
+        access_policy.verify_challenge(&input::RegisterNotification::<AP>::challenge_message(
+            self.ecall_input.notification_key(),
+            expires_at,
+        ))?;
+        let now = now();
+        ensure!(
+            expires_at > now,
+            "The registration has already expired at {}",
+            expires_at
+        );
+        ensure!(
+            expires_at <= now + MAX_REGISTRATION_TTL_SECS,
+            "The registration must expire within {} seconds",
+            MAX_REGISTRATION_TTL_SECS
+        );
+
+        Ok(())
     }
 
     fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
@@ -59,30 +201,101 @@ impl<AP: AccessPolicy> EnclaveEngine for RegisterNotification<AP> {
         C: ContextOps<S = StateType> + Clone,
     {
         let account_id = self.ecall_input.access_policy().into_account_id();
-        enclave_context.set_notification(account_id);
+        let notification_key = self.ecall_input.notification_key().clone();
+        enclave_context.set_notification(
+            account_id,
+            notification_key,
+            self.ecall_input.expires_at(),
+        )?;
 
         Ok(output::Empty::default())
     }
 }
 
-#[cfg(debug_assertions)]
+#[derive(Debug, Clone, Default)]
+pub struct UnregisterNotification<AP: AccessPolicy> {
+    ecall_input: input::UnregisterNotification<AP>,
+}
+
+impl<AP: AccessPolicy> EnclaveEngine for UnregisterNotification<AP> {
+    type EI = SodiumCiphertext;
+    type EO = output::Empty;
+
+    fn decrypt<C>(ciphertext: Self::EI, enclave_context: &C) -> anyhow::Result<Self>
+    where
+        C: ContextOps<S = StateType> + Clone,
+    {
+        let buf = enclave_context.decrypt(ciphertext)?;
+        let ecall_input = serde_json::from_slice(&buf[..])?;
+        Ok(Self { ecall_input })
+    }
+
+    fn eval_policy(&self) -> anyhow::Result<()> {
+        let access_policy = self.ecall_input.access_policy();
+        let expires_at = self.ecall_input.expires_at();
+        access_policy.verify()?;
+        access_policy.verify_challenge(&input::UnregisterNotification::<AP>::challenge_message(
+            expires_at,
+        ))?;
+        let now = now();
+        ensure!(
+            expires_at > now,
+            "The request has already expired at {}",
+            expires_at
+        );
+        ensure!(
+            expires_at <= now + MAX_UNREGISTER_TTL_SECS,
+            "The request must expire within {} seconds",
+            MAX_UNREGISTER_TTL_SECS
+        );
+
+        Ok(())
+    }
+
+    fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        let account_id = self.ecall_input.access_policy().into_account_id();
+        enclave_context.unset_notification(&account_id)?;
+
+        Ok(output::Empty::default())
+    }
+}
+
+#[cfg(all(feature = "sgx", debug_assertions))]
 pub(crate) mod tests {
     use super::*;
     use ed25519_dalek::{
-        PublicKey, Signature, SignatureError, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
+        Keypair, PublicKey, SecretKey, Signature, SignatureError, PUBLIC_KEY_LENGTH,
+        SIGNATURE_LENGTH,
     };
-    use frame_common::crypto::Ed25519ChallengeResponse;
-    use std::{string::String, vec::Vec};
+    use frame_common::{
+        crypto::{Ed25519ChallengeResponse, Sha256},
+        traits::Hash256,
+    };
+    use frame_config::PJ_ROOT_DIR;
+    use frame_kvs::{SealedFileBackend, SealingKey};
+    use std::{fs, string::String, vec::Vec};
     use test_utils::*;
 
     pub(crate) fn run_tests() -> bool {
-        run_tests!(test_notifier,)
+        run_tests!(
+            test_notifier,
+            test_expired_notification,
+            test_persisted_registrations,
+            test_register_challenge,
+            test_unregister_challenge,
+        )
     }
 
     fn test_notifier() {
         let notifier = Notifier::new();
         let access_policy = build_access_right().unwrap();
         let account_id = access_policy.verified_account_id().unwrap();
+        let notification_key = SodiumPubKey::from_bytes(&[1u8; 32]).unwrap();
+        let expires_at = now() + 60;
 
         assert!(
             !notifier.contains(&account_id),
@@ -90,7 +303,9 @@ pub(crate) mod tests {
             account_id
         );
         assert!(
-            notifier.register(account_id.clone()),
+            notifier
+                .register(account_id.clone(), notification_key.clone(), expires_at)
+                .unwrap(),
             "Failed to register account_id: {:?}",
             account_id
         );
@@ -99,6 +314,145 @@ pub(crate) mod tests {
             "notifier doesn't contain registered account_id: {:?}",
             account_id
         );
+        assert_eq!(
+            notifier.notification_key(&account_id).map(|k| k.to_bytes()),
+            Some(notification_key.to_bytes())
+        );
+
+        assert!(notifier.unregister(&account_id).unwrap());
+        assert!(
+            !notifier.contains(&account_id),
+            "notifier contains unregistered account_id: {:?}",
+            account_id
+        );
+        assert!(!notifier.unregister(&account_id).unwrap());
+    }
+
+    fn test_expired_notification() {
+        let notifier = Notifier::new();
+        let account_id = AccountId::from_array([1u8; 20]);
+        let notification_key = SodiumPubKey::from_bytes(&[1u8; 32]).unwrap();
+
+        notifier
+            .register(account_id, notification_key, now() - 1)
+            .unwrap();
+        assert!(
+            !notifier.contains(&account_id),
+            "notifier contains expired account_id: {:?}",
+            account_id
+        );
+    }
+
+    fn test_persisted_registrations() {
+        let dir = PJ_ROOT_DIR.join("test_persisted_registrations");
+        let _ = fs::remove_dir_all(&dir);
+        let backend = || -> Arc<dyn KvsBackend> {
+            Arc::new(SealedFileBackend::new(&dir, SealingKey::new([1u8; 32])).unwrap())
+        };
+        let alice = AccountId::from_array([1u8; 20]);
+        let bob = AccountId::from_array([2u8; 20]);
+        let notification_key = SodiumPubKey::from_bytes(&[1u8; 32]).unwrap();
+
+        let notifier = Notifier::load(backend()).unwrap();
+        notifier
+            .register(alice, notification_key.clone(), now() + 60)
+            .unwrap();
+        notifier
+            .register(bob, notification_key.clone(), now() + 60)
+            .unwrap();
+        notifier.unregister(&bob).unwrap();
+
+        // The registrations are restored after restarting
+        let restarted = Notifier::load(backend()).unwrap();
+        assert_eq!(
+            restarted.notification_key(&alice).map(|k| k.to_bytes()),
+            Some(notification_key.to_bytes())
+        );
+        assert!(
+            !restarted.contains(&bob),
+            "notifier contains unregistered account_id after restarting: {:?}",
+            bob
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn test_register_challenge() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        let notification_key = SodiumPubKey::from_bytes(&[1u8; 32]).unwrap();
+        let register = |expires_at| {
+            let msg = input::RegisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+                &notification_key,
+                expires_at,
+            );
+            let challenge = Sha256::hash(&msg).as_array();
+            let access_policy =
+                Ed25519ChallengeResponse::new(keypair.sign(&challenge), keypair.public, challenge);
+            RegisterNotification {
+                ecall_input: input::RegisterNotification::new(
+                    access_policy,
+                    notification_key.clone(),
+                    expires_at,
+                ),
+            }
+        };
+
+        assert!(register(now() + 60).eval_policy().is_ok());
+        // Expired
+        assert!(register(now() - 1).eval_policy().is_err());
+        // Too far in the future to be replayed after unregistering
+        assert!(register(now() + MAX_REGISTRATION_TTL_SECS + 60)
+            .eval_policy()
+            .is_err());
+    }
+
+    fn test_unregister_challenge() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        let sign = |msg: &[u8]| {
+            let challenge = Sha256::hash(msg).as_array();
+            Ed25519ChallengeResponse::new(keypair.sign(&challenge), keypair.public, challenge)
+        };
+        let unregister = |access_policy, expires_at| UnregisterNotification {
+            ecall_input: input::UnregisterNotification::new(access_policy, expires_at),
+        };
+        let expires_at = now() + 60;
+
+        let msg = input::UnregisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+            expires_at,
+        );
+        assert!(unregister(sign(&msg), expires_at).eval_policy().is_ok());
+
+        // The signature of a registration can't be replayed to unregister
+        let register_msg =
+            input::RegisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+                &SodiumPubKey::from_bytes(&[1u8; 32]).unwrap(),
+                expires_at,
+            );
+        assert!(unregister(sign(&register_msg), expires_at)
+            .eval_policy()
+            .is_err());
+        // Neither can a signature over an arbitrary challenge
+        assert!(unregister(sign(&[1u8; 32]), expires_at)
+            .eval_policy()
+            .is_err());
+
+        // Expired
+        let expires_at = now() - 1;
+        let msg = input::UnregisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+            expires_at,
+        );
+        assert!(unregister(sign(&msg), expires_at).eval_policy().is_err());
+
+        // Too far in the future to be replayed
+        let expires_at = now() + MAX_UNREGISTER_TTL_SECS + 60;
+        let msg = input::UnregisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+            expires_at,
+        );
+        assert!(unregister(sign(&msg), expires_at).eval_policy().is_err());
     }
 
     fn build_access_right() -> Result<Ed25519ChallengeResponse, SignatureError> {
@@ -0,0 +1,109 @@
+use crate::kvs::user_state::USER_STATE_TABLE;
+use anonify_ecall_types::*;
+use anyhow::{anyhow, ensure};
+use frame_common::state_types::StateType;
+use frame_enclave::EnclaveEngine;
+use frame_kvs::{merkle, SealingKey, Snapshot};
+use frame_runtime::traits::*;
+
+const STATE_SNAPSHOT_LABEL: &[u8] = b"state snapshot";
+
+fn snapshot_key<GK: GroupKeyOps>(group_key: &GK) -> anyhow::Result<SealingKey> {
+    let secret = group_key.exporter_secret(STATE_SNAPSHOT_LABEL)?;
+    Ok(SealingKey::derive(&secret))
+}
+
+/// An engine exporting all user states and counters for onboarding a new node.
+/// The snapshot is encrypted with a secret derived from the current epoch of the group key,
+/// so only members of the group can import it.
+#[derive(Debug, Clone, Default)]
+pub struct StateSnapshotExporter;
+
+impl EnclaveEngine for StateSnapshotExporter {
+    type EI = input::Empty;
+    type EO = output::ReturnStateSnapshot;
+
+    fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        // Hold the group key so that the epoch doesn't change while exporting.
+        let group_key = &*enclave_context.read_group_key();
+        let (snapshot, state_root) = enclave_context.export_snapshot()?;
+        let header = StateSnapshotHeader::new(
+            snapshot.state_counter,
+            snapshot.block_num,
+            state_root,
+            group_key.epoch(),
+        );
+
+        let sealed =
+            snapshot_key(group_key)?.seal(&bincode::serialize(&snapshot)?, &header.encode())?;
+
+        Ok(output::ReturnStateSnapshot::new(header, sealed))
+    }
+}
+
+/// An engine importing a state snapshot exported by another node in the same epoch.
+/// It returns the state counter and the block number to resume fetching events from.
+#[derive(Debug, Clone, Default)]
+pub struct StateSnapshotImporter {
+    ecall_input: input::ImportStateSnapshot,
+}
+
+impl EnclaveEngine for StateSnapshotImporter {
+    type EI = input::ImportStateSnapshot;
+    type EO = output::ReturnStateCounter;
+
+    fn decrypt<C>(ecall_input: Self::EI, _enclave_context: &C) -> anyhow::Result<Self>
+    where
+        C: ContextOps<S = StateType> + Clone,
+    {
+        Ok(Self { ecall_input })
+    }
+
+    fn handle<R, C>(self, enclave_context: &C, _max_mem_size: usize) -> anyhow::Result<Self::EO>
+    where
+        R: RuntimeExecutor<C, S = StateType>,
+        C: ContextOps<S = StateType> + Clone,
+    {
+        let group_key = &*enclave_context.read_group_key();
+        let header = self.ecall_input.header;
+        ensure!(
+            header.epoch == group_key.epoch(),
+            "The snapshot's epoch ({:?}) differs from the current group key's ({:?})",
+            header.epoch,
+            group_key.epoch()
+        );
+
+        let plaintext = snapshot_key(group_key)?
+            .unseal(&self.ecall_input.sealed, &header.encode())
+            .map_err(|e| anyhow!("Failed to decrypt the state snapshot: {:?}", e))?;
+        let snapshot: Snapshot = bincode::deserialize(&plaintext)?;
+        ensure!(
+            snapshot.state_counter == header.state_counter && snapshot.block_num == header.block_num,
+            "The snapshot doesn't match its header"
+        );
+
+        let state_root = snapshot
+            .table(USER_STATE_TABLE)
+            .map(merkle::table_root)
+            .unwrap_or_default();
+        ensure!(
+            state_root == header.state_root,
+            "The snapshot's state root ({:?}) differs from the header's ({:?})",
+            state_root,
+            header.state_root
+        );
+
+        enclave_context.import_snapshot(snapshot)?;
+
+        let (state_counter, block_num, payloads_hash) = enclave_context.committed_state_counter();
+        Ok(output::ReturnStateCounter::new(
+            state_counter,
+            block_num,
+            payloads_hash,
+        ))
+    }
+}
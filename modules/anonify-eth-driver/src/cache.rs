@@ -1,43 +1,814 @@
+use crate::{
+    dead_letter::DeadLetterQueue, error::Result, event::PayloadType, utils::write_atomically,
+};
+use frame_common::{
+    chain_types::ChainAddress as ContractAddr,
+    state_types::{PayloadsHash, StateCounter, StateRoot},
+};
+use frame_config::{EVENT_CACHE_PATH, EVENT_REORDER_LIMIT, EVENT_REORDER_TIMEOUT_SECS};
 use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tracing::info;
-use web3::types::Address as ContractAddr;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
 
 type BlockNum = u64;
+pub type BlockHash = [u8; 32];
+
+/// The number of recent state roots kept for each contract.
+const MAX_STATE_ROOTS: usize = 1024;
+/// The number of recent blocks whose hashes are kept for each contract to detect chain reorganizations.
+const MAX_BLOCK_HASHES: usize = 1024;
 
 /// Cache data from events for arrival guarantee and order guarantee.
-/// Unordered events are cached.
+/// Unordered events are cached until their preceding events are processed.
+/// If it is loaded from a file, the cursors and the checkpoints are persisted to it by `persist`.
 #[derive(Debug, Default, Clone)]
 pub struct EventCache {
     inner: Arc<RwLock<InnerEventCache>>,
+    path: Option<PathBuf>,
+    dead_letters: DeadLetterQueue,
 }
 
 impl EventCache {
+    /// Load the cache persisted in EVENT_CACHE_PATH and the dead letters persisted in DEAD_LETTER_PATH,
+    /// or keep them only in memory if they are not set.
+    pub fn from_env() -> Result<Self> {
+        let cache = match &*EVENT_CACHE_PATH {
+            Some(path) => Self::load(path)?,
+            None => Self::default(),
+        };
+
+        Ok(cache.set_dead_letters(DeadLetterQueue::from_env()?))
+    }
+
+    /// Load the cache persisted in the file, which is created by `persist` if it doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let inner = match fs::read(&path) {
+            Ok(bytes) => InnerEventCache::from_persisted(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => InnerEventCache::default(),
+            Err(e) => return Err(e.into()),
+        };
+        info!("Loaded the event cache from {:?}", path);
+
+        Ok(EventCache {
+            inner: Arc::new(RwLock::new(inner)),
+            path: Some(path),
+            dead_letters: DeadLetterQueue::default(),
+        })
+    }
+
+    pub fn set_dead_letters(mut self, dead_letters: DeadLetterQueue) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
     pub fn inner(&self) -> &Arc<RwLock<InnerEventCache>> {
         &self.inner
     }
+
+    /// The events skipped because of errors in the enclave
+    pub fn dead_letters(&self) -> &DeadLetterQueue {
+        &self.dead_letters
+    }
+
+    /// Write the cache to the file atomically, so that either the previous or the new one is left after a crash.
+    pub fn persist(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let bytes = serde_json::to_vec(&self.inner.read().to_persisted())?;
+
+        write_atomically(path, &bytes)
+    }
 }
 
 /// Do not implement `Clone` trait due to cache duplication.
 #[derive(Debug, Default)]
 pub struct InnerEventCache {
     block_num_counter: HashMap<ContractAddr, BlockNum>,
+    committed_state_counter: HashMap<ContractAddr, StateCounter>,
+    state_roots: HashMap<ContractAddr, BTreeMap<StateCounter, StateRoot>>,
+    block_hashes: HashMap<ContractAddr, BTreeMap<BlockNum, ScannedBlock>>,
+    checkpoints: HashMap<ContractAddr, Checkpoint>,
+    /// The events waiting for their preceding events, ordered by the state counter
+    /// and then by roster index, epoch and generation.
+    pending: HashMap<ContractAddr, BTreeMap<(StateCounter, PayloadType), Instant>>,
+    /// The lowest block of the dropped events, from which events are fetched again
+    refetch_from: HashMap<ContractAddr, BlockNum>,
+    reorder_limits: ReorderLimits,
+}
+
+/// The limits of the events waiting for their preceding events.
+#[derive(Debug, Clone, Copy)]
+pub struct ReorderLimits {
+    /// The number of waiting events for each contract
+    pub max_pending: usize,
+    /// The duration for which an event waits
+    pub max_wait: Duration,
+}
+
+impl Default for ReorderLimits {
+    fn default() -> Self {
+        ReorderLimits {
+            max_pending: *EVENT_REORDER_LIMIT,
+            max_wait: Duration::from_secs(*EVENT_REORDER_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// The last event processed by the enclave and the hash chain over all the processed payloads,
+/// which are compared with the enclave's ones after restarting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub block_num: BlockNum,
+    pub log_index: u64,
+    pub state_counter: StateCounter,
+    /// The same hash chain as the enclave computes over the payloads it consumed
+    pub payloads_hash: PayloadsHash,
+}
+
+impl Checkpoint {
+    fn next(
+        &self,
+        block_num: BlockNum,
+        log_index: u64,
+        state_counter: StateCounter,
+        payload: &[u8],
+    ) -> Self {
+        Checkpoint {
+            block_num,
+            log_index,
+            state_counter,
+            payloads_hash: self.payloads_hash.next(state_counter, payload),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedEventCache {
+    contracts: Vec<PersistedContract>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedContract {
+    contract_addr: ContractAddr,
+    next_block_num: Option<BlockNum>,
+    checkpoint: Option<Checkpoint>,
+    block_hashes: Vec<(BlockNum, ScannedBlock)>,
+}
+
+/// A block scanned for events, which is verified to be still on the chain before the next fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScannedBlock {
+    pub hash: BlockHash,
+    /// Whether the events in the block have been inserted into the enclave
+    pub has_events: bool,
 }
 
 impl InnerEventCache {
+    /// Set the block from which events are fetched next.
+    /// If any events are dropped, the cursor doesn't advance past the block of them,
+    /// so that they are fetched again.
     pub fn insert_next_block_num(
         &mut self,
         contract_addr: ContractAddr,
         block_num: BlockNum,
     ) -> Option<BlockNum> {
+        let block_num = match self.refetch_from.remove(&contract_addr) {
+            Some(dropped) if dropped < block_num => {
+                warn!(
+                    "Events are fetched again from the block {} to recover the dropped events",
+                    dropped
+                );
+                dropped
+            }
+            _ => block_num,
+        };
         info!("Insert: Cached block number: {}", block_num);
         self.block_num_counter.insert(contract_addr, block_num)
     }
 
-    pub fn get_latest_block_num(&self, contract_addr: ContractAddr) -> Option<BlockNum> {
-        let block_num = self.block_num_counter.get(&contract_addr).map(|e| *e);
+    pub fn get_latest_block_num(&self, contract_addr: &ContractAddr) -> Option<BlockNum> {
+        let block_num = self.block_num_counter.get(contract_addr).map(|e| *e);
         info!("Get: Cached block number: {:?}", block_num);
         block_num
     }
+
+    /// Resume fetching events from the block which the enclave's persisted states cover.
+    /// Since other events may be left unprocessed in the same block,
+    /// fetching restarts from that block and the events already committed are skipped.
+    /// If the checkpoint is behind the enclave, the hash chain continues from the enclave's one.
+    pub fn resume(
+        &mut self,
+        contract_addr: ContractAddr,
+        state_counter: StateCounter,
+        block_num: BlockNum,
+        payloads_hash: PayloadsHash,
+    ) {
+        info!(
+            "Resume: state counter: {:?}, block number: {}",
+            state_counter, block_num
+        );
+        self.block_num_counter
+            .insert(contract_addr.clone(), block_num);
+        self.committed_state_counter
+            .insert(contract_addr.clone(), state_counter);
+        let checkpoint = self.checkpoints.entry(contract_addr).or_default();
+        if checkpoint.state_counter != state_counter {
+            // The log index of the enclave's last event is unknown, but it is used only for investigation.
+            *checkpoint = Checkpoint {
+                block_num,
+                log_index: 0,
+                state_counter,
+                payloads_hash,
+            };
+        }
+    }
+
+    /// Returns true if the event has already been applied to the persisted states.
+    pub fn is_committed(&self, contract_addr: &ContractAddr, state_counter: StateCounter) -> bool {
+        self.committed_state_counter
+            .get(contract_addr)
+            .map_or(false, |committed| state_counter <= *committed)
+    }
+
+    /// Record that the enclave has consumed the state counter of the event.
+    /// The enclave increments its state counter even if it fails to process the event.
+    pub fn commit_state_counter(
+        &mut self,
+        contract_addr: ContractAddr,
+        state_counter: StateCounter,
+    ) {
+        let committed = self
+            .committed_state_counter
+            .entry(contract_addr)
+            .or_default();
+        if *committed < state_counter {
+            *committed = state_counter;
+        }
+    }
+
+    pub fn set_reorder_limits(&mut self, reorder_limits: ReorderLimits) {
+        self.reorder_limits = reorder_limits;
+    }
+
+    /// Buffer the event until its preceding events are processed.
+    /// If the buffer is full, the event furthest from being processed is dropped,
+    /// and it is fetched again by the next polling.
+    pub fn insert_pending(
+        &mut self,
+        contract_addr: ContractAddr,
+        payload: PayloadType,
+        now: Instant,
+    ) {
+        if self.is_committed(&contract_addr, payload.state_counter()) {
+            return;
+        }
+        let refetch_from = &mut self.refetch_from;
+        let pending = self.pending.entry(contract_addr.clone()).or_default();
+        pending
+            .entry((payload.state_counter(), payload))
+            .or_insert(now);
+        while pending.len() > self.reorder_limits.max_pending {
+            let furthest = pending
+                .keys()
+                .next_back()
+                .cloned()
+                .expect("pending must not be empty");
+            error!(
+                "A waiting event is dropped because too many events are waiting: state counter: {:?}, block number: {}",
+                furthest.0,
+                furthest.1.block_num()
+            );
+            refetch(refetch_from, &contract_addr, furthest.1.block_num());
+            pending.remove(&furthest);
+        }
+    }
+
+    /// Take the events which can be processed in order right after the committed state counter.
+    /// The events waiting longer than the limit are dropped, and they are fetched again by the next polling
+    /// in case their preceding events were missed.
+    pub fn take_ready(&mut self, contract_addr: &ContractAddr, now: Instant) -> Vec<PayloadType> {
+        let committed = self
+            .committed_state_counter
+            .get(contract_addr)
+            .copied()
+            .unwrap_or_default();
+        let max_wait = self.reorder_limits.max_wait;
+        let pending = match self.pending.get_mut(contract_addr) {
+            Some(pending) => pending,
+            None => return vec![],
+        };
+
+        let mut ready = vec![];
+        let mut next = committed.increment();
+        while let Some(key) = pending.keys().next().cloned() {
+            let state_counter = key.0;
+            if state_counter < next {
+                // Duplicated or already processed
+                pending.remove(&key);
+            } else if state_counter == next {
+                pending.remove(&key);
+                ready.push(key.1);
+                next = next.increment();
+            } else {
+                break;
+            }
+        }
+
+        let expired: Vec<_> = pending
+            .iter()
+            .filter(|(_, received_at)| now.duration_since(**received_at) >= max_wait)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            error!(
+                "A waiting event is dropped because the preceding events didn't arrive: state counter: {:?}, expected: {:?}, block number: {}",
+                key.0,
+                next,
+                key.1.block_num()
+            );
+            refetch(&mut self.refetch_from, contract_addr, key.1.block_num());
+            pending.remove(&key);
+        }
+        if !pending.is_empty() {
+            warn!(
+                "{} events are waiting for the event at the state counter {:?}",
+                pending.len(),
+                next
+            );
+        }
+
+        ready
+    }
+
+    /// The number of the events waiting for their preceding events.
+    pub fn pending_len(&self, contract_addr: &ContractAddr) -> usize {
+        self.pending.get(contract_addr).map_or(0, |p| p.len())
+    }
+
+    /// Record the state root computed by the enclave right after the transition at the state counter.
+    /// Only the most recent `MAX_STATE_ROOTS` roots are kept.
+    pub fn insert_state_root(
+        &mut self,
+        contract_addr: ContractAddr,
+        state_counter: StateCounter,
+        state_root: StateRoot,
+    ) {
+        let roots = self.state_roots.entry(contract_addr).or_default();
+        roots.insert(state_counter, state_root);
+        while roots.len() > MAX_STATE_ROOTS {
+            let oldest = *roots.keys().next().expect("roots must not be empty");
+            roots.remove(&oldest);
+        }
+    }
+
+    /// Get the state root at the state counter, or the latest one if the state counter is not specified.
+    pub fn get_state_root(
+        &self,
+        contract_addr: &ContractAddr,
+        state_counter: Option<StateCounter>,
+    ) -> Option<(StateCounter, StateRoot)> {
+        let roots = self.state_roots.get(contract_addr)?;
+        match state_counter {
+            Some(state_counter) => roots.get(&state_counter).map(|root| (state_counter, *root)),
+            None => roots.iter().next_back().map(|(sc, root)| (*sc, *root)),
+        }
+    }
+
+    /// Record the hash of the scanned block. Only the most recent `MAX_BLOCK_HASHES` blocks are kept.
+    pub fn insert_block_hash(
+        &mut self,
+        contract_addr: ContractAddr,
+        block_num: BlockNum,
+        hash: BlockHash,
+        has_events: bool,
+    ) {
+        let blocks = self.block_hashes.entry(contract_addr).or_default();
+        let block = blocks.entry(block_num).or_insert(ScannedBlock {
+            hash,
+            has_events: false,
+        });
+        if block.hash != hash {
+            *block = ScannedBlock {
+                hash,
+                has_events: false,
+            };
+        }
+        block.has_events |= has_events;
+        while blocks.len() > MAX_BLOCK_HASHES {
+            let oldest = *blocks.keys().next().expect("blocks must not be empty");
+            blocks.remove(&oldest);
+        }
+    }
+
+    /// The recorded blocks from the latest one.
+    pub fn get_block_hashes(&self, contract_addr: &ContractAddr) -> Vec<(BlockNum, ScannedBlock)> {
+        self.block_hashes
+            .get(contract_addr)
+            .map(|blocks| blocks.iter().rev().map(|(n, b)| (*n, *b)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Record that the event is consumed by the enclave, whether it succeeded or not,
+    /// chaining its payload into the payloads hash.
+    pub fn insert_checkpoint(
+        &mut self,
+        contract_addr: ContractAddr,
+        block_num: BlockNum,
+        log_index: u64,
+        state_counter: StateCounter,
+        payload: &[u8],
+    ) {
+        let checkpoint = self.checkpoints.entry(contract_addr).or_default();
+        *checkpoint = checkpoint.next(block_num, log_index, state_counter, payload);
+    }
+
+    pub fn get_checkpoint(&self, contract_addr: &ContractAddr) -> Option<Checkpoint> {
+        self.checkpoints.get(contract_addr).copied()
+    }
+
+    /// Fetch events again from the block, e.g. the one after the fork point of a chain reorganization.
+    /// The hashes of the blocks from it are discarded.
+    pub fn rewind(&mut self, contract_addr: ContractAddr, block_num: BlockNum) {
+        info!("Rewind: Cached block number: {}", block_num);
+        if let Some(blocks) = self.block_hashes.get_mut(&contract_addr) {
+            blocks.split_off(&block_num);
+        }
+        if let Some(pending) = self.pending.get_mut(&contract_addr) {
+            let reorged: Vec<_> = pending
+                .keys()
+                .filter(|(_, payload)| payload.block_num() >= block_num)
+                .cloned()
+                .collect();
+            for key in reorged {
+                pending.remove(&key);
+            }
+        }
+        self.block_num_counter.insert(contract_addr, block_num);
+    }
+}
+
+/// Record the block of the dropped event, so that the cursor is rewound to it.
+fn refetch(
+    refetch_from: &mut HashMap<ContractAddr, BlockNum>,
+    contract_addr: &ContractAddr,
+    block_num: BlockNum,
+) {
+    let refetch_from = refetch_from
+        .entry(contract_addr.clone())
+        .or_insert(block_num);
+    *refetch_from = cmp::min(*refetch_from, block_num);
+}
+
+impl InnerEventCache {
+    fn to_persisted(&self) -> PersistedEventCache {
+        let mut contract_addrs: Vec<&ContractAddr> = self
+            .block_num_counter
+            .keys()
+            .chain(self.checkpoints.keys())
+            .collect();
+        contract_addrs.sort();
+        contract_addrs.dedup();
+
+        let contracts = contract_addrs
+            .into_iter()
+            .map(|contract_addr| PersistedContract {
+                contract_addr: contract_addr.clone(),
+                next_block_num: self.persisted_next_block_num(contract_addr),
+                checkpoint: self.get_checkpoint(contract_addr),
+                block_hashes: self.get_block_hashes(contract_addr),
+            })
+            .collect();
+
+        PersistedEventCache { contracts }
+    }
+
+    /// The waiting and the dropped events are not persisted, so they are fetched again after restarting.
+    fn persisted_next_block_num(&self, contract_addr: &ContractAddr) -> Option<BlockNum> {
+        let next_block_num = self.block_num_counter.get(contract_addr).copied();
+        let pending_block_num = self
+            .pending
+            .get(contract_addr)
+            .and_then(|pending| pending.keys().map(|(_, payload)| payload.block_num()).min());
+        let dropped_block_num = self.refetch_from.get(contract_addr).copied();
+        vec![next_block_num, pending_block_num, dropped_block_num]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    fn from_persisted(persisted: PersistedEventCache) -> Self {
+        let mut inner = InnerEventCache::default();
+        for contract in persisted.contracts {
+            if let Some(next_block_num) = contract.next_block_num {
+                inner
+                    .block_num_counter
+                    .insert(contract.contract_addr.clone(), next_block_num);
+            }
+            if let Some(checkpoint) = contract.checkpoint {
+                inner
+                    .checkpoints
+                    .insert(contract.contract_addr.clone(), checkpoint);
+            }
+            inner.block_hashes.insert(
+                contract.contract_addr,
+                contract.block_hashes.into_iter().collect(),
+            );
+        }
+
+        inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_hashes() {
+        let contract_addr = ContractAddr::default();
+        let mut cache = InnerEventCache::default();
+        cache.insert_block_hash(contract_addr.clone(), 10, [1u8; 32], true);
+        cache.insert_block_hash(contract_addr.clone(), 12, [2u8; 32], false);
+        // A block scanned again keeps whether it has events
+        cache.insert_block_hash(contract_addr.clone(), 10, [1u8; 32], false);
+
+        let blocks = cache.get_block_hashes(&contract_addr);
+        assert_eq!(
+            blocks,
+            vec![
+                (
+                    12,
+                    ScannedBlock {
+                        hash: [2u8; 32],
+                        has_events: false
+                    }
+                ),
+                (
+                    10,
+                    ScannedBlock {
+                        hash: [1u8; 32],
+                        has_events: true
+                    }
+                ),
+            ]
+        );
+
+        cache.rewind(contract_addr.clone(), 11);
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(11));
+        assert_eq!(cache.get_block_hashes(&contract_addr).len(), 1);
+
+        for block_num in 0..MAX_BLOCK_HASHES as u64 + 10 {
+            cache.insert_block_hash(contract_addr.clone(), block_num, [0u8; 32], false);
+        }
+        let blocks = cache.get_block_hashes(&contract_addr);
+        assert_eq!(blocks.len(), MAX_BLOCK_HASHES);
+        assert_eq!(blocks[0].0, MAX_BLOCK_HASHES as u64 + 9);
+    }
+
+    fn temp_path() -> PathBuf {
+        let mut suffix = [0u8; 8];
+        frame_common::crypto::rand_assign(&mut suffix).unwrap();
+        std::env::temp_dir().join(format!("anonify-event-cache-{}.json", hex::encode(suffix)))
+    }
+
+    #[test]
+    fn test_persist_checkpoints() {
+        let contract_addr = ContractAddr::default();
+        let path = temp_path();
+
+        let cache = EventCache::load(&path).unwrap();
+        {
+            let mut inner = cache.inner().write();
+            inner.insert_next_block_num(contract_addr.clone(), 20);
+            inner.insert_block_hash(contract_addr.clone(), 19, [3u8; 32], true);
+            inner.insert_checkpoint(contract_addr.clone(), 19, 0, StateCounter::new(1), b"first");
+            inner.insert_checkpoint(
+                contract_addr.clone(),
+                19,
+                2,
+                StateCounter::new(2),
+                b"second",
+            );
+        }
+        let checkpoint = cache.inner().read().get_checkpoint(&contract_addr).unwrap();
+        assert_eq!(checkpoint.block_num, 19);
+        assert_eq!(checkpoint.log_index, 2);
+        assert_eq!(checkpoint.state_counter, StateCounter::new(2));
+        assert_eq!(
+            checkpoint.payloads_hash,
+            PayloadsHash::default()
+                .next(StateCounter::new(1), b"first")
+                .next(StateCounter::new(2), b"second")
+        );
+        // The hash chain depends on the order of the payloads
+        assert_ne!(
+            checkpoint.payloads_hash,
+            PayloadsHash::default()
+                .next(StateCounter::new(1), b"second")
+                .next(StateCounter::new(2), b"first")
+        );
+
+        cache.persist().unwrap();
+        let loaded = EventCache::load(&path).unwrap();
+        let inner = loaded.inner().read();
+        assert_eq!(inner.get_latest_block_num(&contract_addr), Some(20));
+        assert_eq!(inner.get_checkpoint(&contract_addr), Some(checkpoint));
+        assert_eq!(
+            inner.get_block_hashes(&contract_addr),
+            cache.inner().read().get_block_hashes(&contract_addr)
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_checkpoint() {
+        let contract_addr = ContractAddr::default();
+        let cache = EventCache::default();
+        let mut inner = cache.inner().write();
+        inner.insert_checkpoint(contract_addr.clone(), 10, 1, StateCounter::new(1), b"first");
+        let checkpoint = inner.get_checkpoint(&contract_addr).unwrap();
+
+        // The checkpoint agreeing with the enclave is kept
+        inner.resume(
+            contract_addr.clone(),
+            StateCounter::new(1),
+            10,
+            checkpoint.payloads_hash,
+        );
+        assert_eq!(inner.get_checkpoint(&contract_addr), Some(checkpoint));
+
+        // The hash chain continues from the enclave's one if the enclave is ahead
+        let enclave_hash = PayloadsHash::new([1u8; 32]);
+        inner.resume(
+            contract_addr.clone(),
+            StateCounter::new(3),
+            12,
+            enclave_hash,
+        );
+        inner.insert_checkpoint(
+            contract_addr.clone(),
+            13,
+            0,
+            StateCounter::new(4),
+            b"fourth",
+        );
+        let checkpoint = inner.get_checkpoint(&contract_addr).unwrap();
+        assert_eq!(checkpoint.state_counter, StateCounter::new(4));
+        assert_eq!(
+            checkpoint.payloads_hash,
+            enclave_hash.next(StateCounter::new(4), b"fourth")
+        );
+    }
+
+    fn payload(generation: u32, state_counter: u32, block_num: u64) -> PayloadType {
+        PayloadType::from_ciphertext(
+            frame_common::crypto::Ciphertext::new(generation, 0, 0, vec![]),
+            StateCounter::new(state_counter),
+            block_num,
+        )
+    }
+
+    fn state_counters(payloads: &[PayloadType]) -> Vec<u32> {
+        payloads
+            .iter()
+            .map(|p| p.state_counter().as_raw())
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder_pending() {
+        let contract_addr = ContractAddr::default();
+        let mut cache = InnerEventCache::default();
+        cache.set_reorder_limits(ReorderLimits {
+            max_pending: 3,
+            max_wait: Duration::from_secs(10),
+        });
+        let now = Instant::now();
+
+        // The events wait for the first one
+        cache.insert_pending(contract_addr.clone(), payload(2, 3, 11), now);
+        cache.insert_pending(contract_addr.clone(), payload(1, 2, 10), now);
+        assert!(cache.take_ready(&contract_addr, now).is_empty());
+        assert_eq!(cache.pending_len(&contract_addr), 2);
+
+        cache.insert_pending(contract_addr.clone(), payload(0, 1, 10), now);
+        // A duplicated event is buffered once
+        cache.insert_pending(contract_addr.clone(), payload(0, 1, 10), now);
+        let ready = cache.take_ready(&contract_addr, now);
+        assert_eq!(state_counters(&ready), vec![1, 2, 3]);
+        assert_eq!(
+            ready.iter().map(|p| p.generation()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        for p in &ready {
+            cache.commit_state_counter(contract_addr.clone(), p.state_counter());
+        }
+        assert_eq!(cache.pending_len(&contract_addr), 0);
+
+        // The committed events are not buffered again
+        cache.insert_pending(contract_addr.clone(), payload(2, 3, 11), now);
+        assert_eq!(cache.pending_len(&contract_addr), 0);
+
+        // The events furthest from being processed are dropped if too many events are waiting
+        for state_counter in 5..9 {
+            cache.insert_pending(
+                contract_addr.clone(),
+                payload(state_counter, state_counter, 20),
+                now,
+            );
+        }
+        assert_eq!(cache.pending_len(&contract_addr), 3);
+        // The events not persisted are fetched again after restarting
+        cache.insert_next_block_num(contract_addr.clone(), 21);
+        assert_eq!(cache.persisted_next_block_num(&contract_addr), Some(20));
+
+        // The waiting events are processed once the preceding event arrives
+        cache.insert_pending(contract_addr.clone(), payload(4, 4, 20), now);
+        let later = now + Duration::from_secs(10);
+        let ready = cache.take_ready(&contract_addr, later);
+        assert_eq!(state_counters(&ready), vec![4, 5, 6]);
+        for p in &ready {
+            cache.commit_state_counter(contract_addr.clone(), p.state_counter());
+        }
+        assert_eq!(cache.pending_len(&contract_addr), 0);
+
+        // The events waiting too long are dropped
+        cache.insert_pending(contract_addr.clone(), payload(9, 9, 30), later);
+        assert!(cache
+            .take_ready(&contract_addr, later + Duration::from_secs(10))
+            .is_empty());
+        assert_eq!(cache.pending_len(&contract_addr), 0);
+    }
+
+    #[test]
+    fn test_refetch_dropped_events() {
+        let contract_addr = ContractAddr::default();
+        let mut cache = InnerEventCache::default();
+        cache.set_reorder_limits(ReorderLimits {
+            max_pending: 2,
+            max_wait: Duration::from_secs(10),
+        });
+        let now = Instant::now();
+        let poll = |cache: &mut InnerEventCache, payloads: Vec<PayloadType>, next: u64| {
+            for payload in payloads {
+                cache.insert_pending(contract_addr.clone(), payload, now);
+            }
+            let ready = cache.take_ready(&contract_addr, now);
+            for p in &ready {
+                cache.commit_state_counter(contract_addr.clone(), p.state_counter());
+            }
+            cache.insert_next_block_num(contract_addr.clone(), next);
+            state_counters(&ready)
+        };
+
+        // The event 1 is missing, and the event 4 is dropped because too many events are waiting.
+        let ready = poll(
+            &mut cache,
+            vec![payload(2, 2, 11), payload(3, 3, 12), payload(4, 4, 13)],
+            14,
+        );
+        assert!(ready.is_empty());
+        // The cursor doesn't advance past the dropped event.
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(13));
+
+        // The predecessor arrives after the drop. The event 3 is dropped this time,
+        // since the waiting events are still full when the event 4 is fetched again.
+        let ready = poll(&mut cache, vec![payload(4, 4, 13), payload(1, 1, 14)], 15);
+        assert_eq!(ready, vec![1, 2]);
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(12));
+
+        // All the dropped events are processed in the end.
+        let ready = poll(
+            &mut cache,
+            vec![payload(3, 3, 12), payload(4, 4, 13), payload(1, 1, 14)],
+            15,
+        );
+        assert_eq!(ready, vec![3, 4]);
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(15));
+        assert_eq!(cache.pending_len(&contract_addr), 0);
+
+        // The events waiting too long are fetched again as well.
+        cache.insert_pending(contract_addr.clone(), payload(6, 6, 20), now);
+        assert!(cache
+            .take_ready(&contract_addr, now + Duration::from_secs(10))
+            .is_empty());
+        assert_eq!(cache.persisted_next_block_num(&contract_addr), Some(15));
+        cache.insert_next_block_num(contract_addr.clone(), 21);
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(20));
+        let ready = poll(&mut cache, vec![payload(5, 5, 20), payload(6, 6, 20)], 21);
+        assert_eq!(ready, vec![5, 6]);
+        assert_eq!(cache.get_latest_block_num(&contract_addr), Some(21));
+    }
 }
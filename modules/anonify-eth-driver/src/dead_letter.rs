@@ -0,0 +1,363 @@
+use crate::{
+    cache::EventCache,
+    error::{HostError, Result},
+    event::{self, PayloadType},
+    utils::write_atomically,
+};
+use frame_common::{
+    chain_types::ChainAddress,
+    crypto::{Ciphertext, ExportHandshake},
+    state_types::StateCounter,
+};
+use frame_config::DEAD_LETTER_PATH;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sgx_types::sgx_enclave_id_t;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, warn};
+
+/// The number of recent dead letters kept.
+const MAX_DEAD_LETTERS: usize = 10_000;
+
+/// Events skipped because of errors in the enclave, which are kept with the errors
+/// so that operators can investigate divergence after the fact and retry them.
+/// If it is loaded from a file, every change is persisted to it.
+#[derive(Debug, Default, Clone)]
+pub struct DeadLetterQueue {
+    inner: Arc<RwLock<InnerDeadLetterQueue>>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct InnerDeadLetterQueue {
+    next_id: u64,
+    dead_letters: BTreeMap<u64, DeadLetter>,
+    metrics: DeadLetterMetrics,
+}
+
+/// An event skipped because of an error in the enclave.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub contract_addr: ChainAddress,
+    pub kind: PayloadKind,
+    pub roster_idx: u32,
+    pub epoch: u32,
+    pub generation: u32,
+    pub state_counter: StateCounter,
+    pub block_num: u64,
+    pub log_index: u64,
+    /// The encoded ciphertext or handshake
+    pub payload: Vec<u8>,
+    /// A stable identifier of the kind of the last error, e.g. `decode_failure`
+    pub error_code: String,
+    pub error: String,
+    /// The number of failures including the first one
+    pub attempts: u32,
+    /// Unix time in seconds when the event was skipped
+    pub skipped_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKind {
+    Ciphertext,
+    Handshake,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetterMetrics {
+    /// The number of skipped events for each error code
+    pub skipped: BTreeMap<String, u64>,
+    /// The number of retries
+    pub retried: u64,
+    /// The number of events inserted into the enclave by retries
+    pub recovered: u64,
+    /// The number of dead letters kept
+    #[serde(skip)]
+    pub pending: u64,
+}
+
+/// The result of retrying a dead letter
+#[derive(Debug)]
+pub enum RetryOutcome {
+    /// The event is inserted into the enclave and removed from the queue,
+    /// returning the notifications of the updated states.
+    Recovered(Vec<serde_json::Value>),
+    /// The event failed again and is kept with the new error.
+    Failed(DeadLetter),
+    /// The event is not retried, since the enclave has already consumed its state counter
+    /// and would reject it again. Holds the state counter of the enclave.
+    Consumed(StateCounter),
+}
+
+impl DeadLetterQueue {
+    /// Load the dead letters persisted in DEAD_LETTER_PATH, or keep them only in memory if it is not set.
+    pub fn from_env() -> Result<Self> {
+        match &*DEAD_LETTER_PATH {
+            Some(path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Load the dead letters persisted in the file, which is created when an event is skipped if it doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let inner = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => InnerDeadLetterQueue::default(),
+            Err(e) => return Err(e.into()),
+        };
+        info!("Loaded the dead letters from {:?}", path);
+
+        Ok(DeadLetterQueue {
+            inner: Arc::new(RwLock::new(inner)),
+            path: Some(path),
+        })
+    }
+
+    /// Keep the event skipped because of the error. Only the most recent `MAX_DEAD_LETTERS` events are kept.
+    pub fn push(
+        &self,
+        contract_addr: &ChainAddress,
+        payload: &PayloadType,
+        err: &HostError,
+    ) -> u64 {
+        let mut inner = self.inner.write();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        *inner
+            .metrics
+            .skipped
+            .entry(err.code().to_string())
+            .or_default() += 1;
+        inner.dead_letters.insert(
+            id,
+            DeadLetter {
+                id,
+                contract_addr: contract_addr.clone(),
+                kind: PayloadKind::of(payload),
+                roster_idx: payload.roster_idx(),
+                epoch: payload.epoch(),
+                generation: payload.generation(),
+                state_counter: payload.state_counter(),
+                block_num: payload.block_num(),
+                log_index: payload.log_index(),
+                payload: payload.encode_payload(),
+                error_code: err.code().to_string(),
+                error: err.to_string(),
+                attempts: 1,
+                skipped_at: unix_time(),
+            },
+        );
+        while inner.dead_letters.len() > MAX_DEAD_LETTERS {
+            let oldest = *inner
+                .dead_letters
+                .keys()
+                .next()
+                .expect("dead letters must not be empty");
+            warn!("The oldest dead letter is dropped: id: {}", oldest);
+            inner.dead_letters.remove(&oldest);
+        }
+        drop(inner);
+
+        self.persist();
+        id
+    }
+
+    /// The dead letters in the order they are skipped
+    pub fn list(&self) -> Vec<DeadLetter> {
+        self.inner.read().dead_letters.values().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<DeadLetter> {
+        self.inner.read().dead_letters.get(&id).cloned()
+    }
+
+    pub fn metrics(&self) -> DeadLetterMetrics {
+        let inner = self.inner.read();
+        DeadLetterMetrics {
+            pending: inner.dead_letters.len() as u64,
+            ..inner.metrics.clone()
+        }
+    }
+
+    /// Insert the event into the enclave again. The enclave increments its state counter
+    /// even if it fails to process an event, so the retry succeeds only if the event didn't reach the enclave,
+    /// e.g. the ecall itself failed. The event whose state counter is not ahead of `enclave_state_counter`
+    /// is not retried, and otherwise it's kept with the new error if it fails again.
+    /// Returns `None` if the dead letter is not found.
+    pub fn retry(
+        &self,
+        id: u64,
+        eid: sgx_enclave_id_t,
+        fetch_ciphertext_cmd: u32,
+        fetch_handshake_cmd: u32,
+        enclave_state_counter: StateCounter,
+        cache: &EventCache,
+    ) -> Result<Option<RetryOutcome>> {
+        let dead_letter = match self.get(id) {
+            Some(dead_letter) => dead_letter,
+            None => return Ok(None),
+        };
+        if dead_letter.state_counter <= enclave_state_counter {
+            warn!(
+                "A dead letter is not retried because the enclave has consumed its state counter: id: {}, state counter: {:?}, enclave: {:?}",
+                id, dead_letter.state_counter, enclave_state_counter
+            );
+            return Ok(Some(RetryOutcome::Consumed(enclave_state_counter)));
+        }
+        let payload = dead_letter.to_payload()?;
+        let res = event::insert_payload(
+            eid,
+            &dead_letter.contract_addr,
+            &payload,
+            fetch_ciphertext_cmd,
+            fetch_handshake_cmd,
+            cache,
+        );
+
+        let mut inner = self.inner.write();
+        inner.metrics.retried += 1;
+        let outcome = match res {
+            Ok(notifications) => {
+                info!("A dead letter is recovered: id: {}", id);
+                inner.metrics.recovered += 1;
+                inner.dead_letters.remove(&id);
+                RetryOutcome::Recovered(notifications)
+            }
+            Err(err) => {
+                error!("Failed to retry a dead letter: id: {}: {:?}", id, err);
+                let dead_letter = inner.dead_letters.entry(id).or_insert(dead_letter);
+                dead_letter.error_code = err.code().to_string();
+                dead_letter.error = err.to_string();
+                dead_letter.attempts += 1;
+                RetryOutcome::Failed(dead_letter.clone())
+            }
+        };
+        drop(inner);
+
+        self.persist();
+        Ok(Some(outcome))
+    }
+
+    /// A failure to persist doesn't stop processing events, since the dead letters are only for investigation.
+    fn persist(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let res = serde_json::to_vec(&*self.inner.read())
+            .map_err(Into::into)
+            .and_then(|bytes| write_atomically(path, &bytes));
+        if let Err(e) = res {
+            error!("Failed to persist the dead letters: {:?}", e);
+        }
+    }
+}
+
+impl DeadLetter {
+    fn to_payload(&self) -> Result<PayloadType> {
+        let payload = match self.kind {
+            PayloadKind::Ciphertext => PayloadType::from_ciphertext(
+                Ciphertext::decode(&self.payload)?,
+                self.state_counter,
+                self.block_num,
+            ),
+            PayloadKind::Handshake => PayloadType::from_handshake(
+                ExportHandshake::decode(&self.payload)?,
+                self.state_counter,
+                self.block_num,
+            ),
+        };
+
+        Ok(payload.set_log_index(self.log_index))
+    }
+}
+
+impl PayloadKind {
+    fn of(payload: &PayloadType) -> Self {
+        if payload.is_handshake() {
+            PayloadKind::Handshake
+        } else {
+            PayloadKind::Ciphertext
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_common::error::EcallError;
+
+    #[test]
+    fn test_dead_letters() {
+        let contract_addr = ChainAddress::default();
+        let path = std::env::temp_dir().join("anonify_test_dead_letters.json");
+        let _ = fs::remove_file(&path);
+        let ciphertext = Ciphertext::new(2, 1, 0, vec![1, 2, 3]);
+        let payload = PayloadType::from_ciphertext(ciphertext.clone(), StateCounter::new(5), 10)
+            .set_log_index(3);
+
+        let queue = DeadLetterQueue::load(&path).unwrap();
+        let decode_failure = HostError::EcallError(EcallError::Decode("invalid".to_string()));
+        let id = queue.push(&contract_addr, &payload, &decode_failure);
+        queue.push(&contract_addr, &payload, &HostError::EcallOutputNotSet);
+
+        let dead_letter = queue.get(id).unwrap();
+        assert_eq!(dead_letter.kind, PayloadKind::Ciphertext);
+        assert_eq!(dead_letter.state_counter, StateCounter::new(5));
+        assert_eq!(dead_letter.log_index, 3);
+        assert_eq!(dead_letter.error_code, "decode_failure");
+        assert_eq!(dead_letter.attempts, 1);
+        assert_eq!(
+            Ciphertext::decode(&dead_letter.payload).unwrap(),
+            ciphertext
+        );
+        assert_eq!(dead_letter.to_payload().unwrap().log_index(), 3);
+
+        let metrics = queue.metrics();
+        assert_eq!(metrics.skipped.get("decode_failure"), Some(&1));
+        assert_eq!(metrics.skipped.get("internal"), Some(&1));
+        assert_eq!(metrics.pending, 2);
+
+        // The dead letters and the metrics are persisted
+        let loaded = DeadLetterQueue::load(&path).unwrap();
+        assert_eq!(loaded.list(), queue.list());
+        assert_eq!(loaded.metrics(), metrics);
+        assert_eq!(loaded.push(&contract_addr, &payload, &decode_failure), 2);
+
+        // The event whose state counter is consumed by the enclave is not inserted again.
+        let cache = EventCache::default();
+        match loaded
+            .retry(id, 0, 0, 0, StateCounter::new(5), &cache)
+            .unwrap()
+        {
+            Some(RetryOutcome::Consumed(state_counter)) => {
+                assert_eq!(state_counter, StateCounter::new(5))
+            }
+            res => panic!("The retry must be rejected: {:?}", res),
+        }
+        assert_eq!(loaded.get(id).unwrap().attempts, 1);
+        assert_eq!(loaded.metrics().retried, 0);
+        assert!(loaded
+            .retry(100, 0, 0, 0, StateCounter::new(5), &cache)
+            .unwrap()
+            .is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,17 +3,25 @@ use crate::backup::SecretBackup;
 use crate::workflow::*;
 use crate::{
     cache::EventCache,
+    dead_letter::{DeadLetterQueue, RetryOutcome},
     error::{HostError, Result},
+    event,
     traits::*,
     utils::*,
     workflow::host_input,
 };
+use anonify_ecall_types::{output, StateSnapshotHeader};
+use frame_common::{
+    chain_types::{ChainAddress, TxHash},
+    schema::RuntimeSchema,
+    state_types::{PayloadsHash, StateCounter, StateRoot},
+};
 use frame_host::engine::HostEngine;
 use frame_sodium::{SodiumCiphertext, SodiumPubKey};
 use parking_lot::RwLock;
 use sgx_types::sgx_enclave_id_t;
-use std::{fmt::Debug, marker::Send, path::Path};
-use web3::types::{Address, H256};
+use std::{fmt::Debug, marker::Send, path::Path, str::FromStr};
+use tracing::{error, info};
 
 /// This dispatcher communicates with a blockchain node.
 #[derive(Debug)]
@@ -72,7 +80,7 @@ where
 
     pub async fn deploy<P>(
         &self,
-        deploy_user: Address,
+        deploy_user: ChainAddress,
         gas: u64,
         abi_path: P,
         bin_path: P,
@@ -96,24 +104,24 @@ where
 
     pub async fn join_group<P: AsRef<Path> + Copy>(
         &self,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         contract_addr: &str,
         abi_path: P,
         ecall_cmd: u32,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         self.send_report_handshake(signer, gas, contract_addr, abi_path, ecall_cmd, "joinGroup")
             .await
     }
 
     pub async fn register_report<P: AsRef<Path> + Copy>(
         &self,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         contract_addr: &str,
         abi_path: P,
         ecall_cmd: u32,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         self.set_contract_address(contract_addr, abi_path)?;
 
         let inner = self.inner.read();
@@ -133,12 +141,12 @@ where
 
     pub async fn update_mrenclave<P: AsRef<Path> + Copy>(
         &self,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         contract_addr: &str,
         abi_path: P,
         ecall_cmd: u32,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         self.send_report_handshake(
             signer,
             gas,
@@ -152,13 +160,13 @@ where
 
     async fn send_report_handshake<P: AsRef<Path> + Copy>(
         &self,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         contract_addr: &str,
         abi_path: P,
         ecall_cmd: u32,
         method: &str,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         self.set_contract_address(contract_addr, abi_path)?;
 
         let inner = self.inner.read();
@@ -179,10 +187,10 @@ where
     pub async fn send_command(
         &self,
         ciphertext: SodiumCiphertext,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         ecall_cmd: u32,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         let inner = self.inner.read();
         let input = host_input::Command::new(ciphertext, signer, gas, ecall_cmd);
         let eid = inner.deployer.get_enclave_id();
@@ -194,6 +202,7 @@ where
         }
     }
 
+    /// Get a state by the getter. The JSON value is typed as described in the runtime schema.
     pub fn get_state(
         &self,
         ciphertext: SodiumCiphertext,
@@ -205,11 +214,36 @@ where
             .ecall_output
             .ok_or_else(|| HostError::EcallOutputNotSet)?;
 
-        let bytes: Vec<u8> = bincode::deserialize(&state.state.as_bytes())?;
-        serde_json::from_slice(&bytes[..]).map_err(Into::into)
+        serde_json::from_slice(state.state.as_bytes()).map_err(Into::into)
+    }
+
+    /// Get the schema of the runtime's commands, getters and memories.
+    pub fn get_runtime_schema(&self, ecall_cmd: u32) -> Result<RuntimeSchema> {
+        let eid = self.inner.read().deployer.get_enclave_id();
+        let input = host_input::GetRuntimeSchema::new(ecall_cmd);
+        let schema = GetRuntimeSchemaWorkflow::exec(input, eid)?
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+
+        serde_json::from_slice(schema.schema()).map_err(Into::into)
+    }
+
+    /// Reload the measurements of the key vault enclaves trusted by the enclave
+    /// from TRUSTED_KEY_VAULT_MEASUREMENTS_PATH.
+    pub fn reload_trusted_measurements(&self, ecall_cmd: u32) -> Result<()> {
+        let eid = self.inner.read().deployer.get_enclave_id();
+        let input = host_input::ReloadTrustedMeasurements::new(ecall_cmd);
+        let _host_output = ReloadTrustedMeasurementsWorkflow::exec(input, eid)?;
+
+        Ok(())
     }
 
-    pub async fn handshake(&self, signer: Address, gas: u64, ecall_cmd: u32) -> Result<H256> {
+    pub async fn handshake(
+        &self,
+        signer: ChainAddress,
+        gas: u64,
+        ecall_cmd: u32,
+    ) -> Result<TxHash> {
         let inner = self.inner.read();
         let input = host_input::Handshake::new(signer, gas, ecall_cmd);
         let eid = inner.deployer.get_enclave_id();
@@ -225,10 +259,13 @@ where
         Ok(tx_hash)
     }
 
+    /// Fetch the events and insert them into the enclave.
+    /// `state_counter_cmd` tells whether the enclave has consumed an event which failed in it.
     pub async fn fetch_events(
         &self,
         fetch_ciphertext_cmd: u32,
         fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
     ) -> Result<Option<Vec<serde_json::Value>>> {
         let inner = self.inner.read();
         let eid = inner.deployer.get_enclave_id();
@@ -236,11 +273,179 @@ where
             .watcher
             .as_ref()
             .ok_or(HostError::EventWatcherNotSet)?
-            .fetch_events(eid, fetch_ciphertext_cmd, fetch_handshake_cmd)
+            .fetch_events(
+                eid,
+                fetch_ciphertext_cmd,
+                fetch_handshake_cmd,
+                state_counter_cmd,
+            )
             .await
     }
 
-    pub async fn get_account(&self, index: usize, password: &str) -> Result<Address> {
+    /// Resume the event cache from the states persisted in the enclave,
+    /// so that events applied before restarting are not fetched again.
+    /// If the event cache is persisted, its checkpoint must not be ahead of the enclave,
+    /// otherwise the events between them would never be applied to the enclave.
+    /// At the same state counter, the hash chains over the processed payloads must agree.
+    pub fn resume_event_cache(&self, contract_addr: &str, ecall_cmd: u32) -> Result<()> {
+        let inner = self.inner.read();
+        let eid = inner.deployer.get_enclave_id();
+        let input = host_input::GetStateCounter::new(ecall_cmd);
+        let committed = GetStateCounterWorkflow::exec(input, eid)?
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+
+        let checkpoint = inner
+            .cache
+            .inner()
+            .read()
+            .get_checkpoint(&ChainAddress::from_str(contract_addr)?);
+        if let Some(checkpoint) = checkpoint {
+            // The enclave is ahead of the checkpoint if the node stopped before persisting it.
+            if checkpoint.state_counter == committed.state_counter() {
+                if checkpoint.payloads_hash != committed.payloads_hash() {
+                    error!(
+                        "The payloads hash of the checkpoint ({:?}) differs from the enclave's ({:?})",
+                        checkpoint.payloads_hash,
+                        committed.payloads_hash()
+                    );
+                    return Err(HostError::PayloadsHashMismatch(checkpoint.state_counter));
+                }
+                info!("The checkpoint agrees with the enclave: {:?}", checkpoint);
+                return inner.resume_cache(
+                    contract_addr,
+                    checkpoint.state_counter,
+                    checkpoint.block_num,
+                    checkpoint.payloads_hash,
+                );
+            }
+            if checkpoint.state_counter > committed.state_counter() {
+                return Err(HostError::CheckpointMismatch {
+                    checkpoint: checkpoint.state_counter,
+                    enclave: committed.state_counter(),
+                });
+            }
+        }
+
+        // Nothing has been persisted yet.
+        if committed.block_num() == 0 {
+            return Ok(());
+        }
+
+        inner.resume_cache(
+            contract_addr,
+            committed.state_counter(),
+            committed.block_num(),
+            committed.payloads_hash(),
+        )
+    }
+
+    /// Export a snapshot of all user states which can be imported by the other nodes in the group.
+    pub fn export_state_snapshot(&self, ecall_cmd: u32) -> Result<output::ReturnStateSnapshot> {
+        let eid = self.inner.read().deployer.get_enclave_id();
+        let input = host_input::ExportStateSnapshot::new(ecall_cmd);
+        ExportStateSnapshotWorkflow::exec(input, eid)?
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)
+    }
+
+    /// Import a state snapshot exported by another node,
+    /// and resume fetching events from the block number the snapshot covers.
+    pub fn import_state_snapshot(
+        &self,
+        contract_addr: &str,
+        header: StateSnapshotHeader,
+        sealed: Vec<u8>,
+        ecall_cmd: u32,
+    ) -> Result<()> {
+        let inner = self.inner.read();
+        let eid = inner.deployer.get_enclave_id();
+        let input = host_input::ImportStateSnapshot::new(header, sealed, ecall_cmd);
+        let imported = ImportStateSnapshotWorkflow::exec(input, eid)?
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+
+        inner.resume_cache(
+            contract_addr,
+            imported.state_counter(),
+            imported.block_num(),
+            imported.payloads_hash(),
+        )
+    }
+
+    /// Get the state root computed by the enclave right after the transition at the state counter.
+    /// If the state counter is not specified, the latest one is returned.
+    pub fn get_state_root(
+        &self,
+        contract_addr: &str,
+        state_counter: Option<StateCounter>,
+    ) -> Result<Option<(StateCounter, StateRoot)>> {
+        let contract_addr = ChainAddress::from_str(contract_addr)?;
+        let state_root = self
+            .inner
+            .read()
+            .cache
+            .inner()
+            .read()
+            .get_state_root(&contract_addr, state_counter);
+
+        Ok(state_root)
+    }
+
+    /// Compare the state root reported by another node with ours at the same state counter.
+    /// Returns `None` if our root at the state counter is unknown, e.g. not yet reached or already evicted.
+    pub fn verify_state_root(
+        &self,
+        contract_addr: &str,
+        state_counter: StateCounter,
+        state_root: StateRoot,
+    ) -> Result<Option<bool>> {
+        let ours = match self.get_state_root(contract_addr, Some(state_counter))? {
+            Some((_, ours)) => ours,
+            None => return Ok(None),
+        };
+
+        if ours == state_root {
+            info!("State root matched at state counter {:?}", state_counter);
+            Ok(Some(true))
+        } else {
+            error!(
+                "State divergence detected at state counter {:?}: ours: {:?}, theirs: {:?}",
+                state_counter, ours, state_root
+            );
+            Ok(Some(false))
+        }
+    }
+
+    /// The events skipped because of errors in the enclave
+    pub fn dead_letters(&self) -> DeadLetterQueue {
+        self.inner.read().cache.dead_letters().clone()
+    }
+
+    /// Insert the skipped event into the enclave again. Returns `None` if the dead letter is not found.
+    /// Retry the dead letter unless the enclave has already consumed its state counter.
+    pub fn retry_dead_letter(
+        &self,
+        id: u64,
+        fetch_ciphertext_cmd: u32,
+        fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
+    ) -> Result<Option<RetryOutcome>> {
+        let inner = self.inner.read();
+        let eid = inner.deployer.get_enclave_id();
+        let enclave_state_counter = event::enclave_state_counter(eid, state_counter_cmd)?;
+
+        inner.cache.dead_letters().retry(
+            id,
+            eid,
+            fetch_ciphertext_cmd,
+            fetch_handshake_cmd,
+            enclave_state_counter,
+            &inner.cache,
+        )
+    }
+
+    pub async fn get_account(&self, index: usize, password: &str) -> Result<ChainAddress> {
         self.inner
             .read()
             .deployer
@@ -272,6 +477,19 @@ where
         Ok(())
     }
 
+    pub fn unregister_notification(
+        &self,
+        ciphertext: SodiumCiphertext,
+        ecall_cmd: u32,
+    ) -> Result<()> {
+        let inner = self.inner.read();
+        let input = host_input::UnregisterNotification::new(ciphertext, ecall_cmd);
+        let eid = inner.deployer.get_enclave_id();
+        let _host_output = UnregisterNotificationWorkflow::exec(input, eid)?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "backup-enable")]
     pub fn all_backup_to(&self, ecall_cmd: u32) -> Result<()> {
         let inner = self.inner.read();
@@ -286,3 +504,26 @@ where
         inner.backup.all_backup_from(eid, ecall_cmd)
     }
 }
+
+impl<D, S, W> InnerDispatcher<D, S, W>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    fn resume_cache(
+        &self,
+        contract_addr: &str,
+        state_counter: StateCounter,
+        block_num: u64,
+        payloads_hash: PayloadsHash,
+    ) -> Result<()> {
+        let contract_addr = ChainAddress::from_str(contract_addr)?;
+        self.cache
+            .inner()
+            .write()
+            .resume(contract_addr, state_counter, block_num, payloads_hash);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use frame_common::{chain_types::ChainAddress, error::EcallError, state_types::StateCounter};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, HostError>;
@@ -5,7 +6,11 @@ pub type Result<T> = std::result::Result<T, HostError>;
 #[derive(Error, Debug)]
 pub enum HostError {
     #[error("Error: {0}")]
-    Error(#[from] anyhow::Error),
+    Error(#[source] anyhow::Error),
+    #[error("Enclave error: {0}")]
+    EcallError(EcallError),
+    #[error("Invalid address for the blockchain: {0}")]
+    InvalidAddress(ChainAddress),
     #[error("Contract address have not been set.")]
     AddressNotSet,
     #[error("Event watcher have not been set.")]
@@ -29,9 +34,48 @@ pub enum HostError {
     #[error("Ethabi error: {0}")]
     EthabiError(#[from] ethabi::Error),
     #[error("Frame host error: {0}")]
-    FrameHostError(#[from] frame_host::Error),
+    FrameHostError(#[source] frame_host::Error),
     #[error("Serde json error: {0}")]
     SerdeJsonError(#[from] serde_json::Error),
     #[error("Bincode error: {0}")]
     BincodeError(#[from] bincode::Error),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("The block {0} containing the processed events is reorganized. Restore the enclave states from a snapshot before it.")]
+    ChainReorg(u64),
+    #[error("The persisted checkpoint at state counter {checkpoint:?} is ahead of the enclave at {enclave:?}. Restore the enclave states or remove the event cache.")]
+    CheckpointMismatch {
+        checkpoint: StateCounter,
+        enclave: StateCounter,
+    },
+    #[error("The persisted checkpoint at state counter {0:?} has processed different events from the enclave. Restore the enclave states or remove the event cache.")]
+    PayloadsHashMismatch(StateCounter),
+}
+
+impl HostError {
+    /// A stable identifier of the kind of the error, e.g. `decode_failure`
+    pub fn code(&self) -> &'static str {
+        match self {
+            HostError::EcallError(e) => e.code(),
+            _ => "internal",
+        }
+    }
+}
+
+impl From<anyhow::Error> for HostError {
+    fn from(err: anyhow::Error) -> Self {
+        match EcallError::find(&err) {
+            Some(ecall_error) => HostError::EcallError(ecall_error.clone()),
+            None => HostError::Error(err),
+        }
+    }
+}
+
+impl From<frame_host::Error> for HostError {
+    fn from(err: frame_host::Error) -> Self {
+        match err.ecall_error() {
+            Some(ecall_error) => HostError::EcallError(ecall_error.clone()),
+            None => HostError::FrameHostError(err),
+        }
+    }
 }
@@ -1,16 +1,23 @@
 use super::event_watcher::{EthEvent, Web3Logs};
 use crate::{
-    cache::EventCache,
+    cache::{BlockHash, EventCache, ScannedBlock},
     error::{HostError, Result},
     utils::ContractInfo,
     workflow::*,
 };
 use ethabi::{Topic, TopicFilter};
-use std::{env, fs, path::Path};
+use frame_common::chain_types::ChainAddress;
+use std::{
+    cmp, env, fs,
+    future::Future,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::{info, warn};
 use web3::{
     contract::{Contract, Options},
     transports::Http,
-    types::{Address, BlockNumber, Filter, FilterBuilder, Log, H256},
+    types::{Address, BlockId, BlockNumber, Filter, FilterBuilder, Log, H256},
     Web3,
 };
 
@@ -26,6 +33,8 @@ pub struct Web3Contract {
     address: Address, // contract address
     web3_conn: Web3Http,
     event_limit: usize,
+    confirmations: u64,
+    block_range: BlockRange,
 }
 
 impl Web3Contract {
@@ -34,18 +43,29 @@ impl Web3Contract {
         contract_info: ContractInfo<'_, P>,
     ) -> Result<Self> {
         let abi = contract_info.contract_abi()?;
-        let address = contract_info.address()?;
+        let address = eth_address(&contract_info.address()?)?;
         let contract = Contract::new(web3_conn.web3.eth(), address, abi);
         let event_limit = env::var("EVENT_LIMIT")
             .unwrap_or_else(|_| "100".to_string())
             .parse::<usize>()
             .expect("Failed to parse EVENT_LIMIT");
+        let confirmations = env::var("EVENT_CONFIRMATIONS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .expect("Failed to parse EVENT_CONFIRMATIONS");
+        let max_block_range = env::var("EVENT_BLOCK_RANGE")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<u64>()
+            .expect("Failed to parse EVENT_BLOCK_RANGE");
+        assert!(max_block_range > 0, "EVENT_BLOCK_RANGE must be positive");
 
         Ok(Web3Contract {
             contract,
             address,
             web3_conn,
             event_limit,
+            confirmations,
+            block_range: BlockRange::new(max_block_range),
         })
     }
 
@@ -72,7 +92,7 @@ impl Web3Contract {
                     ecall_output.mrenclave_ver(),
                     ecall_output.roster_idx(),
                 ),
-                output.signer,
+                eth_address(&output.signer)?,
                 Options::with(|opt| opt.gas = Some(gas.into())),
             )
             .await
@@ -96,7 +116,7 @@ impl Web3Contract {
                     ecall_output.mrenclave_ver(),
                     ecall_output.roster_idx(),
                 ),
-                output.signer,
+                eth_address(&output.signer)?,
                 Options::with(|opt| opt.gas = Some(gas.into())),
             )
             .await
@@ -123,7 +143,7 @@ impl Web3Contract {
                     ciphertext.generation(),
                     ciphertext.epoch(),
                 ),
-                output.signer,
+                eth_address(&output.signer)?,
                 Options::with(|opt| opt.gas = Some(gas.into())),
             )
             .await
@@ -150,14 +170,19 @@ impl Web3Contract {
                     0 as u32,
                     handshake.prior_epoch() + 1,
                 ),
-                output.signer,
+                eth_address(&output.signer)?,
                 Options::with(|opt| opt.gas = Some(gas.into())),
             )
             .await
             .map_err(Into::into)
     }
 
-    pub async fn get_event(&self, cache: EventCache, key: Address) -> Result<Web3Logs> {
+    /// Fetch the events in the blocks which have `EVENT_CONFIRMATIONS` blocks on top of them,
+    /// so that the events are unlikely to disappear by chain reorganizations.
+    /// The blocks are queried by ranges of at most `EVENT_BLOCK_RANGE` blocks, and the range is halved
+    /// while the provider rejects the query, e.g. because of too many results.
+    /// Once `EVENT_LIMIT` events are fetched, the rest of the blocks are left to the next polling.
+    pub async fn get_event(&self, cache: EventCache, key: &ChainAddress) -> Result<Web3Logs> {
         let events = EthEvent::create_event();
         let ciphertext_sig = events.ciphertext_signature();
         let handshake_sig = events.handshake_signature();
@@ -167,23 +192,69 @@ impl Web3Contract {
             .read()
             .get_latest_block_num(key)
             .unwrap_or_default();
+        let confirmed_num = match self
+            .web3_conn
+            .block_number()
+            .await?
+            .checked_sub(self.confirmations)
+        {
+            Some(num) if num >= latest_fetched_num => num,
+            _ => return Ok(Web3Logs::new(vec![], cache, events, key.clone(), None)),
+        };
 
-        let filter = FilterBuilder::default()
-            .address(vec![self.address])
-            .topic_filter(TopicFilter {
-                topic0: Topic::OneOf(vec![ciphertext_sig, handshake_sig]),
-                topic1: Topic::Any,
-                topic2: Topic::Any,
-                topic3: Topic::Any,
-            })
-            .from_block(BlockNumber::Number(latest_fetched_num.into()))
-            .to_block(BlockNumber::Latest)
-            .limit(self.event_limit)
-            .build();
+        let (logs, scanned) = self
+            .block_range
+            .scan(
+                latest_fetched_num,
+                confirmed_num,
+                self.event_limit,
+                |from_block, to_block| async move {
+                    let filter = FilterBuilder::default()
+                        .address(vec![self.address])
+                        .topic_filter(TopicFilter {
+                            topic0: Topic::OneOf(vec![ciphertext_sig, handshake_sig]),
+                            topic1: Topic::Any,
+                            topic2: Topic::Any,
+                            topic3: Topic::Any,
+                        })
+                        .from_block(BlockNumber::Number(from_block.into()))
+                        .to_block(BlockNumber::Number(to_block.into()))
+                        .build();
 
-        let logs = self.web3_conn.get_logs(&filter).await?;
+                    // The hash is fetched before the logs, so that a reorganization in between is detected by the next polling.
+                    let to_hash = self.web3_conn.block_hash(to_block).await?;
+                    let logs = self.web3_conn.get_logs(&filter).await?;
+                    Ok((to_hash, logs))
+                },
+            )
+            .await?;
 
-        Ok(Web3Logs::new(logs, cache, events))
+        Ok(Web3Logs::new(logs, cache, events, key.clone(), scanned))
+    }
+
+    /// Verify that the scanned blocks are still on the chain. If the chain is reorganized,
+    /// events are fetched again from the block after the fork point.
+    /// The enclave states can't be rolled back, so if any blocks containing the processed events are replaced,
+    /// it fails with `HostError::ChainReorg` until the enclave states are restored.
+    pub async fn check_reorg(&self, cache: &EventCache, key: &ChainAddress) -> Result<()> {
+        let scanned_blocks = cache.inner().read().get_block_hashes(key);
+        let fork_point = find_fork_point(scanned_blocks, |block_num| {
+            self.web3_conn.block_hash(block_num)
+        })
+        .await?;
+        match fork_point {
+            ForkPoint::NotReorged => Ok(()),
+            ForkPoint::Rewind(block_num) => {
+                warn!(
+                    "The chain is reorganized after the block {}, so events are fetched again from the block {}",
+                    block_num - 1,
+                    block_num
+                );
+                cache.inner().write().rewind(key.clone(), block_num);
+                Ok(())
+            }
+            ForkPoint::Reorged(block_num) => Err(HostError::ChainReorg(block_num)),
+        }
     }
 
     pub async fn get_account(&self, index: usize, password: &str) -> Result<Address> {
@@ -195,6 +266,169 @@ impl Web3Contract {
     }
 }
 
+/// The number of blocks queried by a single `eth_getLogs` call.
+/// It is halved while the provider rejects the queries, and grows back up to the maximum
+/// after they are accepted again.
+#[derive(Debug)]
+pub(crate) struct BlockRange {
+    max: u64,
+    current: AtomicU64,
+}
+
+impl BlockRange {
+    pub(crate) fn new(max: u64) -> Self {
+        BlockRange {
+            max,
+            current: AtomicU64::new(max),
+        }
+    }
+
+    pub(crate) fn current(&self) -> u64 {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// Fetch the logs in the blocks from `from_block` to `to_block` by `fetch`, which returns
+    /// the hash of the last block of the queried range and the logs in the range.
+    /// The rest of the blocks are left once `event_limit` logs are fetched.
+    /// Returns the logs and the last scanned block with its hash.
+    pub(crate) async fn scan<T, F, Fut>(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        event_limit: usize,
+        mut fetch: F,
+    ) -> Result<(Vec<T>, Option<(u64, BlockHash)>)>
+    where
+        F: FnMut(u64, u64) -> Fut,
+        Fut: Future<Output = Result<(Option<BlockHash>, Vec<T>)>>,
+    {
+        let mut logs = vec![];
+        let mut scanned = None;
+        let mut from = from_block;
+        while from <= to_block && logs.len() < event_limit {
+            let range = self.current();
+            let to = cmp::min(from + range - 1, to_block);
+            match fetch(from, to).await {
+                Ok((to_hash, chunk)) => {
+                    logs.extend(chunk);
+                    scanned = to_hash.map(|hash| (to, hash));
+                    // Grow the range back gradually after the queries are accepted again.
+                    if range < self.max {
+                        self.current
+                            .store(cmp::min(range * 2, self.max), Ordering::Relaxed);
+                    }
+                }
+                Err(e) if range > 1 && is_range_error(&e) => {
+                    warn!(
+                        "The query of the blocks {}-{} is rejected, so the range is shrunk to {} blocks: {:?}",
+                        from,
+                        to,
+                        range / 2,
+                        e
+                    );
+                    self.current.store(range / 2, Ordering::Relaxed);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            info!(
+                "Fetched events up to the block {} of {} ({} blocks remaining, {} events)",
+                to,
+                to_block,
+                to_block - to,
+                logs.len()
+            );
+            from = to + 1;
+        }
+
+        Ok((logs, scanned))
+    }
+}
+
+/// Hints in the messages of the errors which providers return for too many blocks or results,
+/// e.g. "query returned more than 10000 results" and "Log response size exceeded".
+const RANGE_ERROR_HINTS: &[&str] = &[
+    "range",
+    "more than",
+    "too many",
+    "too large",
+    "size",
+    "limit",
+    "exceed",
+    "413",
+    "timeout",
+    "timed out",
+];
+
+/// Whether the query may be accepted with a smaller range.
+/// Most providers reject too large queries by JSON-RPC errors, but some of them respond
+/// with HTTP errors or time out, which are reported as transport errors.
+fn is_range_error(e: &HostError) -> bool {
+    match e {
+        HostError::Web3Error(web3::Error::Rpc(_)) => true,
+        HostError::Web3Error(web3::Error::Transport(msg))
+        | HostError::Web3Error(web3::Error::InvalidResponse(msg)) => {
+            let msg = msg.to_lowercase();
+            RANGE_ERROR_HINTS.iter().any(|hint| msg.contains(hint))
+        }
+        _ => false,
+    }
+}
+
+/// How the scanned blocks are affected by a chain reorganization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ForkPoint {
+    /// The latest scanned block is still on the chain.
+    NotReorged,
+    /// Only the blocks without processed events are replaced,
+    /// so events are fetched again from the block after the fork point.
+    Rewind(u64),
+    /// The block containing processed events is replaced, or all the scanned blocks are replaced
+    /// so that the fork point is unknown.
+    Reorged(u64),
+}
+
+/// Find the latest scanned block which is still on the chain.
+/// `scanned_blocks` are ordered from the latest one as `get_block_hashes`,
+/// and `block_hash` looks up the hash of the block on the current chain.
+pub(crate) async fn find_fork_point<F, Fut>(
+    scanned_blocks: Vec<(u64, ScannedBlock)>,
+    mut block_hash: F,
+) -> Result<ForkPoint>
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<Option<BlockHash>>>,
+{
+    let mut reorged_num = None;
+    for (block_num, scanned) in scanned_blocks {
+        if block_hash(block_num).await? == Some(scanned.hash) {
+            return match reorged_num {
+                Some(_) => Ok(ForkPoint::Rewind(block_num + 1)),
+                None => Ok(ForkPoint::NotReorged),
+            };
+        }
+        if scanned.has_events {
+            return Ok(ForkPoint::Reorged(block_num));
+        }
+        reorged_num = Some(block_num);
+    }
+
+    match reorged_num {
+        Some(block_num) => Ok(ForkPoint::Reorged(block_num)),
+        None => Ok(ForkPoint::NotReorged),
+    }
+}
+
+/// Convert a chain-neutral address into an Ethereum address.
+pub fn eth_address(address: &ChainAddress) -> Result<Address> {
+    if address.as_bytes().len() != Address::len_bytes() {
+        return Err(HostError::InvalidAddress(address.clone()));
+    }
+
+    Ok(Address::from_slice(address.as_bytes()))
+}
+
 /// Basic web3 connection components via HTTP.
 #[derive(Debug)]
 pub struct Web3Http {
@@ -233,6 +467,21 @@ impl Web3Http {
         Ok(account)
     }
 
+    pub async fn block_number(&self) -> Result<u64> {
+        let block_num = self.web3.eth().block_number().await?;
+        Ok(block_num.as_u64())
+    }
+
+    /// The hash of the block on the current canonical chain, or `None` if the chain doesn't reach the block yet.
+    pub async fn block_hash(&self, block_num: u64) -> Result<Option<BlockHash>> {
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block_num.into())))
+            .await?;
+        Ok(block.and_then(|b| b.hash).map(|hash| hash.to_fixed_bytes()))
+    }
+
     pub async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
         self.web3
             .eth()
@@ -271,7 +520,7 @@ impl Web3Http {
                     ecall_output.mrenclave_ver(),
                     ecall_output.roster_idx(),
                 ),
-                output.signer,
+                eth_address(&output.signer)?,
             )
             .await?;
 
@@ -282,3 +531,159 @@ impl Web3Http {
         &self.eth_url
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn scanned(hash: u8, has_events: bool) -> ScannedBlock {
+        ScannedBlock {
+            hash: [hash; 32],
+            has_events,
+        }
+    }
+
+    async fn fork_point(
+        scanned_blocks: Vec<(u64, ScannedBlock)>,
+        chain: &HashMap<u64, u8>,
+    ) -> ForkPoint {
+        find_fork_point(scanned_blocks, |block_num| {
+            let hash = chain.get(&block_num).map(|hash| [*hash; 32]);
+            async move { Ok(hash) }
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Fetch the block numbers as logs, which are rejected if more than `max_range` blocks are queried.
+    async fn scan(
+        block_range: &BlockRange,
+        from_block: u64,
+        to_block: u64,
+        event_limit: usize,
+        max_range: u64,
+        queried: &mut Vec<(u64, u64)>,
+    ) -> Result<(Vec<u64>, Option<(u64, BlockHash)>)> {
+        block_range
+            .scan(from_block, to_block, event_limit, |from, to| {
+                queried.push((from, to));
+                let res = if to - from + 1 > max_range {
+                    Err(
+                        web3::Error::Transport("query returned more than 10000 results".into())
+                            .into(),
+                    )
+                } else {
+                    Ok((Some([to as u8; 32]), (from..=to).collect()))
+                };
+                async move { res }
+            })
+            .await
+    }
+
+    #[actix_rt::test]
+    async fn test_scan_blocks_shrinks_and_grows_range() {
+        let block_range = BlockRange::new(16);
+        let mut queried = vec![];
+        let (logs, scanned) = scan(&block_range, 0, 40, 100, 4, &mut queried)
+            .await
+            .unwrap();
+        assert_eq!(logs, (0..=40).collect::<Vec<_>>());
+        assert_eq!(scanned, Some((40, [40u8; 32])));
+        // The rejected range is halved until it's accepted, and then doubled for the next query.
+        assert_eq!(&queried[..4], &[(0, 15), (0, 7), (0, 3), (4, 11)]);
+        assert!(queried.contains(&(36, 39)));
+        assert_eq!(block_range.current(), 16);
+
+        // The range grows back up to the maximum once the queries are accepted again.
+        block_range.current.store(2, Ordering::Relaxed);
+        queried.clear();
+        scan(&block_range, 41, 80, 100, 16, &mut queried)
+            .await
+            .unwrap();
+        assert_eq!(
+            queried,
+            vec![(41, 42), (43, 46), (47, 54), (55, 70), (71, 80)]
+        );
+        assert_eq!(block_range.current(), 16);
+    }
+
+    #[actix_rt::test]
+    async fn test_scan_blocks_up_to_event_limit() {
+        let block_range = BlockRange::new(4);
+        let mut queried = vec![];
+        let (logs, scanned) = scan(&block_range, 0, 40, 6, 4, &mut queried).await.unwrap();
+        // The rest of the blocks are left to the next polling.
+        assert_eq!(logs, (0..=7).collect::<Vec<_>>());
+        assert_eq!(scanned, Some((7, [7u8; 32])));
+    }
+
+    #[actix_rt::test]
+    async fn test_scan_blocks_fails_with_unrecoverable_errors() {
+        // A single block is rejected
+        let block_range = BlockRange::new(4);
+        let mut queried = vec![];
+        assert!(scan(&block_range, 0, 40, 100, 0, &mut queried)
+            .await
+            .is_err());
+        assert_eq!(queried, vec![(0, 3), (0, 1), (0, 0)]);
+
+        // The errors unrelated to the range are not retried.
+        let block_range = BlockRange::new(4);
+        let res: Result<(Vec<u64>, _)> = block_range
+            .scan(0, 40, 100, |_, _| async {
+                Err(web3::Error::Transport("Connection refused".into()).into())
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(block_range.current(), 4);
+    }
+
+    #[test]
+    fn test_is_range_error() {
+        let transport = |msg: &str| HostError::from(web3::Error::Transport(msg.to_string()));
+        assert!(is_range_error(&transport(
+            "Unexpected response status code: 413 Payload Too Large"
+        )));
+        assert!(is_range_error(&transport("Log response size exceeded.")));
+        assert!(is_range_error(&transport("operation timed out")));
+        assert!(!is_range_error(&transport("Connection refused")));
+        assert!(!is_range_error(&HostError::from(web3::Error::Unreachable)));
+    }
+
+    #[actix_rt::test]
+    async fn test_find_fork_point() {
+        let scanned_blocks = vec![
+            (12, scanned(12, false)),
+            (11, scanned(11, false)),
+            (10, scanned(10, true)),
+        ];
+        let mut chain: HashMap<u64, u8> = (10..=12).map(|n| (n, n as u8)).collect();
+        assert_eq!(
+            fork_point(scanned_blocks.clone(), &chain).await,
+            ForkPoint::NotReorged
+        );
+        assert_eq!(fork_point(vec![], &chain).await, ForkPoint::NotReorged);
+
+        // The blocks without processed events are replaced, so they are fetched again.
+        chain.insert(11, 0);
+        chain.remove(&12);
+        assert_eq!(
+            fork_point(scanned_blocks.clone(), &chain).await,
+            ForkPoint::Rewind(11)
+        );
+
+        // The block containing processed events is replaced.
+        chain.insert(10, 0);
+        assert_eq!(
+            fork_point(scanned_blocks.clone(), &chain).await,
+            ForkPoint::Reorged(10)
+        );
+
+        // All the scanned blocks are replaced, so the fork point is unknown.
+        assert_eq!(
+            fork_point(scanned_blocks[..2].to_vec(), &chain).await,
+            ForkPoint::Reorged(11)
+        );
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     workflow::*,
 };
 use async_trait::async_trait;
+use frame_common::chain_types::ChainAddress;
 use frame_config::{REQUEST_RETRIES, RETRY_DELAY_MILLS};
 use frame_retrier::{strategy, Retry};
 use sgx_types::sgx_enclave_id_t;
@@ -44,6 +45,8 @@ pub struct EthDeployer {
 
 #[async_trait]
 impl Deployer for EthDeployer {
+    type Contract = Web3Contract;
+
     fn new(enclave_id: sgx_enclave_id_t, node_url: &str) -> Result<Self> {
         let web3_conn = Web3Http::new(node_url)?;
 
@@ -54,7 +57,7 @@ impl Deployer for EthDeployer {
         })
     }
 
-    async fn get_account(&self, index: usize, password: &str) -> Result<Address> {
+    async fn get_account(&self, index: usize, password: &str) -> Result<ChainAddress> {
         Retry::new(
             "get_account",
             *REQUEST_RETRIES,
@@ -63,6 +66,7 @@ impl Deployer for EthDeployer {
         .set_condition(deployer_retry_condition)
         .spawn_async(|| async { self.web3_conn.get_account(index, password).await })
         .await
+        .map(Into::into)
     }
 
     async fn deploy<P>(
@@ -93,16 +97,14 @@ impl Deployer for EthDeployer {
         Ok(hex::encode(contract_addr.as_bytes()))
     }
 
-    fn get_contract<P: AsRef<Path>>(self, abi_path: P) -> Result<ContractKind> {
-        let addr = self
-            .address
-            .expect("The contract hasn't be deployed yet.")
-            .to_string();
+    fn get_contract<P: AsRef<Path>>(self, abi_path: P) -> Result<Web3Contract> {
+        let addr = hex::encode(
+            self.address
+                .expect("The contract hasn't be deployed yet.")
+                .as_bytes(),
+        );
         let contract_info = ContractInfo::new(abi_path, &addr);
-        Ok(ContractKind::Web3Contract(Web3Contract::new(
-            self.web3_conn,
-            contract_info,
-        )?))
+        Web3Contract::new(self.web3_conn, contract_info)
     }
 
     fn get_enclave_id(&self) -> sgx_enclave_id_t {
@@ -1,22 +1,22 @@
 use super::connection::{Web3Contract, Web3Http};
 use crate::{
-    cache::EventCache,
+    cache::{BlockHash, EventCache},
     error::{HostError, Result},
+    event::{EnclaveLog, PayloadType},
     traits::*,
     utils::*,
-    workflow::*,
 };
 use async_trait::async_trait;
 use ethabi::{decode, Event, EventParam, Hash, ParamType};
 use frame_common::{
+    chain_types::ChainAddress,
     crypto::{Ciphertext, ExportHandshake},
     state_types::StateCounter,
 };
-use frame_host::engine::HostEngine;
 use sgx_types::sgx_enclave_id_t;
-use std::{cmp::Ordering, fmt, path::Path};
-use tracing::{debug, error, info, warn};
-use web3::types::{Address, Log};
+use std::{fmt, path::Path};
+use tracing::{error, info, warn};
+use web3::types::Log;
 
 /// Components needed to watch events
 pub struct EventWatcher {
@@ -26,6 +26,8 @@ pub struct EventWatcher {
 
 #[async_trait]
 impl Watcher for EventWatcher {
+    type Contract = Web3Contract;
+
     fn new<P: AsRef<Path>>(
         node_url: &str,
         contract_info: ContractInfo<'_, P>,
@@ -40,27 +42,39 @@ impl Watcher for EventWatcher {
     /// Fetch events of the specified topics on the blockchain.
     /// This method is supposed to be called for polling.
     /// If an error occurs in the process of updating the status due to the fetched events,
-    /// that events will be skipped. (No retry process)
+    /// that events will be skipped if the enclave has consumed their state counters,
+    /// otherwise they are inserted again by the next polling.
     /// If an error occurs on all TEE nodes due to an invalid event etc., skip processing is okay.
+    /// Before fetching, the blocks scanned so far are verified against chain reorganizations.
     async fn fetch_events(
         &self,
         eid: sgx_enclave_id_t,
         fetch_ciphertext_cmd: u32,
         fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
     ) -> Result<Option<Vec<serde_json::Value>>> {
+        let contract_addr = ChainAddress::from(self.contract.address());
+        self.contract
+            .check_reorg(&self.cache, &contract_addr)
+            .await?;
         let enclave_updated_state = self
             .contract
-            .get_event(self.cache.clone(), self.contract.address())
+            .get_event(self.cache.clone(), &contract_addr)
             .await?
             .into_enclave_log()
-            .insert_enclave(eid, fetch_ciphertext_cmd, fetch_handshake_cmd)
-            .save_cache(self.contract.address());
+            .insert_enclave(
+                eid,
+                fetch_ciphertext_cmd,
+                fetch_handshake_cmd,
+                state_counter_cmd,
+            )
+            .save_cache(&contract_addr);
 
         Ok(enclave_updated_state.notify_states())
     }
 
-    fn get_contract(self) -> ContractKind {
-        ContractKind::Web3Contract(self.contract)
+    fn get_contract(self) -> Web3Contract {
+        self.contract
     }
 }
 
@@ -104,42 +118,63 @@ pub struct Web3Logs {
     logs: Vec<EthLog>,
     cache: EventCache,
     events: EthEvent,
+    contract_addr: ChainAddress,
+    /// The last block scanned for the logs and its hash
+    scanned: Option<(u64, BlockHash)>,
 }
 
 impl Web3Logs {
-    pub fn new(logs: Vec<Log>, cache: EventCache, events: EthEvent) -> Self {
+    pub fn new(
+        logs: Vec<Log>,
+        cache: EventCache,
+        events: EthEvent,
+        contract_addr: ChainAddress,
+        scanned: Option<(u64, BlockHash)>,
+    ) -> Self {
         let logs: Vec<EthLog> = logs.into_iter().map(Into::into).collect();
         Web3Logs {
             logs,
             cache,
             events,
+            contract_addr,
+            scanned,
         }
     }
 
     fn into_enclave_log(self) -> EnclaveLog {
         let mut payloads: Vec<PayloadType> = vec![];
 
-        // If log data is not fetched, return empty EnclaveLog.
-        // This is occurred when it fetched data of dupulicated block number.
-        if self.logs.is_empty() {
-            return EnclaveLog {
-                inner: None,
-                cache: self.cache,
-            };
-        }
-
-        let contract_addr = self.logs[0].0.address;
-        let mut latest_blc_num = 0;
+        // The hashes of the blocks containing events are recorded after the scanned one,
+        // so that they take precedence if the chain is reorganized in between.
+        let mut latest_blc_num = match self.scanned {
+            Some((blc_num, hash)) => {
+                self.cache.inner().write().insert_block_hash(
+                    self.contract_addr.clone(),
+                    blc_num,
+                    hash,
+                    false,
+                );
+                Some(blc_num)
+            }
+            None => None,
+        };
 
         for (i, log) in self.logs.iter().enumerate() {
             info!(
                 "Fetched eth event log: {:?}, \npolling event index: {:?}",
                 log, i
             );
-            if contract_addr != log.0.address {
+            if self.contract_addr != ChainAddress::from(log.0.address) {
                 error!("Each log should have same contract address.: index: {}", i);
                 continue;
             }
+            if log.0.removed == Some(true) {
+                warn!(
+                    "A log removed by a chain reorganization is skipped: index: {}",
+                    i
+                );
+                continue;
+            }
 
             let (bytes, state_counter) = match decode_data(&log) {
                 Ok(d) => d,
@@ -148,6 +183,13 @@ impl Web3Logs {
                     continue;
                 }
             };
+            let blc_num = log.0.block_number.map_or(0, |n| n.as_u64());
+            let log_index = log.0.log_index.map_or(0, |i| i.as_u64());
+
+            // Update latest block number
+            if latest_blc_num.map_or(true, |latest| latest < blc_num) {
+                latest_blc_num = Some(blc_num)
+            }
 
             // Processing conditions by ciphertext or handshake event
             if log.0.topics[0] == self.events.ciphertext_signature() {
@@ -158,14 +200,10 @@ impl Web3Logs {
                         continue;
                     }
                 };
-                let payload = PayloadType::new(
-                    res.roster_idx(),
-                    res.epoch(),
-                    res.generation(),
-                    Payload::Ciphertext(res),
-                    state_counter,
+                payloads.push(
+                    PayloadType::from_ciphertext(res, state_counter, blc_num)
+                        .set_log_index(log_index),
                 );
-                payloads.push(payload);
             } else if log.0.topics[0] == self.events.handshake_signature() {
                 let res = match ExportHandshake::decode(&bytes[..]) {
                     Ok(c) => c,
@@ -174,348 +212,32 @@ impl Web3Logs {
                         continue;
                     }
                 };
-                let payload = PayloadType::new(
-                    res.roster_idx(),
-                    res.prior_epoch(),
-                    u32::MAX, // handshake is the last of the generation
-                    Payload::Handshake(res),
-                    state_counter,
+                payloads.push(
+                    PayloadType::from_handshake(res, state_counter, blc_num)
+                        .set_log_index(log_index),
                 );
-                payloads.push(payload);
             } else {
                 error!("Invalid topics: {:?}", log.0.topics[0]);
                 continue;
             }
 
-            // Update latest block number
-            if let Some(blc_num) = log.0.block_number {
-                let blc_num = blc_num.as_u64();
-                if latest_blc_num < blc_num {
-                    latest_blc_num = blc_num
-                }
-            }
-        }
-
-        EnclaveLog {
-            inner: Some(InnerEnclaveLog {
-                contract_addr: contract_addr.to_fixed_bytes(),
-                latest_blc_num,
-                payloads,
-                logs: self.logs,
-            }),
-            cache: self.cache,
-        }
-    }
-}
-
-/// A wrapper type of enclave logs.
-#[derive(Debug)]
-struct EnclaveLog {
-    inner: Option<InnerEnclaveLog>,
-    cache: EventCache,
-}
-
-impl EnclaveLog {
-    /// Store logs into enclave in-memory.
-    /// This returns a latest block number specified by fetched logs.
-    fn insert_enclave(
-        self,
-        eid: sgx_enclave_id_t,
-        fetch_ciphertext_cmd: u32,
-        fetch_handshake_cmd: u32,
-    ) -> EnclaveUpdatedState {
-        match self.inner {
-            Some(log) => {
-                let next_blc_num = log.latest_blc_num + 1;
-                let notify_states =
-                    log.invoke_ecall(eid, fetch_ciphertext_cmd, fetch_handshake_cmd);
-
-                EnclaveUpdatedState {
-                    block_num: Some(next_blc_num),
-                    notify_states,
-                    cache: self.cache,
-                }
-            }
-            None => EnclaveUpdatedState {
-                block_num: None,
-                notify_states: None,
-                cache: self.cache,
-            },
-        }
-    }
-}
-
-/// A log which is sent to enclave. Each log containes ciphertexts data of a given contract address and a given block number.
-#[derive(Debug, Clone)]
-struct InnerEnclaveLog {
-    contract_addr: [u8; 20],
-    latest_blc_num: u64,
-    payloads: Vec<PayloadType>,
-    logs: Vec<EthLog>,
-}
-
-impl InnerEnclaveLog {
-    fn invoke_ecall(
-        self,
-        eid: sgx_enclave_id_t,
-        fetch_ciphertext_cmd: u32,
-        fetch_handshake_cmd: u32,
-    ) -> Option<Vec<serde_json::Value>> {
-        if self.payloads.is_empty() {
-            debug!("No logs to insert into the enclave.");
-            None
-        } else {
-            let mut acc = vec![];
-
-            for e in self.payloads {
-                match e.payload {
-                    Payload::Ciphertext(ref ciphertext) => {
-                        info!(
-                            "Fetch a ciphertext: roster_idx: {}, epoch: {}, generation: {}",
-                            ciphertext.roster_idx(),
-                            ciphertext.epoch(),
-                            ciphertext.generation()
-                        );
-
-                        let inp = host_input::InsertCiphertext::new(
-                            ciphertext.clone(),
-                            e.state_counter(),
-                            fetch_ciphertext_cmd,
-                        );
-                        match InsertCiphertextWorkflow::exec(inp, eid)
-                            .map_err(Into::into)
-                            .and_then(|e| {
-                                e.ecall_output.ok_or_else(|| HostError::EcallOutputNotSet)
-                            }) {
-                            Ok(notify) => {
-                                if let Some(notify_state) = notify.state {
-                                    match bincode::deserialize::<Vec<u8>>(
-                                        &notify_state.into_vec()[..],
-                                    ) {
-                                        Ok(bytes) => match serde_json::from_slice(&bytes[..]) {
-                                            Ok(json) => acc.push(json),
-                                            Err(err) => error!(
-                                                "Error in serde_json::from_slice(&bytes[..]): {:?}",
-                                                err
-                                            ),
-                                        },
-                                        Err(err) => {
-                                            error!("Error in bincode::deserialize: {:?}", err)
-                                        }
-                                    }
-                                }
-                            }
-                            // Even if an error occurs in Enclave, it is unlikely that retry process will succeed,
-                            // so skip the event.
-                            Err(err) => {
-                                error!(
-                                    "Error in enclave (InsertCiphertextWorkflow::exec): {:?}",
-                                    err
-                                );
-
-                                // Logging a skipped event
-                                match (&self.logs)
-                                    .into_iter()
-                                    .find(|log| match decode_data(&log) {
-                                        Ok((bytes, _state_counter)) => match Ciphertext::decode(&mut &bytes[..]) {
-                                            Ok(ref res) => res == ciphertext,
-                                            Err(error) => {
-                                                error!("Ciphertext::decode error: {:?}", error);
-                                                false
-                                            }
-                                        },
-                                        Err(error) => {
-                                            error!("decode_data error: {:?}", error);
-                                            false
-                                        }
-                                    }) {
-                                    Some(skipped_log) => {
-                                        warn!(
-                                            "A event is skipped because of occurring error in enclave: {:?}",
-                                            skipped_log
-                                        )
-                                    }
-                                    None => {
-                                        error!(
-                                            "Not found the skipped event. The corresponding ciphertext is {:?}",
-                                            ciphertext
-                                        );
-                                    }
-                                }
-
-                                continue;
-                            }
-                        };
-                    }
-                    Payload::Handshake(ref handshake) => {
-                        info!(
-                            "Fetch a handshake: roster_idx: {}, epoch: {}",
-                            handshake.roster_idx(),
-                            handshake.prior_epoch(),
-                        );
-
-                        if let Err(e) = Self::insert_handshake(
-                            eid,
-                            handshake.clone(),
-                            e.state_counter(),
-                            fetch_handshake_cmd,
-                        ) {
-                            error!("Error in enclave (InsertHandshakeWorkflow::exec): {:?}", e);
-                            continue;
-                        }
-                    }
-                }
-            }
-
-            if acc.is_empty() {
-                None
-            } else {
-                Some(acc)
+            if let Some(hash) = log.0.block_hash {
+                self.cache.inner().write().insert_block_hash(
+                    self.contract_addr.clone(),
+                    blc_num,
+                    hash.to_fixed_bytes(),
+                    true,
+                );
             }
         }
-    }
-
-    fn insert_handshake(
-        eid: sgx_enclave_id_t,
-        handshake: ExportHandshake,
-        state_counter: StateCounter,
-        fetch_handshake_cmd: u32,
-    ) -> Result<()> {
-        let input = host_input::InsertHandshake::new(handshake, state_counter, fetch_handshake_cmd);
-        InsertHandshakeWorkflow::exec(input, eid)?;
-
-        Ok(())
-    }
-}
 
-#[derive(Debug)]
-pub struct EnclaveUpdatedState {
-    block_num: Option<u64>,
-    notify_states: Option<Vec<serde_json::Value>>,
-    cache: EventCache,
-}
-
-impl EnclaveUpdatedState {
-    /// Only if EnclaveUpdatedState has new block number to log,
-    /// it's set next block number to event cache.
-    pub fn save_cache(self, contract_addr: Address) -> Self {
-        match &self.block_num {
-            Some(block_num) => {
-                let mut w = self.cache.inner().write();
-                w.insert_next_block_num(contract_addr, *block_num);
+        // If no blocks are scanned, e.g. when not enough blocks are confirmed, return empty EnclaveLog.
+        match latest_blc_num {
+            Some(latest_blc_num) => {
+                EnclaveLog::new(self.contract_addr, latest_blc_num, payloads, self.cache)
             }
-            None => {}
-        }
-
-        self
-    }
-
-    pub fn notify_states(self) -> Option<Vec<serde_json::Value>> {
-        self.notify_states
-    }
-}
-
-#[derive(Debug, Clone, Hash)]
-pub struct PayloadType {
-    roster_idx: u32,
-    epoch: u32,
-    generation: u32,
-    payload: Payload,
-    state_counter: StateCounter,
-}
-
-impl PayloadType {
-    pub(crate) fn new(
-        roster_idx: u32,
-        epoch: u32,
-        generation: u32,
-        payload: Payload,
-        state_counter: StateCounter,
-    ) -> Self {
-        PayloadType {
-            roster_idx,
-            epoch,
-            generation,
-            payload,
-            state_counter,
-        }
-    }
-
-    /// other is the next of self
-    pub fn is_next(&self, other: &Self) -> bool {
-        self.roster_idx == other.roster_idx
-            && ((self.epoch == other.epoch && self.generation + 1 == other.generation) ||
-            (self.epoch == other.epoch && other.generation == u32::MAX) || // TODO: order gurantee with handshake
-            (self.epoch + 1 == other.epoch && self.generation == u32::MAX && other.generation == 0))
-    }
-
-    pub fn roster_idx(&self) -> u32 {
-        self.roster_idx
-    }
-
-    pub fn epoch(&self) -> u32 {
-        self.epoch
-    }
-
-    pub fn generation(&self) -> u32 {
-        self.generation
-    }
-
-    pub fn state_counter(&self) -> StateCounter {
-        self.state_counter
-    }
-}
-
-impl PartialEq for PayloadType {
-    fn eq(&self, other: &PayloadType) -> bool {
-        self.roster_idx == other.roster_idx
-            && self.epoch == other.epoch
-            && self.generation == other.generation
-    }
-}
-
-impl Eq for PayloadType {}
-
-/// Ordering PayloadType> like:
-/// epoch      | 0              1            2 ..
-/// generation | 0 1 2 3 .. MAX 0 1 2 .. MAX 0 ..
-impl PartialOrd for PayloadType {
-    fn partial_cmp(&self, other: &PayloadType) -> Option<Ordering> {
-        let roster_idx_ord = self.roster_idx.partial_cmp(&other.roster_idx)?;
-        if roster_idx_ord != Ordering::Equal {
-            return Some(roster_idx_ord);
+            None => EnclaveLog::empty(self.cache),
         }
-
-        let epoch_ord = self.epoch.partial_cmp(&other.epoch)?;
-        if epoch_ord != Ordering::Equal {
-            return Some(epoch_ord);
-        }
-
-        let gen_ord = self.generation.partial_cmp(&other.generation)?;
-        if gen_ord != Ordering::Equal {
-            return Some(gen_ord);
-        }
-
-        Some(Ordering::Equal)
-    }
-}
-
-impl Ord for PayloadType {
-    fn cmp(&self, other: &PayloadType) -> Ordering {
-        self.partial_cmp(&other)
-            .expect("PayloadType must be ordered")
-    }
-}
-
-#[derive(Debug, Clone, Hash)]
-pub(crate) enum Payload {
-    Ciphertext(Ciphertext),
-    Handshake(ExportHandshake),
-}
-
-impl Default for Payload {
-    fn default() -> Self {
-        Payload::Ciphertext(Default::default())
     }
 }
 
@@ -6,12 +6,13 @@ use crate::{
     workflow::*,
 };
 use async_trait::async_trait;
+use frame_common::chain_types::{ChainAddress, TxHash};
 use frame_config::{REQUEST_RETRIES, RETRY_DELAY_MILLS};
 use frame_retrier::{strategy, Retry};
 use sgx_types::sgx_enclave_id_t;
 use std::path::Path;
 use tracing::info;
-use web3::types::{Address, H256};
+use web3::types::H256;
 
 /// Define a retry condition of sending transactions.
 /// If it returns false, don't need to retry sending transactions.
@@ -38,6 +39,8 @@ pub struct EthSender {
 
 #[async_trait]
 impl Sender for EthSender {
+    type Contract = Web3Contract;
+
     fn new<P: AsRef<Path>>(
         enclave_id: sgx_enclave_id_t,
         node_url: &str,
@@ -52,16 +55,14 @@ impl Sender for EthSender {
         })
     }
 
-    fn from_contract(enclave_id: sgx_enclave_id_t, contract: ContractKind) -> Self {
-        match contract {
-            ContractKind::Web3Contract(contract) => EthSender {
-                enclave_id,
-                contract,
-            },
+    fn from_contract(enclave_id: sgx_enclave_id_t, contract: Web3Contract) -> Self {
+        EthSender {
+            enclave_id,
+            contract,
         }
     }
 
-    async fn get_account(&self, index: usize, password: &str) -> Result<Address> {
+    async fn get_account(&self, index: usize, password: &str) -> Result<ChainAddress> {
         Retry::new(
             "get_account",
             *REQUEST_RETRIES,
@@ -69,13 +70,14 @@ impl Sender for EthSender {
         )
         .spawn_async(|| async { self.contract.get_account(index, password).await })
         .await
+        .map(Into::into)
     }
 
     async fn send_report_handshake(
         &self,
         host_output: &host_output::JoinGroup,
         method: &str,
-    ) -> Result<H256> {
+    ) -> Result<TxHash> {
         info!("Sending a handshake to blockchain: {:?}", host_output);
         Retry::new(
             "send_report_handshake",
@@ -89,9 +91,10 @@ impl Sender for EthSender {
                 .await
         })
         .await
+        .map(Into::into)
     }
 
-    async fn register_report(&self, host_output: &host_output::RegisterReport) -> Result<H256> {
+    async fn register_report(&self, host_output: &host_output::RegisterReport) -> Result<TxHash> {
         info!("Registering report to blockchain: {:?}", host_output);
         Retry::new(
             "send_command",
@@ -101,9 +104,10 @@ impl Sender for EthSender {
         .set_condition(sender_retry_condition)
         .spawn_async(|| async { self.contract.register_report(host_output.clone()).await })
         .await
+        .map(Into::into)
     }
 
-    async fn send_command(&self, host_output: &host_output::Command) -> Result<H256> {
+    async fn send_command(&self, host_output: &host_output::Command) -> Result<TxHash> {
         info!("Sending a command to blockchain: {:?}", host_output);
         Retry::new(
             "send_command",
@@ -113,9 +117,10 @@ impl Sender for EthSender {
         .set_condition(sender_retry_condition)
         .spawn_async(|| async { self.contract.send_command(host_output.clone()).await })
         .await
+        .map(Into::into)
     }
 
-    async fn handshake(&self, host_output: &host_output::Handshake) -> Result<H256> {
+    async fn handshake(&self, host_output: &host_output::Handshake) -> Result<TxHash> {
         info!("Sending a handshake to blockchain: {:?}", host_output);
         Retry::new(
             "handshake",
@@ -125,9 +130,10 @@ impl Sender for EthSender {
         .set_condition(sender_retry_condition)
         .spawn_async(|| async { self.contract.handshake(host_output.clone()).await })
         .await
+        .map(Into::into)
     }
 
-    fn get_contract(self) -> ContractKind {
-        ContractKind::Web3Contract(self.contract)
+    fn get_contract(self) -> Web3Contract {
+        self.contract
     }
 }
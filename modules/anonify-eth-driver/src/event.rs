@@ -0,0 +1,510 @@
+use crate::{
+    cache::EventCache,
+    error::{HostError, Result},
+    workflow::*,
+};
+use anonify_ecall_types::Notification;
+use frame_common::{
+    chain_types::ChainAddress,
+    crypto::{Ciphertext, ExportHandshake},
+    state_types::StateCounter,
+};
+use frame_host::engine::HostEngine;
+use sgx_types::sgx_enclave_id_t;
+use std::{cmp::Ordering, time::Instant};
+use tracing::{debug, error, info, warn};
+
+/// Events of a contract decoded from blockchain-specific logs, which are inserted into the enclave.
+#[derive(Debug)]
+pub struct EnclaveLog {
+    inner: Option<InnerEnclaveLog>,
+    cache: EventCache,
+}
+
+impl EnclaveLog {
+    /// Events which have already been applied to the enclave's persisted states are skipped.
+    pub fn new(
+        contract_addr: ChainAddress,
+        latest_blc_num: u64,
+        payloads: Vec<PayloadType>,
+        cache: EventCache,
+    ) -> Self {
+        let payloads = payloads
+            .into_iter()
+            .filter(|payload| {
+                let is_committed = cache
+                    .inner()
+                    .read()
+                    .is_committed(&contract_addr, payload.state_counter());
+                if is_committed {
+                    debug!(
+                        "Skip a committed event: state counter: {:?}",
+                        payload.state_counter()
+                    );
+                }
+                !is_committed
+            })
+            .collect();
+
+        EnclaveLog {
+            inner: Some(InnerEnclaveLog {
+                contract_addr,
+                latest_blc_num,
+                payloads,
+            }),
+            cache,
+        }
+    }
+
+    /// No events are fetched, e.g. when events of a duplicated block number are fetched.
+    pub fn empty(cache: EventCache) -> Self {
+        EnclaveLog { inner: None, cache }
+    }
+
+    /// Store logs into enclave in-memory.
+    /// This returns a latest block number specified by fetched logs.
+    pub fn insert_enclave(
+        self,
+        eid: sgx_enclave_id_t,
+        fetch_ciphertext_cmd: u32,
+        fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
+    ) -> EnclaveUpdatedState {
+        match self.inner {
+            Some(log) => {
+                let next_blc_num = log.latest_blc_num + 1;
+                let notify_states = log.invoke_ecall(
+                    eid,
+                    fetch_ciphertext_cmd,
+                    fetch_handshake_cmd,
+                    state_counter_cmd,
+                    &self.cache,
+                );
+
+                EnclaveUpdatedState {
+                    block_num: Some(next_blc_num),
+                    notify_states,
+                    cache: self.cache,
+                }
+            }
+            None => EnclaveUpdatedState {
+                block_num: None,
+                notify_states: None,
+                cache: self.cache,
+            },
+        }
+    }
+}
+
+/// A log which is sent to enclave. Each log containes ciphertexts data of a given contract address and a given block number.
+#[derive(Debug, Clone)]
+struct InnerEnclaveLog {
+    contract_addr: ChainAddress,
+    latest_blc_num: u64,
+    payloads: Vec<PayloadType>,
+}
+
+impl InnerEnclaveLog {
+    fn invoke_ecall(
+        self,
+        eid: sgx_enclave_id_t,
+        fetch_ciphertext_cmd: u32,
+        fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
+        cache: &EventCache,
+    ) -> Option<Vec<serde_json::Value>> {
+        // The enclave rejects any gap of the state counters, so the events are inserted in the order of them.
+        let payloads = {
+            let mut inner = cache.inner().write();
+            let now = Instant::now();
+            for payload in self.payloads {
+                inner.insert_pending(self.contract_addr.clone(), payload, now);
+            }
+            inner.take_ready(&self.contract_addr, now)
+        };
+
+        if payloads.is_empty() {
+            debug!("No logs to insert into the enclave.");
+            None
+        } else {
+            let mut acc = vec![];
+
+            let mut payloads = payloads.into_iter();
+            while let Some(e) = payloads.next() {
+                let err = match insert_payload(
+                    eid,
+                    &self.contract_addr,
+                    &e,
+                    fetch_ciphertext_cmd,
+                    fetch_handshake_cmd,
+                    cache,
+                ) {
+                    Ok(notifications) => {
+                        cache
+                            .inner()
+                            .write()
+                            .commit_state_counter(self.contract_addr.clone(), e.state_counter());
+                        acc.extend(notifications);
+                        continue;
+                    }
+                    Err(err) => err,
+                };
+                error!("Error in enclave: {:?}", err);
+
+                // The event is skipped only if the enclave has consumed its state counter,
+                // otherwise the enclave would reject all the following events.
+                match enclave_state_counter(eid, state_counter_cmd) {
+                    Ok(enclave) if e.state_counter() <= enclave => {
+                        cache
+                            .inner()
+                            .write()
+                            .commit_state_counter(self.contract_addr.clone(), e.state_counter());
+                        // The enclave has chained the payload into its hash as well.
+                        InnerEnclaveLog::checkpoint(cache, &self.contract_addr, &e);
+                        // Even if an error occurs in Enclave, it is unlikely that retry process will succeed,
+                        // so skip the event and keep it as a dead letter for investigation.
+                        warn!(
+                            "A event is skipped because of occurring error in enclave: state counter: {:?}, block number: {}, payload: {:?}",
+                            e.state_counter(),
+                            e.block_num(),
+                            e.payload
+                        );
+                        cache.dead_letters().push(&self.contract_addr, &e, &err);
+                    }
+                    res => {
+                        if let Err(query_err) = res {
+                            error!(
+                                "Failed to get the state counter of the enclave: {:?}",
+                                query_err
+                            );
+                        }
+                        // The event and the following ones wait to be inserted again by the next polling.
+                        warn!(
+                            "A event is inserted again later because it didn't reach the enclave: state counter: {:?}, block number: {}",
+                            e.state_counter(),
+                            e.block_num()
+                        );
+                        let mut inner = cache.inner().write();
+                        let now = Instant::now();
+                        for payload in std::iter::once(e).chain(payloads) {
+                            inner.insert_pending(self.contract_addr.clone(), payload, now);
+                        }
+                        break;
+                    }
+                }
+            }
+
+            if acc.is_empty() {
+                None
+            } else {
+                Some(acc)
+            }
+        }
+    }
+
+    fn checkpoint(cache: &EventCache, contract_addr: &ChainAddress, payload: &PayloadType) {
+        cache.inner().write().insert_checkpoint(
+            contract_addr.clone(),
+            payload.block_num(),
+            payload.log_index(),
+            payload.state_counter(),
+            &payload.encode_payload(),
+        );
+    }
+
+    fn insert_handshake(
+        eid: sgx_enclave_id_t,
+        handshake: ExportHandshake,
+        state_counter: StateCounter,
+        block_num: u64,
+        fetch_handshake_cmd: u32,
+    ) -> Result<()> {
+        let input = host_input::InsertHandshake::new(
+            handshake,
+            state_counter,
+            block_num,
+            fetch_handshake_cmd,
+        );
+        InsertHandshakeWorkflow::exec(input, eid)?;
+
+        Ok(())
+    }
+}
+
+/// The state counter of the last event the enclave has consumed, whether it succeeded or not.
+pub(crate) fn enclave_state_counter(
+    eid: sgx_enclave_id_t,
+    state_counter_cmd: u32,
+) -> Result<StateCounter> {
+    let input = host_input::GetStateCounter::new(state_counter_cmd);
+    let output = GetStateCounterWorkflow::exec(input, eid)?
+        .ecall_output
+        .ok_or_else(|| HostError::EcallOutputNotSet)?;
+
+    Ok(output.state_counter())
+}
+
+/// Insert the payload into the enclave, returning the notifications of the updated states.
+pub(crate) fn insert_payload(
+    eid: sgx_enclave_id_t,
+    contract_addr: &ChainAddress,
+    e: &PayloadType,
+    fetch_ciphertext_cmd: u32,
+    fetch_handshake_cmd: u32,
+    cache: &EventCache,
+) -> Result<Vec<serde_json::Value>> {
+    let mut acc = vec![];
+    match e.payload {
+        Payload::Ciphertext(ref ciphertext) => {
+            info!(
+                "Fetch a ciphertext: roster_idx: {}, epoch: {}, generation: {}",
+                ciphertext.roster_idx(),
+                ciphertext.epoch(),
+                ciphertext.generation()
+            );
+
+            let inp = host_input::InsertCiphertext::new(
+                ciphertext.clone(),
+                e.state_counter(),
+                e.block_num(),
+                fetch_ciphertext_cmd,
+            );
+            let notify = InsertCiphertextWorkflow::exec(inp, eid)?
+                .ecall_output
+                .ok_or_else(|| HostError::EcallOutputNotSet)?;
+
+            InnerEnclaveLog::checkpoint(cache, contract_addr, e);
+            if let Some((state_counter, state_root)) = notify.state_root {
+                cache.inner().write().insert_state_root(
+                    contract_addr.clone(),
+                    state_counter,
+                    state_root,
+                );
+            }
+            for state in notify.states {
+                match serde_json::to_value(Notification {
+                    state_counter: e.state_counter(),
+                    state,
+                }) {
+                    Ok(json) => acc.push(json),
+                    Err(err) => error!("Error in serde_json::to_value(Notification): {:?}", err),
+                }
+            }
+        }
+        Payload::Handshake(ref handshake) => {
+            info!(
+                "Fetch a handshake: roster_idx: {}, epoch: {}",
+                handshake.roster_idx(),
+                handshake.prior_epoch(),
+            );
+
+            InnerEnclaveLog::insert_handshake(
+                eid,
+                handshake.clone(),
+                e.state_counter(),
+                e.block_num(),
+                fetch_handshake_cmd,
+            )?;
+            InnerEnclaveLog::checkpoint(cache, contract_addr, e);
+        }
+    }
+
+    Ok(acc)
+}
+
+#[derive(Debug)]
+pub struct EnclaveUpdatedState {
+    block_num: Option<u64>,
+    notify_states: Option<Vec<serde_json::Value>>,
+    cache: EventCache,
+}
+
+impl EnclaveUpdatedState {
+    /// Only if EnclaveUpdatedState has new block number to log,
+    /// it's set next block number to event cache, which is persisted with the checkpoints.
+    pub fn save_cache(self, contract_addr: &ChainAddress) -> Self {
+        match &self.block_num {
+            Some(block_num) => {
+                self.cache
+                    .inner()
+                    .write()
+                    .insert_next_block_num(contract_addr.clone(), *block_num);
+                // The enclave may get ahead of the persisted checkpoint, which is allowed when resuming.
+                if let Err(e) = self.cache.persist() {
+                    error!("Failed to persist the event cache: {:?}", e);
+                }
+            }
+            None => {}
+        }
+
+        self
+    }
+
+    pub fn notify_states(self) -> Option<Vec<serde_json::Value>> {
+        self.notify_states
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct PayloadType {
+    roster_idx: u32,
+    epoch: u32,
+    generation: u32,
+    payload: Payload,
+    state_counter: StateCounter,
+    block_num: u64,
+    log_index: u64,
+}
+
+impl PayloadType {
+    pub(crate) fn new(
+        roster_idx: u32,
+        epoch: u32,
+        generation: u32,
+        payload: Payload,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> Self {
+        PayloadType {
+            roster_idx,
+            epoch,
+            generation,
+            payload,
+            state_counter,
+            block_num,
+            log_index: 0,
+        }
+    }
+
+    /// Set the index of the log in the block, which is recorded in the checkpoint.
+    pub fn set_log_index(mut self, log_index: u64) -> Self {
+        self.log_index = log_index;
+        self
+    }
+
+    pub fn from_ciphertext(
+        ciphertext: Ciphertext,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> Self {
+        PayloadType::new(
+            ciphertext.roster_idx(),
+            ciphertext.epoch(),
+            ciphertext.generation(),
+            Payload::Ciphertext(ciphertext),
+            state_counter,
+            block_num,
+        )
+    }
+
+    pub fn from_handshake(
+        handshake: ExportHandshake,
+        state_counter: StateCounter,
+        block_num: u64,
+    ) -> Self {
+        PayloadType::new(
+            handshake.roster_idx(),
+            handshake.prior_epoch(),
+            u32::MAX, // handshake is the last of the generation
+            Payload::Handshake(handshake),
+            state_counter,
+            block_num,
+        )
+    }
+
+    /// other is the next of self
+    pub fn is_next(&self, other: &Self) -> bool {
+        self.roster_idx == other.roster_idx
+            && ((self.epoch == other.epoch && self.generation + 1 == other.generation) ||
+            (self.epoch == other.epoch && other.generation == u32::MAX) || // TODO: order gurantee with handshake
+            (self.epoch + 1 == other.epoch && self.generation == u32::MAX && other.generation == 0))
+    }
+
+    pub fn roster_idx(&self) -> u32 {
+        self.roster_idx
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn state_counter(&self) -> StateCounter {
+        self.state_counter
+    }
+
+    pub fn block_num(&self) -> u64 {
+        self.block_num
+    }
+
+    pub fn log_index(&self) -> u64 {
+        self.log_index
+    }
+
+    pub fn is_handshake(&self) -> bool {
+        matches!(self.payload, Payload::Handshake(_))
+    }
+
+    pub(crate) fn encode_payload(&self) -> Vec<u8> {
+        match &self.payload {
+            Payload::Ciphertext(ciphertext) => ciphertext.encode(),
+            Payload::Handshake(handshake) => handshake.encode(),
+        }
+    }
+}
+
+impl PartialEq for PayloadType {
+    fn eq(&self, other: &PayloadType) -> bool {
+        self.roster_idx == other.roster_idx
+            && self.epoch == other.epoch
+            && self.generation == other.generation
+    }
+}
+
+impl Eq for PayloadType {}
+
+/// Ordering PayloadType> like:
+/// epoch      | 0              1            2 ..
+/// generation | 0 1 2 3 .. MAX 0 1 2 .. MAX 0 ..
+impl PartialOrd for PayloadType {
+    fn partial_cmp(&self, other: &PayloadType) -> Option<Ordering> {
+        let roster_idx_ord = self.roster_idx.partial_cmp(&other.roster_idx)?;
+        if roster_idx_ord != Ordering::Equal {
+            return Some(roster_idx_ord);
+        }
+
+        let epoch_ord = self.epoch.partial_cmp(&other.epoch)?;
+        if epoch_ord != Ordering::Equal {
+            return Some(epoch_ord);
+        }
+
+        let gen_ord = self.generation.partial_cmp(&other.generation)?;
+        if gen_ord != Ordering::Equal {
+            return Some(gen_ord);
+        }
+
+        Some(Ordering::Equal)
+    }
+}
+
+impl Ord for PayloadType {
+    fn cmp(&self, other: &PayloadType) -> Ordering {
+        self.partial_cmp(&other)
+            .expect("PayloadType must be ordered")
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+pub(crate) enum Payload {
+    Ciphertext(Ciphertext),
+    Handshake(ExportHandshake),
+}
+
+impl Default for Payload {
+    fn default() -> Self {
+        Payload::Ciphertext(Default::default())
+    }
+}
@@ -3,13 +3,19 @@
 #[cfg(feature = "backup-enable")]
 mod backup;
 mod cache;
+mod dead_letter;
 pub mod dispatcher;
 mod error;
 pub mod eth;
+mod event;
 pub mod traits;
 mod utils;
 mod workflow;
 
 pub use cache::EventCache;
+pub use dead_letter::{DeadLetter, DeadLetterMetrics, DeadLetterQueue, PayloadKind, RetryOutcome};
 pub use dispatcher::Dispatcher;
 pub use error::HostError;
+pub use event::{EnclaveLog, EnclaveUpdatedState, PayloadType};
+pub use utils::ContractInfo;
+pub use workflow::host_output;
@@ -3,16 +3,20 @@
 use crate::{cache::EventCache, error::Result, utils::*, workflow::*};
 
 use async_trait::async_trait;
+use frame_common::chain_types::{ChainAddress, TxHash};
 use sgx_types::sgx_enclave_id_t;
 use std::{marker::Send, path::Path};
-use web3::types::{Address, H256};
 
-/// A trait for deploying contracts
+/// A trait for deploying contracts.
+/// Addresses and transaction hashes are chain-neutral so that any blockchain backend can implement it.
 #[async_trait]
 pub trait Deployer: Sized {
+    /// The handle of the deployed contract, which is passed to `Sender::from_contract` of the same backend.
+    type Contract;
+
     fn new(enclave_id: sgx_enclave_id_t, node_url: &str) -> Result<Self>;
 
-    async fn get_account(&self, index: usize, password: &str) -> Result<Address>;
+    async fn get_account(&self, index: usize, password: &str) -> Result<ChainAddress>;
 
     /// Deploying contract with attestation.
     async fn deploy<P>(
@@ -25,7 +29,7 @@ pub trait Deployer: Sized {
     where
         P: AsRef<Path> + Send + Sync + Copy;
 
-    fn get_contract<P: AsRef<Path>>(self, abi_path: P) -> Result<ContractKind>;
+    fn get_contract<P: AsRef<Path>>(self, abi_path: P) -> Result<Self::Contract>;
 
     fn get_enclave_id(&self) -> sgx_enclave_id_t;
 
@@ -35,36 +39,40 @@ pub trait Deployer: Sized {
 /// A trait for sending transactions to blockchain nodes
 #[async_trait]
 pub trait Sender: Sized {
+    type Contract;
+
     fn new<P: AsRef<Path>>(
         enclave_id: sgx_enclave_id_t,
         node_url: &str,
         contract_info: ContractInfo<'_, P>,
     ) -> Result<Self>;
 
-    fn from_contract(enclave_id: sgx_enclave_id_t, contract: ContractKind) -> Self;
+    fn from_contract(enclave_id: sgx_enclave_id_t, contract: Self::Contract) -> Self;
 
-    async fn get_account(&self, index: usize, password: &str) -> Result<Address>;
+    async fn get_account(&self, index: usize, password: &str) -> Result<ChainAddress>;
 
     /// Send an encrypted command of state transition to blockchain nodes.
-    async fn send_command(&self, host_output: &host_output::Command) -> Result<H256>;
+    async fn send_command(&self, host_output: &host_output::Command) -> Result<TxHash>;
 
     /// Attestation with deployed contract.
     async fn send_report_handshake(
         &self,
         host_output: &host_output::JoinGroup,
         method: &str,
-    ) -> Result<H256>;
+    ) -> Result<TxHash>;
 
-    async fn register_report(&self, host_output: &host_output::RegisterReport) -> Result<H256>;
+    async fn register_report(&self, host_output: &host_output::RegisterReport) -> Result<TxHash>;
 
-    async fn handshake(&self, host_output: &host_output::Handshake) -> Result<H256>;
+    async fn handshake(&self, host_output: &host_output::Handshake) -> Result<TxHash>;
 
-    fn get_contract(self) -> ContractKind;
+    fn get_contract(self) -> Self::Contract;
 }
 
 /// A trait of fetching event from blockchian nodes
 #[async_trait]
 pub trait Watcher: Sized {
+    type Contract;
+
     fn new<P: AsRef<Path>>(
         node_url: &str,
         contract_info: ContractInfo<'_, P>,
@@ -72,12 +80,14 @@ pub trait Watcher: Sized {
     ) -> Result<Self>;
 
     /// Blocking event fetch from blockchain nodes.
+    /// Returns the notifications of updated states, each of which is a JSON-encoded `Notification`.
     async fn fetch_events(
         &self,
         eid: sgx_enclave_id_t,
         fetch_ciphertext_cmd: u32,
         fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
     ) -> Result<Option<Vec<serde_json::Value>>>;
 
-    fn get_contract(self) -> ContractKind;
+    fn get_contract(self) -> Self::Contract;
 }
@@ -1,8 +1,13 @@
-use crate::{error::Result, eth::connection::Web3Contract};
+use crate::error::Result;
 use anyhow::anyhow;
 use ethabi::Contract as ContractABI;
-use std::{fs::File, io::BufReader, path::Path, str::FromStr};
-use web3::types::Address;
+use frame_common::chain_types::ChainAddress;
+use std::{
+    fs::{self, File},
+    io::{BufReader, Write},
+    path::Path,
+    str::FromStr,
+};
 
 /// Needed information to handle smart contracts.
 #[derive(Debug, Clone, Copy)]
@@ -25,14 +30,22 @@ impl<'a, P: AsRef<Path>> ContractInfo<'a, P> {
             .map_err(Into::into)
     }
 
-    pub fn address(&self) -> Result<Address> {
-        Address::from_str(self.addr)
-            .map_err(|e| anyhow!("{:?}", e))
-            .map_err(Into::into)
+    pub fn address(&self) -> Result<ChainAddress> {
+        ChainAddress::from_str(self.addr).map_err(Into::into)
     }
 }
 
-/// A type of contract
-pub enum ContractKind {
-    Web3Contract(Web3Contract),
+/// Write the file atomically, so that either the previous or the new one is left after a crash.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    // Make the rename durable
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
 }
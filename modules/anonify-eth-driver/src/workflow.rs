@@ -1,13 +1,17 @@
 use anonify_ecall_types::*;
 use frame_common::{
+    chain_types::ChainAddress,
     crypto::{Ciphertext, ExportHandshake},
     state_types::StateCounter,
 };
 use frame_host::engine::*;
 use frame_sodium::SodiumCiphertext;
-use web3::types::Address;
 
 pub const OUTPUT_MAX_LEN: usize = 2048;
+/// A state snapshot contains all user states, so it needs a much larger buffer.
+pub const STATE_SNAPSHOT_OUTPUT_MAX_LEN: usize = 16 * 1024 * 1024;
+/// The runtime schema contains JSON Schema of all parameters and return values.
+pub const RUNTIME_SCHEMA_OUTPUT_MAX_LEN: usize = 64 * 1024;
 
 pub struct CommandWorkflow;
 
@@ -59,6 +63,16 @@ impl HostEngine for RegisterNotificationWorkflow {
     const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
 }
 
+pub struct UnregisterNotificationWorkflow;
+
+impl HostEngine for UnregisterNotificationWorkflow {
+    type HI = host_input::UnregisterNotification;
+    type EI = SodiumCiphertext;
+    type EO = output::Empty;
+    type HO = host_output::UnregisterNotification;
+    const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
+}
+
 pub struct GetStateWorkflow;
 
 impl HostEngine for GetStateWorkflow {
@@ -89,6 +103,46 @@ impl HostEngine for InsertHandshakeWorkflow {
     const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
 }
 
+pub struct GetStateCounterWorkflow;
+
+impl HostEngine for GetStateCounterWorkflow {
+    type HI = host_input::GetStateCounter;
+    type EI = input::Empty;
+    type EO = output::ReturnStateCounter;
+    type HO = host_output::ReturnStateCounter;
+    const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
+}
+
+pub struct GetRuntimeSchemaWorkflow;
+
+impl HostEngine for GetRuntimeSchemaWorkflow {
+    type HI = host_input::GetRuntimeSchema;
+    type EI = input::Empty;
+    type EO = output::ReturnRuntimeSchema;
+    type HO = host_output::ReturnRuntimeSchema;
+    const OUTPUT_MAX_LEN: usize = RUNTIME_SCHEMA_OUTPUT_MAX_LEN;
+}
+
+pub struct ExportStateSnapshotWorkflow;
+
+impl HostEngine for ExportStateSnapshotWorkflow {
+    type HI = host_input::ExportStateSnapshot;
+    type EI = input::Empty;
+    type EO = output::ReturnStateSnapshot;
+    type HO = host_output::ReturnStateSnapshot;
+    const OUTPUT_MAX_LEN: usize = STATE_SNAPSHOT_OUTPUT_MAX_LEN;
+}
+
+pub struct ImportStateSnapshotWorkflow;
+
+impl HostEngine for ImportStateSnapshotWorkflow {
+    type HI = host_input::ImportStateSnapshot;
+    type EI = input::ImportStateSnapshot;
+    type EO = output::ReturnStateCounter;
+    type HO = host_output::ReturnStateCounter;
+    const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
+}
+
 pub struct GetEncryptionKeyWorkflow;
 
 impl HostEngine for GetEncryptionKeyWorkflow {
@@ -119,12 +173,22 @@ impl HostEngine for RecoverPathSecretAllWorkflow {
     const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
 }
 
+pub struct ReloadTrustedMeasurementsWorkflow;
+
+impl HostEngine for ReloadTrustedMeasurementsWorkflow {
+    type HI = host_input::ReloadTrustedMeasurements;
+    type EI = input::Empty;
+    type EO = output::Empty;
+    type HO = host_output::ReloadTrustedMeasurements;
+    const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
+}
+
 pub mod host_input {
     use super::*;
 
     pub struct Command {
         ciphertext: SodiumCiphertext,
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         ecall_cmd: u32,
     }
@@ -132,7 +196,7 @@ pub mod host_input {
     impl Command {
         pub fn new(
             ciphertext: SodiumCiphertext,
-            signer: Address,
+            signer: ChainAddress,
             gas: u64,
             ecall_cmd: u32,
         ) -> Self {
@@ -161,13 +225,13 @@ pub mod host_input {
     }
 
     pub struct JoinGroup {
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         ecall_cmd: u32,
     }
 
     impl JoinGroup {
-        pub fn new(signer: Address, gas: u64, ecall_cmd: u32) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64, ecall_cmd: u32) -> Self {
             JoinGroup {
                 signer,
                 gas,
@@ -192,13 +256,13 @@ pub mod host_input {
     }
 
     pub struct RegisterReport {
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         ecall_cmd: u32,
     }
 
     impl RegisterReport {
-        pub fn new(signer: Address, gas: u64, ecall_cmd: u32) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64, ecall_cmd: u32) -> Self {
             RegisterReport {
                 signer,
                 gas,
@@ -223,13 +287,13 @@ pub mod host_input {
     }
 
     pub struct Handshake {
-        signer: Address,
+        signer: ChainAddress,
         gas: u64,
         ecall_cmd: u32,
     }
 
     impl Handshake {
-        pub fn new(signer: Address, gas: u64, ecall_cmd: u32) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64, ecall_cmd: u32) -> Self {
             Handshake {
                 signer,
                 gas,
@@ -280,6 +344,33 @@ pub mod host_input {
         }
     }
 
+    pub struct UnregisterNotification {
+        ciphertext: SodiumCiphertext,
+        ecall_cmd: u32,
+    }
+
+    impl UnregisterNotification {
+        pub fn new(ciphertext: SodiumCiphertext, ecall_cmd: u32) -> Self {
+            UnregisterNotification {
+                ciphertext,
+                ecall_cmd,
+            }
+        }
+    }
+
+    impl HostInput for UnregisterNotification {
+        type EcallInput = SodiumCiphertext;
+        type HostOutput = host_output::UnregisterNotification;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((self.ciphertext, Self::HostOutput::default()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
+
     pub struct GetState {
         ciphertext: SodiumCiphertext,
         ecall_cmd: u32,
@@ -310,14 +401,21 @@ pub mod host_input {
     pub struct InsertCiphertext {
         ciphertext: Ciphertext,
         state_counter: StateCounter,
+        block_num: u64,
         ecall_cmd: u32,
     }
 
     impl InsertCiphertext {
-        pub fn new(ciphertext: Ciphertext, state_counter: StateCounter, ecall_cmd: u32) -> Self {
+        pub fn new(
+            ciphertext: Ciphertext,
+            state_counter: StateCounter,
+            block_num: u64,
+            ecall_cmd: u32,
+        ) -> Self {
             InsertCiphertext {
                 ciphertext,
                 state_counter,
+                block_num,
                 ecall_cmd,
             }
         }
@@ -328,7 +426,8 @@ pub mod host_input {
         type HostOutput = host_output::InsertCiphertext;
 
         fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
-            let ecall_input = Self::EcallInput::new(self.ciphertext, self.state_counter);
+            let ecall_input =
+                Self::EcallInput::new(self.ciphertext, self.state_counter, self.block_num);
 
             Ok((ecall_input, Self::HostOutput::new()))
         }
@@ -341,6 +440,7 @@ pub mod host_input {
     pub struct InsertHandshake {
         handshake: ExportHandshake,
         state_counter: StateCounter,
+        block_num: u64,
         ecall_cmd: u32,
     }
 
@@ -348,11 +448,13 @@ pub mod host_input {
         pub fn new(
             handshake: ExportHandshake,
             state_counter: StateCounter,
+            block_num: u64,
             ecall_cmd: u32,
         ) -> Self {
             InsertHandshake {
                 handshake,
                 state_counter,
+                block_num,
                 ecall_cmd,
             }
         }
@@ -363,7 +465,8 @@ pub mod host_input {
         type HostOutput = host_output::InsertHandshake;
 
         fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
-            let ecall_input = Self::EcallInput::new(self.handshake, self.state_counter);
+            let ecall_input =
+                Self::EcallInput::new(self.handshake, self.state_counter, self.block_num);
 
             Ok((ecall_input, Self::HostOutput::default()))
         }
@@ -373,6 +476,106 @@ pub mod host_input {
         }
     }
 
+    pub struct GetStateCounter {
+        ecall_cmd: u32,
+    }
+
+    impl GetStateCounter {
+        pub fn new(ecall_cmd: u32) -> Self {
+            GetStateCounter { ecall_cmd }
+        }
+    }
+
+    impl HostInput for GetStateCounter {
+        type EcallInput = input::Empty;
+        type HostOutput = host_output::ReturnStateCounter;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((Self::EcallInput::default(), Self::HostOutput::new()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
+
+    pub struct GetRuntimeSchema {
+        ecall_cmd: u32,
+    }
+
+    impl GetRuntimeSchema {
+        pub fn new(ecall_cmd: u32) -> Self {
+            GetRuntimeSchema { ecall_cmd }
+        }
+    }
+
+    impl HostInput for GetRuntimeSchema {
+        type EcallInput = input::Empty;
+        type HostOutput = host_output::ReturnRuntimeSchema;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((Self::EcallInput::default(), Self::HostOutput::new()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
+
+    pub struct ExportStateSnapshot {
+        ecall_cmd: u32,
+    }
+
+    impl ExportStateSnapshot {
+        pub fn new(ecall_cmd: u32) -> Self {
+            ExportStateSnapshot { ecall_cmd }
+        }
+    }
+
+    impl HostInput for ExportStateSnapshot {
+        type EcallInput = input::Empty;
+        type HostOutput = host_output::ReturnStateSnapshot;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((Self::EcallInput::default(), Self::HostOutput::new()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
+
+    pub struct ImportStateSnapshot {
+        header: StateSnapshotHeader,
+        sealed: Vec<u8>,
+        ecall_cmd: u32,
+    }
+
+    impl ImportStateSnapshot {
+        pub fn new(header: StateSnapshotHeader, sealed: Vec<u8>, ecall_cmd: u32) -> Self {
+            ImportStateSnapshot {
+                header,
+                sealed,
+                ecall_cmd,
+            }
+        }
+    }
+
+    impl HostInput for ImportStateSnapshot {
+        type EcallInput = input::ImportStateSnapshot;
+        type HostOutput = host_output::ReturnStateCounter;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            let ecall_input = Self::EcallInput::new(self.header, self.sealed);
+
+            Ok((ecall_input, Self::HostOutput::new()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
+
     pub struct GetEncryptionKey {
         ecall_cmd: u32,
     }
@@ -441,6 +644,29 @@ pub mod host_input {
             self.ecall_cmd
         }
     }
+
+    pub struct ReloadTrustedMeasurements {
+        ecall_cmd: u32,
+    }
+
+    impl ReloadTrustedMeasurements {
+        pub fn new(ecall_cmd: u32) -> Self {
+            ReloadTrustedMeasurements { ecall_cmd }
+        }
+    }
+
+    impl HostInput for ReloadTrustedMeasurements {
+        type EcallInput = input::Empty;
+        type HostOutput = host_output::ReloadTrustedMeasurements;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((Self::EcallInput::default(), Self::HostOutput::default()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
 }
 
 pub mod host_output {
@@ -448,7 +674,7 @@ pub mod host_output {
 
     #[derive(Debug, Clone)]
     pub struct Command {
-        pub signer: Address,
+        pub signer: ChainAddress,
         pub gas: u64,
         pub ecall_output: Option<output::Command>,
     }
@@ -464,7 +690,7 @@ pub mod host_output {
     }
 
     impl Command {
-        pub fn new(signer: Address, gas: u64) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64) -> Self {
             Command {
                 signer,
                 gas,
@@ -475,7 +701,7 @@ pub mod host_output {
 
     #[derive(Debug, Clone)]
     pub struct JoinGroup {
-        pub signer: Address,
+        pub signer: ChainAddress,
         pub gas: u64,
         pub ecall_output: Option<output::ReturnJoinGroup>,
     }
@@ -491,7 +717,7 @@ pub mod host_output {
     }
 
     impl JoinGroup {
-        pub fn new(signer: Address, gas: u64) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64) -> Self {
             JoinGroup {
                 signer,
                 gas,
@@ -502,7 +728,7 @@ pub mod host_output {
 
     #[derive(Debug, Clone)]
     pub struct RegisterReport {
-        pub signer: Address,
+        pub signer: ChainAddress,
         pub gas: u64,
         pub ecall_output: Option<output::ReturnRegisterReport>,
     }
@@ -518,7 +744,7 @@ pub mod host_output {
     }
 
     impl RegisterReport {
-        pub fn new(signer: Address, gas: u64) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64) -> Self {
             RegisterReport {
                 signer,
                 gas,
@@ -529,7 +755,7 @@ pub mod host_output {
 
     #[derive(Debug, Clone)]
     pub struct Handshake {
-        pub signer: Address,
+        pub signer: ChainAddress,
         pub gas: u64,
         pub ecall_output: Option<output::ReturnHandshake>,
     }
@@ -545,7 +771,7 @@ pub mod host_output {
     }
 
     impl Handshake {
-        pub fn new(signer: Address, gas: u64) -> Self {
+        pub fn new(signer: ChainAddress, gas: u64) -> Self {
             Handshake {
                 signer,
                 gas,
@@ -561,6 +787,13 @@ pub mod host_output {
         type EcallOutput = output::Empty;
     }
 
+    #[derive(Default)]
+    pub struct UnregisterNotification;
+
+    impl HostOutput for UnregisterNotification {
+        type EcallOutput = output::Empty;
+    }
+
     pub struct GetState {
         pub ecall_output: Option<output::ReturnState>,
     }
@@ -608,6 +841,66 @@ pub mod host_output {
         type EcallOutput = output::Empty;
     }
 
+    pub struct ReturnStateCounter {
+        pub ecall_output: Option<output::ReturnStateCounter>,
+    }
+
+    impl HostOutput for ReturnStateCounter {
+        type EcallOutput = output::ReturnStateCounter;
+
+        fn set_ecall_output(mut self, output: Self::EcallOutput) -> anyhow::Result<Self> {
+            self.ecall_output = Some(output);
+
+            Ok(self)
+        }
+    }
+
+    impl ReturnStateCounter {
+        pub fn new() -> Self {
+            ReturnStateCounter { ecall_output: None }
+        }
+    }
+
+    pub struct ReturnRuntimeSchema {
+        pub ecall_output: Option<output::ReturnRuntimeSchema>,
+    }
+
+    impl HostOutput for ReturnRuntimeSchema {
+        type EcallOutput = output::ReturnRuntimeSchema;
+
+        fn set_ecall_output(mut self, output: Self::EcallOutput) -> anyhow::Result<Self> {
+            self.ecall_output = Some(output);
+
+            Ok(self)
+        }
+    }
+
+    impl ReturnRuntimeSchema {
+        pub fn new() -> Self {
+            ReturnRuntimeSchema { ecall_output: None }
+        }
+    }
+
+    pub struct ReturnStateSnapshot {
+        pub ecall_output: Option<output::ReturnStateSnapshot>,
+    }
+
+    impl HostOutput for ReturnStateSnapshot {
+        type EcallOutput = output::ReturnStateSnapshot;
+
+        fn set_ecall_output(mut self, output: Self::EcallOutput) -> anyhow::Result<Self> {
+            self.ecall_output = Some(output);
+
+            Ok(self)
+        }
+    }
+
+    impl ReturnStateSnapshot {
+        pub fn new() -> Self {
+            ReturnStateSnapshot { ecall_output: None }
+        }
+    }
+
     pub struct ReturnEncryptionKey {
         pub ecall_output: Option<output::ReturnEncryptionKey>,
     }
@@ -641,4 +934,11 @@ pub mod host_output {
     impl HostOutput for RecoverPathSecretAll {
         type EcallOutput = output::Empty;
     }
+
+    #[derive(Default)]
+    pub struct ReloadTrustedMeasurements;
+
+    impl HostOutput for ReloadTrustedMeasurements {
+        type EcallOutput = output::Empty;
+    }
 }
@@ -0,0 +1,266 @@
+use crate::{
+    error::Result,
+    node::{local_ledger, Attestation, Call, EventPayload, Ledger, LedgerEntry, Transaction},
+};
+use anonify_eth_driver::{
+    host_output, ContractInfo, EnclaveLog, EventCache, HostError, PayloadType,
+};
+use frame_common::{
+    chain_types::{ChainAddress, TxHash},
+    crypto::{Ciphertext, ExportHandshake},
+};
+use std::{env, path::Path, sync::Arc};
+use tracing::{error, info};
+
+const LOCAL_LEDGER_SCHEME: &str = "local://";
+
+/// A connection to the in-process ledger or the ledger served over HTTP.
+#[derive(Debug, Clone)]
+pub struct LedgerClient {
+    node_url: String,
+    conn: Connection,
+}
+
+#[derive(Debug, Clone)]
+enum Connection {
+    Local(Arc<Ledger>),
+    Http(reqwest::Client),
+}
+
+impl LedgerClient {
+    pub fn new(node_url: &str) -> Result<Self> {
+        let conn = match node_url.strip_prefix(LOCAL_LEDGER_SCHEME) {
+            Some(name) => Connection::Local(local_ledger(name)),
+            None => Connection::Http(reqwest::Client::new()),
+        };
+
+        Ok(LedgerClient {
+            node_url: node_url.trim_end_matches('/').to_string(),
+            conn,
+        })
+    }
+
+    pub async fn get_account(&self, index: usize) -> Result<ChainAddress> {
+        match &self.conn {
+            Connection::Local(ledger) => Ok(ledger.account(index)),
+            Connection::Http(client) => client
+                .get(&format!("{}/accounts/{}", self.node_url, index))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(Into::into),
+        }
+    }
+
+    pub async fn submit(&self, transaction: Transaction) -> Result<LedgerEntry> {
+        match &self.conn {
+            Connection::Local(ledger) => ledger.submit(transaction).map_err(Into::into),
+            Connection::Http(client) => client
+                .post(&format!("{}/transactions", self.node_url))
+                .json(&transaction)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(Into::into),
+        }
+    }
+
+    pub async fn get_events(
+        &self,
+        contract: &ChainAddress,
+        from: u64,
+        limit: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        match &self.conn {
+            Connection::Local(ledger) => Ok(ledger.events(contract, from, limit)),
+            Connection::Http(client) => client
+                .get(&format!("{}/contracts/{}/events", self.node_url, contract))
+                .query(&[("from", from), ("limit", limit as u64)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(Into::into),
+        }
+    }
+
+    pub fn get_node_url(&self) -> &str {
+        &self.node_url
+    }
+}
+
+/// Components to call a contract on the ledger.
+#[derive(Debug)]
+pub struct LedgerContract {
+    address: ChainAddress,
+    client: LedgerClient,
+    event_limit: usize,
+}
+
+impl LedgerContract {
+    pub fn new<P: AsRef<Path>>(
+        client: LedgerClient,
+        contract_info: ContractInfo<'_, P>,
+    ) -> Result<Self> {
+        let address = contract_info.address()?;
+        let event_limit = env::var("EVENT_LIMIT")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<usize>()
+            .expect("Failed to parse EVENT_LIMIT");
+
+        Ok(LedgerContract {
+            address,
+            client,
+            event_limit,
+        })
+    }
+
+    pub async fn send_report_handshake(
+        &self,
+        output: host_output::JoinGroup,
+        method: &str,
+    ) -> Result<TxHash> {
+        let ecall_output = output
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+        let attestation = Attestation {
+            report: ecall_output.report().to_vec(),
+            report_sig: ecall_output.report_sig().to_vec(),
+            mrenclave_ver: ecall_output.mrenclave_ver(),
+            roster_idx: ecall_output.roster_idx(),
+        };
+        let call = Call::report_handshake(method, attestation, ecall_output.handshake().to_vec())?;
+
+        self.submit(output.signer, call).await
+    }
+
+    pub async fn register_report(&self, output: host_output::RegisterReport) -> Result<TxHash> {
+        let ecall_output = output
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+        let attestation = Attestation {
+            report: ecall_output.report().to_vec(),
+            report_sig: ecall_output.report_sig().to_vec(),
+            mrenclave_ver: ecall_output.mrenclave_ver(),
+            roster_idx: ecall_output.roster_idx(),
+        };
+
+        self.submit(output.signer, Call::RegisterReport { attestation })
+            .await
+    }
+
+    pub async fn send_command(&self, output: host_output::Command) -> Result<TxHash> {
+        let ecall_output = output
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+        let ciphertext = ecall_output.ciphertext();
+        let mut enclave_sig = ecall_output.encode_enclave_sig().to_vec();
+        enclave_sig.push(ecall_output.encode_recovery_id());
+        let call = Call::StoreCommand {
+            ciphertext: ciphertext.encode(),
+            enclave_sig,
+            roster_idx: ciphertext.roster_idx(),
+            generation: ciphertext.generation(),
+            epoch: ciphertext.epoch(),
+        };
+
+        self.submit(output.signer, call).await
+    }
+
+    pub async fn handshake(&self, output: host_output::Handshake) -> Result<TxHash> {
+        let ecall_output = output
+            .ecall_output
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+        let handshake = ecall_output.handshake();
+        let mut enclave_sig = ecall_output.encode_enclave_sig().to_vec();
+        enclave_sig.push(ecall_output.encode_recovery_id());
+        let call = Call::Handshake {
+            handshake: handshake.encode(),
+            enclave_sig,
+            roster_idx: handshake.roster_idx(),
+            generation: 0,
+            epoch: handshake.prior_epoch() + 1,
+        };
+
+        self.submit(output.signer, call).await
+    }
+
+    /// Fetch the events after the ones cached as fetched.
+    pub(crate) async fn get_event(&self, cache: EventCache) -> Result<EnclaveLog> {
+        let next_height = cache
+            .inner()
+            .read()
+            .get_latest_block_num(&self.address)
+            .unwrap_or_default();
+        let entries = self
+            .client
+            .get_events(&self.address, next_height, self.event_limit)
+            .await?;
+
+        // The heights of entries are unique, so no events are fetched twice.
+        let latest_height = match entries.last() {
+            Some(entry) => entry.height,
+            None => return Ok(EnclaveLog::empty(cache)),
+        };
+
+        let mut payloads = vec![];
+        for entry in entries {
+            info!(
+                "Fetched a ledger entry: height: {}, tx_hash: {}",
+                entry.height, entry.tx_hash
+            );
+            let height = entry.height;
+            let event = match entry.event {
+                Some(event) => event,
+                None => continue,
+            };
+
+            let state_counter = event.state_counter;
+            let payload = match event.payload {
+                EventPayload::Ciphertext(bytes) => Ciphertext::decode(&bytes[..])
+                    .map(|c| PayloadType::from_ciphertext(c, state_counter, height))
+                    .map_err(|e| e.to_string()),
+                EventPayload::Handshake(bytes) => ExportHandshake::decode(&bytes[..])
+                    .map(|h| PayloadType::from_handshake(h, state_counter, height))
+                    .map_err(|e| e.to_string()),
+            };
+            match payload {
+                Ok(payload) => payloads.push(payload),
+                Err(e) => error!("Invalid event at height {}: {}", height, e),
+            }
+        }
+
+        Ok(EnclaveLog::new(
+            self.address.clone(),
+            latest_height,
+            payloads,
+            cache,
+        ))
+    }
+
+    pub async fn get_account(&self, index: usize) -> Result<ChainAddress> {
+        self.client.get_account(index).await
+    }
+
+    pub fn address(&self) -> &ChainAddress {
+        &self.address
+    }
+
+    async fn submit(&self, sender: ChainAddress, call: Call) -> Result<TxHash> {
+        let entry = self
+            .client
+            .submit(Transaction {
+                sender,
+                to: Some(self.address.clone()),
+                call,
+            })
+            .await?;
+
+        Ok(entry.tx_hash)
+    }
+}
@@ -0,0 +1,344 @@
+use crate::{
+    error::LedgerError,
+    node::{EventPayload, LedgerEvent},
+};
+use frame_common::{chain_types::ChainAddress, state_types::StateCounter};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroupKeyCounter {
+    pub generation: u32,
+    pub epoch: u32,
+}
+
+impl GroupKeyCounter {
+    pub fn new(generation: u32, epoch: u32) -> Self {
+        GroupKeyCounter { generation, epoch }
+    }
+}
+
+/// The storage and the functions of `Anonify.sol`.
+/// Attestation reports and enclave signatures are not verified,
+/// so the checks of `ReportHandle.sol` and the recovery of verifying keys are omitted.
+/// Every function checks all the conditions before updating the storage,
+/// so the storage is left unchanged if a transaction is reverted.
+#[derive(Debug, Clone)]
+pub struct AnonifyContract {
+    owner: ChainAddress,
+    // A version of enclave binary
+    mrenclave_ver: u32,
+    // An counter of registered roster index
+    roster_idx_counter: u32,
+    // Counter for enforcing the order of state transitions
+    state_counter: StateCounter,
+    group_key_counter: HashMap<u32, GroupKeyCounter>,
+    sender_to_roster_idx: HashMap<ChainAddress, u32>,
+}
+
+impl AnonifyContract {
+    /// The constructor of the contract. Returns the event emitted in the constructor as well.
+    pub fn new(
+        sender: ChainAddress,
+        handshake: Vec<u8>,
+        mrenclave_ver: u32,
+        roster_idx: u32,
+    ) -> Result<(Self, LedgerEvent), LedgerError> {
+        require(roster_idx == 0, "First roster_idx must be zero")?;
+
+        let mut group_key_counter = HashMap::new();
+        // The node that joins first does not send command data,
+        // it sends handshake for the first time.
+        group_key_counter.insert(roster_idx, GroupKeyCounter::new(0, 1));
+        let mut sender_to_roster_idx = HashMap::new();
+        sender_to_roster_idx.insert(sender.clone(), roster_idx);
+
+        let mut contract = AnonifyContract {
+            owner: sender,
+            mrenclave_ver,
+            roster_idx_counter: roster_idx,
+            state_counter: StateCounter::default(),
+            group_key_counter,
+            sender_to_roster_idx,
+        };
+        let event = contract.store_handshake(handshake);
+
+        Ok((contract, event))
+    }
+
+    /// A new TEE node joins the group.
+    pub fn join_group(
+        &mut self,
+        sender: &ChainAddress,
+        handshake: Vec<u8>,
+        version: u32,
+        roster_idx: u32,
+    ) -> Result<LedgerEvent, LedgerError> {
+        require(self.mrenclave_ver == version, "Must be same version")?;
+        require(
+            roster_idx == self.roster_idx_counter + 1,
+            "Joining the group must be ordered accordingly by roster index",
+        )?;
+        require(
+            self.roster_idx_of(sender) == 0,
+            "The msg.sender can join only once",
+        )?;
+
+        // It is assumed that the nodes participate in the order of roster index,
+        // and all the nodes finish participating before the state transition.
+        self.group_key_counter
+            .insert(roster_idx, GroupKeyCounter::new(0, roster_idx + 1));
+        self.sender_to_roster_idx.insert(sender.clone(), roster_idx);
+        self.roster_idx_counter = roster_idx;
+
+        Ok(self.store_handshake(handshake))
+    }
+
+    /// A recovered TEE node registers the report.
+    pub fn register_report(
+        &mut self,
+        sender: &ChainAddress,
+        version: u32,
+        roster_idx: u32,
+    ) -> Result<(), LedgerError> {
+        require(self.mrenclave_ver == version, "Must be same version")?;
+
+        self.sender_to_roster_idx.insert(sender.clone(), roster_idx);
+
+        Ok(())
+    }
+
+    pub fn update_mrenclave(
+        &mut self,
+        sender: &ChainAddress,
+        handshake: Vec<u8>,
+        new_version: u32,
+        roster_idx: u32,
+    ) -> Result<LedgerEvent, LedgerError> {
+        require(&self.owner == sender, "caller is not the owner")?;
+        require(self.mrenclave_ver != new_version, "Must be new version")?;
+        require(roster_idx == 0, "Only owner can update mrenclave")?;
+
+        self.mrenclave_ver = new_version;
+
+        Ok(self.store_handshake(handshake))
+    }
+
+    /// Store ciphertexts which is generated by trusted environment.
+    pub fn store_command(
+        &mut self,
+        ciphertext: Vec<u8>,
+        roster_idx: u32,
+        generation: u32,
+        epoch: u32,
+    ) -> Result<LedgerEvent, LedgerError> {
+        let counter = self.group_key_counter(roster_idx);
+        require(
+            generation > counter.generation,
+            "generation must be bigger than the counter",
+        )?;
+        require(
+            epoch == counter.epoch,
+            "epoch must be equal with the counter",
+        )?;
+
+        self.group_key_counter
+            .insert(roster_idx, GroupKeyCounter::new(generation, epoch));
+        self.state_counter = self.state_counter.increment();
+
+        Ok(LedgerEvent {
+            state_counter: self.state_counter,
+            payload: EventPayload::Ciphertext(ciphertext),
+        })
+    }
+
+    pub fn handshake(
+        &mut self,
+        sender: &ChainAddress,
+        handshake: Vec<u8>,
+        roster_idx: u32,
+        generation: u32,
+        epoch: u32,
+    ) -> Result<LedgerEvent, LedgerError> {
+        require(
+            self.roster_idx_of(sender) == roster_idx,
+            "The roster index must be same as the registered one",
+        )?;
+        require(generation == 0, "generation must be zero")?;
+        require(
+            epoch > self.group_key_counter(roster_idx).epoch,
+            "epoch must be bigger than the counter",
+        )?;
+
+        self.group_key_counter
+            .insert(roster_idx, GroupKeyCounter::new(generation, epoch));
+
+        Ok(self.store_handshake(handshake))
+    }
+
+    pub fn state_counter(&self) -> StateCounter {
+        self.state_counter
+    }
+
+    pub fn group_key_counter(&self, roster_idx: u32) -> GroupKeyCounter {
+        self.group_key_counter
+            .get(&roster_idx)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Unregistered senders are regarded as roster index 0 as with the mapping of solidity.
+    pub fn roster_idx_of(&self, sender: &ChainAddress) -> u32 {
+        self.sender_to_roster_idx
+            .get(sender)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn store_handshake(&mut self, handshake: Vec<u8>) -> LedgerEvent {
+        self.state_counter = self.state_counter.increment();
+        LedgerEvent {
+            state_counter: self.state_counter,
+            payload: EventPayload::Handshake(handshake),
+        }
+    }
+}
+
+fn require(cond: bool, msg: &'static str) -> Result<(), LedgerError> {
+    if cond {
+        Ok(())
+    } else {
+        Err(LedgerError::Revert(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(index: u8) -> ChainAddress {
+        ChainAddress::from_bytes(&[index; 20])
+    }
+
+    fn deploy() -> AnonifyContract {
+        AnonifyContract::new(account(0), vec![0], 1, 0).unwrap().0
+    }
+
+    fn reverted<T>(res: Result<T, LedgerError>) -> &'static str {
+        match res {
+            Err(LedgerError::Revert(msg)) => msg,
+            _ => panic!("The transaction must be reverted"),
+        }
+    }
+
+    #[test]
+    fn test_first_roster_idx_must_be_zero() {
+        assert_eq!(
+            reverted(AnonifyContract::new(account(0), vec![0], 1, 1)),
+            "First roster_idx must be zero"
+        );
+    }
+
+    #[test]
+    fn test_join_group_in_order_of_roster_idx() {
+        let mut contract = deploy();
+
+        assert_eq!(
+            reverted(contract.join_group(&account(1), vec![1], 2, 1)),
+            "Must be same version"
+        );
+        assert_eq!(
+            reverted(contract.join_group(&account(2), vec![2], 1, 2)),
+            "Joining the group must be ordered accordingly by roster index"
+        );
+        contract.join_group(&account(1), vec![1], 1, 1).unwrap();
+        assert_eq!(
+            reverted(contract.join_group(&account(1), vec![1], 1, 2)),
+            "The msg.sender can join only once"
+        );
+        contract.join_group(&account(2), vec![2], 1, 2).unwrap();
+
+        assert_eq!(contract.roster_idx_of(&account(2)), 2);
+        assert_eq!(contract.group_key_counter(2), GroupKeyCounter::new(0, 3));
+        assert_eq!(contract.state_counter(), StateCounter::new(3));
+    }
+
+    #[test]
+    fn test_store_command_generation_and_epoch() {
+        let mut contract = deploy();
+
+        assert_eq!(
+            reverted(contract.store_command(vec![1], 0, 0, 1)),
+            "generation must be bigger than the counter"
+        );
+        assert_eq!(
+            reverted(contract.store_command(vec![1], 0, 1, 2)),
+            "epoch must be equal with the counter"
+        );
+
+        let event = contract.store_command(vec![1], 0, 1, 1).unwrap();
+        assert_eq!(
+            event,
+            LedgerEvent {
+                state_counter: StateCounter::new(2),
+                payload: EventPayload::Ciphertext(vec![1]),
+            }
+        );
+        // Generations can be skipped, but not be reused.
+        contract.store_command(vec![2], 0, 3, 1).unwrap();
+        assert!(contract.store_command(vec![3], 0, 3, 1).is_err());
+        assert_eq!(contract.group_key_counter(0), GroupKeyCounter::new(3, 1));
+    }
+
+    #[test]
+    fn test_handshake_resets_generation() {
+        let mut contract = deploy();
+        contract.store_command(vec![1], 0, 1, 1).unwrap();
+
+        assert_eq!(
+            reverted(contract.handshake(&account(1), vec![1], 1, 0, 2)),
+            "The roster index must be same as the registered one"
+        );
+        assert_eq!(
+            reverted(contract.handshake(&account(0), vec![1], 0, 1, 2)),
+            "generation must be zero"
+        );
+        assert_eq!(
+            reverted(contract.handshake(&account(0), vec![1], 0, 0, 1)),
+            "epoch must be bigger than the counter"
+        );
+
+        let event = contract.handshake(&account(0), vec![1], 0, 0, 2).unwrap();
+        assert_eq!(
+            event,
+            LedgerEvent {
+                state_counter: StateCounter::new(3),
+                payload: EventPayload::Handshake(vec![1]),
+            }
+        );
+        // Ciphertexts of the previous epoch are rejected after the handshake.
+        assert!(contract.store_command(vec![2], 0, 2, 1).is_err());
+        contract.store_command(vec![2], 0, 1, 2).unwrap();
+    }
+
+    #[test]
+    fn test_update_mrenclave_by_owner() {
+        let mut contract = deploy();
+
+        assert_eq!(
+            reverted(contract.update_mrenclave(&account(1), vec![1], 2, 0)),
+            "caller is not the owner"
+        );
+        assert_eq!(
+            reverted(contract.update_mrenclave(&account(0), vec![1], 1, 0)),
+            "Must be new version"
+        );
+
+        contract
+            .update_mrenclave(&account(0), vec![1], 2, 0)
+            .unwrap();
+        // Reports of the old version can't be registered anymore.
+        assert!(contract.register_report(&account(1), 1, 1).is_err());
+        contract.register_report(&account(1), 2, 1).unwrap();
+        assert_eq!(contract.roster_idx_of(&account(1)), 1);
+    }
+}
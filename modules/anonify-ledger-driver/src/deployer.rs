@@ -0,0 +1,96 @@
+use crate::{
+    connection::{LedgerClient, LedgerContract},
+    error::Result,
+    node::{Attestation, Call, Transaction},
+};
+use anonify_eth_driver::{host_output, traits::*, ContractInfo, HostError};
+use async_trait::async_trait;
+use frame_common::chain_types::ChainAddress;
+use sgx_types::sgx_enclave_id_t;
+use std::{marker::Send, path::Path};
+
+/// Components needed to deploy a contract on the ledger
+#[derive(Debug)]
+pub struct LedgerDeployer {
+    enclave_id: sgx_enclave_id_t,
+    client: LedgerClient,
+    address: Option<ChainAddress>, // contract address
+}
+
+#[async_trait]
+impl Deployer for LedgerDeployer {
+    type Contract = LedgerContract;
+
+    fn new(enclave_id: sgx_enclave_id_t, node_url: &str) -> Result<Self> {
+        let client = LedgerClient::new(node_url)?;
+
+        Ok(LedgerDeployer {
+            enclave_id,
+            client,
+            address: None,
+        })
+    }
+
+    /// The password is not used because the ledger doesn't verify transaction signatures.
+    async fn get_account(&self, index: usize, _password: &str) -> Result<ChainAddress> {
+        self.client.get_account(index).await
+    }
+
+    /// The ledger has no contract code and its entries are final as soon as they are appended,
+    /// so the ABI, the binary and the confirmations are not used.
+    async fn deploy<P>(
+        &mut self,
+        host_output: &host_output::JoinGroup,
+        _abi_path: P,
+        _bin_path: P,
+        _confirmations: usize,
+    ) -> Result<String>
+    where
+        P: AsRef<Path> + Send + Sync + Copy,
+    {
+        let ecall_output = host_output
+            .ecall_output
+            .as_ref()
+            .ok_or_else(|| HostError::EcallOutputNotSet)?;
+        let attestation = Attestation {
+            report: ecall_output.report().to_vec(),
+            report_sig: ecall_output.report_sig().to_vec(),
+            mrenclave_ver: ecall_output.mrenclave_ver(),
+            roster_idx: ecall_output.roster_idx(),
+        };
+        let entry = self
+            .client
+            .submit(Transaction {
+                sender: host_output.signer.clone(),
+                to: None,
+                call: Call::Deploy {
+                    attestation,
+                    handshake: ecall_output.handshake().to_vec(),
+                },
+            })
+            .await?;
+
+        let contract_addr = hex::encode(entry.contract.as_bytes());
+        self.address = Some(entry.contract);
+
+        Ok(contract_addr)
+    }
+
+    fn get_contract<P: AsRef<Path>>(self, abi_path: P) -> Result<LedgerContract> {
+        let addr = hex::encode(
+            self.address
+                .expect("The contract hasn't be deployed yet.")
+                .as_bytes(),
+        );
+        let contract_info = ContractInfo::new(abi_path, &addr);
+        LedgerContract::new(self.client, contract_info)
+    }
+
+    fn get_enclave_id(&self) -> sgx_enclave_id_t {
+        self.enclave_id
+    }
+
+    fn get_node_url(&self) -> &str {
+        self.client.get_node_url()
+    }
+}
@@ -0,0 +1,29 @@
+use anonify_eth_driver::HostError;
+use frame_common::chain_types::ChainAddress;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, HostError>;
+
+/// Errors of transactions rejected by the ledger
+#[derive(Error, Debug)]
+pub enum LedgerError {
+    /// Reverted by `require` with the same message as `Anonify.sol`.
+    #[error("Reverted: {0}")]
+    Revert(&'static str),
+    #[error("The transaction doesn't specify the contract to call")]
+    MissingRecipient,
+    #[error("A deploy transaction must not specify the contract: {0}")]
+    UnexpectedRecipient(ChainAddress),
+    #[error("The contract is not deployed on the ledger: {0}")]
+    UnknownContract(ChainAddress),
+    #[error("Unknown method of the ledger: {0}")]
+    UnknownMethod(String),
+    #[error("Bincode error: {0}")]
+    BincodeError(#[from] bincode::Error),
+}
+
+impl From<LedgerError> for HostError {
+    fn from(err: LedgerError) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
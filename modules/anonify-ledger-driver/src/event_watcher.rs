@@ -0,0 +1,60 @@
+use crate::{
+    connection::{LedgerClient, LedgerContract},
+    error::Result,
+};
+use anonify_eth_driver::{traits::*, ContractInfo, EventCache};
+use async_trait::async_trait;
+use sgx_types::sgx_enclave_id_t;
+use std::path::Path;
+
+/// Components needed to watch events on the ledger
+#[derive(Debug)]
+pub struct LedgerWatcher {
+    contract: LedgerContract,
+    cache: EventCache,
+}
+
+#[async_trait]
+impl Watcher for LedgerWatcher {
+    type Contract = LedgerContract;
+
+    fn new<P: AsRef<Path>>(
+        node_url: &str,
+        contract_info: ContractInfo<'_, P>,
+        cache: EventCache,
+    ) -> Result<Self> {
+        let client = LedgerClient::new(node_url)?;
+        let contract = LedgerContract::new(client, contract_info)?;
+
+        Ok(LedgerWatcher { contract, cache })
+    }
+
+    /// Fetch the events appended to the ledger after the last fetch.
+    /// As with `EventWatcher`, events which fail in the enclave are skipped
+    /// if the enclave has consumed their state counters.
+    async fn fetch_events(
+        &self,
+        eid: sgx_enclave_id_t,
+        fetch_ciphertext_cmd: u32,
+        fetch_handshake_cmd: u32,
+        state_counter_cmd: u32,
+    ) -> Result<Option<Vec<serde_json::Value>>> {
+        let enclave_updated_state = self
+            .contract
+            .get_event(self.cache.clone())
+            .await?
+            .insert_enclave(
+                eid,
+                fetch_ciphertext_cmd,
+                fetch_handshake_cmd,
+                state_counter_cmd,
+            )
+            .save_cache(self.contract.address());
+
+        Ok(enclave_updated_state.notify_states())
+    }
+
+    fn get_contract(self) -> LedgerContract {
+        self.contract
+    }
+}
@@ -0,0 +1,37 @@
+//! A blockchain backend of a simple append-only log ledger,
+//! which implements the traits of `anonify_eth_driver::traits` to be used with `Dispatcher`.
+//!
+//! Transactions are appended to the log in the order they are submitted.
+//! The ledger enforces the same invariants as `Anonify.sol`, such as the order of roster indexes,
+//! the rules of generations and epochs in `storeCommand` and `handshake`, and the increments of the state counter,
+//! and a reverted transaction is not appended.
+//! Unlike the contract, the ledger doesn't verify attestation reports and enclave signatures,
+//! so it must be operated by a party trusted by the group.
+//!
+//! A node URL of `local://<name>` connects to the in-process ledger named `<name>`,
+//! so that the full flow can be run without any blockchain node.
+//! The other node URLs are connected over HTTP with the following endpoints.
+//!
+//! | Method | Path                                               | Body          | Response           |
+//! |--------|----------------------------------------------------|---------------|--------------------|
+//! | GET    | `/accounts/{index}`                                |               | `ChainAddress`     |
+//! | POST   | `/transactions`                                    | `Transaction` | `LedgerEntry`      |
+//! | GET    | `/contracts/{address}/events?from=&limit=`         |               | `Vec<LedgerEntry>` |
+
+#![crate_type = "lib"]
+
+pub mod connection;
+pub mod contract;
+pub mod deployer;
+mod error;
+pub mod event_watcher;
+pub mod node;
+pub mod sender;
+
+pub use self::connection::{LedgerClient, LedgerContract};
+pub use self::contract::{AnonifyContract, GroupKeyCounter};
+pub use self::deployer::LedgerDeployer;
+pub use self::error::LedgerError;
+pub use self::event_watcher::LedgerWatcher;
+pub use self::node::{local_ledger, Ledger};
+pub use self::sender::LedgerSender;
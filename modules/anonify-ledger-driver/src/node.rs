@@ -0,0 +1,416 @@
+use crate::{contract::AnonifyContract, error::LedgerError};
+use frame_common::{
+    chain_types::{ChainAddress, TxHash},
+    crypto::Sha256,
+    state_types::StateCounter,
+    traits::Hash256,
+};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+const ADDRESS_SIZE: usize = 20;
+
+static LOCAL_LEDGERS: Lazy<Mutex<HashMap<String, Arc<Ledger>>>> = Lazy::new(Default::default);
+
+/// Get the in-process ledger of the name. It's created on the first call.
+pub fn local_ledger(name: &str) -> Arc<Ledger> {
+    LOCAL_LEDGERS
+        .lock()
+        .entry(name.to_string())
+        .or_default()
+        .clone()
+}
+
+/// An attestation report of the enclave submitted to the ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub report: Vec<u8>,
+    pub report_sig: Vec<u8>,
+    pub mrenclave_ver: u32,
+    pub roster_idx: u32,
+}
+
+/// A call of a transaction. These correspond to the functions of the Anonify contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Call {
+    /// Create a contract with the first handshake of the group
+    Deploy {
+        attestation: Attestation,
+        handshake: Vec<u8>,
+    },
+    JoinGroup {
+        attestation: Attestation,
+        handshake: Vec<u8>,
+    },
+    UpdateMrenclave {
+        attestation: Attestation,
+        handshake: Vec<u8>,
+    },
+    RegisterReport {
+        attestation: Attestation,
+    },
+    StoreCommand {
+        ciphertext: Vec<u8>,
+        enclave_sig: Vec<u8>,
+        roster_idx: u32,
+        generation: u32,
+        epoch: u32,
+    },
+    Handshake {
+        handshake: Vec<u8>,
+        enclave_sig: Vec<u8>,
+        roster_idx: u32,
+        generation: u32,
+        epoch: u32,
+    },
+}
+
+impl Call {
+    /// Build the call of sending an attestation report and a handshake by the method name of the contract.
+    pub fn report_handshake(
+        method: &str,
+        attestation: Attestation,
+        handshake: Vec<u8>,
+    ) -> Result<Self, LedgerError> {
+        match method {
+            "joinGroup" => Ok(Call::JoinGroup {
+                attestation,
+                handshake,
+            }),
+            "updateMrenclave" => Ok(Call::UpdateMrenclave {
+                attestation,
+                handshake,
+            }),
+            _ => Err(LedgerError::UnknownMethod(method.to_string())),
+        }
+    }
+
+    /// Call the function of the contract. Returns the event emitted by the function if any.
+    fn execute(
+        &self,
+        contract: &mut AnonifyContract,
+        sender: &ChainAddress,
+    ) -> Result<Option<LedgerEvent>, LedgerError> {
+        match self.clone() {
+            Call::Deploy { .. } => unreachable!("contracts are created by the ledger"),
+            Call::JoinGroup {
+                attestation,
+                handshake,
+            } => contract
+                .join_group(
+                    sender,
+                    handshake,
+                    attestation.mrenclave_ver,
+                    attestation.roster_idx,
+                )
+                .map(Some),
+            Call::UpdateMrenclave {
+                attestation,
+                handshake,
+            } => contract
+                .update_mrenclave(
+                    sender,
+                    handshake,
+                    attestation.mrenclave_ver,
+                    attestation.roster_idx,
+                )
+                .map(Some),
+            Call::RegisterReport { attestation } => contract
+                .register_report(sender, attestation.mrenclave_ver, attestation.roster_idx)
+                .map(|_| None),
+            Call::StoreCommand {
+                ciphertext,
+                roster_idx,
+                generation,
+                epoch,
+                ..
+            } => contract
+                .store_command(ciphertext, roster_idx, generation, epoch)
+                .map(Some),
+            Call::Handshake {
+                handshake,
+                roster_idx,
+                generation,
+                epoch,
+                ..
+            } => contract
+                .handshake(sender, handshake, roster_idx, generation, epoch)
+                .map(Some),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub sender: ChainAddress,
+    /// The contract to call. A contract is created if it's not specified.
+    pub to: Option<ChainAddress>,
+    pub call: Call,
+}
+
+/// An encoded ciphertext or handshake which is inserted into the enclave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "bytes", rename_all = "snake_case")]
+pub enum EventPayload {
+    Ciphertext(Vec<u8>),
+    Handshake(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEvent {
+    pub state_counter: StateCounter,
+    pub payload: EventPayload,
+}
+
+/// A transaction appended to the ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// The position of the entry in the log, which is regarded as the block number.
+    pub height: u64,
+    pub tx_hash: TxHash,
+    pub contract: ChainAddress,
+    pub transaction: Transaction,
+    pub event: Option<LedgerEvent>,
+}
+
+/// An append-only log of transactions, where each contract behaves as `Anonify.sol`.
+/// A reverted transaction is not appended and its error is returned to the sender immediately.
+#[derive(Debug, Default)]
+pub struct Ledger {
+    inner: RwLock<InnerLedger>,
+}
+
+#[derive(Debug, Default)]
+struct InnerLedger {
+    entries: Vec<LedgerEntry>,
+    contracts: HashMap<ChainAddress, AnonifyContract>,
+}
+
+impl Ledger {
+    /// Accounts don't need to be unlocked because the ledger doesn't verify transaction signatures.
+    pub fn account(&self, index: usize) -> ChainAddress {
+        let hash = Sha256::hash(&(index as u64).to_be_bytes());
+        ChainAddress::from_bytes(&hash.as_array()[..ADDRESS_SIZE])
+    }
+
+    /// Append the transaction to the log.
+    pub fn submit(&self, transaction: Transaction) -> Result<LedgerEntry, LedgerError> {
+        let mut inner = self.inner.write();
+        let height = inner.entries.len() as u64;
+        let tx_hash = TxHash::from_bytes(
+            &Sha256::hash(&bincode::serialize(&(height, &transaction))?).as_array()[..],
+        );
+
+        let sender = &transaction.sender;
+        let (contract, event) = match (&transaction.call, &transaction.to) {
+            (
+                Call::Deploy {
+                    attestation,
+                    handshake,
+                },
+                None,
+            ) => {
+                let (anonify, event) = AnonifyContract::new(
+                    sender.clone(),
+                    handshake.clone(),
+                    attestation.mrenclave_ver,
+                    attestation.roster_idx,
+                )?;
+                // The address of the created contract is the last 20 bytes of the transaction hash.
+                let contract = ChainAddress::from_bytes(&tx_hash.as_bytes()[32 - ADDRESS_SIZE..]);
+                inner.contracts.insert(contract.clone(), anonify);
+                (contract, Some(event))
+            }
+            (Call::Deploy { .. }, Some(contract)) => {
+                return Err(LedgerError::UnexpectedRecipient(contract.clone()))
+            }
+            (call, Some(contract)) => {
+                let anonify = inner
+                    .contracts
+                    .get_mut(contract)
+                    .ok_or_else(|| LedgerError::UnknownContract(contract.clone()))?;
+                (contract.clone(), call.execute(anonify, sender)?)
+            }
+            (_, None) => return Err(LedgerError::MissingRecipient),
+        };
+
+        let entry = LedgerEntry {
+            height,
+            tx_hash,
+            contract,
+            transaction,
+            event,
+        };
+        inner.entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    /// Get at most `limit` entries with events of the contract from the height.
+    pub fn events(&self, contract: &ChainAddress, from: u64, limit: usize) -> Vec<LedgerEntry> {
+        self.inner
+            .read()
+            .entries
+            .iter()
+            .skip(from as usize)
+            .filter(|entry| &entry.contract == contract && entry.event.is_some())
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation() -> Attestation {
+        Attestation {
+            report: vec![1],
+            report_sig: vec![2],
+            mrenclave_ver: 0,
+            roster_idx: 0,
+        }
+    }
+
+    fn deploy(ledger: &Ledger) -> ChainAddress {
+        ledger
+            .submit(Transaction {
+                sender: ledger.account(0),
+                to: None,
+                call: Call::Deploy {
+                    attestation: attestation(),
+                    handshake: vec![0],
+                },
+            })
+            .unwrap()
+            .contract
+    }
+
+    fn store_command(
+        ledger: &Ledger,
+        contract: &ChainAddress,
+        generation: u32,
+    ) -> Result<LedgerEntry, LedgerError> {
+        ledger.submit(Transaction {
+            sender: ledger.account(0),
+            to: Some(contract.clone()),
+            call: Call::StoreCommand {
+                ciphertext: vec![generation as u8],
+                enclave_sig: vec![],
+                roster_idx: 0,
+                generation,
+                epoch: 1,
+            },
+        })
+    }
+
+    #[test]
+    fn test_state_counter_per_contract() {
+        let ledger = Ledger::default();
+        let contract = deploy(&ledger);
+        let other = deploy(&ledger);
+        assert_ne!(contract, other);
+
+        store_command(&ledger, &contract, 1).unwrap();
+        store_command(&ledger, &other, 2).unwrap();
+        let entry = store_command(&ledger, &contract, 3).unwrap();
+        assert_eq!(entry.height, 4);
+
+        let events: Vec<_> = ledger
+            .events(&contract, 0, 10)
+            .into_iter()
+            .map(|entry| entry.event.unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                LedgerEvent {
+                    state_counter: StateCounter::new(1),
+                    payload: EventPayload::Handshake(vec![0]),
+                },
+                LedgerEvent {
+                    state_counter: StateCounter::new(2),
+                    payload: EventPayload::Ciphertext(vec![1]),
+                },
+                LedgerEvent {
+                    state_counter: StateCounter::new(3),
+                    payload: EventPayload::Ciphertext(vec![3]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_events_from_height() {
+        let ledger = Ledger::default();
+        let contract = deploy(&ledger);
+        for generation in 1..=5 {
+            store_command(&ledger, &contract, generation).unwrap();
+        }
+        // Reports don't have events to be fetched
+        ledger
+            .submit(Transaction {
+                sender: ledger.account(1),
+                to: Some(contract.clone()),
+                call: Call::RegisterReport {
+                    attestation: attestation(),
+                },
+            })
+            .unwrap();
+
+        let heights: Vec<u64> = ledger
+            .events(&contract, 2, 2)
+            .iter()
+            .map(|entry| entry.height)
+            .collect();
+        assert_eq!(heights, vec![2, 3]);
+        assert_eq!(ledger.events(&contract, 6, 10), vec![]);
+    }
+
+    #[test]
+    fn test_reverted_transaction_is_not_appended() {
+        let ledger = Ledger::default();
+        let contract = deploy(&ledger);
+
+        let res = store_command(&ledger, &contract, 0);
+        assert!(matches!(
+            res,
+            Err(LedgerError::Revert(
+                "generation must be bigger than the counter"
+            ))
+        ));
+
+        let entry = store_command(&ledger, &contract, 1).unwrap();
+        assert_eq!(entry.height, 1);
+        assert_eq!(entry.event.unwrap().state_counter, StateCounter::new(2));
+    }
+
+    #[test]
+    fn test_reject_unknown_contract() {
+        let ledger = Ledger::default();
+        deploy(&ledger);
+        let unknown = ledger.account(0);
+
+        let res = store_command(&ledger, &unknown, 1);
+        assert!(matches!(res, Err(LedgerError::UnknownContract(addr)) if addr == unknown));
+    }
+
+    #[test]
+    fn test_reject_missing_recipient() {
+        let ledger = Ledger::default();
+        deploy(&ledger);
+
+        let res = ledger.submit(Transaction {
+            sender: ledger.account(0),
+            to: None,
+            call: Call::RegisterReport {
+                attestation: attestation(),
+            },
+        });
+        assert!(matches!(res, Err(LedgerError::MissingRecipient)));
+    }
+}
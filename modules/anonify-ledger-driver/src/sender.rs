@@ -0,0 +1,77 @@
+use crate::{
+    connection::{LedgerClient, LedgerContract},
+    error::Result,
+};
+use anonify_eth_driver::{host_output, traits::*, ContractInfo};
+use async_trait::async_trait;
+use frame_common::chain_types::{ChainAddress, TxHash};
+use sgx_types::sgx_enclave_id_t;
+use std::path::Path;
+use tracing::info;
+
+/// Components needed to send a transaction to the ledger
+#[derive(Debug)]
+pub struct LedgerSender {
+    enclave_id: sgx_enclave_id_t,
+    contract: LedgerContract,
+}
+
+#[async_trait]
+impl Sender for LedgerSender {
+    type Contract = LedgerContract;
+
+    fn new<P: AsRef<Path>>(
+        enclave_id: sgx_enclave_id_t,
+        node_url: &str,
+        contract_info: ContractInfo<'_, P>,
+    ) -> Result<Self> {
+        let client = LedgerClient::new(node_url)?;
+        let contract = LedgerContract::new(client, contract_info)?;
+
+        Ok(LedgerSender {
+            enclave_id,
+            contract,
+        })
+    }
+
+    fn from_contract(enclave_id: sgx_enclave_id_t, contract: LedgerContract) -> Self {
+        LedgerSender {
+            enclave_id,
+            contract,
+        }
+    }
+
+    async fn get_account(&self, index: usize, _password: &str) -> Result<ChainAddress> {
+        self.contract.get_account(index).await
+    }
+
+    async fn send_report_handshake(
+        &self,
+        host_output: &host_output::JoinGroup,
+        method: &str,
+    ) -> Result<TxHash> {
+        info!("Sending a handshake to the ledger: {:?}", host_output);
+        self.contract
+            .send_report_handshake(host_output.clone(), method)
+            .await
+    }
+
+    async fn register_report(&self, host_output: &host_output::RegisterReport) -> Result<TxHash> {
+        info!("Registering report to the ledger: {:?}", host_output);
+        self.contract.register_report(host_output.clone()).await
+    }
+
+    async fn send_command(&self, host_output: &host_output::Command) -> Result<TxHash> {
+        info!("Sending a command to the ledger: {:?}", host_output);
+        self.contract.send_command(host_output.clone()).await
+    }
+
+    async fn handshake(&self, host_output: &host_output::Handshake) -> Result<TxHash> {
+        info!("Sending a handshake to the ledger: {:?}", host_output);
+        self.contract.handshake(host_output.clone()).await
+    }
+
+    fn get_contract(self) -> LedgerContract {
+        self.contract
+    }
+}
@@ -1,2 +1,3 @@
 pub const START_SERVER_CMD: u32 = 1;
 pub const STOP_SERVER_CMD: u32 = 2;
+pub const RELOAD_TRUSTED_MEASUREMENTS_CMD: u32 = 3;
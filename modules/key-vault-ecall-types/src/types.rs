@@ -15,6 +15,12 @@ pub mod input {
     pub struct CallServerStopper;
 
     impl EcallInput for CallServerStopper {}
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+    #[serde(crate = "crate::serde")]
+    pub struct CallTrustedMeasurementsReloader;
+
+    impl EcallInput for CallTrustedMeasurementsReloader {}
 }
 
 pub mod output {
@@ -10,5 +10,5 @@ mod handlers;
 pub mod server;
 
 pub mod workflow {
-    pub use crate::server::{ServerStarter, ServerStopper};
+    pub use crate::server::{ServerStarter, ServerStopper, TrustedMeasurementsReloader};
 }
@@ -1,8 +1,8 @@
 use crate::handlers::KeyVaultHandler;
 use frame_common::state_types::StateType;
-use frame_config::{ANONIFY_ENCLAVE_MEASUREMENT, IAS_ROOT_CERT};
+use frame_config::IAS_ROOT_CERT;
 use frame_enclave::EnclaveEngine;
-use frame_mra_tls::{AttestedTlsConfig, Server, ServerConfig};
+use frame_mra_tls::{AttestedTlsConfigCache, Server, ServerConfig, TRUSTED_ANONIFY_MEASUREMENTS};
 use frame_runtime::traits::*;
 use key_vault_ecall_types::*;
 use std::env;
@@ -23,11 +23,16 @@ impl EnclaveEngine for ServerStarter {
         let sub_key = enclave_context.sub_key();
         let spid = enclave_context.spid();
 
-        let attested_tls_config =
-            AttestedTlsConfig::new_by_ra(&spid, &ias_url, &sub_key, IAS_ROOT_CERT.to_vec())?;
+        let attested_tls_config_cache =
+            AttestedTlsConfigCache::new(&spid, &ias_url, &sub_key, IAS_ROOT_CERT.to_vec());
+        // Attest in advance so that the server fails to start if the attestation fails
+        attested_tls_config_cache.get()?;
 
-        let server_config = ServerConfig::from_attested_tls_config(attested_tls_config)?
-            .set_attestation_report_verifier(IAS_ROOT_CERT.to_vec(), *ANONIFY_ENCLAVE_MEASUREMENT);
+        let server_config = ServerConfig::from_attested_tls_config_cache(attested_tls_config_cache)
+            .set_attestation_report_verifier(
+                IAS_ROOT_CERT.to_vec(),
+                TRUSTED_ANONIFY_MEASUREMENTS.clone(),
+            );
 
         let key_vault_address = env::var("KEY_VAULT_ADDRESS")?;
         let mut server = Server::new(key_vault_address, server_config);
@@ -55,3 +60,21 @@ impl EnclaveEngine for ServerStopper {
         Ok(output::Empty::default())
     }
 }
+
+/// A reloader of the measurements of the anonify enclaves trusted by the server,
+/// which are applied to the subsequent connections without restarting the server.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedMeasurementsReloader;
+
+impl EnclaveEngine for TrustedMeasurementsReloader {
+    type EI = input::CallTrustedMeasurementsReloader;
+    type EO = output::Empty;
+
+    fn handle_without_runtime<C>(_enclave_context: &C) -> anyhow::Result<Self::EO>
+    where
+        C: ConfigGetter,
+    {
+        TRUSTED_ANONIFY_MEASUREMENTS.reload()?;
+        Ok(output::Empty::default())
+    }
+}
@@ -35,6 +35,17 @@ impl Dispatcher {
 
         Ok(())
     }
+
+    /// Reload the measurements of the anonify enclaves trusted by the server
+    /// from TRUSTED_ANONIFY_MEASUREMENTS_PATH.
+    pub async fn reload_trusted_measurements(&self, ecall_cmd: u32) -> Result<()> {
+        let inner = self.inner.read();
+        let input = host_input::ReloadTrustedMeasurements::new(ecall_cmd);
+        let eid = inner.get_enclave_id();
+        let _host_output = ReloadTrustedMeasurementsWorkflow::exec(input, eid)?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -1,3 +1,4 @@
+use frame_common::error::EcallError;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, KeyVaultHostError>;
@@ -5,5 +6,16 @@ pub type Result<T> = std::result::Result<T, KeyVaultHostError>;
 #[derive(Error, Debug)]
 pub enum KeyVaultHostError {
     #[error("Error: {0}")]
-    Error(#[from] anyhow::Error),
+    Error(#[source] anyhow::Error),
+    #[error("Enclave error: {0}")]
+    EcallError(EcallError),
+}
+
+impl From<anyhow::Error> for KeyVaultHostError {
+    fn from(err: anyhow::Error) -> Self {
+        match EcallError::find(&err) {
+            Some(ecall_error) => KeyVaultHostError::EcallError(ecall_error.clone()),
+            None => KeyVaultHostError::Error(err),
+        }
+    }
 }
@@ -23,6 +23,16 @@ impl HostEngine for StopServerWorkflow {
     const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
 }
 
+pub struct ReloadTrustedMeasurementsWorkflow;
+
+impl HostEngine for ReloadTrustedMeasurementsWorkflow {
+    type HI = host_input::ReloadTrustedMeasurements;
+    type EI = input::CallTrustedMeasurementsReloader;
+    type EO = output::Empty;
+    type HO = host_output::ReloadTrustedMeasurements;
+    const OUTPUT_MAX_LEN: usize = OUTPUT_MAX_LEN;
+}
+
 pub mod host_input {
     use super::*;
 
@@ -71,6 +81,29 @@ pub mod host_input {
             self.ecall_cmd
         }
     }
+
+    pub struct ReloadTrustedMeasurements {
+        ecall_cmd: u32,
+    }
+
+    impl ReloadTrustedMeasurements {
+        pub fn new(ecall_cmd: u32) -> Self {
+            ReloadTrustedMeasurements { ecall_cmd }
+        }
+    }
+
+    impl HostInput for ReloadTrustedMeasurements {
+        type EcallInput = input::CallTrustedMeasurementsReloader;
+        type HostOutput = host_output::ReloadTrustedMeasurements;
+
+        fn apply(self) -> anyhow::Result<(Self::EcallInput, Self::HostOutput)> {
+            Ok((Self::EcallInput::default(), Self::HostOutput::default()))
+        }
+
+        fn ecall_cmd(&self) -> u32 {
+            self.ecall_cmd
+        }
+    }
 }
 
 pub mod host_output {
@@ -89,4 +122,11 @@ pub mod host_output {
     impl HostOutput for StopServer {
         type EcallOutput = output::Empty;
     }
+
+    #[derive(Default)]
+    pub struct ReloadTrustedMeasurements;
+
+    impl HostOutput for ReloadTrustedMeasurements {
+        type EcallOutput = output::Empty;
+    }
 }
@@ -14,4 +14,5 @@ register_ecall!(
     KeyVaultEnclaveContext,
     (START_SERVER_CMD, ServerStarter),
     (STOP_SERVER_CMD, ServerStopper),
+    (RELOAD_TRUSTED_MEASUREMENTS_CMD, TrustedMeasurementsReloader),
 );
@@ -1,3 +1,6 @@
+use actix_web::{http::StatusCode, HttpResponse};
+use frame_common::error::EcallError;
+use key_vault_host::KeyVaultHostError;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, ServerError>;
@@ -8,7 +11,29 @@ pub enum ServerError {
     AnyhowError(#[from] anyhow::Error),
 
     #[error("{0}")]
-    KeyVaultHostError(#[from] key_vault_host::KeyVaultHostError),
+    KeyVaultHostError(#[from] KeyVaultHostError),
 }
 
-impl actix_web::error::ResponseError for ServerError {}
+impl ServerError {
+    fn ecall_error(&self) -> Option<&EcallError> {
+        match self {
+            ServerError::AnyhowError(e) => EcallError::find(e),
+            ServerError::KeyVaultHostError(KeyVaultHostError::EcallError(e)) => Some(e),
+            ServerError::KeyVaultHostError(_) => None,
+        }
+    }
+}
+
+impl actix_web::error::ResponseError for ServerError {
+    fn status_code(&self) -> StatusCode {
+        self.ecall_error()
+            .and_then(|e| StatusCode::from_u16(e.http_status()).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let code = self.ecall_error().map_or("internal", EcallError::code);
+        HttpResponse::build(self.status_code())
+            .json(state_runtime_node_api::ErrorResponse::new(code, self))
+    }
+}
@@ -23,3 +23,15 @@ pub async fn handle_stop(server: web::Data<Arc<Server>>) -> Result<HttpResponse>
 
     Ok(HttpResponse::Ok().finish())
 }
+
+pub async fn handle_reload_trusted_measurements(
+    server: web::Data<Arc<Server>>,
+) -> Result<HttpResponse> {
+    server
+        .dispatcher
+        .reload_trusted_measurements(RELOAD_TRUSTED_MEASUREMENTS_CMD)
+        .await
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
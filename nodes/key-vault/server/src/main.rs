@@ -23,6 +23,10 @@ async fn main() -> io::Result<()> {
             .data(server.clone())
             .route("/api/v1/start", web::post().to(handle_start))
             .route("/api/v1/stop", web::post().to(handle_stop))
+            .route(
+                "/api/v1/reload_trusted_measurements",
+                web::post().to(handle_reload_trusted_measurements),
+            )
     })
     .bind(anonify_url)?
     .workers(num_workers)
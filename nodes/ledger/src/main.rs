@@ -0,0 +1,68 @@
+//! Serves an append-only log ledger over HTTP, which is a blockchain backend of the state runtime nodes
+//! connected by `LedgerDeployer`, `LedgerSender` and `LedgerWatcher`.
+//! The log is kept in memory, so it is lost when the node stops.
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use anonify_ledger_driver::{node::Transaction, Ledger};
+use frame_common::chain_types::ChainAddress;
+use serde::Deserialize;
+use std::{env, io, str::FromStr, sync::Arc};
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    from: u64,
+    limit: usize,
+}
+
+async fn handle_account(ledger: web::Data<Arc<Ledger>>, index: web::Path<usize>) -> HttpResponse {
+    HttpResponse::Ok().json(ledger.account(index.into_inner()))
+}
+
+async fn handle_submit(
+    ledger: web::Data<Arc<Ledger>>,
+    transaction: web::Json<Transaction>,
+) -> HttpResponse {
+    match ledger.submit(transaction.into_inner()) {
+        Ok(entry) => {
+            debug!(
+                "Appended an entry: height: {}, tx_hash: {}",
+                entry.height, entry.tx_hash
+            );
+            HttpResponse::Ok().json(entry)
+        }
+        Err(e) => {
+            error!("Rejected a transaction: {}", e);
+            HttpResponse::BadRequest().body(e.to_string())
+        }
+    }
+}
+
+async fn handle_events(
+    ledger: web::Data<Arc<Ledger>>,
+    contract: web::Path<String>,
+    query: web::Query<EventsQuery>,
+) -> HttpResponse {
+    match ChainAddress::from_str(&contract) {
+        Ok(contract) => HttpResponse::Ok().json(ledger.events(&contract, query.from, query.limit)),
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+#[actix_web::main]
+async fn main() -> io::Result<()> {
+    tracing_subscriber::fmt::init();
+    let ledger_url = env::var("LEDGER_URL").expect("LEDGER_URL is not set.");
+    let ledger = Arc::new(Ledger::default());
+
+    HttpServer::new(move || {
+        App::new()
+            .data(ledger.clone())
+            .route("/accounts/{index}", web::get().to(handle_account))
+            .route("/transactions", web::post().to(handle_submit))
+            .route("/contracts/{address}/events", web::get().to(handle_events))
+    })
+    .bind(ledger_url)?
+    .run()
+    .await
+}
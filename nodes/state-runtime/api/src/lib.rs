@@ -1,6 +1,13 @@
+use anonify_ecall_types::StateSnapshotHeader;
+use frame_common::{
+    chain_types::TxHash,
+    crypto::Ed25519ChallengeResponse,
+    schema::RuntimeSchema,
+    state_types::{StateCounter, StateRoot},
+};
 use frame_sodium::{SodiumCiphertext, SodiumPubKey};
 use serde::{Deserialize, Serialize};
-use web3::types::H256;
+use std::collections::BTreeMap;
 
 // ----------------------
 //  GET and POST types
@@ -23,7 +30,7 @@ pub mod state {
 
         #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
         pub struct Response {
-            pub tx_hash: H256,
+            pub tx_hash: TxHash,
         }
     }
 
@@ -70,7 +77,7 @@ pub mod join_group {
 
         #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
         pub struct Response {
-            pub tx_hash: H256,
+            pub tx_hash: TxHash,
         }
     }
 }
@@ -86,7 +93,7 @@ pub mod update_mrenclave {
 
         #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
         pub struct Response {
-            pub tx_hash: H256,
+            pub tx_hash: TxHash,
         }
     }
 }
@@ -108,7 +115,7 @@ pub mod key_rotation {
 
         #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
         pub struct Response {
-            pub tx_hash: H256,
+            pub tx_hash: TxHash,
         }
     }
 }
@@ -134,6 +141,8 @@ pub mod register_notification {
     pub mod post {
         use super::super::*;
 
+        /// `ciphertext` is an encrypted `RegisterNotification` input,
+        /// which has the user's key that notified states are encrypted to and the expiry within an hour.
         #[derive(Debug, Clone, Deserialize, Serialize)]
         pub struct Request {
             pub ciphertext: SodiumCiphertext,
@@ -145,6 +154,72 @@ pub mod register_notification {
             }
         }
     }
+
+    pub mod delete {
+        use super::super::*;
+
+        /// `ciphertext` is an encrypted `UnregisterNotification` input.
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Request {
+            pub ciphertext: SodiumCiphertext,
+        }
+
+        impl Request {
+            pub fn new(ciphertext: SodiumCiphertext) -> Self {
+                Request { ciphertext }
+            }
+        }
+    }
+}
+
+pub mod subscribe_notification {
+    pub mod get {
+        use super::super::*;
+
+        /// Prepended to the signed message so that signatures of the other requests can't be used to subscribe.
+        const CHALLENGE_DOMAIN: &[u8] = b"anonify:subscribe_notification";
+
+        /// Notifications are streamed as Server-Sent Events,
+        /// whose data is a JSON-encoded `Notification` and id is its state counter.
+        /// The parameters are passed in the query string so that browsers can subscribe with `EventSource`.
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Request {
+            /// The hex-encoded ed25519 public key of the notified account
+            pub pubkey: String,
+            /// The hex-encoded signature over the sha256 hash of `challenge_message(expires_at)`,
+            /// which proves the ownership of the notified account.
+            pub sig: String,
+            /// Unix time in seconds after which the signature can't be used to subscribe
+            pub expires_at: u64,
+            /// Replay the notifications after the state counter,
+            /// e.g. the last one received before reconnecting.
+            /// If it's not specified, the `Last-Event-ID` header sent by `EventSource` on reconnecting is used.
+            pub state_counter: Option<u32>,
+        }
+
+        impl Request {
+            /// `access_policy` must be created with `challenge_message(expires_at)`.
+            pub fn new(
+                access_policy: &Ed25519ChallengeResponse,
+                expires_at: u64,
+                state_counter: Option<StateCounter>,
+            ) -> Self {
+                Request {
+                    pubkey: hex::encode(access_policy.pubkey().to_bytes()),
+                    sig: hex::encode(&access_policy.sig().to_bytes()[..]),
+                    expires_at,
+                    state_counter: state_counter.map(|c| c.as_raw()),
+                }
+            }
+
+            /// The message which the signature commits to.
+            pub fn challenge_message(expires_at: u64) -> Vec<u8> {
+                let mut msg = CHALLENGE_DOMAIN.to_vec();
+                msg.extend_from_slice(&expires_at.to_be_bytes());
+                msg
+            }
+        }
+    }
 }
 
 pub mod register_report {
@@ -158,7 +233,169 @@ pub mod register_report {
 
         #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
         pub struct Response {
-            pub tx_hash: H256,
+            pub tx_hash: TxHash,
+        }
+    }
+}
+
+pub mod schema {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+        pub struct Response {
+            pub schema: RuntimeSchema,
+        }
+    }
+}
+
+pub mod state_snapshot {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Response {
+            pub header: StateSnapshotHeader,
+            pub sealed: Vec<u8>,
+        }
+    }
+
+    pub mod post {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Request {
+            pub contract_address: String,
+            pub header: StateSnapshotHeader,
+            pub sealed: Vec<u8>,
+        }
+    }
+}
+
+pub mod state_root {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Request {
+            pub contract_address: String,
+            /// The latest state root is returned if not specified.
+            pub state_counter: Option<StateCounter>,
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+        pub struct Response {
+            pub state_counter: StateCounter,
+            pub state_root: StateRoot,
+        }
+    }
+
+    pub mod post {
+        use super::super::*;
+
+        /// A state root reported by another node to be compared with ours.
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Request {
+            pub contract_address: String,
+            pub state_counter: StateCounter,
+            pub state_root: StateRoot,
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+        pub struct Response {
+            /// `None` if our state root at the state counter is unknown.
+            pub matched: Option<bool>,
+        }
+    }
+}
+
+pub mod dead_letters {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Response {
+            pub dead_letters: Vec<DeadLetter>,
+        }
+    }
+}
+
+pub mod dead_letter {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Response {
+            pub dead_letter: DeadLetter,
+            /// The encoded ciphertext or handshake
+            pub payload: Vec<u8>,
+        }
+    }
+
+    /// Insert the skipped event into the enclave again.
+    pub mod post {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        pub struct Response {
+            pub recovered: bool,
+            /// The dead letter kept with the new error if it failed again
+            pub dead_letter: Option<DeadLetter>,
+        }
+    }
+}
+
+pub mod dead_letter_metrics {
+    pub mod get {
+        use super::super::*;
+
+        #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+        pub struct Response {
+            /// The number of skipped events for each error code
+            pub skipped: BTreeMap<String, u64>,
+            pub retried: u64,
+            pub recovered: u64,
+            /// The number of dead letters kept
+            pub pending: u64,
+        }
+    }
+}
+
+/// An event skipped because of an error in the enclave.
+/// The error messages may tell about the transactions of other users,
+/// so only the error code is returned and the messages are left in the node's logs.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub contract_address: String,
+    /// `ciphertext` or `handshake`
+    pub kind: String,
+    pub roster_idx: u32,
+    pub epoch: u32,
+    pub generation: u32,
+    pub state_counter: StateCounter,
+    pub block_num: u64,
+    pub log_index: u64,
+    /// A stable identifier of the kind of the last error, e.g. `decode_failure`
+    pub error_code: String,
+    pub attempts: u32,
+    /// Unix time in seconds when the event was skipped
+    pub skipped_at: u64,
+}
+
+/// The body of error responses
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    /// A stable identifier of the kind of the error, e.g. `invalid_user_counter`
+    pub code: String,
+    pub message: String,
+}
+
+impl ErrorResponse {
+    pub fn new(code: impl ToString, message: impl ToString) -> Self {
+        ErrorResponse {
+            code: code.to_string(),
+            message: message.to_string(),
         }
     }
 }
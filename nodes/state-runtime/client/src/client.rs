@@ -0,0 +1,184 @@
+use crate::error::{ClientError, Result};
+use crate::validate::validate;
+use frame_common::schema::{FnSchema, RuntimeSchema};
+use frame_sodium::{SodiumCiphertext, SodiumPubKey};
+use rand_core::{CryptoRng, RngCore};
+use reqwest::{Client, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use state_runtime_node_api::ErrorResponse;
+
+/// A client of a state runtime node.
+/// Commands and getter requests are validated against the runtime schema fetched on connection,
+/// so that invalid inputs are rejected before being encrypted and sent to the enclave.
+#[derive(Debug, Clone)]
+pub struct StateRuntimeClient {
+    url: String,
+    http: Client,
+    schema: RuntimeSchema,
+}
+
+impl StateRuntimeClient {
+    pub fn connect(url: &str) -> Result<Self> {
+        let http = Client::new();
+        let resp = http.get(&format!("{}/api/v1/schema", url)).send()?;
+        let resp: state_runtime_node_api::schema::get::Response = parse_response(resp)?;
+
+        Ok(StateRuntimeClient {
+            url: url.to_string(),
+            http,
+            schema: resp.schema,
+        })
+    }
+
+    pub fn schema(&self) -> &RuntimeSchema {
+        &self.schema
+    }
+
+    pub fn validate_command(&self, cmd_name: &str, runtime_params: &Value) -> Result<()> {
+        let command = self
+            .schema
+            .command(cmd_name)
+            .ok_or_else(|| ClientError::UnknownCommand {
+                name: cmd_name.to_string(),
+                expected: names(&self.schema.commands),
+            })?;
+
+        validate_params(command, runtime_params)
+    }
+
+    pub fn validate_getter(&self, state_name: &str, runtime_params: &Value) -> Result<()> {
+        let getter = self
+            .schema
+            .getter(state_name)
+            .ok_or_else(|| ClientError::UnknownGetter {
+                name: state_name.to_string(),
+                expected: names(&self.schema.getters),
+            })?;
+
+        validate_params(getter, runtime_params)
+    }
+
+    /// Validate the command and encrypt it to the enclave.
+    pub fn encrypt_command<CR, AP>(
+        &self,
+        csprng: &mut CR,
+        enclave_encryption_key: &SodiumPubKey,
+        access_policy: &AP,
+        cmd_name: &str,
+        runtime_params: Value,
+    ) -> Result<SodiumCiphertext>
+    where
+        CR: RngCore + CryptoRng,
+        AP: Serialize,
+    {
+        self.validate_command(cmd_name, &runtime_params)?;
+        let req = json!({
+            "access_policy": access_policy,
+            "runtime_params": runtime_params,
+            "cmd_name": cmd_name,
+        });
+
+        encrypt(csprng, enclave_encryption_key, &req)
+    }
+
+    /// Validate the getter request and encrypt it to the enclave.
+    pub fn encrypt_get_state<CR, AP>(
+        &self,
+        csprng: &mut CR,
+        enclave_encryption_key: &SodiumPubKey,
+        access_policy: &AP,
+        state_name: &str,
+        runtime_params: Value,
+    ) -> Result<SodiumCiphertext>
+    where
+        CR: RngCore + CryptoRng,
+        AP: Serialize,
+    {
+        self.validate_getter(state_name, &runtime_params)?;
+        let req = json!({
+            "access_policy": access_policy,
+            "runtime_params": runtime_params,
+            "state_name": state_name,
+        });
+
+        encrypt(csprng, enclave_encryption_key, &req)
+    }
+
+    pub fn send_command(
+        &self,
+        ciphertext: SodiumCiphertext,
+    ) -> Result<state_runtime_node_api::state::post::Response> {
+        let resp = self
+            .http
+            .post(&format!("{}/api/v1/state", &self.url))
+            .json(&state_runtime_node_api::state::post::Request::new(
+                ciphertext,
+            ))
+            .send()?;
+
+        parse_response(resp)
+    }
+
+    pub fn get_state(
+        &self,
+        ciphertext: SodiumCiphertext,
+    ) -> Result<state_runtime_node_api::state::get::Response> {
+        let resp = self
+            .http
+            .get(&format!("{}/api/v1/state", &self.url))
+            .json(&state_runtime_node_api::state::get::Request::new(
+                ciphertext,
+            ))
+            .send()?;
+
+        parse_response(resp)
+    }
+}
+
+fn validate_params(schema: &FnSchema, runtime_params: &Value) -> Result<()> {
+    validate(&schema.params_json_schema(), runtime_params).map_err(|source| {
+        ClientError::InvalidParams {
+            name: schema.name.clone(),
+            source,
+        }
+    })
+}
+
+fn encrypt<CR: RngCore + CryptoRng>(
+    csprng: &mut CR,
+    enclave_encryption_key: &SodiumPubKey,
+    req: &Value,
+) -> Result<SodiumCiphertext> {
+    let ciphertext =
+        SodiumCiphertext::encrypt(csprng, enclave_encryption_key, serde_json::to_vec(req)?)?;
+    Ok(ciphertext)
+}
+
+/// Parse the body of the response, or the error returned by the node.
+fn parse_response<T: DeserializeOwned>(mut resp: Response) -> Result<T> {
+    let status = resp.status();
+    if status.is_success() {
+        return resp.json().map_err(Into::into);
+    }
+
+    match resp.json::<ErrorResponse>() {
+        Ok(ErrorResponse { code, message }) => Err(ClientError::Server {
+            status: status.as_u16(),
+            code,
+            message,
+        }),
+        Err(_) => Err(ClientError::Server {
+            status: status.as_u16(),
+            code: "unknown".to_string(),
+            message: status.to_string(),
+        }),
+    }
+}
+
+fn names(fns: &[FnSchema]) -> String {
+    fns.iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,26 @@
+use crate::validate::SchemaError;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Unknown command `{name}`, expected one of: {expected}")]
+    UnknownCommand { name: String, expected: String },
+    #[error("Unknown getter `{name}`, expected one of: {expected}")]
+    UnknownGetter { name: String, expected: String },
+    #[error("Invalid runtime_params for `{name}`: {source}")]
+    InvalidParams { name: String, source: SchemaError },
+    #[error("The node returned {status} ({code}): {message}")]
+    Server {
+        status: u16,
+        code: String,
+        message: String,
+    },
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("{0}")]
+    AnyhowError(#[from] anyhow::Error),
+}
@@ -0,0 +1,10 @@
+//! A client of state runtime nodes which validates commands against the runtime schema
+//! before encrypting them to the enclave.
+
+mod client;
+mod error;
+mod validate;
+
+pub use crate::client::StateRuntimeClient;
+pub use crate::error::{ClientError, Result};
+pub use crate::validate::{validate, SchemaError};
@@ -0,0 +1,243 @@
+//! Validation of JSON values against the subset of JSON Schema emitted by the runtime schema.
+
+use serde_json::Value;
+use std::fmt;
+
+/// A value did not match its schema. `path` locates the offending value, e.g. `recipient[3]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "`{}`: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Validate the value against the schema.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaError> {
+    validate_at(schema, value, "")
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        return any_of
+            .iter()
+            .find(|s| validate_at(s, value, path).is_ok())
+            .map(|_| ())
+            .ok_or_else(|| error(path, format!("{} matches none of the allowed types", value)));
+    }
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("integer") => validate_integer(schema, value, path),
+        Some("boolean") => expect(value.is_boolean(), "a boolean", value, path),
+        Some("string") => expect(value.is_string(), "a string", value, path),
+        Some("null") => expect(value.is_null(), "null", value, path),
+        Some("array") => validate_array(schema, value, path),
+        Some("object") => validate_object(schema, value, path),
+        // Types this validator doesn't know are left to the enclave.
+        _ => Ok(()),
+    }
+}
+
+fn validate_integer(schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+    let n = match value {
+        Value::Number(n) if n.is_u64() || n.is_i64() => n,
+        _ => return Err(mismatch("an integer", value, path)),
+    };
+
+    if let Some(min) = schema.get("minimum").and_then(Value::as_i64) {
+        // A number which doesn't fit in i64 is a large u64, so it's above any minimum.
+        if n.as_i64().map_or(false, |n| n < min) {
+            return Err(error(path, format!("{} is less than the minimum {}", n, min)));
+        }
+    }
+    if let Some(max) = schema.get("maximum").and_then(Value::as_u64) {
+        if n.as_u64().map_or(false, |n| n > max) {
+            return Err(error(path, format!("{} is greater than the maximum {}", n, max)));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_array(schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| mismatch("an array", value, path))?;
+
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (items.len() as u64) < min {
+            return Err(error(
+                path,
+                format!("expected at least {} items, found {}", min, items.len()),
+            ));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if items.len() as u64 > max {
+            return Err(error(
+                path,
+                format!("expected at most {} items, found {}", max, items.len()),
+            ));
+        }
+    }
+
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item_schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_object(schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+    let fields = value
+        .as_object()
+        .ok_or_else(|| mismatch("an object", value, path))?;
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                return Err(error(path, format!("missing field `{}`", name)));
+            }
+        }
+    }
+
+    for (name, field) in fields {
+        let field_path = if path.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", path, name)
+        };
+
+        match properties.and_then(|p| p.get(name)) {
+            Some(field_schema) => validate_at(field_schema, field, &field_path)?,
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let expected = properties
+                        .map(|p| p.keys().map(String::as_str).collect::<Vec<_>>().join(", "))
+                        .unwrap_or_default();
+                    return Err(error(
+                        path,
+                        format!("unknown field `{}`, expected one of: {}", name, expected),
+                    ));
+                }
+                Some(additional) if additional.is_object() => {
+                    validate_at(additional, field, &field_path)?
+                }
+                _ => {}
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn expect(ok: bool, expected: &str, value: &Value, path: &str) -> Result<(), SchemaError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(mismatch(expected, value, path))
+    }
+}
+
+fn mismatch(expected: &str, value: &Value, path: &str) -> SchemaError {
+    error(path, format!("expected {}, found {}", expected, value))
+}
+
+fn error(path: &str, message: String) -> SchemaError {
+    SchemaError {
+        path: path.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_common::{crypto::AccountId, schema::*};
+    use serde_json::json;
+
+    fn transfer() -> FnSchema {
+        FnSchema {
+            name: "transfer".to_string(),
+            params: vec![
+                ParamSchema {
+                    name: "recipient".to_string(),
+                    ty: TypeSchema::of::<AccountId>("AccountId"),
+                },
+                ParamSchema {
+                    name: "amount".to_string(),
+                    ty: TypeSchema::of::<u64>("U64"),
+                },
+            ],
+            returns: None,
+        }
+    }
+
+    fn validate_err(value: Value) -> String {
+        validate(&transfer().params_json_schema(), &value)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn test_valid_params() {
+        let params = json!({ "recipient": [1u8; 20], "amount": 30 });
+        assert!(validate(&transfer().params_json_schema(), &params).is_ok());
+    }
+
+    #[test]
+    fn test_missing_and_unknown_fields() {
+        assert_eq!(
+            validate_err(json!({ "recipient": [1u8; 20] })),
+            "missing field `amount`"
+        );
+        assert_eq!(
+            validate_err(json!({ "recipient": [1u8; 20], "amount": 30, "amout": 30 })),
+            "unknown field `amout`, expected one of: amount, recipient"
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        assert_eq!(
+            validate_err(json!({ "recipient": [1u8; 20], "amount": "30" })),
+            "`amount`: expected an integer, found \"30\""
+        );
+        assert_eq!(
+            validate_err(json!({ "recipient": [1u8; 20], "amount": -1 })),
+            "`amount`: -1 is less than the minimum 0"
+        );
+        assert_eq!(
+            validate_err(json!({ "recipient": [1u8; 19], "amount": 30 })),
+            "`recipient`: expected at least 20 items, found 19"
+        );
+
+        let mut recipient = vec![json!(1); 20];
+        recipient[3] = json!(256);
+        assert_eq!(
+            validate_err(json!({ "recipient": recipient, "amount": 30 })),
+            "`recipient[3]`: 256 is greater than the maximum 255"
+        );
+    }
+
+    #[test]
+    fn test_any_of() {
+        let schema = Option::<u8>::json_schema();
+        assert!(validate(&schema, &json!(null)).is_ok());
+        assert!(validate(&schema, &json!(3)).is_ok());
+        assert!(validate(&schema, &json!(true)).is_err());
+    }
+}
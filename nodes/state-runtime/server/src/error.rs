@@ -1,3 +1,5 @@
+use actix_web::{http::StatusCode, HttpResponse};
+use frame_common::error::EcallError;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, ServerError>;
@@ -12,4 +14,27 @@ pub enum ServerError {
     AnyhowError(#[from] anyhow::Error),
 }
 
-impl actix_web::error::ResponseError for ServerError {}
+impl ServerError {
+    fn ecall_error(&self) -> Option<&EcallError> {
+        match self {
+            ServerError::ModuleError(anonify_eth_driver::HostError::EcallError(e)) => Some(e),
+            ServerError::ModuleError(_) => None,
+            ServerError::FrameError(e) => e.ecall_error(),
+            ServerError::AnyhowError(e) => EcallError::find(e),
+        }
+    }
+}
+
+impl actix_web::error::ResponseError for ServerError {
+    fn status_code(&self) -> StatusCode {
+        self.ecall_error()
+            .and_then(|e| StatusCode::from_u16(e.http_status()).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let code = self.ecall_error().map_or("internal", EcallError::code);
+        HttpResponse::build(self.status_code())
+            .json(state_runtime_node_api::ErrorResponse::new(code, self))
+    }
+}
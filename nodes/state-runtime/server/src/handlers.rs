@@ -1,8 +1,10 @@
 use crate::error::{Result, ServerError};
-use crate::Server;
-use actix_web::{web, HttpResponse};
+use crate::{notification, Server};
+use actix_web::{web, HttpRequest, HttpResponse};
 use anonify_ecall_types::cmd::*;
-use anonify_eth_driver::traits::*;
+use anonify_eth_driver::{traits::*, DeadLetter, PayloadKind, RetryOutcome};
+use frame_common::state_types::StateCounter;
+use futures::StreamExt;
 use std::{sync::Arc, time};
 use tracing::{debug, error, info};
 
@@ -168,7 +170,11 @@ where
 {
     server
         .dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .map_err(|e| ServerError::from(e))?;
 
@@ -180,6 +186,22 @@ where
     Ok(HttpResponse::Ok().json(state_runtime_node_api::state::get::Response { state }))
 }
 
+pub async fn handle_get_schema<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let schema = server
+        .dispatcher
+        .get_runtime_schema(GET_RUNTIME_SCHEMA_CMD)
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().json(state_runtime_node_api::schema::get::Response { schema }))
+}
+
 pub async fn handle_enclave_encryption_key<D, S, W>(
     server: web::Data<Arc<Server<D, S, W>>>,
 ) -> Result<HttpResponse>
@@ -200,6 +222,227 @@ where
     ))
 }
 
+pub async fn handle_export_state_snapshot<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let snapshot = server
+        .dispatcher
+        .export_state_snapshot(EXPORT_STATE_SNAPSHOT_CMD)
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(
+        HttpResponse::Ok().json(state_runtime_node_api::state_snapshot::get::Response {
+            header: snapshot.header,
+            sealed: snapshot.sealed,
+        }),
+    )
+}
+
+pub async fn handle_import_state_snapshot<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    req: web::Json<state_runtime_node_api::state_snapshot::post::Request>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let req = req.into_inner();
+    server
+        .dispatcher
+        .import_state_snapshot(
+            &req.contract_address,
+            req.header,
+            req.sealed,
+            IMPORT_STATE_SNAPSHOT_CMD,
+        )
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_get_state_root<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    req: web::Json<state_runtime_node_api::state_root::get::Request>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let state_root = server
+        .dispatcher
+        .get_state_root(&req.contract_address, req.state_counter)
+        .map_err(|e| ServerError::from(e))?;
+
+    match state_root {
+        Some((state_counter, state_root)) => Ok(HttpResponse::Ok().json(
+            state_runtime_node_api::state_root::get::Response {
+                state_counter,
+                state_root,
+            },
+        )),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+pub async fn handle_verify_state_root<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    req: web::Json<state_runtime_node_api::state_root::post::Request>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let matched = server
+        .dispatcher
+        .verify_state_root(&req.contract_address, req.state_counter, req.state_root)
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().json(state_runtime_node_api::state_root::post::Response { matched }))
+}
+
+pub async fn handle_list_dead_letters<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let dead_letters = server
+        .dispatcher
+        .dead_letters()
+        .list()
+        .into_iter()
+        .map(api_dead_letter)
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .json(state_runtime_node_api::dead_letters::get::Response { dead_letters }))
+}
+
+pub async fn handle_get_dead_letter<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    id: web::Path<u64>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    match server.dispatcher.dead_letters().get(*id) {
+        Some(dead_letter) => {
+            let payload = dead_letter.payload.clone();
+            Ok(
+                HttpResponse::Ok().json(state_runtime_node_api::dead_letter::get::Response {
+                    dead_letter: api_dead_letter(dead_letter),
+                    payload,
+                }),
+            )
+        }
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+pub async fn handle_retry_dead_letter<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    id: web::Path<u64>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let outcome = server
+        .dispatcher
+        .retry_dead_letter(
+            *id,
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .map_err(|e| ServerError::from(e))?;
+
+    let res = match outcome {
+        Some(RetryOutcome::Recovered(notifications)) => {
+            server.notifications.publish(notifications);
+            state_runtime_node_api::dead_letter::post::Response {
+                recovered: true,
+                dead_letter: None,
+            }
+        }
+        Some(RetryOutcome::Failed(dead_letter)) => {
+            state_runtime_node_api::dead_letter::post::Response {
+                recovered: false,
+                dead_letter: Some(api_dead_letter(dead_letter)),
+            }
+        }
+        Some(RetryOutcome::Consumed(state_counter)) => {
+            return Ok(
+                HttpResponse::Conflict().json(state_runtime_node_api::ErrorResponse::new(
+                    "state_counter_consumed",
+                    format!(
+                        "The enclave has already consumed the state counter of the event: {:?}",
+                        state_counter
+                    ),
+                )),
+            )
+        }
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn handle_dead_letter_metrics<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let metrics = server.dispatcher.dead_letters().metrics();
+
+    Ok(
+        HttpResponse::Ok().json(state_runtime_node_api::dead_letter_metrics::get::Response {
+            skipped: metrics.skipped,
+            retried: metrics.retried,
+            recovered: metrics.recovered,
+            pending: metrics.pending,
+        }),
+    )
+}
+
+fn api_dead_letter(dead_letter: DeadLetter) -> state_runtime_node_api::DeadLetter {
+    let kind = match dead_letter.kind {
+        PayloadKind::Ciphertext => "ciphertext",
+        PayloadKind::Handshake => "handshake",
+    };
+
+    state_runtime_node_api::DeadLetter {
+        id: dead_letter.id,
+        contract_address: dead_letter.contract_addr.to_string(),
+        kind: kind.to_string(),
+        roster_idx: dead_letter.roster_idx,
+        epoch: dead_letter.epoch,
+        generation: dead_letter.generation,
+        state_counter: dead_letter.state_counter,
+        block_num: dead_letter.block_num,
+        log_index: dead_letter.log_index,
+        error_code: dead_letter.error_code,
+        attempts: dead_letter.attempts,
+        skipped_at: dead_letter.skipped_at,
+    }
+}
+
 pub async fn handle_start_sync_bc<D, S, W>(
     server: web::Data<Arc<Server<D, S, W>>>,
 ) -> Result<HttpResponse>
@@ -214,10 +457,19 @@ where
             loop {
                 match server
                     .dispatcher
-                    .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+                    .fetch_events(
+                        FETCH_CIPHERTEXT_CMD,
+                        FETCH_HANDSHAKE_CMD,
+                        GET_STATE_COUNTER_CMD,
+                    )
                     .await
                 {
-                    Ok(updated_states) => info!("State updated: {:?}", updated_states),
+                    Ok(updated_states) => {
+                        info!("State updated: {:?}", updated_states);
+                        if let Some(notifications) = updated_states {
+                            server.notifications.publish(notifications);
+                        }
+                    }
                     Err(err) => error!("event fetched error: {:?}", err),
                 };
                 actix_rt::time::delay_for(time::Duration::from_millis(server.sync_time)).await;
@@ -240,6 +492,11 @@ where
     debug!("Starting set a contract address...");
 
     debug!("Contract address: {:?}", &req.contract_address);
+    // Refuse to fetch events if the persisted checkpoint disagrees with the enclave.
+    server
+        .dispatcher
+        .resume_event_cache(&req.contract_address, GET_STATE_COUNTER_CMD)
+        .map_err(|e| ServerError::from(e))?;
     server
         .dispatcher
         .set_contract_address(&req.contract_address, &server.abi_path)
@@ -265,6 +522,63 @@ where
     Ok(HttpResponse::Ok().finish())
 }
 
+pub async fn handle_unregister_notification<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    req: web::Json<state_runtime_node_api::register_notification::delete::Request>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    server
+        .dispatcher
+        .unregister_notification(req.ciphertext.clone(), UNREGISTER_NOTIFICATION_CMD)
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Stream the notifications to the account as Server-Sent Events.
+/// The account must have been registered by `handle_register_notification`,
+/// otherwise the enclave doesn't notify its updated states.
+/// The stream ends at the expiry of the request, so that the client signs a new one to continue.
+pub async fn handle_subscribe_notification<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+    req: web::Query<state_runtime_node_api::subscribe_notification::get::Request>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    let account_id =
+        notification::verify_subscription(&req, notification::now()).map_err(ServerError::from)?;
+    // `EventSource` sends the id of the last received event on reconnecting.
+    let state_counter = req
+        .state_counter
+        .or_else(|| {
+            http_req
+                .headers()
+                .get("Last-Event-ID")
+                .and_then(|id| id.to_str().ok())
+                .and_then(|id| id.parse().ok())
+        })
+        .map(StateCounter::new);
+
+    let stream = server
+        .notifications
+        .subscribe(account_id, state_counter, req.expires_at)
+        .map(|notification| {
+            let id = serde_json::to_string(&notification.state_counter)?;
+            let data = serde_json::to_string(&notification)?;
+            Ok::<_, actix_web::Error>(web::Bytes::from(format!("id: {}\ndata: {}\n\n", id, data)))
+        });
+
+    Ok(HttpResponse::Ok().content_type("text/event-stream").streaming(stream))
+}
+
 pub async fn handle_register_report<D, S, W>(
     server: web::Data<Arc<Server<D, S, W>>>,
     req: web::Json<state_runtime_node_api::register_report::post::Request>,
@@ -295,6 +609,22 @@ where
         .json(state_runtime_node_api::register_report::post::Response { tx_hash }))
 }
 
+pub async fn handle_reload_trusted_measurements<D, S, W>(
+    server: web::Data<Arc<Server<D, S, W>>>,
+) -> Result<HttpResponse>
+where
+    D: Deployer,
+    S: Sender,
+    W: Watcher,
+{
+    server
+        .dispatcher
+        .reload_trusted_measurements(RELOAD_TRUSTED_MEASUREMENTS_CMD)
+        .map_err(|e| ServerError::from(e))?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
 #[cfg(feature = "backup-enable")]
 pub async fn handle_all_backup_to<D, S, W>(
     server: web::Data<Arc<Server<D, S, W>>>,
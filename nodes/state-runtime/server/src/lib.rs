@@ -1,9 +1,11 @@
+use crate::notification::NotificationHub;
 use anonify_eth_driver::{traits::*, Dispatcher, EventCache};
 use sgx_types::sgx_enclave_id_t;
 use std::env;
 
 mod error;
 pub mod handlers;
+pub mod notification;
 #[cfg(test)]
 mod tests;
 
@@ -18,6 +20,7 @@ pub struct Server<D: Deployer, S: Sender, W: Watcher> {
     pub password: String,
     pub sync_time: u64,
     pub dispatcher: Dispatcher<D, S, W>,
+    pub notifications: NotificationHub,
 }
 
 impl<D, S, W> Server<D, S, W>
@@ -44,7 +47,7 @@ where
             .parse()
             .expect("Failed to parse SYNC_BC_TIME to u64");
 
-        let cache = EventCache::default();
+        let cache = EventCache::from_env().expect("Failed to load the event cache");
         let dispatcher = Dispatcher::<D, S, W>::new(eid, &eth_url, cache).unwrap();
 
         Server {
@@ -57,6 +60,7 @@ where
             sync_time,
             password,
             dispatcher,
+            notifications: NotificationHub::default(),
         }
     }
 }
@@ -0,0 +1,384 @@
+use anonify_ecall_types::Notification;
+use frame_common::{
+    crypto::{AccountId, Ed25519ChallengeResponse, Sha256},
+    error::EcallError,
+    state_types::StateCounter,
+    traits::Hash256,
+};
+use futures::{
+    channel::mpsc::{channel, Sender},
+    Stream, StreamExt,
+};
+use parking_lot::Mutex;
+use state_runtime_node_api::subscribe_notification;
+use std::{
+    collections::VecDeque,
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::error;
+
+/// The number of published notifications kept to be replayed after reconnecting.
+pub const MAX_NOTIFICATION_HISTORY: usize = 1024;
+/// The longest time in seconds a subscription request is valid for,
+/// which bounds how long a leaked request can be replayed.
+pub const MAX_SUBSCRIPTION_TTL_SECS: u64 = 3600;
+/// The number of notifications buffered for a subscriber which hasn't received them yet.
+/// Subscribers which fall further behind are dropped, and have to reconnect to be replayed.
+/// It's as large as the history so that replaying the history never overflows.
+pub const MAX_PENDING_NOTIFICATIONS: usize = MAX_NOTIFICATION_HISTORY;
+
+/// Publishes notifications of updated states to the subscribing owners of the states.
+#[derive(Debug, Default)]
+pub struct NotificationHub {
+    inner: Mutex<InnerHub>,
+}
+
+#[derive(Debug, Default)]
+struct InnerHub {
+    history: VecDeque<Notification>,
+    subscribers: Vec<Subscriber>,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    account_id: AccountId,
+    expires_at: u64,
+    tx: Sender<Notification>,
+}
+
+impl Subscriber {
+    fn is_alive(&self, now: u64) -> bool {
+        self.expires_at > now && !self.tx.is_closed()
+    }
+}
+
+impl NotificationHub {
+    /// Publish the notifications returned from `Watcher::fetch_events`.
+    pub fn publish(&self, notifications: Vec<serde_json::Value>) {
+        let mut inner = self.inner.lock();
+        let now = now();
+        for json in notifications {
+            let notification: Notification = match serde_json::from_value(json) {
+                Ok(notification) => notification,
+                Err(err) => {
+                    error!("Invalid notification: {:?}", err);
+                    continue;
+                }
+            };
+
+            // Subscribers whose streams were closed or expired, or which are too slow to receive,
+            // are removed.
+            let subscribers = std::mem::take(&mut inner.subscribers);
+            inner.subscribers = subscribers
+                .into_iter()
+                .filter(|s| s.is_alive(now))
+                .filter_map(|mut s| {
+                    if s.account_id != notification.state.account_id
+                        || s.tx.try_send(notification.clone()).is_ok()
+                    {
+                        Some(s)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            if inner.history.len() == MAX_NOTIFICATION_HISTORY {
+                inner.history.pop_front();
+            }
+            inner.history.push_back(notification);
+        }
+    }
+
+    /// Subscribe the notifications to the account until `expires_at` in Unix time seconds,
+    /// when the returned stream ends.
+    /// The kept notifications after `state_counter` are replayed first if it's specified.
+    pub fn subscribe(
+        &self,
+        account_id: AccountId,
+        state_counter: Option<StateCounter>,
+        expires_at: u64,
+    ) -> impl Stream<Item = Notification> {
+        let (mut tx, rx) = channel(MAX_PENDING_NOTIFICATIONS);
+        // Replaying and subscribing under the same lock so that no notification is missed in between.
+        let mut inner = self.inner.lock();
+        let now = now();
+        // Subscribers of other accounts are removed here as well as on publishing,
+        // since nothing might be published to them anymore.
+        inner.subscribers.retain(|s| s.is_alive(now));
+        if let Some(state_counter) = state_counter {
+            inner
+                .history
+                .iter()
+                .filter(|n| n.state.account_id == account_id && n.state_counter > state_counter)
+                .for_each(|n| {
+                    let _ = tx.try_send(n.clone());
+                });
+        }
+        inner.subscribers.push(Subscriber {
+            account_id,
+            expires_at,
+            tx,
+        });
+
+        let expiry = Duration::from_secs(expires_at.saturating_sub(now));
+        rx.take_until(actix_rt::time::delay_for(expiry))
+    }
+}
+
+/// Verify that the subscription request is signed by the owner of the account and not expired,
+/// and return the account.
+pub fn verify_subscription(
+    req: &subscribe_notification::get::Request,
+    now: u64,
+) -> anyhow::Result<AccountId> {
+    if req.expires_at < now {
+        return Err(EcallError::InvalidSignature("The request is expired".to_string()).into());
+    }
+    if req.expires_at > now + MAX_SUBSCRIPTION_TTL_SECS {
+        return Err(EcallError::InvalidSignature(format!(
+            "The request must expire within {} seconds",
+            MAX_SUBSCRIPTION_TTL_SECS
+        ))
+        .into());
+    }
+
+    let decode = |s: &str| hex::decode(s).map_err(|e| EcallError::Decode(e.to_string()));
+    let invalid_length = |_| EcallError::Decode("Invalid length".to_string());
+    let sig = decode(&req.sig)?;
+    let pubkey = decode(&req.pubkey)?;
+    // The signature must commit to the expiry.
+    let msg = subscribe_notification::get::Request::challenge_message(req.expires_at);
+    let access_policy = Ed25519ChallengeResponse::new_from_bytes(
+        sig[..].try_into().map_err(invalid_length)?,
+        pubkey[..].try_into().map_err(invalid_length)?,
+        Sha256::hash(&msg).as_array(),
+    );
+
+    access_policy.verified_account_id()
+}
+
+/// Unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anonify_ecall_types::{input::RegisterNotification, output::EncryptedNotifyState};
+    use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+    use frame_common::state_types::{MemId, NotifyState};
+    use frame_sodium::{SodiumCiphertext, SodiumPrivateKey, SodiumPubKey};
+    use serde_json::json;
+
+    fn notification(
+        state_counter: u32,
+        account_id: AccountId,
+        notification_key: &SodiumPubKey,
+        balance: u64,
+    ) -> Notification {
+        let state = NotifyState::new(account_id, MemId::from_raw(0), json!(balance));
+        let ciphertext = SodiumCiphertext::encrypt(
+            &mut rand::thread_rng(),
+            notification_key,
+            serde_json::to_vec(&state).unwrap(),
+        )
+        .unwrap();
+
+        Notification {
+            state_counter: StateCounter::new(state_counter),
+            state: EncryptedNotifyState::new(account_id, ciphertext),
+        }
+    }
+
+    fn notification_key() -> (SodiumPrivateKey, SodiumPubKey) {
+        let private_key = SodiumPrivateKey::from_random(&mut rand::thread_rng()).unwrap();
+        let public_key = private_key.public_key();
+        (private_key, public_key)
+    }
+
+    /// Publish the notifications as they are returned from `Watcher::fetch_events`.
+    fn sync(hub: &NotificationHub, notifications: Vec<Notification>) {
+        let fetched = notifications
+            .iter()
+            .map(|n| serde_json::to_value(n).unwrap())
+            .collect();
+        hub.publish(fetched);
+    }
+
+    fn subscribers(hub: &NotificationHub) -> usize {
+        hub.inner.lock().subscribers.len()
+    }
+
+    #[actix_rt::test]
+    async fn test_stream_to_owner() {
+        let alice = AccountId::from_array([1u8; 20]);
+        let bob = AccountId::from_array([2u8; 20]);
+        let (alice_private_key, alice_key) = notification_key();
+        let (_, bob_key) = notification_key();
+        let hub = NotificationHub::default();
+        let mut alice_rx = Box::pin(hub.subscribe(alice, None, now() + 60));
+
+        sync(
+            &hub,
+            vec![
+                notification(1, alice, &alice_key, 70),
+                notification(1, bob, &bob_key, 30),
+            ],
+        );
+
+        let received = alice_rx.next().await.unwrap();
+        assert_eq!(received.state.account_id, alice);
+        // Only the owner can read the notified state
+        let plaintext = received
+            .state
+            .ciphertext
+            .decrypt(&alice_private_key)
+            .unwrap();
+        let state: NotifyState = serde_json::from_slice(&plaintext).unwrap();
+        assert_eq!(state.state, json!(70));
+        assert!(
+            futures::poll!(alice_rx.next()).is_pending(),
+            "bob's notification must not be streamed"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_replay_after_reconnect() {
+        let alice = AccountId::from_array([1u8; 20]);
+        let (_, alice_key) = notification_key();
+        let hub = NotificationHub::default();
+
+        sync(
+            &hub,
+            vec![
+                notification(1, alice, &alice_key, 100),
+                notification(2, alice, &alice_key, 70),
+                notification(3, alice, &alice_key, 40),
+            ],
+        );
+
+        // The client received notifications up to the state counter 1 before disconnecting.
+        let rx = hub.subscribe(alice, Some(StateCounter::new(1)), now() + 60);
+        sync(&hub, vec![notification(4, alice, &alice_key, 10)]);
+
+        let received: Vec<StateCounter> = rx.take(3).map(|n| n.state_counter).collect().await;
+        assert_eq!(
+            received,
+            vec![
+                StateCounter::new(2),
+                StateCounter::new(3),
+                StateCounter::new(4)
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_close_at_expiry() {
+        let alice = AccountId::from_array([1u8; 20]);
+        let (_, alice_key) = notification_key();
+        let hub = NotificationHub::default();
+
+        let rx = hub.subscribe(alice, None, now());
+        sync(&hub, vec![notification(1, alice, &alice_key, 100)]);
+        let received: Vec<Notification> = rx.collect().await;
+        assert!(
+            received.is_empty(),
+            "the stream must end at the expiry: {:?}",
+            received
+        );
+
+        // The expired subscriber is removed on the next subscription
+        let _rx = hub.subscribe(alice, None, now() + 60);
+        assert_eq!(subscribers(&hub), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_drop_slow_subscriber() {
+        let alice = AccountId::from_array([1u8; 20]);
+        let bob = AccountId::from_array([2u8; 20]);
+        let (_, alice_key) = notification_key();
+        let hub = NotificationHub::default();
+
+        let alice_rx = hub.subscribe(alice, None, now() + 60);
+        let _bob_rx = hub.subscribe(bob, None, now() + 60);
+        // Alice doesn't receive any of them
+        let notifications = (0..MAX_PENDING_NOTIFICATIONS as u32 + 2)
+            .map(|i| notification(i, alice, &alice_key, 100))
+            .collect();
+        sync(&hub, notifications);
+        assert_eq!(subscribers(&hub), 1, "alice must be dropped on overflow");
+
+        // The buffered notifications are still received before the stream ends
+        let received: Vec<Notification> = alice_rx.collect().await;
+        assert!(received.len() >= MAX_PENDING_NOTIFICATIONS);
+    }
+
+    #[actix_rt::test]
+    async fn test_prune_closed_subscribers() {
+        let alice = AccountId::from_array([1u8; 20]);
+        let bob = AccountId::from_array([2u8; 20]);
+        let hub = NotificationHub::default();
+
+        let alice_rx = hub.subscribe(alice, None, now() + 60);
+        drop(alice_rx);
+        // Nothing is published to alice, but her closed stream is removed on subscribing
+        let _bob_rx = hub.subscribe(bob, None, now() + 60);
+        assert_eq!(subscribers(&hub), 1);
+    }
+
+    #[test]
+    fn test_verify_subscription() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        let now = 1_600_000_000;
+        let expires_at = now + 60;
+        let subscribe = |msg: &[u8], expires_at| {
+            let access_policy = Ed25519ChallengeResponse::new_with_message(&keypair, msg);
+            subscribe_notification::get::Request::new(&access_policy, expires_at, None)
+        };
+
+        let req = subscribe(
+            &subscribe_notification::get::Request::challenge_message(expires_at),
+            expires_at,
+        );
+        assert_eq!(
+            verify_subscription(&req, now).unwrap(),
+            AccountId::from_pubkey(&keypair.public)
+        );
+        // Expired
+        assert!(verify_subscription(&req, expires_at + 1).is_err());
+
+        // The expiry can't be extended without signing again
+        let mut extended = req.clone();
+        extended.expires_at += 10;
+        assert!(verify_subscription(&extended, now).is_err());
+
+        // Too far in the future to be replayed
+        let expires_at = now + MAX_SUBSCRIPTION_TTL_SECS + 1;
+        let req = subscribe(
+            &subscribe_notification::get::Request::challenge_message(expires_at),
+            expires_at,
+        );
+        assert!(verify_subscription(&req, now).is_err());
+
+        // A signature for registering a notification can't be replayed to subscribe
+        let notification_key = notification_key().1;
+        let register_msg = RegisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+            &notification_key,
+            now + 60,
+        );
+        let req = subscribe(&register_msg, now + 60);
+        let err = verify_subscription(&req, now).unwrap_err();
+        assert!(matches!(
+            EcallError::find(&err),
+            Some(EcallError::InvalidSignature(_))
+        ));
+    }
+}
@@ -1,15 +1,21 @@
-use crate::{handlers::*, Server};
+use crate::{handlers::*, notification::NotificationHub, Server};
 use actix_web::{test, web, App};
-use anonify_ecall_types::input;
-use anonify_eth_driver::eth::*;
+use anonify_ecall_types::{cmd::*, input};
+use anonify_eth_driver::{eth::*, Dispatcher, EventCache};
+use anonify_ledger_driver::{local_ledger, LedgerDeployer, LedgerSender, LedgerWatcher};
 use ethabi::Contract as ContractABI;
-use frame_common::crypto::{AccountId, Ed25519ChallengeResponse};
+use frame_common::{
+    chain_types::ChainAddress,
+    crypto::{AccountId, Ed25519ChallengeResponse},
+    state_types::StateCounter,
+};
 use frame_host::EnclaveDir;
 use frame_runtime::primitives::U64;
 use frame_sodium::{SodiumCiphertext, SodiumPubKey};
 use integration_tests::set_env_vars;
 use rand_core::{CryptoRng, RngCore};
 use serde_json::json;
+use sgx_types::sgx_enclave_id_t;
 use std::{env, fs::File, io::BufReader, path::Path, str::FromStr, sync::Arc, time};
 use web3::{
     contract::{Contract, Options},
@@ -817,6 +823,123 @@ async fn test_duplicated_out_of_order_request_from_same_user() {
     assert_eq!(balance.state, 80); // success
 }
 
+#[actix_rt::test]
+async fn test_ledger_handlers() {
+    set_env_vars();
+    set_server_env_vars();
+
+    let enclave = EnclaveDir::new()
+        .init_enclave(true)
+        .expect("Failed to initialize enclave.");
+    let eid = enclave.geteid();
+    // just for testing
+    let mut csprng = rand::thread_rng();
+    let name = "test_ledger_handlers";
+    let server = Arc::new(ledger_server(eid, name));
+    let mut app = test::init_service(
+        App::new()
+            .data(server.clone())
+            .route(
+                "/api/v1/deploy",
+                web::post().to(handle_deploy::<LedgerDeployer, LedgerSender, LedgerWatcher>),
+            )
+            .route(
+                "/api/v1/state",
+                web::post().to(handle_send_command::<LedgerDeployer, LedgerSender, LedgerWatcher>),
+            )
+            .route(
+                "/api/v1/state",
+                web::get().to(handle_get_state::<LedgerDeployer, LedgerSender, LedgerWatcher>),
+            )
+            .route(
+                "/api/v1/enclave_encryption_key",
+                web::get().to(handle_enclave_encryption_key::<
+                    LedgerDeployer,
+                    LedgerSender,
+                    LedgerWatcher,
+                >),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post().uri("/api/v1/deploy").to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+    let contract_address: state_runtime_node_api::deploy::post::Response =
+        test::read_body_json(resp).await;
+    // Fetch the handshake of joining the group
+    fetch_events(&server).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/enclave_encryption_key")
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+    let enc_key_resp: state_runtime_node_api::enclave_encryption_key::get::Response =
+        test::read_body_json(resp).await;
+    let enc_key = enc_key_resp.enclave_encryption_key;
+
+    let init_100_req = init_100_req(&mut csprng, &enc_key, 1);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/state")
+        .set_json(&init_100_req)
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+
+    // state transition should not be occured by this transaction.
+    let transfer_110_req = transfer_110_req(&mut csprng, &enc_key, 2);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/state")
+        .set_json(&transfer_110_req)
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+    fetch_events(&server).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/state")
+        .set_json(&balance_of_req(&mut csprng, &enc_key))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+    let balance: state_runtime_node_api::state::get::Response = test::read_body_json(resp).await;
+    assert_eq!(balance.state, 100);
+
+    // Sending five messages before receiving any messages
+    for i in 0..5 {
+        let transfer_10_req = transfer_10_req(&mut csprng, &enc_key, 3 + i);
+        let req = test::TestRequest::post()
+            .uri("/api/v1/state")
+            .set_json(&transfer_10_req)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert!(resp.status().is_success(), "response: {:?}", resp);
+    }
+    fetch_events(&server).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/state")
+        .set_json(&balance_of_req(&mut csprng, &enc_key))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert!(resp.status().is_success(), "response: {:?}", resp);
+    let balance: state_runtime_node_api::state::get::Response = test::read_body_json(resp).await;
+    assert_eq!(balance.state, 50);
+
+    // The handshake and the ciphertexts are numbered by the state counter without any gap.
+    let contract_address = ChainAddress::from_str(&contract_address.contract_address).unwrap();
+    let state_counters: Vec<_> = local_ledger(name)
+        .events(&contract_address, 0, 100)
+        .into_iter()
+        .filter_map(|entry| entry.event.map(|event| event.state_counter))
+        .collect();
+    assert_eq!(
+        state_counters,
+        (1..=8).map(StateCounter::new).collect::<Vec<_>>()
+    );
+}
+
 fn set_server_env_vars() {
     env::set_var("ABI_PATH", "../../../contract-build/Anonify.abi");
     env::set_var("BIN_PATH", "../../../contract-build/Anonify.bin");
@@ -839,6 +962,41 @@ fn other_turn() {
     env::set_var("ACCOUNT_INDEX", "1");
 }
 
+/// A server on the in-process ledger of the name, which needs no blockchain node.
+fn ledger_server(
+    eid: sgx_enclave_id_t,
+    name: &str,
+) -> Server<LedgerDeployer, LedgerSender, LedgerWatcher> {
+    let eth_url = format!("local://{}", name);
+    let dispatcher = Dispatcher::new(eid, &eth_url, EventCache::default()).unwrap();
+
+    Server {
+        eid,
+        eth_url,
+        abi_path: env::var("ABI_PATH").expect("ABI_PATH is not set"),
+        bin_path: env::var("BIN_PATH").expect("BIN_PATH is not set"),
+        confirmations: 0,
+        account_index: 0,
+        password: String::new(),
+        sync_time: SYNC_TIME,
+        dispatcher,
+        notifications: NotificationHub::default(),
+    }
+}
+
+/// Fetch the events from the ledger into the enclave, as the sync loop of the server does.
+async fn fetch_events(server: &Server<LedgerDeployer, LedgerSender, LedgerWatcher>) {
+    server
+        .dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+}
+
 async fn verify_enclave_encryption_key<P: AsRef<Path>>(
     enclave_encryption_key: SodiumPubKey,
     abi_path: P,
@@ -48,6 +48,13 @@ RUST_BACKTRACE=1 RUST_LOG=debug cargo test test_node_recovery -- --nocapture
 sleep 1
 RUST_BACKTRACE=1 RUST_LOG=debug cargo test test_join_group_then_handshake -- --nocapture
 
+# Tests on the in-process ledger with enclaves in the test process
+
+cd ${ANONIFY_ROOT}/tests/integration
+RUST_BACKTRACE=1 RUST_LOG=debug cargo test --features software-enclave test_integration_ledger -- --nocapture
+cd ${ANONIFY_ROOT}/nodes/state-runtime/server
+RUST_BACKTRACE=1 RUST_LOG=debug cargo test --features software-enclave test_ledger_handlers -- --nocapture
+
 # Secret Backup Application Tests
 
 cd ${ANONIFY_ROOT}/scripts
@@ -1,20 +1,32 @@
 #[macro_use]
 extern crate lazy_static;
-use anonify_ecall_types::cmd::*;
+use anonify_ecall_types::{cmd::*, input, Notification};
 use anonify_eth_driver::{dispatcher::*, eth::*, EventCache};
+use anonify_ledger_driver::*;
+use ed25519_dalek::Keypair;
 use ethabi::Contract as ContractABI;
 use frame_common::{
+    chain_types::ChainAddress,
     crypto::{AccountId, Ed25519ChallengeResponse, COMMON_ACCESS_POLICY},
-    state_types::NotifyState,
+    state_types::{NotifyState, StateCounter},
     traits::*,
 };
 use frame_host::EnclaveDir;
 use frame_runtime::primitives::{Approved, U64};
-use frame_sodium::{SodiumCiphertext, SodiumPubKey};
+use frame_sodium::{SodiumCiphertext, SodiumPrivateKey, SodiumPubKey};
 use once_cell::sync::Lazy;
+use rand::Rng;
+use rand_os::OsRng;
 use serde_json::json;
 use sgx_types::*;
-use std::{collections::BTreeMap, env, fs::File, io::BufReader, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::BufReader,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use web3::{
     contract::{Contract, Options},
     transports::Http,
@@ -31,6 +43,58 @@ const PASSWORD: &str = "anonify0101";
 pub static ETH_URL: Lazy<String> =
     Lazy::new(|| env::var("ETH_URL").unwrap_or("http://172.28.0.2:8545".to_string()));
 
+/// Build a request to register the notification until an hour later,
+/// whose challenge commits to the notification key and the expiry.
+pub fn register_notification_req(
+    keypair: &Keypair,
+    notification_key: &SodiumPubKey,
+) -> serde_json::Value {
+    let expires_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let msg = input::RegisterNotification::<Ed25519ChallengeResponse>::challenge_message(
+        notification_key,
+        expires_at,
+    );
+
+    json!({
+        "access_policy": Ed25519ChallengeResponse::new_with_message(keypair, &msg),
+        "notification_key": notification_key,
+        "expires_at": expires_at,
+    })
+}
+
+/// Build a request to unregister the notification, which is valid for an hour.
+pub fn unregister_notification_req(keypair: &Keypair) -> serde_json::Value {
+    let expires_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let msg =
+        input::UnregisterNotification::<Ed25519ChallengeResponse>::challenge_message(expires_at);
+
+    json!({
+        "access_policy": Ed25519ChallengeResponse::new_with_message(keypair, &msg),
+        "expires_at": expires_at,
+    })
+}
+
+pub fn decrypt_notification(
+    notification: serde_json::Value,
+    notification_key: &SodiumPrivateKey,
+) -> NotifyState {
+    let notification: Notification = serde_json::from_value(notification).unwrap();
+    let plaintext = notification
+        .state
+        .ciphertext
+        .decrypt(notification_key)
+        .unwrap();
+    serde_json::from_slice(&plaintext).unwrap()
+}
+
 pub async fn get_enclave_encryption_key(
     contract_addr: &str,
     dispatcher: &Dispatcher<EthDeployer, EthSender, EventWatcher>,
@@ -102,7 +166,11 @@ async fn test_integration_eth_construct() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -133,7 +201,11 @@ async fn test_integration_eth_construct() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -180,7 +252,10 @@ async fn test_auto_notification() {
     let eid = enclave.geteid();
     // just for testing
     let mut csprng = rand::thread_rng();
-    let my_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+    let mut os_rng = OsRng::new().unwrap();
+    let my_keypair = Keypair::generate(&mut os_rng);
+    let my_access_policy =
+        Ed25519ChallengeResponse::new_with_message(&my_keypair, &csprng.gen::<[u8; 32]>());
     let other_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
 
     let gas = 5_000_000;
@@ -212,7 +287,11 @@ async fn test_auto_notification() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -239,9 +318,8 @@ async fn test_auto_notification() {
         .await
         .unwrap();
 
-    let req = json!({
-        "access_policy": my_access_policy.clone(),
-    });
+    let notification_key = SodiumPrivateKey::from_random(&mut csprng).unwrap();
+    let req = register_notification_req(&my_keypair, &notification_key.public_key());
     let encrypted_req =
         SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
     dispatcher
@@ -250,13 +328,17 @@ async fn test_auto_notification() {
 
     // Get logs from contract and update state inside enclave.
     let updated_state = dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap()
         .unwrap();
     let notified_state: Vec<NotifyState> = updated_state
         .into_iter()
-        .map(|e| serde_json::from_value(e).unwrap())
+        .map(|e| decrypt_notification(e, &notification_key))
         .collect();
 
     assert_eq!(notified_state.len(), 1);
@@ -285,20 +367,29 @@ async fn test_auto_notification() {
     let encrypted_command =
         SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
     let receipt = dispatcher
-        .send_command(encrypted_command, deployer_addr, gas, SEND_COMMAND_CMD)
+        .send_command(
+            encrypted_command,
+            deployer_addr.clone(),
+            gas,
+            SEND_COMMAND_CMD,
+        )
         .await
         .unwrap();
     println!("receipt: {:?}", receipt);
 
     // Update state inside enclave
     let updated_state = dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap()
         .unwrap();
     let notified_state: Vec<NotifyState> = updated_state
         .into_iter()
-        .map(|e| serde_json::from_value(e).unwrap())
+        .map(|e| decrypt_notification(e, &notification_key))
         .collect();
 
     assert_eq!(notified_state.len(), 1);
@@ -311,6 +402,188 @@ async fn test_auto_notification() {
         serde_json::from_value::<U64>(notified_state[0].state.clone()).unwrap(),
         U64::from_raw(70)
     );
+
+    // No notification after unregistering
+    let req = unregister_notification_req(&my_keypair);
+    let encrypted_req =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .unregister_notification(encrypted_req, UNREGISTER_NOTIFICATION_CMD)
+        .unwrap();
+
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "amount": amount,
+            "recipient": recipient,
+        },
+        "cmd_name": "transfer",
+        "counter": 3,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(encrypted_command, deployer_addr, gas, SEND_COMMAND_CMD)
+        .await
+        .unwrap();
+    let updated_state = dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap()
+        .unwrap_or_default();
+    assert!(updated_state.is_empty());
+}
+
+#[actix_rt::test]
+async fn test_multiple_notifications() {
+    set_env_vars();
+    let enclave = EnclaveDir::new().init_enclave(true).unwrap();
+    let eid = enclave.geteid();
+    // just for testing
+    let mut csprng = rand::thread_rng();
+    let mut os_rng = OsRng::new().unwrap();
+    let my_keypair = Keypair::generate(&mut os_rng);
+    let my_access_policy =
+        Ed25519ChallengeResponse::new_with_message(&my_keypair, &csprng.gen::<[u8; 32]>());
+    let other_keypair = Keypair::generate(&mut os_rng);
+    let other_access_policy =
+        Ed25519ChallengeResponse::new_with_message(&other_keypair, &csprng.gen::<[u8; 32]>());
+
+    let gas = 5_000_000;
+    let cache = EventCache::default();
+    let dispatcher =
+        Dispatcher::<EthDeployer, EthSender, EventWatcher>::new(eid, &*ETH_URL, cache).unwrap();
+
+    // Deploy
+    let deployer_addr = dispatcher
+        .get_account(ACCOUNT_INDEX, PASSWORD)
+        .await
+        .unwrap();
+    let contract_addr = dispatcher
+        .deploy(
+            deployer_addr.clone(),
+            gas,
+            ABI_PATH,
+            BIN_PATH,
+            CONFIRMATIONS,
+            JOIN_GROUP_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .set_contract_address(&contract_addr, ABI_PATH)
+        .unwrap();
+
+    // Get handshake from contract
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Init state
+    let pubkey = get_enclave_encryption_key(&contract_addr, &dispatcher).await;
+    let total_supply: u64 = 100;
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "total_supply": total_supply,
+        },
+        "cmd_name": "construct",
+        "counter": 1,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(
+            encrypted_command,
+            deployer_addr.clone(),
+            gas,
+            SEND_COMMAND_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Both the sender and the recipient register notifications
+    let my_notification_key = SodiumPrivateKey::from_random(&mut csprng).unwrap();
+    let other_notification_key = SodiumPrivateKey::from_random(&mut csprng).unwrap();
+    for (keypair, notification_key) in &[
+        (&my_keypair, &my_notification_key),
+        (&other_keypair, &other_notification_key),
+    ] {
+        let req = register_notification_req(keypair, &notification_key.public_key());
+        let encrypted_req =
+            SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap())
+                .unwrap();
+        dispatcher
+            .register_notification(encrypted_req, REGISTER_NOTIFICATION_CMD)
+            .unwrap();
+    }
+
+    // Send a transaction to contract
+    let amount: u64 = 30;
+    let recipient = other_access_policy.into_account_id();
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "amount": amount,
+            "recipient": recipient,
+        },
+        "cmd_name": "transfer",
+        "counter": 2,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(encrypted_command, deployer_addr, gas, SEND_COMMAND_CMD)
+        .await
+        .unwrap();
+
+    // Update state inside enclave
+    let updated_state = dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated_state.len(), 2);
+    // Each notification can be decrypted only by the key of its owner
+    let notified_state = vec![
+        decrypt_notification(updated_state[0].clone(), &my_notification_key),
+        decrypt_notification(updated_state[1].clone(), &other_notification_key),
+    ];
+
+    assert_eq!(
+        notified_state[0].account_id,
+        my_access_policy.into_account_id()
+    );
+    assert_eq!(
+        serde_json::from_value::<U64>(notified_state[0].state.clone()).unwrap(),
+        U64::from_raw(70)
+    );
+    assert_eq!(notified_state[1].account_id, recipient);
+    assert_eq!(
+        serde_json::from_value::<U64>(notified_state[1].state.clone()).unwrap(),
+        U64::from_raw(amount)
+    );
 }
 
 #[actix_rt::test]
@@ -353,7 +626,11 @@ async fn test_integration_eth_transfer() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -385,7 +662,11 @@ async fn test_integration_eth_transfer() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -442,7 +723,11 @@ async fn test_integration_eth_transfer() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -479,6 +764,390 @@ async fn test_integration_eth_transfer() {
     assert_eq!(third_updated_state, 0);
 }
 
+#[actix_rt::test]
+async fn test_integration_ledger_transfer() {
+    set_env_vars();
+    let enclave = EnclaveDir::new().init_enclave(true).unwrap();
+    let eid = enclave.geteid();
+    // just for testing
+    let mut csprng = rand::thread_rng();
+    let my_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+    let other_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+
+    let gas = 5_000_000;
+    let cache = EventCache::default();
+    // The in-process ledger stands in for a blockchain node.
+    let dispatcher = Dispatcher::<LedgerDeployer, LedgerSender, LedgerWatcher>::new(
+        eid,
+        "local://test_integration_ledger_transfer",
+        cache,
+    )
+    .unwrap();
+
+    // Deploy
+    let deployer_addr = dispatcher
+        .get_account(ACCOUNT_INDEX, PASSWORD)
+        .await
+        .unwrap();
+    let contract_addr = dispatcher
+        .deploy(
+            deployer_addr.clone(),
+            gas,
+            ABI_PATH,
+            BIN_PATH,
+            CONFIRMATIONS,
+            JOIN_GROUP_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .set_contract_address(&contract_addr, ABI_PATH)
+        .unwrap();
+    println!("deployed contract account_id: {}", contract_addr);
+
+    // Get handshake from the ledger
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Init state
+    let total_supply: u64 = 100;
+    let pubkey = dispatcher
+        .get_enclave_encryption_key(GET_ENCLAVE_ENCRYPTION_KEY_CMD)
+        .unwrap();
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "total_supply": total_supply,
+        },
+        "cmd_name": "construct",
+        "counter": 1,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(
+            encrypted_command,
+            deployer_addr.clone(),
+            gas,
+            SEND_COMMAND_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Send a transaction to the ledger
+    let amount: u64 = 30;
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "amount": amount,
+            "recipient": other_access_policy.into_account_id(),
+        },
+        "cmd_name": "transfer",
+        "counter": 2,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    let tx_hash = dispatcher
+        .send_command(encrypted_command, deployer_addr, gas, SEND_COMMAND_CMD)
+        .await
+        .unwrap();
+    println!("tx_hash: {}", tx_hash);
+
+    // Both of the commands are fetched at once
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {},
+        "state_name": "balance_of",
+    });
+    let encrypted_req =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    let my_updated_state = dispatcher.get_state(encrypted_req, GET_STATE_CMD).unwrap();
+
+    let req = json!({
+        "access_policy": other_access_policy.clone(),
+        "runtime_params": {},
+        "state_name": "balance_of",
+    });
+    let encrypted_req =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    let other_updated_state = dispatcher.get_state(encrypted_req, GET_STATE_CMD).unwrap();
+
+    assert_eq!(my_updated_state, total_supply - amount);
+    assert_eq!(other_updated_state, amount);
+}
+
+#[actix_rt::test]
+async fn test_state_snapshot_rollback() {
+    set_env_vars();
+    let enclave = EnclaveDir::new().init_enclave(true).unwrap();
+    let eid = enclave.geteid();
+    // just for testing
+    let mut csprng = rand::thread_rng();
+    let my_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+    let other_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+
+    let gas = 5_000_000;
+    let cache = EventCache::default();
+    let dispatcher = Dispatcher::<LedgerDeployer, LedgerSender, LedgerWatcher>::new(
+        eid,
+        "local://test_state_snapshot_rollback",
+        cache,
+    )
+    .unwrap();
+
+    // Deploy
+    let deployer_addr = dispatcher
+        .get_account(ACCOUNT_INDEX, PASSWORD)
+        .await
+        .unwrap();
+    let contract_addr = dispatcher
+        .deploy(
+            deployer_addr.clone(),
+            gas,
+            ABI_PATH,
+            BIN_PATH,
+            CONFIRMATIONS,
+            JOIN_GROUP_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .set_contract_address(&contract_addr, ABI_PATH)
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Init state
+    let pubkey = dispatcher
+        .get_enclave_encryption_key(GET_ENCLAVE_ENCRYPTION_KEY_CMD)
+        .unwrap();
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "total_supply": 100,
+        },
+        "cmd_name": "construct",
+        "counter": 1,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(
+            encrypted_command,
+            deployer_addr.clone(),
+            gas,
+            SEND_COMMAND_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+    let old_snapshot = dispatcher
+        .export_state_snapshot(EXPORT_STATE_SNAPSHOT_CMD)
+        .unwrap();
+
+    // Advance the state counter past the exported snapshot
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "amount": 30,
+            "recipient": other_access_policy.into_account_id(),
+        },
+        "cmd_name": "transfer",
+        "counter": 2,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(encrypted_command, deployer_addr, gas, SEND_COMMAND_CMD)
+        .await
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+    let new_snapshot = dispatcher
+        .export_state_snapshot(EXPORT_STATE_SNAPSHOT_CMD)
+        .unwrap();
+    assert!(new_snapshot.header.state_counter > old_snapshot.header.state_counter);
+
+    // The older snapshot must not roll the enclave back
+    assert!(dispatcher
+        .import_state_snapshot(
+            &contract_addr,
+            old_snapshot.header,
+            old_snapshot.sealed,
+            IMPORT_STATE_SNAPSHOT_CMD,
+        )
+        .is_err());
+    dispatcher
+        .import_state_snapshot(
+            &contract_addr,
+            new_snapshot.header,
+            new_snapshot.sealed,
+            IMPORT_STATE_SNAPSHOT_CMD,
+        )
+        .unwrap();
+
+    let req = json!({
+        "access_policy": my_access_policy,
+        "runtime_params": {},
+        "state_name": "balance_of",
+    });
+    let encrypted_req =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    let my_state = dispatcher.get_state(encrypted_req, GET_STATE_CMD).unwrap();
+    assert_eq!(my_state, 70);
+}
+
+#[actix_rt::test]
+async fn test_integration_ledger_key_rotation() {
+    set_env_vars();
+    let enclave = EnclaveDir::new().init_enclave(true).unwrap();
+    let eid = enclave.geteid();
+    // just for testing
+    let mut csprng = rand::thread_rng();
+    let my_access_policy = Ed25519ChallengeResponse::new_from_rng().unwrap();
+
+    let gas = 5_000_000;
+    let cache = EventCache::default();
+    let name = "test_integration_ledger_key_rotation";
+    let node_url = format!("local://{}", name);
+    let dispatcher =
+        Dispatcher::<LedgerDeployer, LedgerSender, LedgerWatcher>::new(eid, &node_url, cache)
+            .unwrap();
+
+    // Deploy
+    let deployer_addr = dispatcher
+        .get_account(ACCOUNT_INDEX, PASSWORD)
+        .await
+        .unwrap();
+    let contract_addr = dispatcher
+        .deploy(
+            deployer_addr.clone(),
+            gas,
+            ABI_PATH,
+            BIN_PATH,
+            CONFIRMATIONS,
+            JOIN_GROUP_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .set_contract_address(&contract_addr, ABI_PATH)
+        .unwrap();
+
+    // Get handshake from the ledger
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Send handshake, which is accepted only if the epoch is incremented
+    dispatcher
+        .handshake(deployer_addr.clone(), gas, SEND_HANDSHAKE_CMD)
+        .await
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    // Init state with the rotated group key
+    let total_supply: u64 = 100;
+    let pubkey = dispatcher
+        .get_enclave_encryption_key(GET_ENCLAVE_ENCRYPTION_KEY_CMD)
+        .unwrap();
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {
+            "total_supply": total_supply,
+        },
+        "cmd_name": "construct",
+        "counter": 1,
+    });
+    let encrypted_command =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    dispatcher
+        .send_command(
+            encrypted_command,
+            deployer_addr.clone(),
+            gas,
+            SEND_COMMAND_CMD,
+        )
+        .await
+        .unwrap();
+    dispatcher
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
+        .await
+        .unwrap();
+
+    let req = json!({
+        "access_policy": my_access_policy.clone(),
+        "runtime_params": {},
+        "state_name": "balance_of",
+    });
+    let encrypted_req =
+        SodiumCiphertext::encrypt(&mut csprng, &pubkey, serde_json::to_vec(&req).unwrap()).unwrap();
+    let my_state = dispatcher.get_state(encrypted_req, GET_STATE_CMD).unwrap();
+    assert_eq!(my_state, total_supply);
+
+    // The events are numbered by the state counter in the order they are appended.
+    let contract_addr = ChainAddress::from_str(&contract_addr).unwrap();
+    let state_counters: Vec<_> = local_ledger(name)
+        .events(&contract_addr, 0, 10)
+        .into_iter()
+        .filter_map(|entry| entry.event.map(|event| event.state_counter))
+        .collect();
+    assert_eq!(
+        state_counters,
+        (1..=3).map(StateCounter::new).collect::<Vec<_>>()
+    );
+}
+
 #[actix_rt::test]
 async fn test_key_rotation() {
     set_env_vars();
@@ -519,7 +1188,11 @@ async fn test_key_rotation() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -532,7 +1205,11 @@ async fn test_key_rotation() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -562,7 +1239,11 @@ async fn test_key_rotation() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -637,7 +1318,11 @@ async fn test_integration_eth_approve() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -668,7 +1353,11 @@ async fn test_integration_eth_approve() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -719,7 +1408,11 @@ async fn test_integration_eth_approve() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -790,7 +1483,11 @@ async fn test_integration_eth_transfer_from() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -821,7 +1518,11 @@ async fn test_integration_eth_transfer_from() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -919,7 +1620,11 @@ async fn test_integration_eth_transfer_from() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1015,7 +1720,11 @@ async fn test_integration_eth_transfer_from() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1127,7 +1836,11 @@ async fn test_integration_eth_mint() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1158,7 +1871,11 @@ async fn test_integration_eth_mint() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1185,7 +1902,11 @@ async fn test_integration_eth_mint() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1260,7 +1981,11 @@ async fn test_integration_eth_burn() {
 
     // Get handshake from contract
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1291,7 +2016,11 @@ async fn test_integration_eth_burn() {
 
     // Get logs from contract and update state inside enclave.
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1322,7 +2051,11 @@ async fn test_integration_eth_burn() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1346,7 +2079,11 @@ async fn test_integration_eth_burn() {
 
     // Update state inside enclave
     dispatcher
-        .fetch_events(FETCH_CIPHERTEXT_CMD, FETCH_HANDSHAKE_CMD)
+        .fetch_events(
+            FETCH_CIPHERTEXT_CMD,
+            FETCH_HANDSHAKE_CMD,
+            GET_STATE_COUNTER_CMD,
+        )
         .await
         .unwrap();
 
@@ -1398,4 +2135,16 @@ pub fn set_env_vars() {
     env::set_var("AUDITOR_ENDPOINT", "test");
     env::set_var("ENCLAVE_PKG_NAME", "erc20");
     env::set_var("BACKUP", "disable");
+    #[cfg(feature = "software-enclave")]
+    set_software_enclave();
+}
+
+/// Initialize enclaves in the test process, whose attestation is mocked without IAS.
+#[cfg(feature = "software-enclave")]
+fn set_software_enclave() {
+    env::set_var("SPID", "");
+    env::set_var("SUB_KEY", "");
+    frame_host::software_enclave::set_enclave_factory(|| {
+        Ok(std::sync::Arc::new(anonifyenclave::software_enclave()?))
+    });
 }
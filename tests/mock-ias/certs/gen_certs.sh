@@ -0,0 +1,25 @@
+#!/bin/bash
+# Generate the test root CA and the report signing certificate of the mock IAS.
+# The root CA is copied to config/mock_ias_root_cert.pem to be loaded by enclaves via IAS_ROOT_CERT_PATH.
+
+set -eu
+
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey rsa:3072 -nodes -sha256 -days 36500 \
+    -subj "/CN=Anonify Mock IAS Root CA" \
+    -addext "basicConstraints=critical,CA:TRUE" \
+    -addext "keyUsage=critical,keyCertSign,cRLSign" \
+    -keyout root_ca_key.pem -out root_ca.pem
+
+openssl req -newkey rsa:2048 -nodes -sha256 \
+    -subj "/CN=Anonify Mock IAS Report Signing" \
+    -keyout signing_key.rsa.pem -out signing.csr
+openssl x509 -req -in signing.csr -sha256 -days 36500 \
+    -CA root_ca.pem -CAkey root_ca_key.pem -CAcreateserial \
+    -extfile <(printf "basicConstraints=critical,CA:FALSE\nkeyUsage=critical,digitalSignature,nonRepudiation\nsubjectAltName=DNS:localhost\n") \
+    -out signing_cert.pem
+openssl pkcs8 -topk8 -nocrypt -in signing_key.rsa.pem -out signing_key.pem
+
+rm -f signing.csr signing_key.rsa.pem root_ca.srl root_ca_key.pem
+cp root_ca.pem ../../../config/mock_ias_root_cert.pem
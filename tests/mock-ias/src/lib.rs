@@ -0,0 +1,244 @@
+//! A stand-in of Intel Attestation Service for testing remote attestation without the subscription of IAS.
+//!
+//! Attestation reports are returned in the same format as the API version 3 of IAS,
+//! and signed by the report signing key whose certificate is issued by the test root CA
+//! in `certs/` (copied to `config/mock_ias_root_cert.pem`).
+//! Quotes are not verified at all, so any quote including the ones of software enclaves is attested
+//! with the configured `isvEnclaveQuoteStatus`.
+
+mod server;
+
+pub use server::{config, spawn, MOCK_IAS_PATH};
+
+use anyhow::{anyhow, Result};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{RsaKeyPair, RSA_PKCS1_SHA256},
+};
+use serde_json::json;
+
+const SIGNING_KEY: &str = include_str!("../certs/signing_key.pem");
+const SIGNING_CERT: &str = include_str!("../certs/signing_cert.pem");
+const ROOT_CERT: &str = include_str!("../certs/root_ca.pem");
+
+/// The length of the quote body included in the report, which excludes the signature of the quote.
+const QUOTE_BODY_LEN: usize = 432;
+const ADVISORY_URL: &str = "https://security-center.intel.com";
+
+/// The parameters of attestation reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportParams {
+    pub quote_status: String,
+    pub advisory_ids: Vec<String>,
+    pub version: u64,
+}
+
+impl Default for ReportParams {
+    fn default() -> Self {
+        ReportParams {
+            quote_status: "OK".to_string(),
+            advisory_ids: vec![],
+            version: 3,
+        }
+    }
+}
+
+impl ReportParams {
+    pub fn new(quote_status: &str) -> Self {
+        ReportParams {
+            quote_status: quote_status.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_advisory_ids(mut self, advisory_ids: Vec<String>) -> Self {
+        self.advisory_ids = advisory_ids;
+        self
+    }
+}
+
+/// A response of the attestation, which corresponds to the body and the headers of IAS responses.
+#[derive(Debug, Clone)]
+pub struct AttestationResponse {
+    pub request_id: String,
+    pub report: Vec<u8>,
+    pub report_sig: Vec<u8>,
+    pub cert_chain: String,
+}
+
+impl AttestationResponse {
+    /// The value of `X-IASReport-Signature` header
+    pub fn signature_header(&self) -> String {
+        base64::encode(&self.report_sig)
+    }
+
+    /// The value of `X-IASReport-Signing-Certificate` header, which is the percent-encoded PEM
+    /// of the signing certificate followed by the root certificate.
+    pub fn certificate_header(&self) -> String {
+        utf8_percent_encode(&self.cert_chain, NON_ALPHANUMERIC).to_string()
+    }
+}
+
+/// Signs attestation reports with the test key.
+pub struct MockIas {
+    key_pair: RsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl MockIas {
+    pub fn new() -> Result<Self> {
+        let key = pem::parse(SIGNING_KEY)?;
+        let key_pair = RsaKeyPair::from_pkcs8(&key.contents)
+            .map_err(|e| anyhow!("Invalid signing key: {}", e))?;
+
+        Ok(MockIas {
+            key_pair,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// DER-encoded root certificate which the reports are verified with.
+    pub fn root_cert() -> Vec<u8> {
+        pem::parse(ROOT_CERT)
+            .expect("Cannot parse PEM File")
+            .contents
+    }
+
+    /// Attest the quote with the parameters. The quote itself is not verified.
+    pub fn attest(&self, quote: &[u8], params: &ReportParams) -> Result<AttestationResponse> {
+        if quote.len() < QUOTE_BODY_LEN {
+            return Err(anyhow!(
+                "The quote is too short: {} bytes, expected at least {} bytes",
+                quote.len(),
+                QUOTE_BODY_LEN
+            ));
+        }
+
+        let mut id = [0u8; 16];
+        self.rng
+            .fill(&mut id)
+            .map_err(|_| anyhow!("Failed to generate a report id"))?;
+        let id = u128::from_be_bytes(id);
+
+        let mut report = json!({
+            "id": id.to_string(),
+            "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.6f").to_string(),
+            "version": params.version,
+            "isvEnclaveQuoteStatus": params.quote_status,
+            "isvEnclaveQuoteBody": base64::encode(&quote[..QUOTE_BODY_LEN]),
+        });
+        if params.quote_status != "OK" {
+            // The platform info blob is opaque to the verifiers, so a zeroed TLV is returned.
+            report["platformInfoBlob"] = json!(hex_zeros(105));
+            report["advisoryURL"] = json!(ADVISORY_URL);
+            report["advisoryIDs"] = json!(params.advisory_ids);
+        }
+        if params.quote_status == "REVOKED" {
+            // keyCompromise of RFC 5280
+            report["revocationReason"] = json!(1);
+        }
+        let report = serde_json::to_vec(&report)?;
+
+        let mut report_sig = vec![0u8; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(&RSA_PKCS1_SHA256, &self.rng, &report, &mut report_sig)
+            .map_err(|e| anyhow!("Failed to sign the report: {}", e))?;
+
+        Ok(AttestationResponse {
+            request_id: format!("{:032x}", id),
+            report,
+            report_sig,
+            cert_chain: format!("{}{}", SIGNING_CERT, ROOT_CERT),
+        })
+    }
+}
+
+fn hex_zeros(len: usize) -> String {
+    "00".repeat(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::time::SystemTime;
+
+    static SUPPORTED_SIG_ALGS: &[&webpki::SignatureAlgorithm] = &[
+        &webpki::RSA_PKCS1_2048_8192_SHA256,
+        &webpki::RSA_PKCS1_2048_8192_SHA384,
+        &webpki::RSA_PKCS1_3072_8192_SHA384,
+    ];
+
+    /// Verify the response in the same way as `AttestedReport::verify_attested_report`.
+    fn verify(resp: &AttestationResponse) -> Value {
+        let cert_header = resp.certificate_header().replace("%0A", "");
+        let cert_chain = percent_encoding::percent_decode_str(&cert_header)
+            .decode_utf8()
+            .unwrap();
+        let v: Vec<&str> = cert_chain.split("-----").collect();
+        let signing_cert = base64::decode(v[2]).unwrap();
+        let root_cert = MockIas::root_cert();
+
+        let anchors = [webpki::trust_anchor_util::cert_der_as_trust_anchor(&root_cert).unwrap()];
+        let now = webpki::Time::try_from(SystemTime::now()).unwrap();
+        let cert = webpki::EndEntityCert::from(&signing_cert).unwrap();
+        cert.verify_is_valid_tls_server_cert(
+            SUPPORTED_SIG_ALGS,
+            &webpki::TLSServerTrustAnchors(&anchors),
+            &[&root_cert],
+            now,
+        )
+        .unwrap();
+
+        let report_sig = base64::decode(&resp.signature_header()).unwrap();
+        cert.verify_signature(
+            &webpki::RSA_PKCS1_2048_8192_SHA256,
+            &resp.report,
+            &report_sig,
+        )
+        .unwrap();
+
+        serde_json::from_slice(&resp.report).unwrap()
+    }
+
+    #[test]
+    fn test_report_is_signed_by_test_root_ca() {
+        let ias = MockIas::new().unwrap();
+        let quote = vec![1u8; 1116];
+        let resp = ias.attest(&quote, &ReportParams::default()).unwrap();
+
+        let report = verify(&resp);
+        assert_eq!(report["version"], 3);
+        assert_eq!(report["isvEnclaveQuoteStatus"], "OK");
+        assert_eq!(
+            base64::decode(report["isvEnclaveQuoteBody"].as_str().unwrap()).unwrap(),
+            vec![1u8; QUOTE_BODY_LEN]
+        );
+        assert!(report.get("advisoryIDs").is_none());
+    }
+
+    #[test]
+    fn test_report_with_quote_status() {
+        let ias = MockIas::new().unwrap();
+        let quote = vec![0u8; QUOTE_BODY_LEN];
+
+        let params = ReportParams::new("GROUP_OUT_OF_DATE")
+            .set_advisory_ids(vec!["INTEL-SA-00334".to_string()]);
+        let report = verify(&ias.attest(&quote, &params).unwrap());
+        assert_eq!(report["isvEnclaveQuoteStatus"], "GROUP_OUT_OF_DATE");
+        assert_eq!(report["advisoryIDs"], json!(["INTEL-SA-00334"]));
+
+        let report = verify(&ias.attest(&quote, &ReportParams::new("REVOKED")).unwrap());
+        assert_eq!(report["isvEnclaveQuoteStatus"], "REVOKED");
+        assert_eq!(report["revocationReason"], 1);
+    }
+
+    #[test]
+    fn test_reject_short_quote() {
+        let ias = MockIas::new().unwrap();
+        assert!(ias
+            .attest(&[0u8; QUOTE_BODY_LEN - 1], &ReportParams::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use actix_web::{App, HttpServer};
+use mock_ias::{MockIas, ReportParams};
+use std::{env, io, sync::Arc};
+
+#[actix_web::main]
+async fn main() -> io::Result<()> {
+    tracing_subscriber::fmt::init();
+    let addr = env::var("MOCK_IAS_ADDR").unwrap_or_else(|_| "0.0.0.0:8081".to_string());
+    let quote_status = env::var("MOCK_IAS_QUOTE_STATUS").unwrap_or_else(|_| "OK".to_string());
+    let advisory_ids = env::var("MOCK_IAS_ADVISORY_IDS")
+        .map(|ids| ids.split(',').map(|id| id.trim().to_string()).collect())
+        .unwrap_or_default();
+    let params = ReportParams::new(&quote_status).set_advisory_ids(advisory_ids);
+    let ias = Arc::new(MockIas::new().expect("Failed to load the signing key"));
+
+    HttpServer::new(move || {
+        App::new()
+            .data(ias.clone())
+            .data(params.clone())
+            .configure(mock_ias::config)
+    })
+    .bind(addr)?
+    .run()
+    .await
+}
@@ -0,0 +1,161 @@
+use crate::{AttestationResponse, MockIas, ReportParams};
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde::Deserialize;
+use std::{net::TcpListener, sync::Arc, thread};
+use tracing::{debug, error};
+
+/// The path of the report API, which is the same as the development environment of IAS.
+pub const MOCK_IAS_PATH: &str = "/sgx/dev/attestation/v3/report";
+
+#[derive(Debug, Deserialize)]
+struct ReportRequest {
+    #[serde(rename = "isvEnclaveQuote")]
+    isv_enclave_quote: String,
+}
+
+/// Returns the report with the configured quote status.
+async fn handle_report(
+    ias: web::Data<Arc<MockIas>>,
+    params: web::Data<ReportParams>,
+    body: web::Bytes,
+) -> HttpResponse {
+    respond(&ias, &params, &body)
+}
+
+/// Returns the report with the quote status in the path,
+/// e.g. `/status/GROUP_OUT_OF_DATE/sgx/dev/attestation/v3/report`,
+/// so that a running server can attest with any status.
+async fn handle_report_with_status(
+    ias: web::Data<Arc<MockIas>>,
+    params: web::Data<ReportParams>,
+    path: web::Path<(String, String)>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let (quote_status, _) = path.into_inner();
+    let params = ReportParams {
+        quote_status,
+        ..params.get_ref().clone()
+    };
+    respond(&ias, &params, &body)
+}
+
+fn respond(ias: &MockIas, params: &ReportParams, body: &[u8]) -> HttpResponse {
+    // The body is parsed by hand because the content type is not checked by IAS.
+    let quote = serde_json::from_slice::<ReportRequest>(body)
+        .map_err(anyhow::Error::from)
+        .and_then(|req| base64::decode(&req.isv_enclave_quote).map_err(Into::into));
+    let resp = match quote.and_then(|quote| ias.attest(&quote, params)) {
+        Ok(resp) => resp,
+        Err(e) => {
+            error!("Invalid attestation evidence payload: {}", e);
+            return HttpResponse::BadRequest().finish();
+        }
+    };
+    debug!(
+        "Attested a quote: request_id: {}, isvEnclaveQuoteStatus: {}",
+        resp.request_id, params.quote_status
+    );
+
+    report_response(resp)
+}
+
+fn report_response(resp: AttestationResponse) -> HttpResponse {
+    HttpResponse::Ok()
+        .header("Request-ID", resp.request_id.as_str())
+        .header("X-IASReport-Signature", resp.signature_header())
+        .header("X-IASReport-Signing-Certificate", resp.certificate_header())
+        .content_type("application/json")
+        .body(resp.report)
+}
+
+/// Register the routes of the report API.
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/status/{quote_status}/{tail:.*}",
+        web::post().to(handle_report_with_status),
+    )
+    .route("/{tail:.*}", web::post().to(handle_report));
+}
+
+/// Start the server in a background thread. The address is bound before returning,
+/// so requests can be sent right after this returns.
+pub fn spawn(addr: &str, params: ReportParams) -> anyhow::Result<()> {
+    let ias = Arc::new(MockIas::new()?);
+    let listener = TcpListener::bind(addr)?;
+
+    thread::spawn(move || {
+        let mut sys = actix_web::rt::System::new("mock-ias");
+        let server = HttpServer::new(move || {
+            App::new()
+                .data(ias.clone())
+                .data(params.clone())
+                .configure(config)
+        })
+        .listen(listener)
+        .expect("Failed to listen")
+        .run();
+        sys.block_on(server)
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::Value;
+
+    async fn post_quote(path: &str, body: String) -> (StatusCode, Option<Value>) {
+        let mut app = test::init_service(
+            App::new()
+                .data(Arc::new(MockIas::new().unwrap()))
+                .data(ReportParams::default())
+                .configure(config),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(path)
+            .header("Content-Type", "application/json")
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let status = resp.status();
+        if !status.is_success() {
+            return (status, None);
+        }
+
+        assert!(resp.headers().contains_key("X-IASReport-Signature"));
+        assert!(resp
+            .headers()
+            .contains_key("X-IASReport-Signing-Certificate"));
+        let report = test::read_body(resp).await;
+        (status, Some(serde_json::from_slice(&report).unwrap()))
+    }
+
+    fn quote_body() -> String {
+        format!(
+            "{{\"isvEnclaveQuote\":\"{}\"}}\r\n",
+            base64::encode(&[0u8; 432][..])
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_report_api() {
+        let (status, report) = post_quote(MOCK_IAS_PATH, quote_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report.unwrap()["isvEnclaveQuoteStatus"], "OK");
+
+        let path = format!("/status/REVOKED{}", MOCK_IAS_PATH);
+        let (status, report) = post_quote(&path, quote_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report.unwrap()["isvEnclaveQuoteStatus"], "REVOKED");
+    }
+
+    #[actix_rt::test]
+    async fn test_invalid_quote() {
+        let body = "{\"isvEnclaveQuote\":\"invalid\"}".to_string();
+        let (status, _) = post_quote(MOCK_IAS_PATH, body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
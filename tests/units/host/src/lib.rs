@@ -1,4 +1,5 @@
 use frame_host::EnclaveDir;
+use mock_ias::ReportParams;
 use sgx_types::{sgx_enclave_id_t, sgx_status_t};
 
 extern "C" {
@@ -7,6 +8,10 @@ extern "C" {
 
 #[test]
 fn test_in_enclave() {
+    // The quote status is configured per request by the tests in enclave
+    // Statuses except OK are reported with an advisory ID to test the attestation policy
+    let params = ReportParams::default().set_advisory_ids(vec!["INTEL-SA-00334".to_string()]);
+    mock_ias::spawn("0.0.0.0:8081", params).unwrap();
     let enclave = EnclaveDir::new().init_enclave(true).unwrap();
     let ret = unsafe { ecall_run_tests(enclave.geteid()) };
 
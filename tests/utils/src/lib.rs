@@ -145,6 +145,11 @@ pub fn test_end(ntestcases: u64, failurecases: Vec<String>) -> bool {
     failurecases.is_empty()
 }
 
+/// The mock IAS spawned by the host of unit tests
+pub const MOCK_IAS_URL: &str = "http://localhost:8081";
+/// The test root CA of the mock IAS relative to PJ_ROOT_DIR
+pub const MOCK_IAS_ROOT_CERT_PATH: &str = "config/mock_ias_root_cert.pem";
+
 pub fn set_env_vars() {
     env::set_var("RUST_LOG", "DEBUG");
     env::set_var("MY_ROSTER_IDX", "0");